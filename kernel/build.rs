@@ -24,6 +24,6 @@ fn main() {
     let platform = std::env::var("PLATFORM").unwrap();
     let mut common_defs: Vec<String> = defs.split_whitespace().map(|s| s.to_string()).collect();
     asm_gen(&platform, &mut common_defs);
-    let linker_path = rel4_config::generator::linker_gen(&platform);
+    let linker_path = rel4_config::generator::linker_gen(&platform, &common_defs);
     println!("cargo:rustc-link-arg=-T{}", linker_path.to_str().unwrap());
 }
@@ -1,10 +1,14 @@
 use super::consts::*;
+#[cfg(feature = "hypervisor")]
+use super::GicVCpuCtrlMap;
 use super::{GicCpuIfaceMap, GicDistMap};
 use aarch64_cpu::registers::Readable;
 use tock_registers::interfaces::Writeable;
 
 static GIC_DIST: GicDistMap = GicDistMap::new(GIC_V2_DISTRIBUTOR_PPTR as *mut u8);
 static GIC_CPUIFACE: GicCpuIfaceMap = GicCpuIfaceMap::new(GIC_V2_CONTROLLER_PPTR as *mut u8);
+#[cfg(feature = "hypervisor")]
+static GIC_VCPUCTRL: GicVCpuCtrlMap = GicVCpuCtrlMap::new(GIC_V2_VCPUCTRL_PPTR as *mut u8);
 
 // This is for aarch64 only
 pub fn cpu_iface_init() {
@@ -62,6 +66,13 @@ pub fn dist_pending_clr(irq: usize) {
     GIC_DIST.regs().pending_clr[word].set(1 << bits);
 }
 
+/// Whether `irq` is pending, the private IRQs of this core only
+pub fn irq_is_pending(irq: usize) -> bool {
+    let word = irq >> 5;
+    let bits = (irq & 0x1f) as u32;
+    (GIC_DIST.regs().pending_set[word].get() & (1 << bits)) != 0
+}
+
 /// Get the current interrupt number
 pub fn gic_int_ack() -> usize {
     GIC_CPUIFACE.regs().int_ack.get() as usize
@@ -72,6 +83,52 @@ pub fn ack_irq(irq: usize) {
     GIC_CPUIFACE.regs().eoi.set(irq as _);
 }
 
+/// Read the VGIC type register, it tells how many list registers the hardware has.
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_vtr() -> u32 {
+    GIC_VCPUCTRL.regs().vtr.get()
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_hcr() -> u32 {
+    GIC_VCPUCTRL.regs().hcr.get()
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_hcr(hcr: u32) {
+    GIC_VCPUCTRL.regs().hcr.set(hcr);
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_vmcr() -> u32 {
+    GIC_VCPUCTRL.regs().vmcr.get()
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_vmcr(vmcr: u32) {
+    GIC_VCPUCTRL.regs().vmcr.set(vmcr);
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_apr() -> u32 {
+    GIC_VCPUCTRL.regs().apr.get()
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_apr(apr: u32) {
+    GIC_VCPUCTRL.regs().apr.set(apr);
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_lr(num: usize) -> usize {
+    GIC_VCPUCTRL.regs().lr[num].get() as usize
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_lr(num: usize, lr: usize) {
+    GIC_VCPUCTRL.regs().lr[num].set(lr as u32);
+}
+
 pub fn dist_init() {
     let nirqs = 32 * ((GIC_DIST.regs().ic_type.get() & 0x1f) + 1) as usize;
 
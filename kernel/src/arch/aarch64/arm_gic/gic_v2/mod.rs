@@ -1,14 +1,12 @@
 pub mod consts;
 pub mod gic_v2;
 
+pub use consts::{IRQ_MASK, IRQ_NONE};
 use core::ptr::NonNull;
-pub use gic_v2::{dist_pending_clr, irq_disable, irq_enable, irq_is_edge_triggered};
+pub use gic_v2::*;
 use tock_registers::register_structs;
 use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
 
-#[cfg(feature = "enable_smp")]
-pub use gic_v2::ipi_send_target;
-
 register_structs! {
     /// GIC Distributor registers.
     #[allow(non_snake_case)]
@@ -88,6 +86,28 @@ register_structs! {
     }
 }
 
+#[cfg(feature = "hypervisor")]
+register_structs! {
+    /// GIC virtual CPU interface control registers (GICH).
+    #[allow(non_snake_case)]
+    pub GicVCpuCtrlMapRegs {
+        (0x0000 => hcr: ReadWrite<u32>),
+        (0x0004 => vtr: ReadOnly<u32>),
+        (0x0008 => vmcr: ReadWrite<u32>),
+        (0x000c => _reserved_1),
+        (0x0010 => misr: ReadOnly<u32>),
+        (0x0014 => _reserved_2),
+        (0x0020 => eisr: [ReadOnly<u32>; 0x2]),
+        (0x0028 => _reserved_3),
+        (0x0030 => elsr: [ReadOnly<u32>; 0x2]),
+        (0x0038 => _reserved_4),
+        (0x00f0 => apr: ReadWrite<u32>),
+        (0x00f4 => _reserved_5),
+        (0x0100 => lr: [ReadWrite<u32>; 0x40]),
+        (0x0200 => @END),
+    }
+}
+
 pub struct GicDistMap {
     base: NonNull<GicDistMapRegs>,
 }
@@ -102,6 +122,16 @@ unsafe impl Sync for GicDistMap {}
 unsafe impl Send for GicCpuIfaceMap {}
 unsafe impl Sync for GicCpuIfaceMap {}
 
+#[cfg(feature = "hypervisor")]
+pub struct GicVCpuCtrlMap {
+    base: NonNull<GicVCpuCtrlMapRegs>,
+}
+
+#[cfg(feature = "hypervisor")]
+unsafe impl Send for GicVCpuCtrlMap {}
+#[cfg(feature = "hypervisor")]
+unsafe impl Sync for GicVCpuCtrlMap {}
+
 impl GicDistMap {
     /// Construct a new GIC distributor instance from the base address.
     pub const fn new(base: *mut u8) -> Self {
@@ -127,3 +157,17 @@ impl GicCpuIfaceMap {
         unsafe { self.base.as_ref() }
     }
 }
+
+#[cfg(feature = "hypervisor")]
+impl GicVCpuCtrlMap {
+    /// Construct a new GIC virtual CPU interface control instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &GicVCpuCtrlMapRegs {
+        unsafe { self.base.as_ref() }
+    }
+}
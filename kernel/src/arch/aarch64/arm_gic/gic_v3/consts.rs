@@ -0,0 +1,52 @@
+use sel4_common::arch::config::KDEV_BASE;
+
+pub const GIC_V3_PPTR: usize = KDEV_BASE;
+pub const GIC_V3_DISTRIBUTOR_PPTR: usize = GIC_V3_PPTR + 0x1000;
+/// GICD_IROUTER<n> live in the 7th and 8th page of the distributor.
+pub const GIC_V3_DIST_ROUTE_PPTR: usize = GIC_V3_PPTR + 0x2000;
+/// Every redistributor maps its RD_base and SGI_base page next to each other.
+pub const GIC_V3_REDIST_PPTR: usize = GIC_V3_PPTR + 0x4000;
+pub const GIC_V3_REDIST_STRIDE: usize = 0x2000;
+pub const GIC_V3_REDIST_SGI_OFFSET: usize = 0x1000;
+/// The number of redistributors mapped by the platform.
+pub const GIC_V3_MAX_NUM_REDIST: usize = 16;
+
+pub const IRQ_SET_ALL: u32 = 0xffffffff;
+pub const IRQ_MASK: u32 = (1 << (24)) - 1;
+pub const IRQ_NONE: u32 = 1023;
+
+pub const SGI_START: usize = 0;
+pub const PPI_START: usize = 16;
+pub const SPI_START: usize = 32;
+
+/// Default priority of all the interrupts.
+pub const GIC_PRI_IRQ: u32 = 0xa0;
+/// Allow all the interrupt priorities.
+pub const DEFAULT_PMR_VALUE: usize = 0xff;
+
+pub const GICD_CTLR_RWP: u32 = 1 << 31;
+pub const GICD_CTLR_ARE_NS: u32 = 1 << 4;
+pub const GICD_CTLR_ENABLE_G1NS: u32 = 1 << 1;
+pub const GICD_CTLR_ENABLE_G0: u32 = 1 << 0;
+pub const GICD_TYPE_LINESNR: u32 = 0x1f;
+
+pub const GICR_CTLR_RWP: u32 = 1 << 3;
+pub const GICR_WAKER_PROCESSOR_SLEEP: u32 = 1 << 1;
+pub const GICR_WAKER_CHILDREN_ASLEEP: u32 = 1 << 2;
+pub const GICR_TYPER_LAST: u64 = 1 << 4;
+
+pub const ICC_SRE_EL1_SRE: usize = 1 << 0;
+#[cfg(feature = "hypervisor")]
+pub const ICC_SRE_EL2_SRE: usize = 1 << 0;
+#[cfg(feature = "hypervisor")]
+pub const ICC_SRE_EL2_ENABLE: usize = 1 << 3;
+pub const ICC_CTLR_EL1_EOIMODE: usize = 1 << 1;
+/// ICH_VTR_EL2.ListRegs, the number of implemented list registers minus one.
+#[cfg(feature = "hypervisor")]
+pub const ICH_VTR_EL2_LIST_REGS: u32 = 0x1f;
+pub const ICC_SGI1R_INT_ID_SHIFT: usize = 24;
+pub const ICC_SGI1R_AFF1_SHIFT: usize = 16;
+pub const ICC_SGI1R_AFF2_SHIFT: usize = 32;
+pub const ICC_SGI1R_AFF3_SHIFT: usize = 48;
+/// Aff3, Aff2 and Aff1 of MPIDR_EL1, the cores sharing them can be targeted by one SGI.
+pub const MPIDR_CLUSTER_MASK: u64 = 0xff_00ff_ff00;
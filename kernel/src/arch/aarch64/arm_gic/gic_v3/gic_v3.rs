@@ -0,0 +1,392 @@
+use super::consts::*;
+use super::{GicDistMap, GicDistRouteMap, GicRDistMap, GicRDistSgiMap};
+use aarch64_cpu::asm::barrier::{self, isb};
+use aarch64_cpu::registers::{Readable, MPIDR_EL1};
+use core::arch::asm;
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::cpu_id;
+use tock_registers::interfaces::Writeable;
+
+static GIC_DIST: GicDistMap = GicDistMap::new(GIC_V3_DISTRIBUTOR_PPTR as *mut u8);
+static GIC_DIST_ROUTE: GicDistRouteMap = GicDistRouteMap::new(GIC_V3_DIST_ROUTE_PPTR as *mut u8);
+
+/// The redistributor index found by each core in `gicr_locate_interface`.
+static mut GICR_INDEX: [usize; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+/// The MPIDR of each core, used to route SPIs and to target SGIs.
+static mut MPIDR_MAP: [u64; CONFIG_MAX_NUM_NODES] = [0; CONFIG_MAX_NUM_NODES];
+
+macro_rules! read_sysreg {
+    ($name:literal) => {{
+        let value: usize;
+        unsafe { asm!(concat!("mrs {}, ", $name), out(reg) value) };
+        value
+    }};
+}
+
+macro_rules! write_sysreg {
+    ($name:literal, $value:expr) => {
+        unsafe { asm!(concat!("msr ", $name, ", {}"), in(reg) $value) }
+    };
+}
+
+fn gicr_rd(index: usize) -> GicRDistMap {
+    GicRDistMap::new((GIC_V3_REDIST_PPTR + index * GIC_V3_REDIST_STRIDE) as *mut u8)
+}
+
+fn gicr_sgi(index: usize) -> GicRDistSgiMap {
+    GicRDistSgiMap::new(
+        (GIC_V3_REDIST_PPTR + index * GIC_V3_REDIST_STRIDE + GIC_V3_REDIST_SGI_OFFSET) as *mut u8,
+    )
+}
+
+/// The SGI_base frame of the redistributor owned by the current core.
+fn gicr_local_sgi() -> GicRDistSgiMap {
+    gicr_sgi(unsafe { GICR_INDEX[cpu_id()] })
+}
+
+/// Affinity value used by GICD_IROUTER: Aff3 at [39:32], Aff2..Aff0 at [23:0].
+fn mpidr_to_gic_affinity(mpidr: u64) -> u64 {
+    ((mpidr >> 32) & 0xff) << 32 | (mpidr & 0xffffff)
+}
+
+fn gicd_wait_for_rwp() {
+    while GIC_DIST.regs().ctlr.get() & GICD_CTLR_RWP != 0 {}
+}
+
+fn gicr_wait_for_rwp() {
+    let rd = gicr_rd(unsafe { GICR_INDEX[cpu_id()] });
+    while rd.regs().ctlr.get() & GICR_CTLR_RWP != 0 {}
+}
+
+/// Find the redistributor whose GICR_TYPER affinity matches the current core, a core
+/// without one can't take any interrupt so the boot stops.
+fn gicr_locate_interface() {
+    let mpidr = MPIDR_EL1.get();
+    let aff = ((mpidr >> 32) & 0xff) << 24 | (mpidr & 0xffffff);
+    for i in 0..GIC_V3_MAX_NUM_REDIST {
+        let typer = gicr_rd(i).regs().typer.get();
+        if typer >> 32 == aff {
+            unsafe {
+                GICR_INDEX[cpu_id()] = i;
+            }
+            return;
+        }
+        if typer & GICR_TYPER_LAST != 0 {
+            break;
+        }
+    }
+    log::error!(
+        "GICv3: could not locate the redistributor of CPU {}, mpidr {:#x}",
+        cpu_id(),
+        mpidr
+    );
+    crate::halt();
+}
+
+fn gicr_init() {
+    let rd = gicr_rd(unsafe { GICR_INDEX[cpu_id()] });
+    let sgi = gicr_local_sgi();
+
+    // Wake up the redistributor.
+    let waker = rd.regs().waker.get();
+    rd.regs().waker.set(waker & !GICR_WAKER_PROCESSOR_SLEEP);
+    while rd.regs().waker.get() & GICR_WAKER_CHILDREN_ASLEEP != 0 {}
+
+    // Deactivate all the SGIs and PPIs, and reset their priority.
+    sgi.regs().icactiver0.set(IRQ_SET_ALL);
+    for i in (SGI_START..SPI_START).step_by(4) {
+        sgi.regs().ipriorityr[i >> 2].set(GIC_PRI_IRQ * 0x01010101);
+    }
+
+    // Disable all the PPIs and enable all the SGIs.
+    sgi.regs().icenabler0.set(0xffff0000);
+    sgi.regs().isenabler0.set(0x0000ffff);
+
+    // All the PPIs are level-triggered.
+    sgi.regs().icfgr1.set(0);
+
+    gicr_wait_for_rwp();
+}
+
+fn cpu_iface_init() {
+    // Use the system register interface.
+    let sre = read_sysreg!("icc_sre_el1");
+    write_sysreg!("icc_sre_el1", sre | ICC_SRE_EL1_SRE);
+    #[cfg(feature = "hypervisor")]
+    {
+        let sre = read_sysreg!("icc_sre_el2");
+        write_sysreg!("icc_sre_el2", sre | ICC_SRE_EL2_SRE | ICC_SRE_EL2_ENABLE);
+    }
+    isb(barrier::SY);
+
+    // No priority grouping, accept all the priorities.
+    write_sysreg!("icc_bpr1_el1", 0usize);
+    write_sysreg!("icc_pmr_el1", DEFAULT_PMR_VALUE);
+
+    // Priority drop and deactivation both happen on EOI.
+    let ctlr = read_sysreg!("icc_ctlr_el1");
+    write_sysreg!("icc_ctlr_el1", ctlr & !ICC_CTLR_EL1_EOIMODE);
+
+    // Enable group 1 interrupts.
+    write_sysreg!("icc_igrpen1_el1", 1usize);
+    isb(barrier::SY);
+}
+
+pub fn cpu_init_local_irq_controller() {
+    unsafe {
+        MPIDR_MAP[cpu_id()] = MPIDR_EL1.get();
+    }
+    gicr_locate_interface();
+    gicr_init();
+    cpu_iface_init();
+}
+
+/// Enable the IRQ controller
+pub fn irq_enable(irq: usize) {
+    let bits = (irq & 0x1f) as u32;
+    if irq < SPI_START {
+        gicr_local_sgi().regs().isenabler0.set(1 << bits);
+        gicr_wait_for_rwp();
+    } else {
+        GIC_DIST.regs().isenabler[irq >> 5].set(1 << bits);
+        gicd_wait_for_rwp();
+    }
+}
+
+/// Disable the IRQ controller
+pub fn irq_disable(irq: usize) {
+    let bits = (irq & 0x1f) as u32;
+    if irq < SPI_START {
+        gicr_local_sgi().regs().icenabler0.set(1 << bits);
+        gicr_wait_for_rwp();
+    } else {
+        GIC_DIST.regs().icenabler[irq >> 5].set(1 << bits);
+        gicd_wait_for_rwp();
+    }
+}
+
+/// is edge triggered
+pub fn irq_is_edge_triggered(irq: usize) -> bool {
+    // SGIs are deactivated by the EOI alone, there is no pending bit to clear.
+    if irq < PPI_START {
+        return false;
+    }
+    let bits = ((irq & 0xf) * 2) as u32;
+    let icfgr = if irq < SPI_START {
+        gicr_local_sgi().regs().icfgr1.get()
+    } else {
+        GIC_DIST.regs().icfgr[irq >> 4].get()
+    };
+    (icfgr & (1 << (bits + 1))) != 0
+}
+
+/// pending clear
+pub fn dist_pending_clr(irq: usize) {
+    let bits = (irq & 0x1f) as u32;
+    if irq < SPI_START {
+        gicr_local_sgi().regs().icpendr0.set(1 << bits);
+    } else {
+        GIC_DIST.regs().icpendr[irq >> 5].set(1 << bits);
+    }
+}
+
+/// Whether `irq` is pending, the private IRQs of this core only
+pub fn irq_is_pending(irq: usize) -> bool {
+    let bits = (irq & 0x1f) as u32;
+    let ispendr = if irq < SPI_START {
+        gicr_local_sgi().regs().ispendr0.get()
+    } else {
+        GIC_DIST.regs().ispendr[irq >> 5].get()
+    };
+    (ispendr & (1 << bits)) != 0
+}
+
+/// Get the current interrupt number
+pub fn gic_int_ack() -> usize {
+    read_sysreg!("icc_iar1_el1")
+}
+
+/// Acknowledge the interrupt
+pub fn ack_irq(irq: usize) {
+    write_sysreg!("icc_eoir1_el1", irq);
+    isb(barrier::SY);
+}
+
+/// Read the VGIC type register, it tells how many list registers the hardware has.
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_vtr() -> u32 {
+    read_sysreg!("ich_vtr_el2") as u32
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_hcr() -> u32 {
+    read_sysreg!("ich_hcr_el2") as u32
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_hcr(hcr: u32) {
+    write_sysreg!("ich_hcr_el2", hcr as usize);
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_vmcr() -> u32 {
+    read_sysreg!("ich_vmcr_el2") as u32
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_vmcr(vmcr: u32) {
+    write_sysreg!("ich_vmcr_el2", vmcr as usize);
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_apr() -> u32 {
+    read_sysreg!("ich_ap1r0_el2") as u32
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_apr(apr: u32) {
+    write_sysreg!("ich_ap1r0_el2", apr as usize);
+}
+
+/// The number of list registers in ICH_VTR_EL2.ListRegs, the others are not implemented
+/// and accessing them is undefined.
+#[cfg(feature = "hypervisor")]
+fn ich_num_list_regs() -> usize {
+    (get_gic_vcpu_ctrl_vtr() & ICH_VTR_EL2_LIST_REGS) as usize + 1
+}
+
+/// Read ICH_LR<num>_EL2, an unimplemented list register reads as 0.
+#[cfg(feature = "hypervisor")]
+pub fn get_gic_vcpu_ctrl_lr(num: usize) -> usize {
+    if num >= ich_num_list_regs() {
+        return 0;
+    }
+    match num {
+        0 => read_sysreg!("ich_lr0_el2"),
+        1 => read_sysreg!("ich_lr1_el2"),
+        2 => read_sysreg!("ich_lr2_el2"),
+        3 => read_sysreg!("ich_lr3_el2"),
+        4 => read_sysreg!("ich_lr4_el2"),
+        5 => read_sysreg!("ich_lr5_el2"),
+        6 => read_sysreg!("ich_lr6_el2"),
+        7 => read_sysreg!("ich_lr7_el2"),
+        8 => read_sysreg!("ich_lr8_el2"),
+        9 => read_sysreg!("ich_lr9_el2"),
+        10 => read_sysreg!("ich_lr10_el2"),
+        11 => read_sysreg!("ich_lr11_el2"),
+        12 => read_sysreg!("ich_lr12_el2"),
+        13 => read_sysreg!("ich_lr13_el2"),
+        14 => read_sysreg!("ich_lr14_el2"),
+        15 => read_sysreg!("ich_lr15_el2"),
+        _ => unreachable!(),
+    }
+}
+
+/// Write ICH_LR<num>_EL2, a write to an unimplemented list register is ignored.
+#[cfg(feature = "hypervisor")]
+pub fn set_gic_vcpu_ctrl_lr(num: usize, lr: usize) {
+    if num >= ich_num_list_regs() {
+        return;
+    }
+    match num {
+        0 => write_sysreg!("ich_lr0_el2", lr),
+        1 => write_sysreg!("ich_lr1_el2", lr),
+        2 => write_sysreg!("ich_lr2_el2", lr),
+        3 => write_sysreg!("ich_lr3_el2", lr),
+        4 => write_sysreg!("ich_lr4_el2", lr),
+        5 => write_sysreg!("ich_lr5_el2", lr),
+        6 => write_sysreg!("ich_lr6_el2", lr),
+        7 => write_sysreg!("ich_lr7_el2", lr),
+        8 => write_sysreg!("ich_lr8_el2", lr),
+        9 => write_sysreg!("ich_lr9_el2", lr),
+        10 => write_sysreg!("ich_lr10_el2", lr),
+        11 => write_sysreg!("ich_lr11_el2", lr),
+        12 => write_sysreg!("ich_lr12_el2", lr),
+        13 => write_sysreg!("ich_lr13_el2", lr),
+        14 => write_sysreg!("ich_lr14_el2", lr),
+        15 => write_sysreg!("ich_lr15_el2", lr),
+        _ => unreachable!(),
+    }
+}
+
+pub fn dist_init() {
+    GIC_DIST.regs().ctlr.set(0);
+    gicd_wait_for_rwp();
+
+    let nr_lines = 32 * ((GIC_DIST.regs().typer.get() & GICD_TYPE_LINESNR) + 1) as usize;
+
+    // All the SPIs are level-triggered.
+    for i in (SPI_START..nr_lines).step_by(16) {
+        GIC_DIST.regs().icfgr[i >> 4].set(0);
+    }
+
+    // Reset the priority of all the SPIs.
+    for i in (SPI_START..nr_lines).step_by(4) {
+        GIC_DIST.regs().ipriorityr[i >> 2].set(GIC_PRI_IRQ * 0x01010101);
+    }
+
+    // Disable and clear all the SPIs.
+    for i in (SPI_START..nr_lines).step_by(32) {
+        GIC_DIST.regs().icenabler[i >> 5].set(IRQ_SET_ALL);
+        GIC_DIST.regs().icpendr[i >> 5].set(IRQ_SET_ALL);
+    }
+
+    gicd_wait_for_rwp();
+
+    // Enable the distributor with affinity routing.
+    GIC_DIST
+        .regs()
+        .ctlr
+        .set(GICD_CTLR_ENABLE_G0 | GICD_CTLR_ENABLE_G1NS | GICD_CTLR_ARE_NS);
+    gicd_wait_for_rwp();
+
+    // Route all the SPIs to the boot core.
+    let affinity = mpidr_to_gic_affinity(MPIDR_EL1.get());
+    for i in SPI_START..nr_lines {
+        GIC_DIST_ROUTE.regs().irouter[i - SPI_START].set(affinity);
+    }
+}
+
+/// Send the SGI `irq` to every core set in the `target` bitmask.
+///
+/// ICC_SGI1R_EL1 can only address cores sharing Aff3..Aff1 at once, so the
+/// targets are grouped by cluster and one SGI is generated per cluster.
+#[allow(unused)]
+pub fn ipi_send_target(irq: usize, target: usize) {
+    let mut pending = target;
+    while pending != 0 {
+        let first = pending.trailing_zeros() as usize;
+        let cluster = unsafe { MPIDR_MAP[first] } & MPIDR_CLUSTER_MASK;
+        let mut target_list = 0;
+        for i in first..CONFIG_MAX_NUM_NODES {
+            if pending & (1 << i) == 0 {
+                continue;
+            }
+            let mpidr = unsafe { MPIDR_MAP[i] };
+            if mpidr & MPIDR_CLUSTER_MASK == cluster {
+                target_list |= 1 << (mpidr & 0xf);
+                pending &= !(1 << i);
+            }
+        }
+        let aff1 = ((cluster >> 8) & 0xff) as usize;
+        let aff2 = ((cluster >> 16) & 0xff) as usize;
+        let aff3 = ((cluster >> 32) & 0xff) as usize;
+        let val = (aff3 << ICC_SGI1R_AFF3_SHIFT)
+            | (aff2 << ICC_SGI1R_AFF2_SHIFT)
+            | (irq << ICC_SGI1R_INT_ID_SHIFT)
+            | (aff1 << ICC_SGI1R_AFF1_SHIFT)
+            | target_list;
+        write_sysreg!("icc_sgi1r_el1", val);
+    }
+    isb(barrier::SY);
+}
+
+#[allow(unused)]
+#[cfg(feature = "enable_smp")]
+pub fn set_irq_target(irq: usize, target: usize) {
+    if irq < SPI_START {
+        return;
+    }
+    let affinity = mpidr_to_gic_affinity(unsafe { MPIDR_MAP[target] });
+    GIC_DIST_ROUTE.regs().irouter[irq - SPI_START].set(affinity);
+}
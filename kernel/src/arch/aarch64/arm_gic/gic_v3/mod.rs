@@ -0,0 +1,177 @@
+pub mod consts;
+pub mod gic_v3;
+
+pub use consts::{IRQ_MASK, IRQ_NONE};
+use core::ptr::NonNull;
+pub use gic_v3::*;
+use tock_registers::register_structs;
+use tock_registers::registers::{ReadOnly, ReadWrite, WriteOnly};
+
+register_structs! {
+    /// GICv3 Distributor registers.
+    #[allow(non_snake_case)]
+    pub GicDistMapRegs {
+        (0x0000 => ctlr: ReadWrite<u32>),
+        (0x0004 => typer: ReadOnly<u32>),
+        (0x0008 => iidr: ReadOnly<u32>),
+        (0x000c => _reserved_1),
+        (0x0080 => igroupr: [ReadWrite<u32>; 0x20]),
+        (0x0100 => isenabler: [ReadWrite<u32>; 0x20]),
+        (0x0180 => icenabler: [ReadWrite<u32>; 0x20]),
+        (0x0200 => ispendr: [ReadWrite<u32>; 0x20]),
+        (0x0280 => icpendr: [ReadWrite<u32>; 0x20]),
+        (0x0300 => isactiver: [ReadWrite<u32>; 0x20]),
+        (0x0380 => icactiver: [ReadWrite<u32>; 0x20]),
+        (0x0400 => ipriorityr: [ReadWrite<u32>; 0xff]),
+        (0x07fc => _reserved_2),
+        (0x0800 => itargetsr: [ReadWrite<u32>; 0xff]),
+        (0x0bfc => _reserved_3),
+        (0x0c00 => icfgr: [ReadWrite<u32>; 0x40]),
+        (0x0d00 => igrpmodr: [ReadWrite<u32>; 0x20]),
+        (0x0d80 => _reserved_4),
+        (0x0e00 => nsacr: [ReadWrite<u32>; 0x40]),
+        (0x0f00 => sgir: WriteOnly<u32>),
+        (0x0f04 => _reserved_5),
+        (0x0f10 => cpendsgir: [ReadWrite<u32>; 0x4]),
+        (0x0f20 => spendsgir: [ReadWrite<u32>; 0x4]),
+        (0x0f30 => _reserved_6),
+        (0x1000 => @END),
+    }
+}
+
+register_structs! {
+    /// GICv3 Distributor interrupt routing registers, GICD_IROUTER<n> for SPIs.
+    #[allow(non_snake_case)]
+    pub GicDistRouteMapRegs {
+        (0x0000 => _reserved_1),
+        (0x0100 => irouter: [ReadWrite<u64>; 988]),
+        (0x1fe0 => _reserved_2),
+        (0x2000 => @END),
+    }
+}
+
+register_structs! {
+    /// GICv3 Redistributor registers, the RD_base frame.
+    #[allow(non_snake_case)]
+    pub GicRDistMapRegs {
+        (0x0000 => ctlr: ReadWrite<u32>),
+        (0x0004 => iidr: ReadOnly<u32>),
+        (0x0008 => typer: ReadOnly<u64>),
+        (0x0010 => statusr: ReadWrite<u32>),
+        (0x0014 => waker: ReadWrite<u32>),
+        (0x0018 => _reserved_1),
+        (0x1000 => @END),
+    }
+}
+
+register_structs! {
+    /// GICv3 Redistributor registers, the SGI_base frame for SGIs and PPIs.
+    #[allow(non_snake_case)]
+    pub GicRDistSgiMapRegs {
+        (0x0000 => _reserved_1),
+        (0x0080 => igroupr0: ReadWrite<u32>),
+        (0x0084 => _reserved_2),
+        (0x0100 => isenabler0: ReadWrite<u32>),
+        (0x0104 => _reserved_3),
+        (0x0180 => icenabler0: ReadWrite<u32>),
+        (0x0184 => _reserved_4),
+        (0x0200 => ispendr0: ReadWrite<u32>),
+        (0x0204 => _reserved_5),
+        (0x0280 => icpendr0: ReadWrite<u32>),
+        (0x0284 => _reserved_6),
+        (0x0300 => isactiver0: ReadWrite<u32>),
+        (0x0304 => _reserved_7),
+        (0x0380 => icactiver0: ReadWrite<u32>),
+        (0x0384 => _reserved_8),
+        (0x0400 => ipriorityr: [ReadWrite<u32>; 0x8]),
+        (0x0420 => _reserved_9),
+        (0x0c00 => icfgr0: ReadWrite<u32>),
+        (0x0c04 => icfgr1: ReadWrite<u32>),
+        (0x0c08 => _reserved_10),
+        (0x0d00 => igrpmodr0: ReadWrite<u32>),
+        (0x0d04 => _reserved_11),
+        (0x0e00 => nsacr: ReadWrite<u32>),
+        (0x0e04 => _reserved_12),
+        (0x1000 => @END),
+    }
+}
+
+pub struct GicDistMap {
+    base: NonNull<GicDistMapRegs>,
+}
+
+pub struct GicDistRouteMap {
+    base: NonNull<GicDistRouteMapRegs>,
+}
+
+pub struct GicRDistMap {
+    base: NonNull<GicRDistMapRegs>,
+}
+
+pub struct GicRDistSgiMap {
+    base: NonNull<GicRDistSgiMapRegs>,
+}
+
+unsafe impl Send for GicDistMap {}
+unsafe impl Sync for GicDistMap {}
+
+unsafe impl Send for GicDistRouteMap {}
+unsafe impl Sync for GicDistRouteMap {}
+
+unsafe impl Send for GicRDistMap {}
+unsafe impl Sync for GicRDistMap {}
+
+unsafe impl Send for GicRDistSgiMap {}
+unsafe impl Sync for GicRDistSgiMap {}
+
+impl GicDistMap {
+    /// Construct a new GIC distributor instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &GicDistMapRegs {
+        unsafe { self.base.as_ref() }
+    }
+}
+
+impl GicDistRouteMap {
+    /// Construct a new GIC distributor routing instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &GicDistRouteMapRegs {
+        unsafe { self.base.as_ref() }
+    }
+}
+
+impl GicRDistMap {
+    /// Construct a new GIC redistributor instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &GicRDistMapRegs {
+        unsafe { self.base.as_ref() }
+    }
+}
+
+impl GicRDistSgiMap {
+    /// Construct a new GIC redistributor SGI/PPI instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &GicRDistSgiMapRegs {
+        unsafe { self.base.as_ref() }
+    }
+}
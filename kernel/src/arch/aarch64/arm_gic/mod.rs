@@ -1,5 +1,11 @@
+#[cfg(not(feature = "arm_gic_v3"))]
 pub mod gic_v2;
+#[cfg(feature = "arm_gic_v3")]
+pub mod gic_v3;
 
-// TODO: add a select for gicv2 and gicv3
+#[cfg(not(feature = "arm_gic_v3"))]
 #[allow(unused)]
 pub use gic_v2::*;
+#[cfg(feature = "arm_gic_v3")]
+#[allow(unused)]
+pub use gic_v3::*;
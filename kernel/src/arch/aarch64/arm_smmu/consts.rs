@@ -0,0 +1,95 @@
+use sel4_common::arch::config::KDEV_BASE;
+
+/// SMMU register page 0.
+pub const SMMU_V3_PPTR: usize = KDEV_BASE + 0x40000;
+/// SMMU register page 1, only the event queue pointers are used.
+pub const SMMU_V3_PAGE1_PPTR: usize = KDEV_BASE + 0x41000;
+
+/// The number of stream IDs the stream table covers.
+pub const SMMU_SID_BITS: usize = 8;
+pub const SMMU_MAX_SID: usize = bit!(SMMU_SID_BITS);
+/// The number of context descriptors, one per `ARMCB`.
+pub const SMMU_MAX_CB: usize = 64;
+
+/// Entries of the command queue and the event queue, as log2.
+pub const SMMU_CMDQ_BITS: usize = 8;
+pub const SMMU_EVTQ_BITS: usize = 7;
+
+pub const SMMU_STE_WORDS: usize = 8;
+pub const SMMU_CD_WORDS: usize = 8;
+pub const SMMU_CMD_WORDS: usize = 2;
+pub const SMMU_EVT_WORDS: usize = 4;
+
+pub const IDR0_S1P: u32 = 1 << 1;
+pub const IDR0_TTF_AARCH64: u32 = 1 << 3;
+pub const IDR1_SIDSIZE_MASK: u32 = 0x3f;
+pub const IDR1_EVENTQS_SHIFT: u32 = 16;
+pub const IDR1_CMDQS_SHIFT: u32 = 21;
+pub const IDR1_QS_MASK: u32 = 0x1f;
+
+pub const CR0_SMMUEN: u32 = 1 << 0;
+pub const CR0_EVENTQEN: u32 = 1 << 2;
+pub const CR0_CMDQEN: u32 = 1 << 3;
+
+/// Inner shareable, write-back cacheable table and queue walks.
+pub const CR1_TABLE_SH_ISH: u32 = 3 << 10;
+pub const CR1_TABLE_OC_WB: u32 = 1 << 8;
+pub const CR1_TABLE_IC_WB: u32 = 1 << 6;
+pub const CR1_QUEUE_SH_ISH: u32 = 3 << 4;
+pub const CR1_QUEUE_OC_WB: u32 = 1 << 2;
+pub const CR1_QUEUE_IC_WB: u32 = 1 << 0;
+
+pub const CR2_PTM: u32 = 1 << 2;
+pub const CR2_RECINVSID: u32 = 1 << 1;
+
+pub const GBPA_UPDATE: u32 = 1 << 31;
+pub const GBPA_ABORT: u32 = 1 << 20;
+
+pub const IRQ_CTRL_GERROR_IRQEN: u32 = 1 << 0;
+pub const IRQ_CTRL_EVENTQ_IRQEN: u32 = 1 << 2;
+
+pub const STRTAB_BASE_RA: u64 = 1 << 62;
+pub const STRTAB_BASE_CFG_FMT_LINEAR: u32 = 0 << 16;
+pub const Q_BASE_RWA: u64 = 1 << 62;
+pub const Q_BASE_ADDR_MASK: u64 = 0xf_ffff_ffff_ffe0;
+
+/// STE word 0.
+pub const STE_0_V: u64 = 1 << 0;
+pub const STE_0_CFG_ABORT: u64 = 0 << 1;
+pub const STE_0_CFG_S1_TRANS: u64 = 5 << 1;
+pub const STE_0_S1CTXPTR_MASK: u64 = 0xf_ffff_ffff_ffc0;
+/// STE word 1.
+pub const STE_1_S1CIR_WBRA: u64 = 1 << 2;
+pub const STE_1_S1COR_WBRA: u64 = 1 << 4;
+pub const STE_1_S1CSH_ISH: u64 = 3 << 6;
+pub const STE_1_SHCFG_INCOMING: u64 = 1 << 44;
+
+/// CD word 0.
+pub const CD_0_TCR_T0SZ: u64 = 16;
+pub const CD_0_TCR_IRGN0_WBWA: u64 = 1 << 8;
+pub const CD_0_TCR_ORGN0_WBWA: u64 = 1 << 10;
+pub const CD_0_TCR_SH0_ISH: u64 = 3 << 12;
+pub const CD_0_TCR_EPD1: u64 = 1 << 30;
+pub const CD_0_V: u64 = 1 << 31;
+pub const CD_0_TCR_IPS_SHIFT: u64 = 32;
+pub const CD_0_AA64: u64 = 1 << 41;
+pub const CD_0_R: u64 = 1 << 45;
+pub const CD_0_A: u64 = 1 << 46;
+pub const CD_0_ASET: u64 = 1 << 47;
+pub const CD_0_ASID_SHIFT: u64 = 48;
+pub const CD_1_TTB0_MASK: u64 = 0xf_ffff_ffff_fff0;
+
+pub const CMD_CFGI_STE: u64 = 0x03;
+pub const CMD_CFGI_ALL: u64 = 0x04;
+pub const CMD_CFGI_CD: u64 = 0x05;
+pub const CMD_TLBI_NH_ASID: u64 = 0x11;
+pub const CMD_TLBI_NH_VA: u64 = 0x12;
+pub const CMD_TLBI_NSNH_ALL: u64 = 0x30;
+pub const CMD_SYNC: u64 = 0x46;
+pub const CMD_SID_SHIFT: u64 = 32;
+pub const CMD_ASID_SHIFT: u64 = 48;
+pub const CMD_LEAF: u64 = 1 << 0;
+pub const CMD_CFGI_ALL_RANGE: u64 = 31;
+
+pub const EVT_ID_MASK: u64 = 0xff;
+pub const EVT_SID_SHIFT: u64 = 32;
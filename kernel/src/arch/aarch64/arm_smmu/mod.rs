@@ -0,0 +1,91 @@
+pub mod consts;
+pub mod smmu_v3;
+
+use core::ptr::NonNull;
+pub use smmu_v3::*;
+use tock_registers::register_structs;
+use tock_registers::registers::{ReadOnly, ReadWrite};
+
+register_structs! {
+    /// SMMUv3 registers in page 0.
+    #[allow(non_snake_case)]
+    pub SmmuRegs {
+        (0x0000 => idr0: ReadOnly<u32>),
+        (0x0004 => idr1: ReadOnly<u32>),
+        (0x0008 => _reserved_1),
+        (0x0020 => cr0: ReadWrite<u32>),
+        (0x0024 => cr0ack: ReadOnly<u32>),
+        (0x0028 => cr1: ReadWrite<u32>),
+        (0x002c => cr2: ReadWrite<u32>),
+        (0x0030 => _reserved_2),
+        (0x0044 => gbpa: ReadWrite<u32>),
+        (0x0048 => _reserved_3),
+        (0x0050 => irq_ctrl: ReadWrite<u32>),
+        (0x0054 => irq_ctrlack: ReadOnly<u32>),
+        (0x0058 => _reserved_4),
+        (0x0060 => gerror: ReadOnly<u32>),
+        (0x0064 => gerrorn: ReadWrite<u32>),
+        (0x0068 => _reserved_5),
+        (0x0080 => strtab_base: ReadWrite<u64>),
+        (0x0088 => strtab_base_cfg: ReadWrite<u32>),
+        (0x008c => _reserved_6),
+        (0x0090 => cmdq_base: ReadWrite<u64>),
+        (0x0098 => cmdq_prod: ReadWrite<u32>),
+        (0x009c => cmdq_cons: ReadOnly<u32>),
+        (0x00a0 => eventq_base: ReadWrite<u64>),
+        (0x00a8 => _reserved_7),
+        (0x1000 => @END),
+    }
+}
+
+register_structs! {
+    /// SMMUv3 registers in page 1.
+    #[allow(non_snake_case)]
+    pub SmmuPage1Regs {
+        (0x0000 => _reserved_1),
+        (0x00a8 => eventq_prod: ReadOnly<u32>),
+        (0x00ac => eventq_cons: ReadWrite<u32>),
+        (0x00b0 => _reserved_2),
+        (0x1000 => @END),
+    }
+}
+
+pub struct SmmuMap {
+    base: NonNull<SmmuRegs>,
+}
+
+pub struct SmmuPage1Map {
+    base: NonNull<SmmuPage1Regs>,
+}
+
+unsafe impl Send for SmmuMap {}
+unsafe impl Sync for SmmuMap {}
+
+unsafe impl Send for SmmuPage1Map {}
+unsafe impl Sync for SmmuPage1Map {}
+
+impl SmmuMap {
+    /// Construct a new SMMU instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &SmmuRegs {
+        unsafe { self.base.as_ref() }
+    }
+}
+
+impl SmmuPage1Map {
+    /// Construct a new SMMU page 1 instance from the base address.
+    pub const fn new(base: *mut u8) -> Self {
+        Self {
+            base: NonNull::new(base).unwrap().cast(),
+        }
+    }
+
+    pub const fn regs(&self) -> &SmmuPage1Regs {
+        unsafe { self.base.as_ref() }
+    }
+}
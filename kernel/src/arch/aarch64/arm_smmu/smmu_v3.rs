@@ -0,0 +1,260 @@
+use super::consts::*;
+use super::{SmmuMap, SmmuPage1Map};
+use aarch64_cpu::asm::barrier::{self, dsb};
+use aarch64_cpu::registers::{Readable, MAIR_EL1};
+use sel4_common::arch::config::PA_SIZE_BITS;
+use sel4_vspace::kpptr_to_paddr;
+use tock_registers::interfaces::Writeable;
+
+static SMMU: SmmuMap = SmmuMap::new(SMMU_V3_PPTR as *mut u8);
+static SMMU_PAGE1: SmmuPage1Map = SmmuPage1Map::new(SMMU_V3_PAGE1_PPTR as *mut u8);
+
+/// The linear stream table, one STE per stream ID.
+#[repr(C, align(16384))]
+struct StreamTable([[u64; SMMU_STE_WORDS]; SMMU_MAX_SID]);
+
+/// One context descriptor per `ARMCB`, an STE points to the CD of its bound CB.
+#[repr(C, align(64))]
+struct ContextDescTable([[u64; SMMU_CD_WORDS]; SMMU_MAX_CB]);
+
+#[repr(C, align(4096))]
+struct CommandQueue([[u64; SMMU_CMD_WORDS]; bit!(SMMU_CMDQ_BITS)]);
+
+#[repr(C, align(4096))]
+struct EventQueue([[u64; SMMU_EVT_WORDS]; bit!(SMMU_EVTQ_BITS)]);
+
+static mut STRTAB: StreamTable = StreamTable([[0; SMMU_STE_WORDS]; SMMU_MAX_SID]);
+static mut CD_TABLE: ContextDescTable = ContextDescTable([[0; SMMU_CD_WORDS]; SMMU_MAX_CB]);
+static mut CMDQ: CommandQueue = CommandQueue([[0; SMMU_CMD_WORDS]; bit!(SMMU_CMDQ_BITS)]);
+static mut EVTQ: EventQueue = EventQueue([[0; SMMU_EVT_WORDS]; bit!(SMMU_EVTQ_BITS)]);
+
+/// The queue sizes actually used, capped by SMMU_IDR1.
+static mut CMDQ_BITS: usize = SMMU_CMDQ_BITS;
+static mut EVTQ_BITS: usize = SMMU_EVTQ_BITS;
+
+/// The MAIR_EL1 of the kernel, the SMMU walks the user page tables with the same attributes.
+static mut SMMU_MAIR: u64 = 0;
+
+/// The index and the wrap bit of a queue pointer.
+#[inline]
+fn queue_idx(ptr: u32, bits: usize) -> usize {
+    ptr as usize & mask_bits!(bits)
+}
+
+#[inline]
+fn queue_wrap(ptr: u32, bits: usize) -> bool {
+    ptr as usize & bit!(bits) != 0
+}
+
+#[inline]
+fn queue_inc(ptr: u32, bits: usize) -> u32 {
+    ((ptr as usize + 1) & mask_bits!(bits + 1)) as u32
+}
+
+fn write_cr0(val: u32) {
+    SMMU.regs().cr0.set(val);
+    while SMMU.regs().cr0ack.get() != val {}
+}
+
+fn submit_cmd(w0: u64, w1: u64) {
+    let bits = unsafe { CMDQ_BITS };
+    let prod = SMMU.regs().cmdq_prod.get();
+    // Wait for a free entry, the queue is full when only the wrap bits differ.
+    loop {
+        let cons = SMMU.regs().cmdq_cons.get();
+        if queue_idx(prod, bits) != queue_idx(cons, bits)
+            || queue_wrap(prod, bits) == queue_wrap(cons, bits)
+        {
+            break;
+        }
+    }
+    unsafe {
+        CMDQ.0[queue_idx(prod, bits)] = [w0, w1];
+    }
+    dsb(barrier::ISH);
+    SMMU.regs().cmdq_prod.set(queue_inc(prod, bits));
+}
+
+/// Issue CMD_SYNC and wait until the SMMU has consumed every command before it.
+fn sync() {
+    submit_cmd(CMD_SYNC, 0);
+    let prod = SMMU.regs().cmdq_prod.get();
+    let bits = unsafe { CMDQ_BITS };
+    while queue_idx(SMMU.regs().cmdq_cons.get(), bits) != queue_idx(prod, bits) {}
+}
+
+/// Reset the SMMU with every stream aborting, the stream IDs are opened up by `ARMSIDBindCB`.
+pub fn smmu_init() {
+    let idr0 = SMMU.regs().idr0.get();
+    let idr1 = SMMU.regs().idr1.get();
+    if idr0 & IDR0_S1P == 0 || idr0 & IDR0_TTF_AARCH64 == 0 {
+        log::warn!("SMMUv3: stage 1 AArch64 translation is not supported");
+        return;
+    }
+    if ((idr1 & IDR1_SIDSIZE_MASK) as usize) < SMMU_SID_BITS {
+        log::warn!("SMMUv3: only {} stream ID bits", idr1 & IDR1_SIDSIZE_MASK);
+    }
+    unsafe {
+        CMDQ_BITS = SMMU_CMDQ_BITS.min(((idr1 >> IDR1_CMDQS_SHIFT) & IDR1_QS_MASK) as usize);
+        EVTQ_BITS = SMMU_EVTQ_BITS.min(((idr1 >> IDR1_EVENTQS_SHIFT) & IDR1_QS_MASK) as usize);
+        SMMU_MAIR = MAIR_EL1.get();
+    }
+
+    // Abort all the incoming transactions while the SMMU is being set up.
+    write_cr0(0);
+    SMMU.regs().gbpa.set(GBPA_UPDATE | GBPA_ABORT);
+    while SMMU.regs().gbpa.get() & GBPA_UPDATE != 0 {}
+
+    SMMU.regs().cr1.set(
+        CR1_TABLE_SH_ISH
+            | CR1_TABLE_OC_WB
+            | CR1_TABLE_IC_WB
+            | CR1_QUEUE_SH_ISH
+            | CR1_QUEUE_OC_WB
+            | CR1_QUEUE_IC_WB,
+    );
+    SMMU.regs().cr2.set(CR2_PTM | CR2_RECINVSID);
+
+    // A valid STE with the abort config for every stream ID.
+    for sid in 0..SMMU_MAX_SID {
+        write_ste(sid, None);
+    }
+    let strtab = kpptr_to_paddr(&raw const STRTAB as usize).as_u64();
+    SMMU.regs().strtab_base.set(STRTAB_BASE_RA | strtab);
+    SMMU.regs()
+        .strtab_base_cfg
+        .set(STRTAB_BASE_CFG_FMT_LINEAR | SMMU_SID_BITS as u32);
+
+    let cmdq = kpptr_to_paddr(&raw const CMDQ as usize).as_u64();
+    SMMU.regs()
+        .cmdq_base
+        .set(Q_BASE_RWA | (cmdq & Q_BASE_ADDR_MASK) | unsafe { CMDQ_BITS } as u64);
+    SMMU.regs().cmdq_prod.set(0);
+    let evtq = kpptr_to_paddr(&raw const EVTQ as usize).as_u64();
+    SMMU.regs()
+        .eventq_base
+        .set(Q_BASE_RWA | (evtq & Q_BASE_ADDR_MASK) | unsafe { EVTQ_BITS } as u64);
+    SMMU_PAGE1.regs().eventq_cons.set(0);
+    dsb(barrier::SY);
+
+    write_cr0(CR0_CMDQEN);
+    submit_cmd(CMD_CFGI_ALL, CMD_CFGI_ALL_RANGE);
+    submit_cmd(CMD_TLBI_NSNH_ALL, 0);
+    sync();
+
+    write_cr0(CR0_CMDQEN | CR0_EVENTQEN);
+    // Fault events are delivered through the event queue and the global error IRQs,
+    // which user level claims with IRQControl and binds to notifications.
+    SMMU.regs()
+        .irq_ctrl
+        .set(IRQ_CTRL_EVENTQ_IRQEN | IRQ_CTRL_GERROR_IRQEN);
+    while SMMU.regs().irq_ctrlack.get() != IRQ_CTRL_EVENTQ_IRQEN | IRQ_CTRL_GERROR_IRQEN {}
+    write_cr0(CR0_CMDQEN | CR0_EVENTQEN | CR0_SMMUEN);
+    log::info!(
+        "SMMUv3: {} stream IDs, {} context banks",
+        SMMU_MAX_SID,
+        SMMU_MAX_CB
+    );
+}
+
+/// Point the STE of `sid` to the CD of `cb`, or make it abort all the transactions.
+pub fn write_ste(sid: usize, cb: Option<usize>) {
+    let ste = unsafe { &mut *(&raw mut STRTAB.0[sid]) };
+    *ste = [0; SMMU_STE_WORDS];
+    match cb {
+        Some(cb) => {
+            let cd = kpptr_to_paddr(unsafe { &raw const CD_TABLE.0[cb] } as usize).as_u64();
+            ste[1] = STE_1_S1CIR_WBRA | STE_1_S1COR_WBRA | STE_1_S1CSH_ISH | STE_1_SHCFG_INCOMING;
+            // Word 0 holds the valid bit, write it last.
+            dsb(barrier::ISH);
+            ste[0] = STE_0_V | STE_0_CFG_S1_TRANS | (cd & STE_0_S1CTXPTR_MASK);
+        }
+        None => ste[0] = STE_0_V | STE_0_CFG_ABORT,
+    }
+    dsb(barrier::ISH);
+}
+
+/// Invalidate the cached STE of `sid` after `write_ste`.
+pub fn invalidate_ste(sid: usize) {
+    submit_cmd(CMD_CFGI_STE | ((sid as u64) << CMD_SID_SHIFT), CMD_LEAF);
+    sync();
+}
+
+/// Make the CD of `cb` translate with the user page table `vspace_root`.
+pub fn write_cd(cb: usize, asid: usize, vspace_root: usize) {
+    let ips: u64 = if PA_SIZE_BITS == 44 { 0b100 } else { 0b010 };
+    let cd = unsafe { &mut *(&raw mut CD_TABLE.0[cb]) };
+    *cd = [0; SMMU_CD_WORDS];
+    cd[1] = kpptr_to_paddr(vspace_root).as_u64() & CD_1_TTB0_MASK;
+    cd[3] = unsafe { SMMU_MAIR };
+    dsb(barrier::ISH);
+    cd[0] = CD_0_TCR_T0SZ
+        | CD_0_TCR_IRGN0_WBWA
+        | CD_0_TCR_ORGN0_WBWA
+        | CD_0_TCR_SH0_ISH
+        | CD_0_TCR_EPD1
+        | CD_0_V
+        | (ips << CD_0_TCR_IPS_SHIFT)
+        | CD_0_AA64
+        | CD_0_R
+        | CD_0_A
+        | CD_0_ASET
+        | ((asid as u64) << CD_0_ASID_SHIFT);
+    dsb(barrier::ISH);
+}
+
+pub fn clear_cd(cb: usize) {
+    unsafe {
+        CD_TABLE.0[cb] = [0; SMMU_CD_WORDS];
+    }
+    dsb(barrier::ISH);
+}
+
+/// Invalidate the cached CDs of `sid` after `write_cd` or `clear_cd`.
+pub fn invalidate_cd(sid: usize) {
+    submit_cmd(CMD_CFGI_CD | ((sid as u64) << CMD_SID_SHIFT), CMD_LEAF);
+    sync();
+}
+
+pub fn tlb_invalidate_all() {
+    submit_cmd(CMD_TLBI_NSNH_ALL, 0);
+    sync();
+}
+
+pub fn tlb_invalidate_asid(asid: usize) {
+    submit_cmd(CMD_TLBI_NH_ASID | ((asid as u64) << CMD_ASID_SHIFT), 0);
+    sync();
+}
+
+pub fn tlb_invalidate_va(asid: usize, vaddr: usize) {
+    submit_cmd(
+        CMD_TLBI_NH_VA | ((asid as u64) << CMD_ASID_SHIFT),
+        (vaddr as u64 & !0xfff) | CMD_LEAF,
+    );
+    sync();
+}
+
+/// Pop the oldest record from the event queue.
+pub fn read_event() -> Option<[u64; SMMU_EVT_WORDS]> {
+    let bits = unsafe { EVTQ_BITS };
+    let prod = SMMU_PAGE1.regs().eventq_prod.get();
+    let cons = SMMU_PAGE1.regs().eventq_cons.get();
+    if queue_idx(prod, bits) == queue_idx(cons, bits)
+        && queue_wrap(prod, bits) == queue_wrap(cons, bits)
+    {
+        return None;
+    }
+    dsb(barrier::SY);
+    let event = unsafe { EVTQ.0[queue_idx(cons, bits)] };
+    SMMU_PAGE1.regs().eventq_cons.set(queue_inc(cons, bits));
+    Some(event)
+}
+
+/// The global errors that are active and not yet acknowledged.
+pub fn global_error() -> u32 {
+    SMMU.regs().gerror.get() ^ SMMU.regs().gerrorn.get()
+}
+
+pub fn clear_global_error() {
+    SMMU.regs().gerrorn.set(SMMU.regs().gerror.get());
+}
@@ -20,6 +20,8 @@ use crate::{
 
 use sel4_common::sel4_config::{BI_FRAME_SIZE_BITS, USER_TOP};
 
+#[cfg(feature = "arm_smmu")]
+use super::arm_smmu::smmu_init;
 use super::platform::init_irq_controller;
 use crate::interrupt::intStateIRQNodeToR;
 #[cfg(feature = "enable_smp")]
@@ -73,6 +75,13 @@ pub fn try_init_kernel(
 
     // Map kernel window area
     rust_map_kernel_window();
+    // The DTB is read through the kernel window, and the devices and the timer frequency
+    // it gives are taken before init_cpu sets them up.
+    #[cfg(feature = "dtb_platform")]
+    {
+        sel4_vspace::activate_kernel_vspace();
+        crate::boot::discover::discover_platform(dtb_phys_addr, dtb_size);
+    }
 
     // Initialize cpu
     let inited = init_cpu();
@@ -90,6 +99,8 @@ pub fn try_init_kernel(
         return false;
     }
 
+    crate::boot::cmdline::parse_bootargs(dtb_phys_addr, dtb_size);
+
     let extra_bi_size_bits = calculate_extra_bi_size_bits(extra_bi_size);
 
     let it_v_reg = VRegion {
@@ -177,6 +188,11 @@ pub fn try_init_kernel_secondary_core(_hartid: usize, _core_id: usize) -> bool {
     set_irq_state_by_irq(IRQState::IRQIPI, IRQ_REMOTE_CALL_IPI);
     set_irq_state_by_irq(IRQState::IRQIPI, IRQ_RESCHEDULE_IPI);
     set_irq_state_by_irq(IRQState::IRQTimer, KERNEL_TIMER_IRQ);
+    #[cfg(feature = "hypervisor")]
+    set_irq_state_by_irq(
+        IRQState::IRQReserved,
+        sel4_common::platform::INTERRUPT_VTIMER_EVENT,
+    );
 
     clh_lock_acquire(cpu_id(), false);
     ksNumCPUs.lock().add_assign(1);
@@ -193,11 +209,12 @@ pub fn try_init_kernel_secondary_core(_hartid: usize, _core_id: usize) -> bool {
 
 #[cfg(feature = "enable_smp")]
 pub(crate) fn release_secondary_cpus() {
-    use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
     *node_boot_lock.lock() = 1;
-    while ksNumCPUs.lock().ne(&CONFIG_MAX_NUM_NODES) {}
+    while ksNumCPUs.lock().ne(&crate::boot::num_boot_cpus()) {}
 }
 
 fn init_plat() {
-    init_irq_controller()
+    init_irq_controller();
+    #[cfg(feature = "arm_smmu")]
+    smmu_init();
 }
@@ -1,11 +1,12 @@
 use crate::interrupt::handler::handle_interrupt_entry;
 use crate::syscall::slowpath;
 use core::arch::asm;
+use sel4_common::kernel_state::kernel_entry_t;
 
 #[cfg(feature = "enable_smp")]
 use crate::{
     interrupt::get_active_irq,
-    smp::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_release},
+    smp::{clh_lock_acquire, clh_lock_release, kernel_lock_release_if_held, syscall_lock_acquire},
 };
 
 #[cfg(feature = "enable_smp")]
@@ -37,9 +38,7 @@ pub fn restore_user_context() {
     // #endif /* CONFIG_HAVE_FPU */
     unsafe {
         #[cfg(feature = "enable_smp")]
-        if clh_is_self_in_queue() {
-            clh_lock_release(cpu_id());
-        }
+        kernel_lock_release_if_held(cpu_id());
 
         #[cfg(feature = "have_fpu")]
         lazy_fpu_restore(get_currenct_thread());
@@ -107,6 +106,20 @@ pub fn fastpath_restore(_badge: usize, _msgInfo: usize, cur_thread: *mut tcb_t)
         {
             lazy_fpu_restore(get_currenct_thread());
         }
+        #[cfg(feature = "hypervisor")]
+        macro_rules! restore {
+            () => {
+                r#"msr elr_el2, x22
+                msr spsr_el2, x23"#
+            };
+        }
+        #[cfg(not(feature = "hypervisor"))]
+        macro_rules! restore {
+            () => {
+                r#"msr elr_el1, x22
+                msr spsr_el1, x23"#
+            };
+        }
         asm!(
             "mov     x0, {0}                     \n",
             "mov     x1, {1}                     \n",
@@ -115,13 +128,7 @@ pub fn fastpath_restore(_badge: usize, _msgInfo: usize, cur_thread: *mut tcb_t)
             "ldp     x21, x22, [sp, #31 * 8]  \n",
             "ldr     x23, [sp, #33 * 8]     \n",
             "msr     sp_el0, x21                \n",
-            // #ifdef CONFIG_ARM_HYPERVISOR_SUPPORT
-            // 		"msr     elr_el2, x22               \n"
-            // 		"msr     spsr_el2, x23              \n"
-            // #else
-            "msr     elr_el1, x22               \n",
-            "msr     spsr_el1, x23              \n",
-            // #endif
+            restore!(),
 
             /* Restore remaining registers */
             "ldp     x2,  x3,  [sp, #16 * 1]    \n",
@@ -163,20 +170,22 @@ pub fn c_handle_interrupt() {
     }
     entry_hook();
 
-    handle_interrupt_entry();
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    handle_interrupt_entry(entry.state());
     restore_user_context();
 }
 
 #[no_mangle]
 pub fn c_handle_syscall(_cptr: usize, _msgInfo: usize, syscall: usize) {
     #[cfg(feature = "enable_smp")]
-    clh_lock_acquire(cpu_id(), false);
+    syscall_lock_acquire(cpu_id(), syscall);
     entry_hook();
     // if hart_id() == 0 {
     //     debug!("c_handle_syscall: syscall: {},", syscall as isize);
     // }
     // sel4_common::println!("c handle syscall");
-    slowpath(syscall);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    slowpath(syscall, entry.state());
     // debug!("c_handle_syscall complete");
 }
 
@@ -194,7 +203,8 @@ pub fn c_handle_fastpath_call(cptr: usize, msgInfo: usize) -> ! {
     clh_lock_acquire(cpu_id(), false);
     entry_hook();
     use crate::kernel::fastpath::fastpath_call;
-    fastpath_call(cptr, msgInfo);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    fastpath_call(cptr, msgInfo, entry.state());
     unreachable!()
 }
 
@@ -205,7 +215,8 @@ pub fn c_handle_fastpath_reply_recv(cptr: usize, msgInfo: usize) -> ! {
     #[cfg(feature = "enable_smp")]
     clh_lock_acquire(cpu_id(), false);
     entry_hook();
-    crate::kernel::fastpath::fastpath_reply_recv(cptr, msgInfo);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    crate::kernel::fastpath::fastpath_reply_recv(cptr, msgInfo, entry.state());
     unreachable!()
 }
 
@@ -216,7 +227,8 @@ pub fn c_handle_fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize) -
     #[cfg(feature = "enable_smp")]
     clh_lock_acquire(cpu_id(), false);
     entry_hook();
-    crate::kernel::fastpath::fastpath_reply_recv(cptr, msgInfo, reply);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    crate::kernel::fastpath::fastpath_reply_recv(cptr, msgInfo, reply, entry.state());
     unreachable!()
 }
 
@@ -231,7 +243,8 @@ pub fn c_handle_undefined_instruction() -> ! {
 
     // Only support aarch64
     // No hypervisor support
-    super::exception::handleUserLevelFault(get_esr(), 0);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    super::exception::handleUserLevelFault(get_esr(), 0, entry.state());
     restore_user_context();
     unreachable!()
 }
@@ -244,3 +257,25 @@ pub fn c_handle_enfp() -> ! {
     restore_user_context();
     unreachable!()
 }
+
+/// The first use of SVE by a thread, which loads its SVE state. A thread that has not
+/// been configured with `ARMTCBSetSVE` gets an undefined instruction fault.
+#[cfg(feature = "arm_sve")]
+#[no_mangle]
+pub fn c_handle_sve() -> ! {
+    use super::fpu::{handle_fpu_fault, thread_uses_sve};
+    use crate::arch::aarch64::instruction::get_esr;
+
+    #[cfg(feature = "enable_smp")]
+    clh_lock_acquire(cpu_id(), false);
+    entry_hook();
+
+    if thread_uses_sve(get_currenct_thread()) {
+        unsafe { handle_fpu_fault() };
+    } else {
+        let mut entry = unsafe { kernel_entry_t::enter() };
+        super::exception::handleUserLevelFault(get_esr(), 0, entry.state());
+    }
+    restore_user_context();
+    unreachable!()
+}
@@ -17,7 +17,9 @@ use crate::syscall::{
 use aarch64_cpu::registers::{self, Readable};
 use log::debug;
 use sel4_common::arch::ArchReg::{self, *};
-use sel4_common::ffi::current_fault;
+#[cfg(feature = "build_binary")]
+use sel4_common::kernel_state::kernel_entry_t;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::platform::timer;
 use sel4_common::platform::Timer_func;
 use sel4_common::print;
@@ -26,8 +28,9 @@ use sel4_common::structures::exception_t;
 use sel4_common::structures_gen::cap_tag;
 use sel4_common::structures_gen::seL4_Fault_UnknownSyscall;
 use sel4_common::structures_gen::seL4_Fault_UserException;
+#[cfg(feature = "hypervisor")]
+use sel4_common::structures_gen::seL4_Fault_VCPUFault;
 use sel4_common::structures_gen::seL4_Fault_VMFault;
-use sel4_common::utils::global_read;
 use sel4_task::{activateThread, get_currenct_thread, get_current_domain, schedule};
 #[cfg(feature = "kernel_mcs")]
 use sel4_task::{check_budget_restart, update_timestamp};
@@ -41,7 +44,7 @@ use crate::smp::clh_lock_acquire;
 use sel4_common::utils::cpu_id;
 
 #[no_mangle]
-pub fn handle_unknown_syscall(w: isize) -> exception_t {
+pub fn handle_unknown_syscall(w: isize, ctx: &mut kernel_state_t) -> exception_t {
     let thread = get_currenct_thread();
     if w == SYS_DEBUG_PUT_CHAR {
         print!("{}", thread.tcbArch.get_register(Cap) as u8 as char);
@@ -95,6 +98,16 @@ pub fn handle_unknown_syscall(w: isize) -> exception_t {
         // setThreadName(TCB_PTR(cap_thread_cap_get_capTCBPtr(lu_ret.cap)), name);
         return exception_t::EXCEPTION_NONE;
     }
+    #[cfg(feature = "clh_lock_stats")]
+    if w == crate::syscall::SYS_DEBUG_LOCK_STATS {
+        crate::smp::handle_debug_lock_stats(thread);
+        return exception_t::EXCEPTION_NONE;
+    }
+    #[cfg(feature = "irq_stats")]
+    if w == crate::syscall::SYS_DEBUG_IRQ_STATS {
+        crate::interrupt::stats::handle_debug_irq_stats(thread);
+        return exception_t::EXCEPTION_NONE;
+    }
     if w == SYS_GET_CLOCK {
         /*no implementation of aarch64 get clock*/
         let current = timer.get_current_time();
@@ -102,17 +115,17 @@ pub fn handle_unknown_syscall(w: isize) -> exception_t {
         return exception_t::EXCEPTION_NONE;
     }
     #[cfg(not(feature = "kernel_mcs"))]
-    unsafe {
-        current_fault = seL4_Fault_UnknownSyscall::new(w as u64).unsplay();
-        handle_fault(get_currenct_thread());
+    {
+        ctx.fault = seL4_Fault_UnknownSyscall::new(w as u64).unsplay();
+        handle_fault(get_currenct_thread(), ctx);
     }
     #[cfg(feature = "kernel_mcs")]
     {
         update_timestamp();
         if likely(check_budget_restart()) {
-            unsafe {
-                current_fault = seL4_Fault_UnknownSyscall::new(w as u64).unsplay();
-                handle_fault(get_currenct_thread());
+            {
+                ctx.fault = seL4_Fault_UnknownSyscall::new(w as u64).unsplay();
+                handle_fault(get_currenct_thread(), ctx);
             }
         }
     }
@@ -122,21 +135,21 @@ pub fn handle_unknown_syscall(w: isize) -> exception_t {
 }
 
 #[no_mangle]
-pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
+pub fn handleUserLevelFault(w_a: usize, w_b: usize, ctx: &mut kernel_state_t) -> exception_t {
     #[cfg(feature = "kernel_mcs")]
     {
         update_timestamp();
         if likely(check_budget_restart()) {
-            unsafe {
-                current_fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
-                handle_fault(get_currenct_thread());
+            {
+                ctx.fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
+                handle_fault(get_currenct_thread(), ctx);
             }
         }
     }
     #[cfg(not(feature = "kernel_mcs"))]
-    unsafe {
-        current_fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
-        handle_fault(get_currenct_thread());
+    {
+        ctx.fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
+        handle_fault(get_currenct_thread(), ctx);
     }
     schedule();
     activateThread();
@@ -144,22 +157,22 @@ pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
 }
 
 #[no_mangle]
-pub fn handleVMFaultEvent(vm_faultType: usize) -> exception_t {
+pub fn handleVMFaultEvent(vm_faultType: usize, ctx: &mut kernel_state_t) -> exception_t {
     #[cfg(feature = "kernel_mcs")]
     {
         update_timestamp();
         if likely(check_budget_restart()) {
-            let status = handle_vm_fault(vm_faultType);
+            let status = handle_vm_fault(vm_faultType, ctx);
             if status != exception_t::EXCEPTION_NONE {
-                handle_fault(get_currenct_thread());
+                handle_fault(get_currenct_thread(), ctx);
             }
         }
     }
     #[cfg(not(feature = "kernel_mcs"))]
     {
-        let status = handle_vm_fault(vm_faultType);
+        let status = handle_vm_fault(vm_faultType, ctx);
         if status != exception_t::EXCEPTION_NONE {
-            handle_fault(get_currenct_thread());
+            handle_fault(get_currenct_thread(), ctx);
         }
     }
 
@@ -169,7 +182,7 @@ pub fn handleVMFaultEvent(vm_faultType: usize) -> exception_t {
     exception_t::EXCEPTION_NONE
 }
 
-pub fn handle_vm_fault(type_: usize) -> exception_t {
+pub fn handle_vm_fault(type_: usize, ctx: &mut kernel_state_t) -> exception_t {
     /*
     exception_t handleVMFault(tcb_t *thread, vm_fault_type_t vm_faultType)
     {
@@ -212,21 +225,16 @@ pub fn handle_vm_fault(type_: usize) -> exception_t {
             let addr = get_far();
             let fault = get_esr();
             log::debug!("fault addr: {:#x} esr: {:#x}", addr, fault);
-            unsafe {
-                current_fault = seL4_Fault_VMFault::new(addr as u64, fault as u64, 0)
-                    .unsplay()
-                    .clone();
-            }
-            let current_fault_cpy = unsafe { current_fault.clone() };
-            log::debug!("current_fault: {:#x?}", global_read!(current_fault_cpy));
+            ctx.fault = seL4_Fault_VMFault::new(addr as u64, fault as u64, 0)
+                .unsplay()
+                .clone();
+            log::debug!("current_fault: {:#x?}", ctx.fault);
             exception_t::EXCEPTION_FAULT
         }
         ARM_PREFETCH_ABORT => {
             let pc = get_currenct_thread().tcbArch.get_register(ArchReg::FaultIP);
             let fault = get_esr();
-            unsafe {
-                current_fault = seL4_Fault_VMFault::new(pc as u64, fault as u64, 1).unsplay();
-            }
+            ctx.fault = seL4_Fault_VMFault::new(pc as u64, fault as u64, 1).unsplay();
 
             #[cfg(not(feature = "hypervisor"))]
             log::debug!("ttbr0_el1: {:#x?}", registers::TTBR0_EL1.get());
@@ -245,7 +253,8 @@ pub fn c_handle_vm_fault(type_: usize) -> ! {
     #[cfg(feature = "enable_smp")]
     clh_lock_acquire(cpu_id(), false);
     entry_hook();
-    handleVMFaultEvent(type_);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    handleVMFaultEvent(type_, entry.state());
     restore_user_context();
     unreachable!()
 }
@@ -262,8 +271,48 @@ pub fn c_handle_instruction_fault() -> ! {
     c_handle_vm_fault(INSTRUCTION_FAULT)
 }
 
+/// Handle the traps of a VCPU thread that the kernel does not resolve itself.
+/// Return true if the trap has been handled and no fault needs to be delivered.
+#[cfg(feature = "hypervisor")]
+fn armv_handle_vcpu_fault(_hsr: usize) -> bool {
+    // EC 0x07: Access to SIMD or floating-point registers
+    #[cfg(feature = "have_fpu")]
+    if (_hsr >> 26) & 0x3f == 0x07 && unsafe { !crate::arch::fpu::is_fpu_enable() } {
+        unsafe { crate::arch::fpu::handle_fpu_fault() };
+        return true;
+    }
+    false
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn handle_vcpu_fault(hsr: usize, ctx: &mut kernel_state_t) -> exception_t {
+    #[cfg(feature = "kernel_mcs")]
+    {
+        update_timestamp();
+        if likely(check_budget_restart()) && !armv_handle_vcpu_fault(hsr) {
+            ctx.fault = seL4_Fault_VCPUFault::new(hsr as u64).unsplay();
+            handle_fault(get_currenct_thread(), ctx);
+        }
+    }
+    #[cfg(not(feature = "kernel_mcs"))]
+    if !armv_handle_vcpu_fault(hsr) {
+        ctx.fault = seL4_Fault_VCPUFault::new(hsr as u64).unsplay();
+        handle_fault(get_currenct_thread(), ctx);
+    }
+    schedule();
+    activateThread();
+    exception_t::EXCEPTION_NONE
+}
+
 #[no_mangle]
-pub fn c_handle_vcpu_fault(hsr: usize) {
+#[cfg(all(feature = "hypervisor", feature = "build_binary"))]
+pub fn c_handle_vcpu_fault(hsr: usize) -> ! {
+    #[cfg(feature = "enable_smp")]
+    clh_lock_acquire(cpu_id(), false);
+    entry_hook();
     log::debug!("handle vcpu fault hsr: {:#x}", hsr);
-    loop {}
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    handle_vcpu_fault(hsr, entry.state());
+    restore_user_context();
+    unreachable!()
 }
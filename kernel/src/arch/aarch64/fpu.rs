@@ -6,15 +6,31 @@ use core::{
 };
 
 use sel4_common::{
-    arch::arch_tcb::FPUState, sel4_config::CONFIG_FPU_MAX_RESTORES_SINCE_SWITCH, utils::cpu_id,
+    arch::arch_tcb::FPUState,
+    sel4_config::{CONFIG_FPU_MAX_RESTORES_SINCE_SWITCH, CONFIG_MAX_NUM_NODES},
+    utils::cpu_id,
 };
-use sel4_task::{get_currenct_thread, tcb_t, NODE_STATE, SET_NODE_STATE};
+use sel4_task::{get_currenct_thread, tcb_t, NODE_STATE, NODE_STATE_ON_CORE, SET_NODE_STATE};
+
+#[cfg(feature = "arm_sve")]
+use core::mem::size_of;
+#[cfg(feature = "arm_sve")]
+use sel4_common::{
+    sel4_config::{SEL4_TCB_BITS, TCB_SVE_STATE},
+    structures_gen::{cap, cap_tag},
+    utils::convert_to_type_ref,
+};
+#[cfg(feature = "arm_sve")]
+use sel4_cspace::interface::cte_t;
 
 #[cfg(feature = "enable_smp")]
 use crate::smp::ipi::remote_switch_fpu_owner;
 
-// TODO: support smp
-static mut is_fpu_enabled_cached: bool = false;
+#[cfg(all(feature = "arm_sve", feature = "hypervisor"))]
+compile_error!("SVE is only supported without hypervisor");
+
+/// Whether the FPU is enabled for the thread running on each core.
+static mut is_fpu_enabled_cached: [bool; CONFIG_MAX_NUM_NODES] = [false; CONFIG_MAX_NUM_NODES];
 
 core::arch::global_asm!(include_str!("fpu.S"));
 extern "C" {
@@ -22,24 +38,73 @@ extern "C" {
     pub fn load_fpu_state(src: usize, src_fpsr: usize);
 }
 
+#[cfg(feature = "arm_sve")]
+core::arch::global_asm!(include_str!("sve.S"));
+#[cfg(feature = "arm_sve")]
+extern "C" {
+    fn save_sve_state(dest: usize);
+    fn load_sve_state(src: usize);
+}
+
+/// The frame the SVE registers of the owner of `fpu_state` are saved to, and its vector
+/// length. `None` if the thread does not use SVE or its frame has been deleted.
+#[cfg(feature = "arm_sve")]
+fn sve_state(fpu_state: usize) -> Option<(usize, usize)> {
+    let vl = unsafe { (*(fpu_state as *const FPUState)).sve_vl() };
+    let tcb_cnode = fpu_state & !mask_bits!(SEL4_TCB_BITS);
+    let slot = convert_to_type_ref::<cte_t>(tcb_cnode + TCB_SVE_STATE * size_of::<cte_t>());
+    if vl == 0 || slot.capability.get_tag() != cap_tag::cap_frame_cap {
+        return None;
+    }
+    Some((
+        cap::cap_frame_cap(&slot.capability).get_capFBasePtr() as usize,
+        vl,
+    ))
+}
+
+#[cfg(feature = "arm_sve")]
+pub fn thread_uses_sve(thread: &mut tcb_t) -> bool {
+    sve_state(thread.tcbArch.fpu_state_ptr() as usize).is_some()
+}
+
+/// Limit the vector length of EL0 and EL1 to `vl` bytes through `ZCR_EL1.LEN`.
+#[cfg(feature = "arm_sve")]
+#[inline]
+unsafe fn set_sve_vl(vl: usize) {
+    asm!("msr S3_0_C1_C2_0, {0}", "isb", in(reg) vl / 16 - 1);
+}
+
+/// Let EL0 use SVE through `CPACR_EL1.ZEN` when `enabled`, the first use traps
+/// otherwise. The kernel itself may always use SVE.
+#[cfg(feature = "arm_sve")]
+#[inline]
+pub(crate) unsafe fn set_sve_user_access(enabled: bool) {
+    let zen: usize = if enabled { 0x3 } else { 0x1 };
+    asm!(
+        "mrs {0}, cpacr_el1",
+        "bic {0}, {0}, #(0x3 << 16)",
+        "orr {0}, {0}, {1}",
+        "msr cpacr_el1, {0}",
+        "isb",
+        out(reg) _,
+        in(reg) zen << 16,
+    );
+}
+
+/// Stop trapping the FP/SIMD instructions, through `CPTR_EL2.TFP` and `TCPAC` when the
+/// kernel runs in EL2, `CPACR_EL1.FPEN` otherwise.
 #[inline]
 pub(crate) unsafe fn enable_fpu() -> usize {
-    // TODO: don't support EL2
     let mut cpacr: usize = 0;
     #[cfg(feature = "hypervisor")]
     asm!(
         "mrs {0}, cptr_el2",
-        "bic x8, x8, #(1 << 10)",
-        "bic x8, x8, #(1 << 31)",
+        "bic {0}, {0}, #(1 << 10)",
+        "bic {0}, {0}, #(1 << 31)",
         "msr cptr_el2, {0}",
         "isb",
         inout(reg) cpacr
     );
-    // {
-    //     asm!("mrs {0}, cptr_el2", out(reg) cpacr);
-    //     cpacr &= !((1 << 10) | (1 << 31));
-    //     asm!("msr cptr_el2, {0};isb", in(reg) cpacr);
-    // }
     #[cfg(not(feature = "hypervisor"))]
     asm!(
         "mrs {0}, cpacr_el1",
@@ -49,37 +114,37 @@ pub(crate) unsafe fn enable_fpu() -> usize {
         inout(reg) cpacr,
     );
 
-    is_fpu_enabled_cached = true;
+    is_fpu_enabled_cached[cpu_id()] = true;
     cpacr
 }
 
 #[inline]
 pub(crate) unsafe fn disable_fpu() {
     #[cfg(feature = "hypervisor")]
-    {
-        asm!(
-            "mrs x8, cptr_el2",
-            "orr x8, x8, #(1 << 10)",
-            "orr x8, x8, #(1 << 31)",
-            "msr cptr_el2, x8",
-            "isb",
-        );
-    }
+    asm!(
+        "mrs {0}, cptr_el2",
+        "orr {0}, {0}, #(1 << 10)",
+        "orr {0}, {0}, #(1 << 31)",
+        "msr cptr_el2, {0}",
+        "isb",
+        out(reg) _,
+    );
     #[cfg(not(feature = "hypervisor"))]
     asm!(
-        "mrs x8, cpacr_el1",
-        "bic x8, x8, #(0x3 << 20)",
-        "orr x8, x8, #(0x1 << 20)",
-        "msr cpacr_el1, x8",
-        "isb"
+        "mrs {0}, cpacr_el1",
+        "bic {0}, {0}, #(0x3 << 20)",
+        "orr {0}, {0}, #(0x1 << 20)",
+        "msr cpacr_el1, {0}",
+        "isb",
+        out(reg) _,
     );
-    is_fpu_enabled_cached = false
+    is_fpu_enabled_cached[cpu_id()] = false
 }
 
 #[inline]
 #[allow(unused)]
 pub unsafe fn is_fpu_enable() -> bool {
-    return is_fpu_enabled_cached;
+    return is_fpu_enabled_cached[cpu_id()];
 }
 
 #[inline]
@@ -90,6 +155,11 @@ pub unsafe fn switch_local_fpu_owner(new_owner: usize) {
         let ksActiveFPUState = NODE_STATE!(ksActiveFPUState);
         if ksActiveFPUState != 0 {
             save_fpu_state(ksActiveFPUState, ksActiveFPUState + 16 * 32);
+            // The Z registers contain the V registers, so they are saved last.
+            #[cfg(feature = "arm_sve")]
+            if let Some((frame, _)) = sve_state(ksActiveFPUState) {
+                save_sve_state(frame);
+            }
         }
 
         if new_owner != 0 {
@@ -98,6 +168,15 @@ pub unsafe fn switch_local_fpu_owner(new_owner: usize) {
                 new_owner as *const FPUState as usize,
                 new_owner as *const FPUState as usize + 16 * 32,
             );
+            #[cfg(feature = "arm_sve")]
+            {
+                let sve = sve_state(new_owner);
+                if let Some((frame, vl)) = sve {
+                    set_sve_vl(vl);
+                    load_sve_state(frame);
+                }
+                set_sve_user_access(sve.is_some());
+            }
         } else {
             disable_fpu();
         }
@@ -121,11 +200,16 @@ pub(crate) unsafe fn handle_fpu_fault() {
     switch_local_fpu_owner(new_owner as usize);
 }
 
+/// Whether `thread` owns the FPU of the core it is affine to, which may not be the
+/// current core.
 #[inline(always)]
 unsafe fn native_thread_using_fpu(thread: &mut tcb_t) -> bool {
-    return thread.tcbArch.fpu_state_ptr() as usize == NODE_STATE!(ksActiveFPUState);
+    return thread.tcbArch.fpu_state_ptr() as usize
+        == NODE_STATE_ON_CORE!(thread.get_cpu(), ksActiveFPUState);
 }
 
+/// Give up the FPU owned by `thread`, saving its state. A thread affine to another
+/// core is handled there through a remote call, see [`switch_fpu_owner`].
 #[cfg(feature = "enable_smp")]
 #[inline(always)]
 pub fn fpu_thread_delete(thread: &mut tcb_t) {
@@ -10,8 +10,12 @@ mod platform;
 pub mod vcpu;
 
 pub mod arm_gic;
+#[cfg(feature = "arm_smmu")]
+pub mod arm_smmu;
 #[cfg(feature = "have_fpu")]
 pub mod fpu;
+#[cfg(feature = "arm_smmu")]
+pub mod smmu;
 #[cfg(feature = "enable_smp")]
 pub use arm_gic::ipi_send_target;
 
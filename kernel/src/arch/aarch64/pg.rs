@@ -1,5 +1,6 @@
 use crate::syscall::invocation::decode::arch::decode_mmu_invocation;
 use sel4_common::arch::MessageLabel;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::sel4_config::TCB_VTABLE;
 use sel4_common::structures::exception_t;
 use sel4_common::structures::seL4_IPCBuffer;
@@ -8,6 +9,7 @@ use sel4_cspace::capability::cap_arch_func;
 use sel4_cspace::interface::cte_t;
 use sel4_task::get_currenct_thread;
 use sel4_vspace::asid_t;
+use sel4_vspace::get_hw_asid;
 use sel4_vspace::set_current_user_vspace_root;
 use sel4_vspace::ttbr_new;
 
@@ -21,8 +23,12 @@ extern "C" fn decodeARMMMUInvocation(
     _cap: cap,
     call: bool,
     buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
 ) -> exception_t {
-    decode_mmu_invocation(invLabel, length, cte, call, buffer)
+    match decode_mmu_invocation(invLabel, length, cte, call, buffer, ctx) {
+        Ok(()) => exception_t::EXCEPTION_NONE,
+        Err(error) => error.into(),
+    }
 }
 
 /// Set VMRoot and flush if necessary
@@ -35,6 +41,6 @@ pub fn set_vm_root_for_flush(vspace: usize, asid: asid_t) -> bool {
         return false;
     }
 
-    set_current_user_vspace_root(ttbr_new(asid, paddr!(vspace)));
+    set_current_user_vspace_root(ttbr_new(get_hw_asid(asid), paddr!(vspace)));
     true
 }
@@ -17,7 +17,7 @@ use crate::utils::{fpsime_hw_cap_test, set_vtable};
 use log::debug;
 use sel4_vspace::*;
 
-use super::arm_gic::gic_v2::gic_v2::{cpu_init_local_irq_controller, dist_init};
+use super::arm_gic::{cpu_init_local_irq_controller, dist_init};
 
 pub fn init_cpu() -> bool {
     activate_kernel_vspace();
@@ -44,11 +44,16 @@ pub fn init_cpu() -> bool {
     #[cfg(feature = "hypervisor")]
     registers::TPIDR_EL2.set(stack_top as _);
 
+    #[cfg(feature = "clh_lock_stats")]
+    sel4_common::arch::enable_cycle_counter();
+
     let haveHWFPU = fpsime_hw_cap_test();
 
     if haveHWFPU {
         unsafe {
             disable_fpu();
+            #[cfg(feature = "arm_sve")]
+            super::fpu::set_sve_user_access(false);
         }
     }
 
@@ -149,7 +154,7 @@ fn clean_invalidate_d_by_level(level: usize) {
     }
 }
 
-fn invalidate_i_pou() {
+pub fn invalidate_i_pou() {
     unsafe { asm!("ic iallu") };
     isb(barrier::SY);
 }
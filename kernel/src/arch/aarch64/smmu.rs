@@ -0,0 +1,280 @@
+//! The kernel state of the `ARMSID` and `ARMCB` objects.
+//!
+//! A stream ID is bound to a context bank by keeping a copy of the `ARMCB` cap in the
+//! slot of the stream ID, and a context bank uses a vspace by keeping a copy of the
+//! vspace cap in the slot of the context bank, so a bound object can not go away
+//! under the SMMU until it is unbound.
+use core::mem::size_of;
+
+use sel4_common::sel4_config::{CB_INVALID, SID_INVALID};
+use sel4_common::structures_gen::{cap, cap_Splayed, cap_sid_cap, cap_tag};
+use sel4_common::utils::convert_to_mut_type_ref;
+use sel4_cspace::interface::{cte_insert, cte_t};
+use sel4_vspace::{asid_t, decrease_asid_bind_cb, increase_asid_bind_cb};
+
+use super::arm_smmu::consts::{
+    EVT_ID_MASK, EVT_SID_SHIFT, SMMU_EVT_WORDS, SMMU_MAX_CB, SMMU_MAX_SID,
+};
+use super::arm_smmu::{
+    clear_cd, clear_global_error, global_error, invalidate_cd, invalidate_ste, read_event,
+    tlb_invalidate_all, tlb_invalidate_asid, tlb_invalidate_va, write_cd, write_ste,
+};
+
+#[cfg(feature = "hypervisor")]
+compile_error!("the SMMU only supports stage 1 translation, disable hypervisor");
+
+#[repr(align(32))]
+struct SmmuNode<const N: usize>([[u8; size_of::<cte_t>()]; N]);
+
+static mut SID_TABLE: [bool; SMMU_MAX_SID] = [false; SMMU_MAX_SID];
+static mut SID_NODE: SmmuNode<SMMU_MAX_SID> = SmmuNode([[0; size_of::<cte_t>()]; SMMU_MAX_SID]);
+
+static mut CB_TABLE: [bool; SMMU_MAX_CB] = [false; SMMU_MAX_CB];
+static mut CB_NODE: SmmuNode<SMMU_MAX_CB> = SmmuNode([[0; size_of::<cte_t>()]; SMMU_MAX_CB]);
+/// The asid of the vspace assigned to each context bank.
+static mut CB_ASID: [asid_t; SMMU_MAX_CB] = [0; SMMU_MAX_CB];
+
+/// A translation fault reported by the SMMU.
+#[derive(Clone, Copy, Default)]
+pub struct SmmuFault {
+    pub event: usize,
+    pub sid: usize,
+    pub address: usize,
+    pub info: usize,
+}
+
+impl SmmuFault {
+    pub fn is_valid(&self) -> bool {
+        self.event != 0
+    }
+}
+
+/// Faults of the stream IDs that are not bound to any context bank.
+static mut GLOBAL_FAULT: SmmuFault = SmmuFault {
+    event: 0,
+    sid: 0,
+    address: 0,
+    info: 0,
+};
+/// The first fault of each context bank, kept until it is cleared.
+static mut CB_FAULT: [SmmuFault; SMMU_MAX_CB] = [SmmuFault {
+    event: 0,
+    sid: 0,
+    address: 0,
+    info: 0,
+}; SMMU_MAX_CB];
+
+#[inline]
+pub fn sid_node_slot(sid: usize) -> &'static mut cte_t {
+    convert_to_mut_type_ref::<cte_t>(unsafe { &raw mut SID_NODE.0[sid] } as usize)
+}
+
+#[inline]
+pub fn cb_node_slot(cb: usize) -> &'static mut cte_t {
+    convert_to_mut_type_ref::<cte_t>(unsafe { &raw mut CB_NODE.0[cb] } as usize)
+}
+
+#[inline]
+pub fn sid_is_assigned(sid: usize) -> bool {
+    unsafe { SID_TABLE[sid] }
+}
+
+#[inline]
+pub fn cb_is_assigned(cb: usize) -> bool {
+    unsafe { CB_TABLE[cb] }
+}
+
+#[inline]
+pub fn assign_sid(sid: usize) {
+    unsafe { SID_TABLE[sid] = true }
+}
+
+#[inline]
+pub fn assign_cb(cb: usize) {
+    unsafe { CB_TABLE[cb] = true }
+}
+
+/// The context bank `sid` is bound to.
+fn sid_bound_cb(sid: usize) -> Option<usize> {
+    let slot = sid_node_slot(sid);
+    match slot.capability.clone().splay() {
+        cap_Splayed::cb_cap(data) => Some(data.get_capCB() as usize),
+        _ => None,
+    }
+}
+
+/// Bind `sid` to the context bank of the `ARMCB` cap in `cb_slot`, which becomes the
+/// parent of the copy.
+pub fn bind_sid(sid: usize, cb_slot: &mut cte_t) {
+    let new_cap = cb_slot.capability.clone();
+    cap::cap_cb_cap(&new_cap).set_capBindSID(sid as u64);
+    let cb = cap::cap_cb_cap(&new_cap).get_capCB() as usize;
+    cte_insert(&new_cap, cb_slot, sid_node_slot(sid));
+    write_ste(sid, Some(cb));
+    invalidate_ste(sid);
+}
+
+/// Remove the binding of `sid`, this finalises the `ARMCB` copy through [`sid_unbind_cb`].
+#[inline]
+pub fn unbind_sid(sid: usize) {
+    sid_node_slot(sid).delete_one();
+}
+
+/// The `ARMCB` cap bound to `sid` has been deleted.
+fn sid_unbind_cb(sid: usize) {
+    write_ste(sid, None);
+    invalidate_ste(sid);
+}
+
+/// Assign the vspace of the vspace cap in `vspace_slot` to `cb`, the slot becomes the
+/// parent of the copy.
+pub fn assign_vspace(cb: usize, vspace_slot: &mut cte_t) {
+    let new_cap = vspace_slot.capability.clone();
+    let vspace_cap = cap::cap_vspace_cap(&new_cap);
+    let asid = vspace_cap.get_capVSMappedASID() as usize;
+    let vspace_root = vspace_cap.get_capVSBasePtr() as usize;
+    vspace_cap.set_capVSMappedCB(cb as u64);
+    cte_insert(&new_cap, vspace_slot, cb_node_slot(cb));
+    unsafe { CB_ASID[cb] = asid };
+    increase_asid_bind_cb(asid);
+    write_cd(cb, asid, vspace_root);
+    invalidate_cb(cb);
+}
+
+/// Remove the vspace of `cb`, this finalises the vspace copy through [`cb_unassign_vspace`].
+#[inline]
+pub fn unassign_vspace(cb: usize) {
+    cb_node_slot(cb).delete_one();
+}
+
+/// The vspace cap assigned to `cb` has been deleted.
+fn cb_unassign_vspace(cb: usize) {
+    clear_cd(cb);
+    invalidate_cb(cb);
+    let asid = unsafe { CB_ASID[cb] };
+    tlb_invalidate_asid(asid);
+    decrease_asid_bind_cb(asid);
+}
+
+/// Drop the cached CD of `cb` for every stream ID bound to it.
+fn invalidate_cb(cb: usize) {
+    for sid in 0..SMMU_MAX_SID {
+        if sid_bound_cb(sid) == Some(cb) {
+            invalidate_cd(sid);
+        }
+    }
+}
+
+/// The asid of the vspace assigned to `cb`.
+pub fn cb_asid(cb: usize) -> Option<asid_t> {
+    match cb_node_slot(cb).capability.get_tag() {
+        cap_tag::cap_vspace_cap => Some(unsafe { CB_ASID[cb] }),
+        _ => None,
+    }
+}
+
+pub fn cb_tlb_invalidate(cb: usize) {
+    if let Some(asid) = cb_asid(cb) {
+        tlb_invalidate_asid(asid);
+    }
+}
+
+pub fn smmu_tlb_invalidate_all() {
+    tlb_invalidate_all();
+}
+
+/// Move the pending events into the fault records.
+fn collect_faults() {
+    while let Some(event) = read_event() {
+        record_fault(&event);
+    }
+}
+
+fn record_fault(event: &[u64; SMMU_EVT_WORDS]) {
+    let fault = SmmuFault {
+        event: (event[0] & EVT_ID_MASK) as usize,
+        sid: (event[0] >> EVT_SID_SHIFT) as usize,
+        address: event[2] as usize,
+        info: event[1] as usize,
+    };
+    let record = match fault.sid {
+        sid if sid < SMMU_MAX_SID => match sid_bound_cb(sid) {
+            Some(cb) => unsafe { &raw mut CB_FAULT[cb] },
+            None => &raw mut GLOBAL_FAULT,
+        },
+        _ => &raw mut GLOBAL_FAULT,
+    };
+    let record = unsafe { &mut *record };
+    if !record.is_valid() {
+        *record = fault;
+    }
+}
+
+/// The fault of the stream IDs without a context bank, and the active global errors.
+pub fn global_fault() -> (SmmuFault, usize) {
+    collect_faults();
+    (unsafe { GLOBAL_FAULT }, global_error() as usize)
+}
+
+pub fn clear_global_fault() {
+    unsafe { GLOBAL_FAULT = SmmuFault::default() };
+    clear_global_error();
+}
+
+pub fn cb_fault(cb: usize) -> SmmuFault {
+    collect_faults();
+    unsafe { CB_FAULT[cb] }
+}
+
+pub fn clear_cb_fault(cb: usize) {
+    unsafe { CB_FAULT[cb] = SmmuFault::default() };
+}
+
+/// Called by `arch_finalise_cap` for the SMMU caps and the vspace caps.
+pub fn smmu_finalise_cap(capability: &cap, final_: bool) {
+    match capability.clone().splay() {
+        cap_Splayed::sid_cap(data) => {
+            if final_ {
+                delete_sid(&data);
+            }
+        }
+        cap_Splayed::cb_cap(data) => {
+            let sid = data.get_capBindSID() as usize;
+            if sid != SID_INVALID {
+                sid_unbind_cb(sid);
+            }
+            if final_ {
+                delete_cb(data.get_capCB() as usize);
+            }
+        }
+        cap_Splayed::vspace_cap(data) => {
+            let cb = data.get_capVSMappedCB() as usize;
+            if cb != CB_INVALID {
+                cb_unassign_vspace(cb);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn delete_sid(sid_cap: &cap_sid_cap) {
+    let sid = sid_cap.get_capSID() as usize;
+    unbind_sid(sid);
+    unsafe { SID_TABLE[sid] = false };
+}
+
+fn delete_cb(cb: usize) {
+    unassign_vspace(cb);
+    clear_cb_fault(cb);
+    unsafe { CB_TABLE[cb] = false };
+}
+
+#[no_mangle]
+pub fn smmu_tlb_invalidate_asid(asid: asid_t) {
+    tlb_invalidate_asid(asid);
+}
+
+#[no_mangle]
+pub fn smmu_tlb_invalidate_asid_va(asid: asid_t, vaddr: usize) {
+    tlb_invalidate_va(asid, vaddr);
+}
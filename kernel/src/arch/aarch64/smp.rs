@@ -63,6 +63,13 @@ pub fn arch_pause() {
     // TODO
 }
 
+/// Drop the translations and instructions cached while this core was parked.
+#[cfg(feature = "cpu_hotplug")]
+pub fn cpu_flush_local() {
+    super::platform::invalidate_local_tlb();
+    super::platform::invalidate_i_pou();
+}
+
 /// doRemoteInvalidateTranslationASID in seL4
 #[no_mangle]
 pub fn remote_invalidate_tlb_asid(asid: sel4_vspace::asid_t) {
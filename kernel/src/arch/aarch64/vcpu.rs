@@ -1,24 +1,38 @@
-use core::intrinsics::likely;
+use core::arch::asm;
+use core::intrinsics::{likely, unlikely};
 
 use aarch64_cpu::{
     asm::barrier,
-    registers::{Readable, Writeable, CPACR_EL1, HCR_EL2, ID_AA64MMFR0_EL1, SCTLR_EL1, VTCR_EL2},
+    registers::{Readable, Writeable, HCR_EL2, ID_AA64MMFR0_EL1, VTCR_EL2},
 };
-use sel4_task::tcb_t;
+use sel4_common::kernel_state::kernel_state_t;
+use sel4_common::platform::INTERRUPT_VTIMER_EVENT;
+use sel4_common::sel4_config::SEL4_VCPU_BITS;
+use sel4_common::structures_gen::seL4_Fault_VPPIEvent;
+use sel4_task::{get_currenct_thread, tcb_t};
 
+use crate::arch::arm_gic::{
+    get_gic_vcpu_ctrl_apr, get_gic_vcpu_ctrl_hcr, get_gic_vcpu_ctrl_lr, get_gic_vcpu_ctrl_vmcr,
+    get_gic_vcpu_ctrl_vtr, set_gic_vcpu_ctrl_apr, set_gic_vcpu_ctrl_hcr, set_gic_vcpu_ctrl_lr,
+    set_gic_vcpu_ctrl_vmcr,
+};
 use crate::interrupt::mask_interrupt;
+use crate::syscall::handle_fault;
 
 const VMCS_SIZE: usize = 4096;
 const IOBITMAP_SIZE: usize = 8192;
 
-/// TODO: GIC_V2 is 16, and GIC_V3 is 64
-const GIC_VCPU_MAX_NUM_LR: usize = 16;
+/// Both GICH_LR<n> on GICv2 and ICH_LR<n>_EL2 on GICv3 have at most 16 list registers.
+pub const GIC_VCPU_MAX_NUM_LR: usize = 16;
+
+const SCTLR_DEFAULT: usize = 0xc5187c;
+const ACTLR_DEFAULT: usize = 0x40;
 
-const SCTLR_DEFAULT: u64 = 0xc5187c;
-const ACTLR_DEFAULT: u64 = 0x40;
+/// GICH_HCR.En, enable the virtual CPU interface.
+const VGIC_HCR_EN: u32 = 1;
 
-/// TODO: read this irq number dynamically.
-const INTERRUPT_VTIMER_EVENT: usize = 2;
+/// The number of VPPI events a VCPU can mask, only the virtual timer for now.
+const N_VPPI_EVENT_IRQ: usize = 1;
 
 /// HCR_EL2 <https://developer.arm.com/documentation/ddi0601/2025-06/AArch64-Registers/HCR-EL2--Hypervisor-Configuration-Register>
 const HCR_COMMON: u64 = HCR_EL2::VM::SET.value
@@ -37,9 +51,50 @@ const HCR_NATIVE: u64 = HCR_COMMON
 // TWE(14) | TWI(13)
 const HCR_VCPU: u64 = HCR_COMMON | bits!(14, 13);
 
+// seL4_VCPUReg, the order must match libsel4.
+pub const VCPU_REG_SCTLR: usize = 0;
+pub const VCPU_REG_TTBR0: usize = 1;
+pub const VCPU_REG_TTBR1: usize = 2;
+pub const VCPU_REG_TCR: usize = 3;
+pub const VCPU_REG_MAIR: usize = 4;
+pub const VCPU_REG_AMAIR: usize = 5;
+pub const VCPU_REG_CIDR: usize = 6;
+pub const VCPU_REG_ACTLR: usize = 7;
+pub const VCPU_REG_CPACR: usize = 8;
+pub const VCPU_REG_AFSR0: usize = 9;
+pub const VCPU_REG_AFSR1: usize = 10;
+pub const VCPU_REG_ESR: usize = 11;
+pub const VCPU_REG_FAR: usize = 12;
+pub const VCPU_REG_ISR: usize = 13;
+pub const VCPU_REG_VBAR: usize = 14;
+pub const VCPU_REG_TPIDR_EL1: usize = 15;
+pub const VCPU_REG_VMPIDR_EL2: usize = 16;
+pub const VCPU_REG_SP_EL1: usize = 17;
+pub const VCPU_REG_ELR_EL1: usize = 18;
+pub const VCPU_REG_SPSR_EL1: usize = 19;
+pub const VCPU_REG_CNTV_CTL: usize = 20;
+pub const VCPU_REG_CNTV_CVAL: usize = 21;
+pub const VCPU_REG_CNTVOFF: usize = 22;
+pub const VCPU_REG_CNTKCTL_EL1: usize = 23;
+pub const VCPU_REG_NUM: usize = 24;
+
 static mut ARM_HS_CUR_VCPU: *mut VCpu = 0 as _;
 static mut ARM_HS_VCPU_ACTIVE: bool = false;
-// const HCR_NATIVE: HCR_EL2 = HCR_EL2::
+static mut GIC_VCPU_NUM_LIST_REGS: usize = 0;
+
+macro_rules! read_sysreg {
+    ($name:literal) => {{
+        let value: usize;
+        unsafe { asm!(concat!("mrs {}, ", $name), out(reg) value) };
+        value
+    }};
+}
+
+macro_rules! write_sysreg {
+    ($name:literal, $value:expr) => {
+        unsafe { asm!(concat!("msr ", $name, ", {}"), in(reg) $value) }
+    };
+}
 
 /// Armv8 init vcpu in boot stage
 ///
@@ -49,12 +104,13 @@ pub fn armv_vcpu_boot_init() {
     if ID_AA64MMFR0_EL1.matches_all(ID_AA64MMFR0_EL1::TGran4::NotSupported) {
         panic!("Processor doesn't support 4KB");
     }
+    // 40-bit IPA starts the stage-2 walk at level 1, 44-bit IPA at level 0.
+    #[cfg(not(feature = "arm_pa_size_bits_44"))]
+    let pa_config = VTCR_EL2::T0SZ.val(24) + VTCR_EL2::PS::PA_40B_1TB + VTCR_EL2::SL0.val(1);
+    #[cfg(feature = "arm_pa_size_bits_44")]
+    let pa_config = VTCR_EL2::T0SZ.val(20) + VTCR_EL2::PS::PA_44B_16TB + VTCR_EL2::SL0.val(2);
     VTCR_EL2.write(
-        // CONFIG_ARM_PA_SIZE_BITS_40
-        VTCR_EL2::T0SZ.val(24)
-            + VTCR_EL2::PS::PA_40B_1TB
-            + VTCR_EL2::SL0.val(1)
-        // END CONFIG_ARM_PA_SIZE_BITS_40
+        pa_config
             + VTCR_EL2::IRGN0::NormalWBRAWA
             + VTCR_EL2::ORGN0::NormalWBRAWA
             + VTCR_EL2::SH0::Inner
@@ -63,10 +119,36 @@ pub fn armv_vcpu_boot_init() {
     barrier::dsb(barrier::SY);
 }
 
+/// Find out how many list registers the VGIC has and reset the virtual CPU interface.
+fn vgic_init() {
+    // GICH_VTR.ListRegs is bits [5:0], ICH_VTR_EL2.ListRegs is bits [4:0]
+    #[cfg(not(feature = "arm_gic_v3"))]
+    let mut num_list_regs = (get_gic_vcpu_ctrl_vtr() & 0x3f) as usize + 1;
+    #[cfg(feature = "arm_gic_v3")]
+    let mut num_list_regs = (get_gic_vcpu_ctrl_vtr() & 0x1f) as usize + 1;
+    if num_list_regs > GIC_VCPU_MAX_NUM_LR {
+        log::warn!(
+            "Too many list registers: {}, only {} are used",
+            num_list_regs,
+            GIC_VCPU_MAX_NUM_LR
+        );
+        num_list_regs = GIC_VCPU_MAX_NUM_LR;
+    }
+    unsafe {
+        GIC_VCPU_NUM_LIST_REGS = num_list_regs;
+    }
+    set_gic_vcpu_ctrl_hcr(0);
+    for i in 0..num_list_regs {
+        set_gic_vcpu_ctrl_lr(i, 0);
+    }
+    set_gic_vcpu_ctrl_apr(0);
+}
+
 pub fn vcpu_boot_init() {
     armv_vcpu_boot_init();
-    // TODO: initialize VGIC
-    log::warn!("initialize vgic is not implemented");
+    vgic_init();
+    // The virtual timer stays off until a VCPU loads its own
+    write_sysreg!("cntv_ctl_el0", 0usize);
     vcpu_disable(0 as _);
     unsafe {
         ARM_HS_CUR_VCPU = 0 as _;
@@ -74,84 +156,304 @@ pub fn vcpu_boot_init() {
     }
 }
 
+/// The number of list registers the VGIC provides.
+#[inline]
+pub fn gic_vcpu_num_list_regs() -> usize {
+    unsafe { GIC_VCPU_NUM_LIST_REGS }
+}
+
+/// Read a VCPU register from the hardware.
+///
+/// The kernel timer is the EL2 physical timer in hyp mode, so the virtual timer
+/// belongs to the loaded VCPU.
+fn vcpu_hw_read_reg(vcpu: &VCpu, field: usize) -> usize {
+    match field {
+        VCPU_REG_SCTLR => read_sysreg!("sctlr_el1"),
+        VCPU_REG_TTBR0 => read_sysreg!("ttbr0_el1"),
+        VCPU_REG_TTBR1 => read_sysreg!("ttbr1_el1"),
+        VCPU_REG_TCR => read_sysreg!("tcr_el1"),
+        VCPU_REG_MAIR => read_sysreg!("mair_el1"),
+        VCPU_REG_AMAIR => read_sysreg!("amair_el1"),
+        VCPU_REG_CIDR => read_sysreg!("contextidr_el1"),
+        VCPU_REG_ACTLR => read_sysreg!("actlr_el1"),
+        VCPU_REG_CPACR => read_sysreg!("cpacr_el1"),
+        VCPU_REG_AFSR0 => read_sysreg!("afsr0_el1"),
+        VCPU_REG_AFSR1 => read_sysreg!("afsr1_el1"),
+        VCPU_REG_ESR => read_sysreg!("esr_el1"),
+        VCPU_REG_FAR => read_sysreg!("far_el1"),
+        VCPU_REG_ISR => read_sysreg!("isr_el1"),
+        VCPU_REG_VBAR => read_sysreg!("vbar_el1"),
+        VCPU_REG_TPIDR_EL1 => read_sysreg!("tpidr_el1"),
+        VCPU_REG_VMPIDR_EL2 => read_sysreg!("vmpidr_el2"),
+        VCPU_REG_SP_EL1 => read_sysreg!("sp_el1"),
+        VCPU_REG_ELR_EL1 => read_sysreg!("elr_el1"),
+        VCPU_REG_SPSR_EL1 => read_sysreg!("spsr_el1"),
+        VCPU_REG_CNTV_CTL => read_sysreg!("cntv_ctl_el0"),
+        VCPU_REG_CNTV_CVAL => read_sysreg!("cntv_cval_el0"),
+        VCPU_REG_CNTVOFF => read_sysreg!("cntvoff_el2"),
+        VCPU_REG_CNTKCTL_EL1 => read_sysreg!("cntkctl_el1"),
+        _ => vcpu.regs[field],
+    }
+}
+
+/// Write a VCPU register to the hardware, see [`vcpu_hw_read_reg`].
+fn vcpu_hw_write_reg(vcpu: &mut VCpu, field: usize, value: usize) {
+    match field {
+        VCPU_REG_SCTLR => write_sysreg!("sctlr_el1", value),
+        VCPU_REG_TTBR0 => write_sysreg!("ttbr0_el1", value),
+        VCPU_REG_TTBR1 => write_sysreg!("ttbr1_el1", value),
+        VCPU_REG_TCR => write_sysreg!("tcr_el1", value),
+        VCPU_REG_MAIR => write_sysreg!("mair_el1", value),
+        VCPU_REG_AMAIR => write_sysreg!("amair_el1", value),
+        VCPU_REG_CIDR => write_sysreg!("contextidr_el1", value),
+        VCPU_REG_ACTLR => write_sysreg!("actlr_el1", value),
+        VCPU_REG_CPACR => write_sysreg!("cpacr_el1", value),
+        VCPU_REG_AFSR0 => write_sysreg!("afsr0_el1", value),
+        VCPU_REG_AFSR1 => write_sysreg!("afsr1_el1", value),
+        VCPU_REG_ESR => write_sysreg!("esr_el1", value),
+        VCPU_REG_FAR => write_sysreg!("far_el1", value),
+        // ISR_EL1 is read only
+        VCPU_REG_ISR => {}
+        VCPU_REG_VBAR => write_sysreg!("vbar_el1", value),
+        VCPU_REG_TPIDR_EL1 => write_sysreg!("tpidr_el1", value),
+        VCPU_REG_VMPIDR_EL2 => write_sysreg!("vmpidr_el2", value),
+        VCPU_REG_SP_EL1 => write_sysreg!("sp_el1", value),
+        VCPU_REG_ELR_EL1 => write_sysreg!("elr_el1", value),
+        VCPU_REG_SPSR_EL1 => write_sysreg!("spsr_el1", value),
+        VCPU_REG_CNTV_CTL => write_sysreg!("cntv_ctl_el0", value),
+        VCPU_REG_CNTV_CVAL => write_sysreg!("cntv_cval_el0", value),
+        VCPU_REG_CNTVOFF => write_sysreg!("cntvoff_el2", value),
+        VCPU_REG_CNTKCTL_EL1 => write_sysreg!("cntkctl_el1", value),
+        _ => vcpu.regs[field] = value,
+    }
+}
+
+/// These registers are saved into the VCPU object when it is disabled,
+/// so the hardware copy is stale while the VCPU is inactive.
+#[inline]
+fn vcpu_reg_saved_when_disabled(field: usize) -> bool {
+    matches!(
+        field,
+        VCPU_REG_SCTLR
+            | VCPU_REG_CPACR
+            | VCPU_REG_CNTV_CTL
+            | VCPU_REG_CNTV_CVAL
+            | VCPU_REG_CNTVOFF
+            | VCPU_REG_CNTKCTL_EL1
+    )
+}
+
+/// Save the virtual timer of `vcpu` and stop it, so it does not fire for the next VCPU.
+fn save_virt_timer(vcpu: &mut VCpu) {
+    vcpu.regs[VCPU_REG_CNTV_CTL] = read_sysreg!("cntv_ctl_el0");
+    write_sysreg!("cntv_ctl_el0", 0usize);
+    vcpu.regs[VCPU_REG_CNTV_CVAL] = read_sysreg!("cntv_cval_el0");
+    vcpu.regs[VCPU_REG_CNTVOFF] = read_sysreg!("cntvoff_el2");
+    vcpu.regs[VCPU_REG_CNTKCTL_EL1] = read_sysreg!("cntkctl_el1");
+    // Remember when the VCPU stopped to hide the time it did not run
+    vcpu.vtimer.last_pcount = read_sysreg!("cntpct_el0") as u64;
+}
+
+/// Load the virtual timer of `vcpu`, the virtual counter skips the time it did not run.
+fn restore_virt_timer(vcpu: &mut VCpu) {
+    let pcount = read_sysreg!("cntpct_el0") as u64;
+    let delta = pcount.wrapping_sub(vcpu.vtimer.last_pcount);
+    let offset = (vcpu.regs[VCPU_REG_CNTVOFF] as u64).wrapping_add(delta);
+    vcpu.regs[VCPU_REG_CNTVOFF] = offset as usize;
+    write_sysreg!("cntvoff_el2", vcpu.regs[VCPU_REG_CNTVOFF]);
+    write_sysreg!("cntv_cval_el0", vcpu.regs[VCPU_REG_CNTV_CVAL]);
+    write_sysreg!("cntkctl_el1", vcpu.regs[VCPU_REG_CNTKCTL_EL1]);
+    write_sysreg!("cntv_ctl_el0", vcpu.regs[VCPU_REG_CNTV_CTL]);
+    barrier::isb(barrier::SY);
+    if let Some(index) = irq_vppi_event_index(INTERRUPT_VTIMER_EVENT) {
+        mask_interrupt(vcpu.vppi_masked[index], INTERRUPT_VTIMER_EVENT);
+    }
+}
+
 pub fn vcpu_disable(vcpu: *mut VCpu) {
     barrier::dsb(barrier::SY);
 
     if likely(!vcpu.is_null()) {
         let vcpu = unsafe { &mut *vcpu };
-        // hcr =
-        log::warn!("save vgic hcr");
-        vcpu.regs.sctlr = SCTLR_EL1.get();
-        vcpu.regs.cpacr = CPACR_EL1.get();
+        vcpu.vgic.hcr = get_gic_vcpu_ctrl_hcr();
+        vcpu.regs[VCPU_REG_SCTLR] = read_sysreg!("sctlr_el1");
+        vcpu.regs[VCPU_REG_CPACR] = read_sysreg!("cpacr_el1");
+        save_virt_timer(vcpu);
+        barrier::isb(barrier::SY);
     }
 
-    log::warn!("set_gic_vcpu_ctrl_hcr to Turn Off VGic");
+    // Turn off the VGIC
+    set_gic_vcpu_ctrl_hcr(0);
     barrier::isb(barrier::SY);
 
-    SCTLR_EL1.set(SCTLR_DEFAULT);
+    write_sysreg!("sctlr_el1", SCTLR_DEFAULT);
     barrier::isb(barrier::SY);
     HCR_EL2.set(HCR_NATIVE);
 
     barrier::isb(barrier::SY);
     if likely(!vcpu.is_null()) {
-        log::warn!("save_virt_timer vcpu");
-        #[cfg(feature = "enable_smp")]
-        log::warn!("convert core_irq to irqt");
+        // The timer PPI is banked, this only masks it on the current core
         mask_interrupt(true, INTERRUPT_VTIMER_EVENT);
     }
 }
 
-pub fn vcpu_enable(vcpu: &VCpu) {
-    SCTLR_EL1.set(vcpu.regs.sctlr);
+pub fn vcpu_enable(vcpu: &mut VCpu) {
+    write_sysreg!("sctlr_el1", vcpu.regs[VCPU_REG_SCTLR]);
     HCR_EL2.set(HCR_VCPU);
     barrier::isb(barrier::SY);
-    log::warn!("set_gic_vcpu_ctrl_hcr to vcpu's gic hcr");
-    CPACR_EL1.set(vcpu.regs.cpacr);
-    log::warn!("restore virt timer");
+    set_gic_vcpu_ctrl_hcr(vcpu.vgic.hcr);
+    write_sysreg!("cpacr_el1", vcpu.regs[VCPU_REG_CPACR]);
+    restore_virt_timer(vcpu);
 }
 
-pub struct VTimer {
-    last_pcount: u64,
+fn vcpu_save(vcpu: &mut VCpu, active: bool) {
+    barrier::dsb(barrier::SY);
+    if active {
+        vcpu.regs[VCPU_REG_SCTLR] = read_sysreg!("sctlr_el1");
+        vcpu.vgic.hcr = get_gic_vcpu_ctrl_hcr();
+        save_virt_timer(vcpu);
+    }
+    vcpu.vgic.vmcr = get_gic_vcpu_ctrl_vmcr();
+    vcpu.vgic.apr = get_gic_vcpu_ctrl_apr();
+    for i in 0..gic_vcpu_num_list_regs() {
+        vcpu.vgic.lr[i] = get_gic_vcpu_ctrl_lr(i);
+    }
+    for field in VCPU_REG_TTBR0..=VCPU_REG_SPSR_EL1 {
+        let value = vcpu_hw_read_reg(vcpu, field);
+        vcpu.regs[field] = value;
+    }
+    barrier::isb(barrier::SY);
+}
+
+fn vcpu_restore(vcpu: &mut VCpu) {
+    barrier::dsb(barrier::SY);
+    // Turn off the VGIC while the list registers are loaded
+    set_gic_vcpu_ctrl_hcr(0);
+    barrier::isb(barrier::SY);
+
+    set_gic_vcpu_ctrl_vmcr(vcpu.vgic.vmcr);
+    set_gic_vcpu_ctrl_apr(vcpu.vgic.apr);
+    for i in 0..gic_vcpu_num_list_regs() {
+        set_gic_vcpu_ctrl_lr(i, vcpu.vgic.lr[i]);
+    }
+    for field in VCPU_REG_TTBR0..=VCPU_REG_SPSR_EL1 {
+        let value = vcpu.regs[field];
+        vcpu_hw_write_reg(vcpu, field, value);
+    }
+    vcpu_enable(vcpu);
+}
+
+/// Switch the hardware to `new`, a null pointer leaves the current VCPU state loaded but
+/// turns the VGIC and the stage 1 MMU of EL1 off.
+#[no_mangle]
+pub fn vcpu_switch(new: usize) {
+    let new = new as *mut VCpu;
+    unsafe {
+        if likely(ARM_HS_CUR_VCPU != new) {
+            if unlikely(!new.is_null()) {
+                if unlikely(!ARM_HS_CUR_VCPU.is_null()) {
+                    vcpu_save(&mut *ARM_HS_CUR_VCPU, ARM_HS_VCPU_ACTIVE);
+                }
+                vcpu_restore(&mut *new);
+                ARM_HS_CUR_VCPU = new;
+                ARM_HS_VCPU_ACTIVE = true;
+            } else if unlikely(ARM_HS_VCPU_ACTIVE) {
+                vcpu_disable(ARM_HS_CUR_VCPU);
+                ARM_HS_VCPU_ACTIVE = false;
+            }
+        } else if likely(!ARM_HS_VCPU_ACTIVE && !new.is_null()) {
+            barrier::isb(barrier::SY);
+            vcpu_enable(&mut *new);
+            ARM_HS_VCPU_ACTIVE = true;
+        }
+    }
+}
+
+fn vcpu_invalidate_active() {
+    unsafe {
+        if ARM_HS_VCPU_ACTIVE {
+            vcpu_disable(0 as _);
+            ARM_HS_VCPU_ACTIVE = false;
+        }
+        ARM_HS_CUR_VCPU = 0 as _;
+    }
+}
+
+/// Dissociate the VCPU of a thread that is being deleted.
+pub fn vcpu_thread_delete(tcb: &mut tcb_t) {
+    if tcb.tcbArch.vcpu != 0 {
+        let vcpu = unsafe { &mut *(tcb.tcbArch.vcpu as *mut VCpu) };
+        vcpu.dissociate(tcb);
+    }
+}
+
+/// Map an IRQ to its index in `VCpu::vppi_masked`.
+pub fn irq_vppi_event_index(irq: usize) -> Option<usize> {
+    match irq {
+        INTERRUPT_VTIMER_EVENT => Some(0),
+        _ => None,
+    }
 }
 
-struct VCpuRegSet {
-    // System control registers EL1
-    pub sctlr: u64,
-    pub cpacr: u64,
-    pub ttbr0: usize,
-    pub ttbr1: usize,
-    pub tcr: usize,
-    pub mair: usize,
-    pub amair: usize,
-    pub cidr: usize,
-    pub actlr: usize,
-
-    // exception handling registers EL1
-    pub afsr0: usize,
-    pub afsr1: usize,
-    pub esr: usize,
-    pub far: usize,
-    pub isr: usize,
-    pub vbar: usize,
-
-    // thread pointer/ID registers EL0/EL1
-    pub tpidr_el1: usize,
-
-    // Virtualisation Multiprocessor ID Register
-    pub vmpidr_el2: usize,
-
-    // general registers x0 to x30 have been saved by traps.S
-    pub sp_el1: usize,
-    pub elr_el1: usize,
-    pub spsr_el1: usize,
-
-    // generic timer registers, to be completed
-    pub cntv_ctl: usize,
-    pub cntv_cval: usize,
-    pub cntv_off: usize,
-    pub cntk_ctl_el1: usize,
+/// A virtual PPI of the loaded VCPU fired, mask it until the VMM acks it with
+/// `ARMVCPUAckVPPI` and send the VMM a VPPIEvent fault.
+pub fn vppi_event(irq: usize, ctx: &mut kernel_state_t) {
+    unsafe {
+        if ARM_HS_VCPU_ACTIVE {
+            let Some(index) = irq_vppi_event_index(irq) else {
+                debug_assert!(false, "not a VPPI event IRQ");
+                return;
+            };
+            mask_interrupt(true, irq);
+            (*ARM_HS_CUR_VCPU).vppi_masked[index] = true;
+            ctx.fault = seL4_Fault_VPPIEvent::new(irq as u64).unsplay();
+            let thread = get_currenct_thread();
+            // An active VCPU means the current thread is the runnable thread it is bound to
+            debug_assert!(thread.is_runnable());
+            if thread.is_runnable() {
+                handle_fault(thread, ctx);
+            }
+        }
+    }
+}
+
+/// Build a pending virtual IRQ for a GICv2 list register.
+#[cfg(not(feature = "arm_gic_v3"))]
+pub fn virq_pending_new(group: usize, priority: usize, vid: usize) -> usize {
+    // virqType(pending) | eoiirqen
+    ((group & 1) << 30) | (1 << 28) | ((priority & 0x1f) << 23) | (1 << 19) | (vid & 0x3ff)
+}
+
+/// Build a pending virtual IRQ for a GICv3 ICH_LR<n>_EL2.
+///
+/// The 5 bit priority goes to the top of the 8 bit Priority field, which is the part
+/// every implementation has.
+#[cfg(feature = "arm_gic_v3")]
+pub fn virq_pending_new(group: usize, priority: usize, vid: usize) -> usize {
+    // virqType(pending) | eoiirqen
+    (1 << 62) | ((group & 1) << 60) | ((priority & 0x1f) << 51) | (1 << 41) | (vid & 0xffffffff)
+}
+
+/// Whether a list register holds an active virtual IRQ.
+#[cfg(not(feature = "arm_gic_v3"))]
+#[inline]
+pub fn virq_is_active(lr: usize) -> bool {
+    (lr >> 28) & 0x3 == 2
 }
 
+/// Whether a list register holds an active virtual IRQ.
+#[cfg(feature = "arm_gic_v3")]
+#[inline]
+pub fn virq_is_active(lr: usize) -> bool {
+    (lr >> 62) & 0x3 == 2
+}
+
+#[repr(C)]
+pub struct VTimer {
+    last_pcount: u64,
+}
+
+#[repr(C)]
 struct GICVCpuIface {
     hcr: u32,
     vmcr: u32,
@@ -160,12 +462,13 @@ struct GICVCpuIface {
     lr: [usize; GIC_VCPU_MAX_NUM_LR],
 }
 
+#[repr(C)]
 pub struct VCpu {
     /* TCB associated with this VCPU. */
     tcb: *mut tcb_t,
     vgic: GICVCpuIface,
-    regs: VCpuRegSet,
-    vppi_masked: [bool; 1],
+    regs: [usize; VCPU_REG_NUM],
+    pub vppi_masked: [bool; N_VPPI_EVENT_IRQ],
     /* word_t vcpu_padding; */
     /* vTimer is 8-bytes wide and has the same 8-byte alignment requirement.
      * If the sum of n_VPPIEventIRQ and seL4_VCPUReg_Num is odd, we do not need
@@ -175,3 +478,104 @@ pub struct VCpu {
      */
     vtimer: VTimer,
 }
+
+const _: () = assert!(
+    core::mem::size_of::<VCpu>() <= bit!(SEL4_VCPU_BITS),
+    "VCpu does not fit in a VCPU object"
+);
+
+impl VCpu {
+    /// Initialise a newly retyped VCPU, the memory has already been cleared.
+    pub fn init(&mut self) {
+        self.regs[VCPU_REG_SCTLR] = SCTLR_DEFAULT;
+        self.regs[VCPU_REG_ACTLR] = ACTLR_DEFAULT;
+        self.vgic.hcr = VGIC_HCR_EN;
+        self.vtimer.last_pcount = 0;
+    }
+
+    #[inline]
+    fn is_current(&self) -> bool {
+        unsafe { ARM_HS_CUR_VCPU as *const VCpu == self as *const VCpu }
+    }
+
+    /// Called when the last cap to this VCPU is deleted.
+    pub fn finalise(&mut self) {
+        if !self.tcb.is_null() {
+            let tcb = unsafe { &mut *self.tcb };
+            self.dissociate(tcb);
+        }
+    }
+
+    /// Bind this VCPU to `tcb`, dropping any previous binding of either side.
+    pub fn associate(&mut self, tcb: &mut tcb_t) {
+        if tcb.tcbArch.vcpu != 0 {
+            let old = unsafe { &mut *(tcb.tcbArch.vcpu as *mut VCpu) };
+            old.dissociate(tcb);
+        }
+        if !self.tcb.is_null() {
+            let old = unsafe { &mut *self.tcb };
+            self.dissociate(old);
+        }
+        tcb.tcbArch.vcpu = self as *mut VCpu as usize;
+        self.tcb = tcb as *mut tcb_t;
+        if tcb.is_current() {
+            vcpu_switch(self as *mut VCpu as usize);
+        }
+    }
+
+    pub fn dissociate(&mut self, tcb: &mut tcb_t) {
+        assert!(
+            tcb.tcbArch.vcpu == self as *mut VCpu as usize && self.tcb == tcb as *mut tcb_t,
+            "TCB and VCPU not associated."
+        );
+        if self.is_current() {
+            vcpu_invalidate_active();
+        }
+        tcb.tcbArch.vcpu = 0;
+        self.tcb = 0 as _;
+        // Without a VCPU the thread may only run in user mode
+        tcb.tcbArch.sanitise_spsr(false);
+    }
+
+    pub fn read_reg(&self, field: usize) -> usize {
+        if self.is_current() {
+            if vcpu_reg_saved_when_disabled(field) && unsafe { !ARM_HS_VCPU_ACTIVE } {
+                self.regs[field]
+            } else {
+                vcpu_hw_read_reg(self, field)
+            }
+        } else {
+            self.regs[field]
+        }
+    }
+
+    pub fn write_reg(&mut self, field: usize, value: usize) {
+        if self.is_current() {
+            if vcpu_reg_saved_when_disabled(field) && unsafe { !ARM_HS_VCPU_ACTIVE } {
+                self.regs[field] = value;
+            } else {
+                vcpu_hw_write_reg(self, field, value);
+            }
+        } else {
+            self.regs[field] = value;
+        }
+    }
+
+    /// Read a list register, from the hardware if this VCPU is loaded.
+    pub fn get_lr(&self, index: usize) -> usize {
+        if self.is_current() {
+            get_gic_vcpu_ctrl_lr(index)
+        } else {
+            self.vgic.lr[index]
+        }
+    }
+
+    /// Put a virtual IRQ into a list register.
+    pub fn inject_irq(&mut self, index: usize, virq: usize) {
+        if self.is_current() {
+            set_gic_vcpu_ctrl_lr(index, virq);
+        } else {
+            self.vgic.lr[index] = virq;
+        }
+    }
+}
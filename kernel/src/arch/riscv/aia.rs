@@ -0,0 +1,160 @@
+//! The RISC-V AIA: an S-level APLIC that forwards the wired sources as MSIs, and the
+//! S-mode IMSIC interrupt file of every hart.
+//!
+//! The kernel IRQs are the IMSIC identities. The APLIC sources 1..=`APLIC_MAX_IRQ` send
+//! their own number, the identities up to `AIA_MAX_IRQ` are the MSIs of user-level
+//! drivers and the two identities above them are the IPIs. Every identity is enabled in
+//! every interrupt file and a claim through `stopei` clears it, so the APLIC sources are
+//! masked at the APLIC. An MSI can't be masked, it goes to the hart whose file the
+//! device writes.
+//!
+//! The M-level firmware delegates the sources to the S-level domain and sets its MSI
+//! address, the interrupt file of a hart is at `IMSIC_PADDR + (hart << 12)`.
+use core::ptr::{read_volatile, write_volatile};
+
+use sel4_common::platform::{
+    AIA_MAX_IRQ, APLIC_MAX_IRQ, APLIC_PPTR, IMSIC_PADDR, IMSIC_PPTR, IRQ_INVALID, PLIC_MAX_IRQ,
+};
+
+use super::core_hart_id;
+
+const _: () = assert!(
+    AIA_MAX_IRQ > APLIC_MAX_IRQ && PLIC_MAX_IRQ == 0,
+    "riscv_aia needs device.aia and no device.plic in the platform"
+);
+
+const APLIC_DOMAINCFG: usize = 0x0;
+const APLIC_DOMAINCFG_IE: u32 = bit!(8) as u32;
+const APLIC_DOMAINCFG_DM_MSI: u32 = bit!(2) as u32;
+const APLIC_SOURCECFG: usize = 0x4;
+const APLIC_SOURCECFG_EDGE_RISE: u32 = 4;
+const APLIC_SOURCECFG_LEVEL_HIGH: u32 = 6;
+const APLIC_SETIP: usize = 0x1c00;
+const APLIC_SETIENUM: usize = 0x1edc;
+const APLIC_CLRIENUM: usize = 0x1f1c;
+const APLIC_TARGET: usize = 0x3004;
+const APLIC_TARGET_HART_SHIFT: usize = 18;
+
+const CSR_SISELECT: usize = 0x150;
+const CSR_SIREG: usize = 0x151;
+const CSR_STOPEI: usize = 0x15c;
+const IMSIC_EIDELIVERY: usize = 0x70;
+const IMSIC_EITHRESHOLD: usize = 0x72;
+const IMSIC_EIP0: usize = 0x80;
+const IMSIC_EIE0: usize = 0xc0;
+const IMSIC_TOPEI_ID_SHIFT: usize = 16;
+const IMSIC_TOPEI_ID_MASK: usize = 0x7ff;
+const IMSIC_FILE_BITS: usize = 12;
+
+#[inline]
+fn aplic_writel(val: u32, offset: usize) {
+    unsafe { write_volatile((APLIC_PPTR + offset) as *mut u32, val) }
+}
+
+#[inline]
+fn aplic_readl(offset: usize) -> u32 {
+    unsafe { read_volatile((APLIC_PPTR + offset) as *const u32) }
+}
+
+#[inline]
+fn imsic_write_reg(reg: usize, val: usize) {
+    unsafe {
+        core::arch::asm!(
+            "csrw {siselect}, {0}",
+            "csrw {sireg}, {1}",
+            in(reg) reg,
+            in(reg) val,
+            siselect = const CSR_SISELECT,
+            sireg = const CSR_SIREG,
+        )
+    };
+}
+
+/// Whether `irq` is a wired source of the APLIC.
+#[inline]
+pub fn is_aplic_irq(irq: usize) -> bool {
+    irq != IRQ_INVALID && irq <= APLIC_MAX_IRQ
+}
+
+/// Whether `irq` is an identity left for the MSIs of user-level drivers.
+#[inline]
+pub fn is_msi_irq(irq: usize) -> bool {
+    irq > APLIC_MAX_IRQ && irq <= AIA_MAX_IRQ
+}
+
+/// The address a device writes an MSI to for it to reach the hart of `core`.
+#[inline]
+pub fn imsic_msi_address(core: usize) -> usize {
+    IMSIC_PADDR + (core_hart_id(core) << IMSIC_FILE_BITS)
+}
+
+/// Claim the highest priority pending identity of this hart, `IRQ_INVALID` if none.
+pub fn imsic_claim() -> usize {
+    let topei: usize;
+    unsafe { core::arch::asm!("csrrw {0}, {csr}, zero", out(reg) topei, csr = const CSR_STOPEI) };
+    match (topei >> IMSIC_TOPEI_ID_SHIFT) & IMSIC_TOPEI_ID_MASK {
+        0 => IRQ_INVALID,
+        irq => irq,
+    }
+}
+
+/// Raise identity `irq` in the interrupt file of `hart_id`.
+#[cfg(feature = "enable_smp")]
+pub fn imsic_send_ipi(hart_id: usize, irq: usize) {
+    let seteipnum = IMSIC_PPTR + (hart_id << IMSIC_FILE_BITS);
+    unsafe { write_volatile(seteipnum as *mut u32, irq as u32) };
+}
+
+/// Mask or unmask the APLIC source `irq`.
+pub fn aplic_mask_irq(disable: bool, irq: usize) {
+    let reg = if disable {
+        APLIC_CLRIENUM
+    } else {
+        APLIC_SETIENUM
+    };
+    aplic_writel(irq as u32, reg);
+}
+
+/// Whether the APLIC source `irq` is pending, a masked source keeps its pending bit.
+pub fn aplic_irq_pending(irq: usize) -> bool {
+    aplic_readl(APLIC_SETIP + (irq / 32) * 4) & (1 << (irq % 32)) != 0
+}
+
+/// Make the APLIC source `irq` rising edge or level high triggered.
+pub fn aplic_set_trigger(irq: usize, edge_triggered: bool) {
+    let mode = if edge_triggered {
+        APLIC_SOURCECFG_EDGE_RISE
+    } else {
+        APLIC_SOURCECFG_LEVEL_HIGH
+    };
+    aplic_writel(mode, APLIC_SOURCECFG + (irq - 1) * 4);
+}
+
+/// Forward the APLIC source `irq` to the interrupt file of `core`.
+pub fn aplic_set_irq_target(irq: usize, core: usize) {
+    let target = (core_hart_id(core) << APLIC_TARGET_HART_SHIFT) | irq;
+    aplic_writel(target as u32, APLIC_TARGET + (irq - 1) * 4);
+}
+
+/// Deliver every identity to this hart, the masking is done at the APLIC.
+pub fn imsic_init_hart() {
+    imsic_write_reg(IMSIC_EIDELIVERY, 1);
+    imsic_write_reg(IMSIC_EITHRESHOLD, 0);
+    // only the even registers exist on rv64, each one holds 64 identities
+    for i in 0..=(AIA_MAX_IRQ + 2) / 64 {
+        imsic_write_reg(IMSIC_EIP0 + i * 2, 0);
+        imsic_write_reg(IMSIC_EIE0 + i * 2, usize::MAX);
+    }
+}
+
+/// Switch the S-level APLIC domain to MSI delivery, with every source masked, level
+/// high triggered and forwarded to the boot hart.
+pub fn aplic_init_controller() {
+    aplic_writel(0, APLIC_DOMAINCFG);
+    for irq in 1..=APLIC_MAX_IRQ {
+        aplic_mask_irq(true, irq);
+        aplic_set_trigger(irq, false);
+        aplic_set_irq_target(irq, 0);
+    }
+    aplic_writel(APLIC_DOMAINCFG_IE | APLIC_DOMAINCFG_DM_MSI, APLIC_DOMAINCFG);
+}
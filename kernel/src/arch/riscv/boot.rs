@@ -57,6 +57,13 @@ pub fn try_init_kernel(
     let bi_frame_vptr = ipcbuf_vptr + bit!(PAGE_BITS);
     let extra_bi_frame_vptr = bi_frame_vptr + bit!(BI_FRAME_SIZE_BITS);
     rust_map_kernel_window();
+    // The DTB is read through the kernel window, and the timer frequency it gives is
+    // taken before init_cpu programs the timer.
+    #[cfg(feature = "dtb_platform")]
+    {
+        sel4_vspace::activate_kernel_vspace();
+        crate::boot::discover::discover_platform(dtb_phys_addr, dtb_size);
+    }
     init_cpu();
     init_plat();
 
@@ -65,6 +72,34 @@ pub fn try_init_kernel(
         return false;
     }
 
+    crate::boot::cmdline::parse_bootargs(dtb_phys_addr, dtb_size);
+
+    #[cfg(not(feature = "riscv_ext_sstc"))]
+    if super::isa::isa_has_ext(dtb_phys_addr, dtb_size, "sstc") {
+        info!("Sstc detected, programming the timer through stimecmp");
+        sel4_common::arch::enable_sstc();
+    }
+
+    #[cfg(feature = "riscv_ext_v")]
+    if super::isa::isa_has_ext(dtb_phys_addr, dtb_size, "v") && super::fpu::init_vector() {
+        info!(
+            "V detected, switching the {} byte vector state lazily with the FPU",
+            super::fpu::vector_state_size()
+        );
+    }
+
+    #[cfg(feature = "riscv_ext_svpbmt")]
+    if super::isa::isa_has_ext(dtb_phys_addr, dtb_size, "svpbmt") {
+        info!("Svpbmt detected, enabling page-based memory types");
+        sel4_vspace::enable_svpbmt();
+    }
+
+    #[cfg(feature = "riscv_ext_svnapot")]
+    if super::isa::isa_has_ext(dtb_phys_addr, dtb_size, "svnapot") {
+        info!("Svnapot detected, mapping 64KiB pages as NAPOT entries");
+        sel4_vspace::enable_svnapot();
+    }
+
     let extra_bi_size_bits = calculate_extra_bi_size_bits(extra_bi_size);
 
     let it_v_reg = VRegion::new(
@@ -128,12 +163,11 @@ pub fn try_init_kernel(
 
 #[cfg(feature = "enable_smp")]
 pub(crate) fn release_secondary_cores() {
-    use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
     *node_boot_lock.lock() = 1;
     unsafe {
         asm!("fence rw, rw");
     }
-    while ksNumCPUs.lock().ne(&CONFIG_MAX_NUM_NODES) {}
+    while ksNumCPUs.lock().ne(&crate::boot::num_boot_cpus()) {}
 }
 
 #[cfg(feature = "enable_smp")]
@@ -158,4 +192,9 @@ pub fn try_init_kernel_secondary_core(_hartid: usize, _core_id: usize) -> bool {
     true
 }
 
-pub fn init_plat() {}
+pub fn init_plat() {
+    #[cfg(not(feature = "riscv_aia"))]
+    super::plic::plic_init_controller();
+    #[cfg(feature = "riscv_aia")]
+    super::aia::aplic_init_controller();
+}
@@ -6,6 +6,7 @@ use crate::syscall::slowpath;
 #[cfg(feature = "have_fpu")]
 use crate::arch::fpu::{handle_fpu_fault, is_fpu_enable, lazy_fpu_restore, set_tcb_fs_state};
 use sel4_common::arch::ArchReg;
+use sel4_common::kernel_state::kernel_entry_t;
 use sel4_common::sel4_config::{
     RISCV_INSTRUCTION_ACCESS_FAULT, RISCV_INSTRUCTION_PAGE_FAULT, RISCV_LOAD_ACCESS_FAULT,
     RISCV_LOAD_PAGE_FAULT, RISCV_STORE_ACCESS_FAULT, RISCV_STORE_PAGE_FAULT,
@@ -19,7 +20,7 @@ use crate::interrupt::handler::handle_interrupt_entry;
 #[cfg(feature = "enable_smp")]
 use crate::{
     interrupt::get_active_irq,
-    smp::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_release},
+    smp::{clh_lock_acquire, kernel_lock_release_if_held, syscall_lock_acquire},
 };
 
 #[cfg(feature = "enable_smp")]
@@ -32,9 +33,7 @@ pub fn restore_user_context() {
         let cur_thread_reg: usize = get_currenct_thread().tcbArch.raw_ptr();
         #[cfg(feature = "enable_smp")]
         {
-            if clh_is_self_in_queue() {
-                clh_lock_release(cpu_id());
-            }
+            kernel_lock_release_if_held(cpu_id());
             // debug!("restore_user_context2");
             #[allow(unused)]
             let mut cur_sp: usize = 8;
@@ -107,9 +106,7 @@ pub fn fastpath_restore(_badge: usize, _msgInfo: usize, cur_thread: *mut tcb_t)
         let cur_thread_reg = (*cur_thread).tcbArch.raw_ptr() as usize;
         #[cfg(feature = "enable_smp")]
         {
-            if clh_is_self_in_queue() {
-                clh_lock_release(cpu_id());
-            }
+            kernel_lock_release_if_held(cpu_id());
             let mut sp: usize;
             asm!(
                 "csrr {0}, sscratch",
@@ -191,7 +188,8 @@ pub fn c_handle_interrupt() {
         }
     }
     // debug!("c_handle_interrupt");
-    handle_interrupt_entry();
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    handle_interrupt_entry(entry.state());
     restore_user_context();
 }
 
@@ -203,6 +201,7 @@ pub fn c_handle_exception() {
     //     debug!("c_handle_exception");
     // }
 
+    let mut entry = unsafe { kernel_entry_t::enter() };
     let cause = read_scause();
     match cause {
         RISCV_INSTRUCTION_ACCESS_FAULT
@@ -211,7 +210,7 @@ pub fn c_handle_exception() {
         | RISCV_LOAD_PAGE_FAULT
         | RISCV_STORE_PAGE_FAULT
         | RISCV_INSTRUCTION_PAGE_FAULT => {
-            handleVMFaultEvent(cause);
+            handleVMFaultEvent(cause, entry.state());
         }
         _ => {
             // #ifdef CONFIG_HAVE_FPU
@@ -230,7 +229,7 @@ pub fn c_handle_exception() {
                         .tcbArch
                         .set_register(ArchReg::NextIP, pc);
                 } else {
-                    handleUserLevelFault(cause, 0);
+                    handleUserLevelFault(cause, 0, entry.state());
                 }
             }
         }
@@ -241,11 +240,12 @@ pub fn c_handle_exception() {
 #[no_mangle]
 pub fn c_handle_syscall(_cptr: usize, _msgInfo: usize, syscall: usize) {
     #[cfg(feature = "enable_smp")]
-    clh_lock_acquire(cpu_id(), false);
+    syscall_lock_acquire(cpu_id(), syscall);
     // if hart_id() == 0 {
     //     debug!("c_handle_syscall: syscall: {},", syscall as isize);
     // }
-    slowpath(syscall);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    slowpath(syscall, entry.state());
     // debug!("c_handle_syscall complete");
 }
 
@@ -256,7 +256,8 @@ pub fn c_handle_fastpath_call(cptr: usize, msgInfo: usize) {
     use crate::kernel::fastpath::fastpath_call;
     #[cfg(feature = "enable_smp")]
     clh_lock_acquire(cpu_id(), false);
-    fastpath_call(cptr, msgInfo);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    fastpath_call(cptr, msgInfo, entry.state());
 }
 
 #[no_mangle]
@@ -267,7 +268,8 @@ pub fn c_handle_fastpath_reply_recv(cptr: usize, msgInfo: usize) {
     use crate::kernel::fastpath::fastpath_reply_recv;
     #[cfg(feature = "enable_smp")]
     clh_lock_acquire(cpu_id(), false);
-    fastpath_reply_recv(cptr, msgInfo);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    fastpath_reply_recv(cptr, msgInfo, entry.state());
 }
 
 #[no_mangle]
@@ -278,5 +280,6 @@ pub fn c_handle_fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize) {
     use crate::kernel::fastpath::fastpath_reply_recv;
     #[cfg(feature = "enable_smp")]
     clh_lock_acquire(cpu_id(), false);
-    fastpath_reply_recv(cptr, msgInfo, reply);
+    let mut entry = unsafe { kernel_entry_t::enter() };
+    fastpath_reply_recv(cptr, msgInfo, reply, entry.state());
 }
@@ -14,7 +14,7 @@ use core::intrinsics::likely;
 use log::debug;
 use sel4_common::{
     arch::ArchReg::*,
-    ffi::current_fault,
+    kernel_state::kernel_state_t,
     platform::read_time,
     print,
     sel4_config::*,
@@ -28,7 +28,7 @@ use sel4_task::{activateThread, get_currenct_thread, schedule};
 use sel4_task::{check_budget_restart, update_timestamp};
 
 #[no_mangle]
-pub fn handle_unknown_syscall(w: isize) -> exception_t {
+pub fn handle_unknown_syscall(w: isize, ctx: &mut kernel_state_t) -> exception_t {
     let thread = get_currenct_thread();
     if w == SYS_DEBUG_PUT_CHAR {
         print!("{}", thread.tcbArch.get_register(Cap) as u8 as char);
@@ -80,36 +80,44 @@ pub fn handle_unknown_syscall(w: isize) -> exception_t {
         // setThreadName(TCB_PTR(cap_thread_cap_get_capTCBPtr(lu_ret.cap)), name);
         return exception_t::EXCEPTION_NONE;
     }
+    #[cfg(feature = "clh_lock_stats")]
+    if w == crate::syscall::SYS_DEBUG_LOCK_STATS {
+        crate::smp::handle_debug_lock_stats(thread);
+        return exception_t::EXCEPTION_NONE;
+    }
+    #[cfg(feature = "irq_stats")]
+    if w == crate::syscall::SYS_DEBUG_IRQ_STATS {
+        crate::interrupt::stats::handle_debug_irq_stats(thread);
+        return exception_t::EXCEPTION_NONE;
+    }
     if w == SYS_GET_CLOCK {
         let current = read_time();
         thread.tcbArch.set_register(Cap, current);
         return exception_t::EXCEPTION_NONE;
     }
-    unsafe {
-        current_fault = seL4_Fault_UnknownSyscall::new(w as u64).unsplay();
-        handle_fault(get_currenct_thread());
-    }
+    ctx.fault = seL4_Fault_UnknownSyscall::new(w as u64).unsplay();
+    handle_fault(get_currenct_thread(), ctx);
     schedule();
     activateThread();
     exception_t::EXCEPTION_NONE
 }
 
 #[no_mangle]
-pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
+pub fn handleUserLevelFault(w_a: usize, w_b: usize, ctx: &mut kernel_state_t) -> exception_t {
     #[cfg(feature = "kernel_mcs")]
     {
         update_timestamp();
         if likely(check_budget_restart()) {
-            unsafe {
-                current_fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
-                handle_fault(get_currenct_thread());
+            {
+                ctx.fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
+                handle_fault(get_currenct_thread(), ctx);
             }
         }
     }
     #[cfg(not(feature = "kernel_mcs"))]
-    unsafe {
-        current_fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
-        handle_fault(get_currenct_thread());
+    {
+        ctx.fault = seL4_Fault_UserException::new(w_a as u64, w_b as u64).unsplay();
+        handle_fault(get_currenct_thread(), ctx);
     }
     schedule();
     activateThread();
@@ -117,22 +125,22 @@ pub fn handleUserLevelFault(w_a: usize, w_b: usize) -> exception_t {
 }
 
 #[no_mangle]
-pub fn handleVMFaultEvent(vm_faultType: usize) -> exception_t {
+pub fn handleVMFaultEvent(vm_faultType: usize, ctx: &mut kernel_state_t) -> exception_t {
     #[cfg(feature = "kernel_mcs")]
     {
         update_timestamp();
         if likely(check_budget_restart()) {
-            let status = handle_vm_fault(vm_faultType);
+            let status = handle_vm_fault(vm_faultType, ctx);
             if status != exception_t::EXCEPTION_NONE {
-                handle_fault(get_currenct_thread());
+                handle_fault(get_currenct_thread(), ctx);
             }
         }
     }
     #[cfg(not(feature = "kernel_mcs"))]
     {
-        let status = handle_vm_fault(vm_faultType);
+        let status = handle_vm_fault(vm_faultType, ctx);
         if status != exception_t::EXCEPTION_NONE {
-            handle_fault(get_currenct_thread());
+            handle_fault(get_currenct_thread(), ctx);
         }
     }
     schedule();
@@ -140,31 +148,23 @@ pub fn handleVMFaultEvent(vm_faultType: usize) -> exception_t {
     exception_t::EXCEPTION_NONE
 }
 
-pub fn handle_vm_fault(type_: usize) -> exception_t {
+pub fn handle_vm_fault(type_: usize, ctx: &mut kernel_state_t) -> exception_t {
     let addr = read_stval();
     match type_ {
         RISCV_LOAD_PAGE_FAULT | RISCV_LOAD_ACCESS_FAULT => {
-            unsafe {
-                current_fault =
-                    seL4_Fault_VMFault::new(addr as u64, RISCV_LOAD_ACCESS_FAULT as u64, 0)
-                        .unsplay();
-            }
+            ctx.fault =
+                seL4_Fault_VMFault::new(addr as u64, RISCV_LOAD_ACCESS_FAULT as u64, 0).unsplay();
             exception_t::EXCEPTION_FAULT
         }
         RISCV_STORE_PAGE_FAULT | RISCV_STORE_ACCESS_FAULT => {
-            unsafe {
-                current_fault =
-                    seL4_Fault_VMFault::new(addr as u64, RISCV_STORE_ACCESS_FAULT as u64, 0)
-                        .unsplay();
-            }
+            ctx.fault =
+                seL4_Fault_VMFault::new(addr as u64, RISCV_STORE_ACCESS_FAULT as u64, 0).unsplay();
             exception_t::EXCEPTION_FAULT
         }
         RISCV_INSTRUCTION_ACCESS_FAULT | RISCV_INSTRUCTION_PAGE_FAULT => {
-            unsafe {
-                current_fault =
-                    seL4_Fault_VMFault::new(addr as u64, RISCV_INSTRUCTION_ACCESS_FAULT as u64, 1)
-                        .unsplay();
-            }
+            ctx.fault =
+                seL4_Fault_VMFault::new(addr as u64, RISCV_INSTRUCTION_ACCESS_FAULT as u64, 1)
+                    .unsplay();
             exception_t::EXCEPTION_FAULT
         }
         _ => panic!("Invalid VM fault type:{}", type_),
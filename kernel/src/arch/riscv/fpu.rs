@@ -7,9 +7,17 @@ use core::{
 
 use sel4_common::arch::arch_tcb::FPUState;
 use sel4_common::arch::ArchReg;
-use sel4_common::sel4_config::CONFIG_FPU_MAX_RESTORES_SINCE_SWITCH;
+use sel4_common::sel4_config::{CONFIG_FPU_MAX_RESTORES_SINCE_SWITCH, CONFIG_MAX_NUM_NODES};
 use sel4_common::utils::cpu_id;
-use sel4_task::{get_currenct_thread, tcb_t, NODE_STATE, SET_NODE_STATE};
+use sel4_task::{get_currenct_thread, tcb_t, NODE_STATE, NODE_STATE_ON_CORE, SET_NODE_STATE};
+
+#[cfg(feature = "riscv_ext_v")]
+use core::{
+    mem::size_of,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+#[cfg(feature = "riscv_ext_v")]
+use sel4_common::sel4_config::{SEL4_TCB_BITS, TCB_OFFSET};
 
 #[cfg(feature = "enable_smp")]
 use crate::smp::ipi::remote_switch_fpu_owner;
@@ -19,12 +27,37 @@ const SSTATUS_FS_CLEAN: u32 = 0x00004000;
 const SSTATUS_FS_INITIAL: u32 = 0x00002000;
 const SSTATUS_FS_DIRTY: u32 = 0x00006000;
 
+#[cfg(feature = "riscv_ext_v")]
+const SSTATUS_VS: usize = 0x00000600;
+#[cfg(feature = "riscv_ext_v")]
+const SSTATUS_VS_CLEAN: usize = 0x00000400;
+
+/// `vstart`, `vtype`, `vl` and `vcsr` come first in the vector state, then v0-v31.
+#[cfg(feature = "riscv_ext_v")]
+const VSTATE_REGS_OFFSET: usize = 4 * size_of::<usize>();
+
+/// The vector state of a thread lives in the TCB object right after the `tcb_t`.
+#[cfg(feature = "riscv_ext_v")]
+const VSTATE_OFFSET: usize = (TCB_OFFSET + size_of::<tcb_t>()).next_multiple_of(16);
+
+/// The smallest `vlenb` of V, whose VLEN is at least 128, must fit in every TCB object.
+#[cfg(feature = "riscv_ext_v")]
+const _: () = assert!(
+    VSTATE_OFFSET + VSTATE_REGS_OFFSET + 32 * 16 <= bit!(SEL4_TCB_BITS),
+    "no room for the vector state in the TCB object"
+);
+
+/// The size of the vector state of a thread, sized from `vlenb` at boot. 0 if the vector
+/// state is not switched and any vector instruction faults.
+#[cfg(feature = "riscv_ext_v")]
+static VSTATE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
 // extern "C" {
 //     pub fn saveFpuState(dest: usize);
 //     pub fn loadFpuState(src: usize);
 // }
-// TODO: support smp
-static mut IS_FPU_ENABLE_CACHED: bool = false;
+/// Whether the FPU is enabled for the thread running on each core.
+static mut IS_FPU_ENABLE_CACHED: [bool; CONFIG_MAX_NUM_NODES] = [false; CONFIG_MAX_NUM_NODES];
 
 #[cfg(feature = "riscv_ext_d")]
 #[inline]
@@ -199,6 +232,113 @@ pub fn load_fpu_state(src: usize) {
     }
 }
 
+/// Read `vlenb`, size the vector state of the threads from it and enable the vector state
+/// switching if it fits in the space left in the TCB object. Only called if every hart
+/// implements V.
+#[cfg(feature = "riscv_ext_v")]
+pub fn init_vector() -> bool {
+    let vlenb: usize;
+    unsafe {
+        set_vs_clean();
+        asm!(".option push", ".option arch, +v", "csrr {0}, vlenb", ".option pop", out(reg) vlenb);
+    }
+    let size = VSTATE_REGS_OFFSET + 32 * vlenb;
+    if VSTATE_OFFSET + size > bit!(SEL4_TCB_BITS) {
+        log::warn!("vlenb {} is too large for the TCB, vector disabled", vlenb);
+        return false;
+    }
+    VSTATE_SIZE.store(size, Ordering::Relaxed);
+    true
+}
+
+/// The size of the vector state of a thread, 0 if the vector state is not switched.
+#[cfg(feature = "riscv_ext_v")]
+#[inline]
+pub fn vector_state_size() -> usize {
+    VSTATE_SIZE.load(Ordering::Relaxed)
+}
+
+#[cfg(feature = "riscv_ext_v")]
+#[inline]
+fn vector_enabled() -> bool {
+    vector_state_size() != 0
+}
+
+/// The size of a group of 8 vector registers, the unit of the whole register loads and
+/// stores.
+#[cfg(feature = "riscv_ext_v")]
+#[inline]
+fn vector_group_size() -> usize {
+    (vector_state_size() - VSTATE_REGS_OFFSET) / 4
+}
+
+/// The vector state of the thread owning `fpu_state`.
+#[cfg(feature = "riscv_ext_v")]
+#[inline]
+fn vector_state_ptr(fpu_state: usize) -> usize {
+    (fpu_state & !mask_bits!(SEL4_TCB_BITS)) + VSTATE_OFFSET
+}
+
+#[cfg(feature = "riscv_ext_v")]
+unsafe fn save_vector_state(dest: usize) {
+    set_vs_clean();
+    asm!(
+        ".option push",
+        ".option arch, +v",
+        "csrr {tmp}, vstart",
+        "sd {tmp}, 0({0})",
+        "csrr {tmp}, vtype",
+        "sd {tmp}, 8({0})",
+        "csrr {tmp}, vl",
+        "sd {tmp}, 16({0})",
+        "csrr {tmp}, vcsr",
+        "sd {tmp}, 24({0})",
+        "csrw vstart, zero",
+        "addi {0}, {0}, 32",
+        "vs8r.v v0, ({0})",
+        "add {0}, {0}, {group}",
+        "vs8r.v v8, ({0})",
+        "add {0}, {0}, {group}",
+        "vs8r.v v16, ({0})",
+        "add {0}, {0}, {group}",
+        "vs8r.v v24, ({0})",
+        ".option pop",
+        inout(reg) dest => _,
+        group = in(reg) vector_group_size(),
+        tmp = out(reg) _,
+    );
+}
+
+#[cfg(feature = "riscv_ext_v")]
+unsafe fn load_vector_state(src: usize) {
+    set_vs_clean();
+    asm!(
+        ".option push",
+        ".option arch, +v",
+        "csrw vstart, zero",
+        "addi {regs}, {0}, 32",
+        "vl8r.v v0, ({regs})",
+        "add {regs}, {regs}, {group}",
+        "vl8r.v v8, ({regs})",
+        "add {regs}, {regs}, {group}",
+        "vl8r.v v16, ({regs})",
+        "add {regs}, {regs}, {group}",
+        "vl8r.v v24, ({regs})",
+        "ld {tmp}, 16({0})",
+        "ld {regs}, 8({0})",
+        "vsetvl zero, {tmp}, {regs}",
+        "ld {tmp}, 24({0})",
+        "csrw vcsr, {tmp}",
+        "ld {tmp}, 0({0})",
+        "csrw vstart, {tmp}",
+        ".option pop",
+        in(reg) src,
+        group = in(reg) vector_group_size(),
+        regs = out(reg) _,
+        tmp = out(reg) _,
+    );
+}
+
 #[inline]
 pub(crate) fn read_fcsr() -> u32 {
     let fcsr: u32;
@@ -229,29 +369,44 @@ pub unsafe fn set_fs_dirty() {
 pub unsafe fn set_fs_off() {
     asm!("csrc sstatus, {0}", in(reg) SSTATUS_FS);
 }
+#[cfg(feature = "riscv_ext_v")]
+#[inline]
+pub unsafe fn set_vs_clean() {
+    asm!("csrs sstatus, {0}", in(reg) SSTATUS_VS_CLEAN);
+}
 
 #[inline]
 pub(crate) unsafe fn enable_fpu() {
-    IS_FPU_ENABLE_CACHED = true;
+    IS_FPU_ENABLE_CACHED[cpu_id()] = true;
 }
 
 #[inline]
 pub(crate) unsafe fn disable_fpu() {
-    IS_FPU_ENABLE_CACHED = false
+    IS_FPU_ENABLE_CACHED[cpu_id()] = false
 }
 
 #[inline]
 #[allow(unused)]
 pub unsafe fn is_fpu_enable() -> bool {
-    return IS_FPU_ENABLE_CACHED;
+    return IS_FPU_ENABLE_CACHED[cpu_id()];
 }
 #[inline]
 #[allow(unused)]
 pub unsafe fn set_tcb_fs_state(tcb: &mut tcb_t, enabled: bool) {
     let mut sstatus: usize = tcb.tcbArch.get_register(ArchReg::SSTATUS);
     sstatus &= !SSTATUS_FS;
+    #[cfg(feature = "riscv_ext_v")]
+    {
+        sstatus &= !SSTATUS_VS;
+    }
     if enabled {
         sstatus |= SSTATUS_FS_CLEAN as usize;
+        // The vector unit follows the FPU, so the first vector instruction of a thread
+        // that does not own the FPU traps as well.
+        #[cfg(feature = "riscv_ext_v")]
+        if vector_enabled() {
+            sstatus |= SSTATUS_VS_CLEAN;
+        }
     }
     tcb.tcbArch.set_register(ArchReg::SSTATUS, sstatus);
 }
@@ -264,11 +419,19 @@ pub unsafe fn switch_local_fpu_owner(new_owner: usize) {
         let ksActiveFPUState = NODE_STATE!(ksActiveFPUState);
         if ksActiveFPUState != 0 {
             save_fpu_state(ksActiveFPUState);
+            #[cfg(feature = "riscv_ext_v")]
+            if vector_enabled() {
+                save_vector_state(vector_state_ptr(ksActiveFPUState));
+            }
         }
 
         if new_owner != 0 {
             SET_NODE_STATE!(ks_fpu_restore_since_switch = 0);
             load_fpu_state(new_owner as *const FPUState as usize);
+            #[cfg(feature = "riscv_ext_v")]
+            if vector_enabled() {
+                load_vector_state(vector_state_ptr(new_owner));
+            }
         } else {
             disable_fpu();
         }
@@ -292,11 +455,16 @@ pub(crate) unsafe fn handle_fpu_fault() {
     switch_local_fpu_owner(new_owner as usize);
 }
 
+/// Whether `thread` owns the FPU of the core it is affine to, which may not be the
+/// current core.
 #[inline(always)]
 unsafe fn native_thread_using_fpu(thread: &mut tcb_t) -> bool {
-    return thread.tcbArch.fpu_state_ptr() as usize == NODE_STATE!(ksActiveFPUState);
+    return thread.tcbArch.fpu_state_ptr() as usize
+        == NODE_STATE_ON_CORE!(thread.get_cpu(), ksActiveFPUState);
 }
 
+/// Give up the FPU owned by `thread`, saving its state. A thread affine to another
+/// core is handled there through a remote call, see [`switch_fpu_owner`].
 #[cfg(feature = "enable_smp")]
 #[inline(always)]
 pub fn fpu_thread_delete(thread: &mut tcb_t) {
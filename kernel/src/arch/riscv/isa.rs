@@ -0,0 +1,47 @@
+//! The optional ISA extensions, probed from the `riscv,isa` strings of the DTB.
+use rel4_arch::basic::PAddr;
+use rel4_utils::fdt::{prop_strings, FdtNode};
+
+use crate::boot::fdt::fdt_at;
+
+/// Whether the hart of `cpu` implements the extension `ext`.
+///
+/// Both the `riscv,isa` string, in which the single-letter extensions follow the `rv64`
+/// prefix and the multi-letter extensions follow the base ISA separated by `_`, and the
+/// newer `riscv,isa-extensions` list are checked.
+fn hart_has_ext(cpu: &FdtNode, ext: &str) -> bool {
+    if let Some(isa) = cpu.property_str("riscv,isa") {
+        let mut parts = isa.split('_');
+        let base = parts
+            .next()
+            .and_then(|base| base.get(4..))
+            .unwrap_or_default();
+        if let [letter] = ext.as_bytes() {
+            if base.bytes().any(|b| b.eq_ignore_ascii_case(letter)) {
+                return true;
+            }
+        } else if parts.any(|e| e.eq_ignore_ascii_case(ext)) {
+            return true;
+        }
+    }
+    cpu.property("riscv,isa-extensions")
+        .is_some_and(|exts| prop_strings(exts).any(|e| e.eq_ignore_ascii_case(ext)))
+}
+
+/// Whether every hart described by the DTB implements `ext`, false without a DTB.
+pub fn isa_has_ext(dtb_phys_addr: PAddr, dtb_size: usize, ext: &str) -> bool {
+    let Some(fdt) = fdt_at(dtb_phys_addr, dtb_size) else {
+        return false;
+    };
+    let mut harts = 0;
+    let mut found = 0;
+    fdt.for_each_node(|node| {
+        if node.property_str("device_type") == Some("cpu") {
+            harts += 1;
+            if hart_has_ext(node, ext) {
+                found += 1;
+            }
+        }
+    });
+    harts > 0 && found == harts
+}
@@ -1,7 +1,12 @@
 mod boot;
 mod c_traps;
 mod exception;
+mod isa;
 mod platform;
+pub mod plic;
+
+#[cfg(feature = "riscv_aia")]
+pub mod aia;
 
 #[cfg(feature = "have_fpu")]
 pub mod fpu;
@@ -22,6 +27,52 @@ pub use exception::handle_unknown_syscall;
 #[cfg(feature = "enable_smp")]
 pub use boot::try_init_kernel_secondary_core;
 
+/// Claim the pending external interrupt of this hart, from the IMSIC with `riscv_aia`.
+#[inline]
+pub fn claim_external_irq() -> usize {
+    #[cfg(feature = "riscv_aia")]
+    {
+        aia::imsic_claim()
+    }
+    #[cfg(not(feature = "riscv_aia"))]
+    {
+        plic::plic_get_claim()
+    }
+}
+
+/// Set how the external interrupt `irq` is triggered, the PLIC has no such setting.
+#[inline]
+#[cfg_attr(not(feature = "riscv_aia"), allow(unused_variables))]
+pub fn set_irq_trigger(irq: usize, edge_triggered: bool) {
+    #[cfg(feature = "riscv_aia")]
+    if aia::is_aplic_irq(irq) {
+        aia::aplic_set_trigger(irq, edge_triggered);
+    }
+}
+
+/// Route the external interrupt `irq` to `core`.
+#[cfg(feature = "enable_smp")]
+pub fn set_irq_target(irq: usize, core: usize) {
+    #[cfg(feature = "riscv_aia")]
+    aia::aplic_set_irq_target(irq, core);
+    #[cfg(not(feature = "riscv_aia"))]
+    plic::plic_set_irq_target(irq, core);
+}
+
+/// The hart id of the core with index `core`.
+#[inline]
+#[cfg_attr(not(feature = "enable_smp"), allow(unused_variables))]
+pub fn core_hart_id(core: usize) -> usize {
+    #[cfg(feature = "enable_smp")]
+    {
+        sel4_common::arch::cpu_index_to_id(core)
+    }
+    #[cfg(not(feature = "enable_smp"))]
+    {
+        sel4_common::sel4_config::CONFIG_FIRST_HART_ID
+    }
+}
+
 pub fn read_stval() -> usize {
     let temp: usize;
     unsafe {
@@ -5,7 +5,7 @@ use crate::interrupt::set_sie_mask;
 use log::debug;
 use rel4_arch::basic::{PRegion, Region};
 use riscv::register::{stvec, utvec::TrapMode};
-use sel4_common::arch::config::RESET_CYCLES;
+use sel4_common::arch::config::reset_cycles;
 use sel4_common::arch::{config::KERNEL_ELF_BASE, get_time, set_timer};
 use sel4_common::sel4_config::*;
 use sel4_vspace::activate_kernel_vspace;
@@ -19,6 +19,10 @@ pub fn init_cpu() {
     unsafe {
         stvec::write(trap_entry as usize, TrapMode::Direct);
     }
+    #[cfg(not(feature = "riscv_aia"))]
+    super::plic::plic_init_hart();
+    #[cfg(feature = "riscv_aia")]
+    super::aia::imsic_init_hart();
     #[cfg(feature = "enable_smp")]
     {
         set_sie_mask(bit!(SIE_SEIE) | bit!(SIE_STIE) | bit!(SIE_SSIE));
@@ -27,7 +31,7 @@ pub fn init_cpu() {
     {
         set_sie_mask(bit!(SIE_SEIE) | bit!(SIE_STIE));
     }
-    set_timer(get_time() + RESET_CYCLES);
+    set_timer(get_time() + reset_cycles());
 
     unsafe {
         set_fs_off();
@@ -0,0 +1,161 @@
+//! The RISC-V PLIC, riscv_plic0 in seL4.
+//!
+//! Every source is enabled on the S-mode context of a single hart. Without SMP that is
+//! the boot hart, with SMP each source keeps a target core, core 0 unless set by
+//! `RISCVIRQIssueIRQHandlerTriggerCore` or `IRQSetTargetCore`. A platform without a
+//! PLIC has `PLIC_MAX_IRQ == 0` and never touches the registers.
+use core::ptr::{read_volatile, write_volatile};
+
+use sel4_common::platform::{IRQ_INVALID, PLIC_MAX_IRQ, PLIC_PPTR};
+use sel4_common::utils::cpu_id;
+
+use super::core_hart_id;
+
+const PLIC_PRIO: usize = 0x0;
+const PLIC_PRIO_PER_ID: usize = 0x4;
+const PLIC_PENDING: usize = 0x1000;
+const PLIC_EN: usize = 0x2000;
+const PLIC_EN_PER_HART: usize = 0x100;
+const PLIC_EN_PER_CONTEXT: usize = 0x80;
+const PLIC_THRES: usize = 0x200000;
+const PLIC_SVC_CONTEXT: usize = 1;
+const PLIC_THRES_PER_HART: usize = 0x2000;
+const PLIC_THRES_PER_CONTEXT: usize = 0x1000;
+const PLIC_THRES_CLAIM: usize = 0x4;
+
+/// The core each source is enabled on.
+#[cfg(feature = "enable_smp")]
+static mut plic_irq_target: [usize; PLIC_MAX_IRQ + 1] = [0; PLIC_MAX_IRQ + 1];
+
+#[inline]
+fn plic_enable_offset(hart_id: usize, context_id: usize) -> usize {
+    PLIC_EN + hart_id * PLIC_EN_PER_HART + context_id * PLIC_EN_PER_CONTEXT
+}
+
+#[inline]
+fn plic_thres_offset(hart_id: usize, context_id: usize) -> usize {
+    PLIC_THRES + hart_id * PLIC_THRES_PER_HART + context_id * PLIC_THRES_PER_CONTEXT
+}
+
+#[inline]
+fn plic_claim_offset(hart_id: usize, context_id: usize) -> usize {
+    plic_thres_offset(hart_id, context_id) + PLIC_THRES_CLAIM
+}
+
+#[inline]
+fn readl(offset: usize) -> u32 {
+    unsafe { read_volatile((PLIC_PPTR + offset) as *const u32) }
+}
+
+#[inline]
+fn writel(val: u32, offset: usize) {
+    unsafe { write_volatile((PLIC_PPTR + offset) as *mut u32, val) }
+}
+
+#[inline]
+#[cfg_attr(not(feature = "enable_smp"), allow(unused_variables))]
+fn plic_irq_core(irq: usize) -> usize {
+    #[cfg(feature = "enable_smp")]
+    unsafe {
+        plic_irq_target[irq]
+    }
+    #[cfg(not(feature = "enable_smp"))]
+    {
+        0
+    }
+}
+
+/// Whether `irq` is a PLIC source.
+#[inline]
+pub fn is_plic_irq(irq: usize) -> bool {
+    irq != IRQ_INVALID && irq <= PLIC_MAX_IRQ
+}
+
+/// Whether the gateway forwarded `irq` and it is not claimed yet.
+pub fn plic_pending_interrupt(irq: usize) -> bool {
+    readl(PLIC_PENDING + (irq / 32) * 4) & (1 << (irq % 32)) != 0
+}
+
+fn plic_enable_irq(enable: bool, irq: usize, core: usize) {
+    let offset = plic_enable_offset(core_hart_id(core), PLIC_SVC_CONTEXT) + (irq / 32) * 4;
+    let val = readl(offset);
+    if enable {
+        writel(val | (1 << (irq % 32)), offset);
+    } else {
+        writel(val & !(1 << (irq % 32)), offset);
+    }
+}
+
+#[cfg(feature = "enable_smp")]
+fn plic_irq_enabled(irq: usize, core: usize) -> bool {
+    let offset = plic_enable_offset(core_hart_id(core), PLIC_SVC_CONTEXT) + (irq / 32) * 4;
+    readl(offset) & (1 << (irq % 32)) != 0
+}
+
+/// Claim the highest priority pending source of this hart, `IRQ_INVALID` if none.
+pub fn plic_get_claim() -> usize {
+    if PLIC_MAX_IRQ == 0 {
+        return IRQ_INVALID;
+    }
+    let hart_id = core_hart_id(cpu_id());
+    match readl(plic_claim_offset(hart_id, PLIC_SVC_CONTEXT)) as usize {
+        0 => IRQ_INVALID,
+        irq => irq,
+    }
+}
+
+/// Complete the claim of `irq`, the source raises again only after this.
+///
+/// The completion goes to the context `irq` is enabled on, so the hart that acks it
+/// needn't be the one that claimed it.
+pub fn plic_complete_claim(irq: usize) {
+    let hart_id = core_hart_id(plic_irq_core(irq));
+    writel(irq as u32, plic_claim_offset(hart_id, PLIC_SVC_CONTEXT));
+}
+
+/// Mask or unmask `irq` on the hart it targets.
+pub fn plic_mask_irq(disable: bool, irq: usize) {
+    plic_enable_irq(!disable, irq, plic_irq_core(irq));
+}
+
+/// Route `irq` to `core`, it stays masked or unmasked as it was on the old core.
+#[cfg(feature = "enable_smp")]
+pub fn plic_set_irq_target(irq: usize, core: usize) {
+    let old = plic_irq_core(irq);
+    if old == core {
+        return;
+    }
+    let enabled = plic_irq_enabled(irq, old);
+    if enabled {
+        plic_enable_irq(false, irq, old);
+    }
+    unsafe { plic_irq_target[irq] = core };
+    if enabled {
+        plic_enable_irq(true, irq, core);
+    }
+}
+
+/// Mask every source on the context of this hart and let all priorities through.
+pub fn plic_init_hart() {
+    let core = cpu_id();
+    for irq in 1..=PLIC_MAX_IRQ {
+        plic_enable_irq(false, irq, core);
+    }
+    if PLIC_MAX_IRQ > 0 {
+        writel(0, plic_thres_offset(core_hart_id(core), PLIC_SVC_CONTEXT));
+    }
+}
+
+/// Drop the claims left by the firmware and give every source the same priority.
+pub fn plic_init_controller() {
+    let hart_id = core_hart_id(cpu_id());
+    for irq in 1..=PLIC_MAX_IRQ {
+        if plic_pending_interrupt(irq) {
+            readl(plic_claim_offset(hart_id, PLIC_SVC_CONTEXT));
+            writel(irq as u32, plic_claim_offset(hart_id, PLIC_SVC_CONTEXT));
+        }
+    }
+    for irq in 1..=PLIC_MAX_IRQ {
+        writel(2, PLIC_PRIO + PLIC_PRIO_PER_ID * irq);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::smp::clh_is_ipi_pending;
 use core::sync::atomic::{fence, Ordering};
 use sel4_common::arch::config::{IRQ_REMOTE_CALL_IPI, IRQ_RESCHEDULE_IPI};
-use sel4_common::arch::{hart_id_to_core_id, sbi_send_ipi};
+use sel4_common::arch::hart_id_to_core_id;
+#[cfg(not(feature = "riscv_aia"))]
+use sel4_common::arch::sbi_send_ipi;
 use sel4_common::platform::IRQ_INVALID;
 use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
 use sel4_common::utils::cpu_id;
@@ -21,7 +23,14 @@ pub fn arch_pause() {
     fence(Ordering::SeqCst);
 }
 
+/// Drop the translations and instructions cached while this hart was parked.
+#[cfg(feature = "cpu_hotplug")]
+pub fn cpu_flush_local() {
+    unsafe { core::arch::asm!("sfence.vma", "fence.i") };
+}
+
 pub fn ipi_send_target(irq: usize, target: usize) {
+    #[cfg(not(feature = "riscv_aia"))]
     let mask = bit!(target);
     let core_id = hart_id_to_core_id(target);
     assert!(core_id < CONFIG_MAX_NUM_NODES);
@@ -34,6 +43,9 @@ pub fn ipi_send_target(irq: usize, target: usize) {
         ipi_irq[core_id] = irq;
     }
     fence(Ordering::SeqCst);
+    #[cfg(feature = "riscv_aia")]
+    super::aia::imsic_send_ipi(target, irq);
+    #[cfg(not(feature = "riscv_aia"))]
     sbi_send_ipi(mask);
 }
 
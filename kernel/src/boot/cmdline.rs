@@ -0,0 +1,121 @@
+//! Kernel options from the `bootargs` of the DTB `/chosen` node.
+//!
+//! The command line is a list of options separated by spaces, each either a flag or a
+//! `key=value` pair:
+//!
+//! * `loglevel=off|error|warn|info|debug|trace` - replaces the `LOG` level of the build
+//! * `log=<module>[,<module>...]` - only print the records of these modules
+//! * `nofastpath` - send every call and reply through the slowpath
+//! * `dom_schedule=<domain>:<length>[,...]` - the domain schedule, lengths are in the
+//!   unit of the built-in schedule
+//! * `console=default|off|<paddr>` - print on the platform UART, nowhere, or on the
+//!   platform UART at `paddr`, a kernel device with the desc `uart`
+//! * `idle=wfi|suspend` - whether an idle core may suspend, with `idle_suspend`
+//!
+//! Options the kernel doesn't know are left to the user, who also gets the DTB. Invalid
+//! values are reported and the built-in setting is kept.
+use log::{warn, LevelFilter};
+use rel4_arch::basic::PAddr;
+use rel4_utils::cmdline::{options, parse_usize};
+use sel4_common::console::{set_console, CONSOLE_DEFAULT, CONSOLE_OFF};
+use sel4_common::logging::{set_level, set_module_filter};
+use sel4_common::platform::{kernel_device_frames, KERNEL_UART_PADDRS};
+use sel4_common::sel4_config::CONFIG_NUM_DOMAINS;
+use sel4_task::{dschedule_t, set_dom_schedule, set_idle_suspend, KS_DOM_SCHEDULE_MAX};
+
+use super::fdt::fdt_at;
+use crate::kernel::fastpath::set_fastpath_enabled;
+
+/// The levels of `rel4_utils::cmdline::LOG_LEVELS`.
+const LEVEL_FILTERS: [LevelFilter; 6] = [
+    LevelFilter::Off,
+    LevelFilter::Error,
+    LevelFilter::Warn,
+    LevelFilter::Info,
+    LevelFilter::Debug,
+    LevelFilter::Trace,
+];
+
+fn parse_level(value: &str) -> Option<LevelFilter> {
+    rel4_utils::cmdline::parse_level(value).map(|level| LEVEL_FILTERS[level])
+}
+
+/// The domain schedule in `value` and its number of entries, None if an entry is invalid.
+fn parse_dom_schedule(value: &str) -> Option<([dschedule_t; KS_DOM_SCHEDULE_MAX], usize)> {
+    let mut entries = [(0, 0); KS_DOM_SCHEDULE_MAX];
+    let n = rel4_utils::cmdline::parse_dom_schedule(value, CONFIG_NUM_DOMAINS, &mut entries)?;
+    let mut schedule = [dschedule_t {
+        domain: 0,
+        length: 0,
+    }; KS_DOM_SCHEDULE_MAX];
+    for (entry, &(domain, length)) in schedule.iter_mut().zip(&entries[..n]) {
+        *entry = dschedule_t { domain, length };
+    }
+    Some((schedule, n))
+}
+
+/// The console for `value`, a UART given by its physical address must be one of the
+/// platform UARTs.
+fn parse_console(value: &str) -> Option<usize> {
+    match value {
+        "default" => return Some(CONSOLE_DEFAULT),
+        "off" => return Some(CONSOLE_OFF),
+        _ => {}
+    }
+    if cfg!(target_arch = "riscv64") {
+        // the SBI console has no UART to choose
+        return None;
+    }
+    let paddr = parse_usize(value)?;
+    if !KERNEL_UART_PADDRS.contains(&paddr) {
+        return None;
+    }
+    unsafe {
+        (*(&raw const kernel_device_frames))
+            .iter()
+            .find(|frame| frame.paddr.raw() == paddr)
+            .map(|frame| frame.pptr.raw())
+    }
+}
+
+fn apply_option(key: &str, value: Option<&str>) {
+    let valid = match (key, value) {
+        ("loglevel", Some(value)) => parse_level(value).map(set_level).is_some(),
+        ("log", Some(value)) => set_module_filter(value),
+        ("nofastpath", None) => {
+            set_fastpath_enabled(false);
+            true
+        }
+        ("dom_schedule", Some(value)) => {
+            parse_dom_schedule(value).is_some_and(|(schedule, n)| set_dom_schedule(&schedule[..n]))
+        }
+        ("console", Some(value)) => parse_console(value).map(set_console).is_some(),
+        ("idle", Some("wfi")) => set_idle_suspend(false),
+        ("idle", Some("suspend")) => set_idle_suspend(true),
+        ("idle", _) => false,
+        _ => return,
+    };
+    if !valid {
+        warn!(
+            "bootargs: ignoring invalid option {}={}",
+            key,
+            value.unwrap_or("")
+        );
+    }
+}
+
+/// Apply the kernel options of the DTB `bootargs`, before the root server is created.
+pub fn parse_bootargs(dtb_phys_addr: PAddr, dtb_size: usize) {
+    let Some(fdt) = fdt_at(dtb_phys_addr, dtb_size) else {
+        return;
+    };
+    let mut bootargs = None;
+    fdt.for_each_node(|node| {
+        if node.depth == 1 && node.name == "chosen" {
+            bootargs = node.property_str("bootargs");
+        }
+    });
+    for (key, value) in options(bootargs.unwrap_or("")) {
+        apply_option(key, value);
+    }
+}
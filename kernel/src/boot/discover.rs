@@ -0,0 +1,172 @@
+//! Discovery of the platform from the device tree, with `dtb_platform`.
+//!
+//! [`discover_platform`] runs on the boot core once the kernel window is mapped, before
+//! the timer, the interrupt controller and the free memory are set up. What the DTB
+//! describes replaces the platform YAML, which is kept for what it doesn't:
+//!
+//! * the RAM, minus the `/memreserve/` entries and the `reserved-memory` nodes, replaces
+//!   the `avail_mem_zone`
+//! * the number of enabled `cpu` nodes bounds the cores the boot core waits for
+//! * the timer frequency replaces the one the kernel is built for
+//! * on aarch64 the console is the UART of `stdout-path`, a platform UART as it is or
+//!   another one in the place of the default UART, and the GIC kernel devices move by
+//!   the offset between the first interrupt controller and the GIC distributor
+//!
+//! On riscv64 the console is the SBI one, and the PLIC or APLIC stays where the YAML
+//! puts it: the DTB lists the machine-level controllers as well, which the first
+//! `interrupt-controller` doesn't tell apart. The DTB is parsed with
+//! [`rel4_utils::fdt_platform`].
+use log::{info, warn};
+use rel4_arch::basic::{PAddr, PRegion};
+use rel4_utils::fdt_platform::DtPlatform;
+use sel4_common::platform::time_def::{set_timer_clock_hz, TIMER_CLOCK_HZ};
+use sel4_common::sel4_config::{CONFIG_MAX_NUM_NODES, MAX_NUM_FREEMEM_REG};
+
+use super::fdt::fdt_at;
+use super::interface::pRegsToR;
+use super::mm::{avail_p_regs_addr, avail_p_regs_size};
+
+#[link_section = ".boot.bss"]
+static mut dt_avail_p_regs: [PRegion; MAX_NUM_FREEMEM_REG] =
+    [PRegion::empty(); MAX_NUM_FREEMEM_REG];
+
+/// The cores started at boot, all of them unless the DTB describes fewer.
+static mut dt_num_cpus: usize = CONFIG_MAX_NUM_NODES;
+
+/// Print on the UART at `uart`, on aarch64. A platform UART becomes the console, another
+/// one takes the kernel mapping of the default UART.
+#[cfg(target_arch = "aarch64")]
+fn use_uart(uart: usize) {
+    use sel4_common::arch::config::KDEV_BASE;
+    use sel4_common::console::set_console;
+    use sel4_common::platform::{kernel_device_frames, KERNEL_UART_PADDRS};
+    use sel4_common::sel4_config::PAGE_BITS;
+
+    let frames = unsafe { &*(&raw const kernel_device_frames) };
+    let Some(default) = frames
+        .iter()
+        .find(|frame| frame.pptr.raw() == KDEV_BASE)
+        .map(|frame| frame.paddr.raw())
+    else {
+        return;
+    };
+    if uart == default {
+        return;
+    }
+    let platform_uart = frames
+        .iter()
+        .find(|frame| frame.paddr.raw() == uart && KERNEL_UART_PADDRS.contains(&uart));
+    if let Some(frame) = platform_uart {
+        set_console(frame.pptr.raw());
+    } else if uart % bit!(PAGE_BITS) == 0 {
+        sel4_vspace::move_kernel_devices(&[default], uart as isize - default as isize);
+    } else {
+        warn!(
+            "DTB: the UART at {:#x} is not page aligned, keeping {:#x}",
+            uart, default
+        );
+        return;
+    }
+    info!("DTB: console on the UART at {:#x}", uart);
+}
+
+/// Move the GIC kernel devices so that the distributor is at `intc`, on aarch64. The
+/// other GIC frames keep their offset to the distributor.
+#[cfg(target_arch = "aarch64")]
+fn use_intc(intc: usize) {
+    use sel4_common::platform::KERNEL_INTC_PADDRS;
+    use sel4_common::sel4_config::PAGE_BITS;
+
+    let Some(&distributor) = KERNEL_INTC_PADDRS.first() else {
+        return;
+    };
+    if intc == distributor {
+        return;
+    }
+    if intc % bit!(PAGE_BITS) != 0 {
+        warn!(
+            "DTB: the interrupt controller at {:#x} is not page aligned, keeping {:#x}",
+            intc, distributor
+        );
+        return;
+    }
+    sel4_vspace::move_kernel_devices(KERNEL_INTC_PADDRS, intc as isize - distributor as isize);
+    info!("DTB: interrupt controller at {:#x}", intc);
+}
+
+/// Warn about a DTB whose RAM or cores the kernel can't all use.
+fn validate<const N: usize>(dt: &DtPlatform<N>) {
+    if dt.overflow {
+        warn!(
+            "DTB: more memory regions than MAX_NUM_FREEMEM_REG ({}), some are left out",
+            MAX_NUM_FREEMEM_REG
+        );
+    }
+    if dt.num_cpus > CONFIG_MAX_NUM_NODES {
+        warn!(
+            "DTB: {} cores, only the first {} are used",
+            dt.num_cpus, CONFIG_MAX_NUM_NODES
+        );
+    }
+    let zones = unsafe {
+        core::slice::from_raw_parts(avail_p_regs_addr as *const PRegion, avail_p_regs_size)
+    };
+    let covered = zones.iter().all(|zone| {
+        dt.mem[..dt.n_mem]
+            .iter()
+            .any(|&(start, end)| start <= zone.start.raw() && zone.end.raw() <= end)
+    });
+    if !covered {
+        warn!("DTB: the memory differs from the avail_mem_zone of the platform");
+    }
+}
+
+/// Take the platform from the DTB, on the boot core with the kernel vspace active and
+/// before `init_cpu`.
+pub fn discover_platform(dtb_phys_addr: PAddr, dtb_size: usize) {
+    let Some(fdt) = fdt_at(dtb_phys_addr, dtb_size) else {
+        info!("DTB: none, using the static platform configuration");
+        return;
+    };
+    let dt = DtPlatform::<MAX_NUM_FREEMEM_REG>::probe(&fdt);
+    validate(&dt);
+    if let Some(freq) = dt.timer_freq.filter(|&freq| freq != 0) {
+        set_timer_clock_hz(freq);
+        if freq != TIMER_CLOCK_HZ {
+            info!("DTB: timer at {} Hz", freq);
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if let Some(uart) = dt.uart {
+            use_uart(uart);
+        }
+        if let Some(intc) = dt.intc {
+            use_intc(intc);
+        }
+    }
+    if dt.num_cpus > 0 {
+        unsafe { dt_num_cpus = dt.num_cpus.min(CONFIG_MAX_NUM_NODES) };
+        #[cfg(feature = "cpu_hotplug")]
+        for core in num_cpus()..CONFIG_MAX_NUM_NODES {
+            crate::smp::hotplug::core_absent(core);
+        }
+    }
+    if dt.n_mem == 0 {
+        warn!("DTB: no memory, using the avail_mem_zone of the platform");
+        return;
+    }
+    unsafe {
+        for (i, &(start, end)) in dt.mem[..dt.n_mem].iter().enumerate() {
+            dt_avail_p_regs[i] = PRegion::new(PAddr::new(start), PAddr::new(end));
+        }
+        pRegsToR(&raw const dt_avail_p_regs as *const usize, dt.n_mem);
+    }
+    info!("DTB: {} memory regions, {} cores", dt.n_mem, num_cpus());
+}
+
+/// The number of cores to bring up.
+#[inline]
+pub fn num_cpus() -> usize {
+    unsafe { dt_num_cpus }
+}
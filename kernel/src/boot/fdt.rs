@@ -0,0 +1,14 @@
+//! The device tree passed in by the bootloader, parsed with [`rel4_utils::fdt`].
+use rel4_arch::basic::PAddr;
+use rel4_utils::fdt::{Fdt, FDT_HEADER_SIZE};
+use sel4_common::arch::config::PADDR_TOP;
+
+/// The device tree at `paddr`, read through the kernel window.
+pub fn fdt_at(paddr: PAddr, size: usize) -> Option<Fdt<'static>> {
+    let end = paddr.raw().checked_add(size)?;
+    if size < FDT_HEADER_SIZE || end > PADDR_TOP {
+        return None;
+    }
+    let data = unsafe { core::slice::from_raw_parts(paddr.to_pptr().raw() as *const u8, size) };
+    Fdt::new(data)
+}
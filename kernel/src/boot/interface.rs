@@ -58,6 +58,8 @@ pub fn tcb_sched_enqueue(tcb: *mut tcb_t) {
 #[no_mangle]
 pub fn switch_to_idle_thread() {
     // panic!("should not be invoke!");
+    #[cfg(feature = "hypervisor")]
+    crate::arch::vcpu::vcpu_switch(0);
     let _ = get_idle_thread().set_vm_root();
     set_current_thread(get_idle_thread());
 }
@@ -1,3 +1,7 @@
+pub mod cmdline;
+#[cfg(feature = "dtb_platform")]
+pub mod discover;
+pub mod fdt;
 pub mod interface;
 pub mod mm;
 mod root_server;
@@ -40,6 +44,19 @@ pub static mut ndks_boot: ndks_boot_t = ndks_boot_t {
     slot_pos_cur: SEL4_NUM_INITIAL_CAPS,
 };
 
+/// The number of cores that boot, fewer than `CONFIG_MAX_NUM_NODES` if the DTB says so.
+#[inline]
+pub fn num_boot_cpus() -> usize {
+    #[cfg(feature = "dtb_platform")]
+    {
+        discover::num_cpus()
+    }
+    #[cfg(not(feature = "dtb_platform"))]
+    {
+        CONFIG_MAX_NUM_NODES
+    }
+}
+
 pub fn calculate_extra_bi_size_bits(size: usize) -> usize {
     if size == 0 {
         return 0;
@@ -17,6 +17,8 @@ use sel4_common::structures_gen::cap_page_table_cap;
 use sel4_common::structures_gen::cap_smc_cap;
 #[cfg(target_arch = "aarch64")]
 use sel4_common::structures_gen::cap_vspace_cap;
+#[cfg(feature = "arm_smmu")]
+use sel4_common::structures_gen::{cap_cb_control_cap, cap_sid_control_cap};
 use sel4_common::{
     arch::{ArchReg, ArchTCB},
     platform::{IRQ_INVALID, KERNEL_TIMER_IRQ, MAX_IRQ},
@@ -76,8 +78,12 @@ pub fn root_server_init(
     init_irqs(&root_cnode_cap);
     #[cfg(feature = "enable_smc")]
     init_smc(&root_cnode_cap);
+    #[cfg(feature = "arm_smmu")]
+    init_smmu(&root_cnode_cap);
+    #[cfg(feature = "cpu_hotplug")]
+    init_cpu_control(&root_cnode_cap);
     unsafe {
-        rust_populate_bi_frame(0, CONFIG_MAX_NUM_NODES, ipcbuf_vptr, extra_bi_size);
+        rust_populate_bi_frame(0, super::num_boot_cpus(), ipcbuf_vptr, extra_bi_size);
     }
     let it_pd_cap = unsafe { rust_create_it_address_space(&root_cnode_cap, it_v_reg) };
     if it_pd_cap.clone().unsplay().get_tag() == cap_tag::cap_null_cap {
@@ -99,7 +105,7 @@ pub fn root_server_init(
     //     init_sched_control(root_cnode_cap, CONFIG_MAX_NUM_NODES);
     // #endif
     #[cfg(feature = "kernel_mcs")]
-    init_sched_control(&root_cnode_cap, CONFIG_MAX_NUM_NODES);
+    init_sched_control(&root_cnode_cap, super::num_boot_cpus());
 
     let ipcbuf_cap = unsafe { create_ipcbuf_frame_cap(&root_cnode_cap, &it_pd_cap, ipcbuf_vptr) };
     if ipcbuf_cap.clone().unsplay().get_tag() == cap_tag::cap_null_cap {
@@ -400,6 +406,34 @@ pub fn init_smc(root_cnode_cap: &cap_cnode_cap) {
     }
 }
 
+#[cfg(feature = "arm_smmu")]
+pub fn init_smmu(root_cnode_cap: &cap_cnode_cap) {
+    unsafe {
+        let pos = root_cnode_cap.get_capCNodePtr() as *mut cte_t;
+        write_slot(
+            pos.add(SEL4_CAP_SMMU_SID_CONTROL),
+            cap_sid_control_cap::new().unsplay(),
+        );
+        write_slot(
+            pos.add(SEL4_CAP_SMMU_CB_CONTROL),
+            cap_cb_control_cap::new().unsplay(),
+        );
+    }
+}
+
+#[cfg(feature = "cpu_hotplug")]
+fn init_cpu_control(root_cnode_cap: &cap_cnode_cap) {
+    use sel4_common::structures_gen::cap_cpu_control_cap;
+
+    unsafe {
+        let pos = root_cnode_cap.get_capCNodePtr() as *mut cte_t;
+        write_slot(
+            pos.add(SEL4_CAP_CPU_CONTROL),
+            cap_cpu_control_cap::new().unsplay(),
+        );
+    }
+}
+
 #[cfg(feature = "kernel_mcs")]
 //TODO: MCS: Done
 fn init_sched_control(root_cnode_cap: &cap_cnode_cap, num_nodes: usize) -> bool {
@@ -624,8 +658,8 @@ unsafe fn create_rootserver_objects(start: usize, it_v_reg: VRegion, extra_bi_si
 }
 
 fn create_domain_cap(root_cnode_cap: &cap_cnode_cap) {
-    assert!(KS_DOM_SCHEDULE_LENGTH > 0);
-    for i in 0..KS_DOM_SCHEDULE_LENGTH {
+    assert!(unsafe { ksDomScheduleLength } > 0);
+    for i in 0..unsafe { ksDomScheduleLength } {
         unsafe {
             assert!(ksDomSchedule[i].domain < CONFIG_NUM_DOMAINS);
             assert!(ksDomSchedule[i].length > 0);
@@ -645,6 +679,11 @@ fn init_irqs(root_cnode_cap: &cap_cnode_cap) {
         }
     }
     set_irq_state_by_irq(IRQState::IRQTimer, KERNEL_TIMER_IRQ);
+    #[cfg(feature = "hypervisor")]
+    set_irq_state_by_irq(
+        IRQState::IRQReserved,
+        sel4_common::platform::INTERRUPT_VTIMER_EVENT,
+    );
     #[cfg(all(feature = "enable_smp", target_arch = "riscv64"))]
     {
         use sel4_common::platform::{INTERRUPT_IPI_0, INTERRUPT_IPI_1};
@@ -707,6 +746,19 @@ unsafe fn rust_create_it_address_space(
     lvl1pt_cap
 }
 
+/// A vspace cap of the initial thread, not assigned to any SMMU context bank.
+#[cfg(target_arch = "aarch64")]
+fn new_vspace_cap(asid: usize, base: usize, mapped: bool) -> cap_vspace_cap {
+    #[cfg(not(feature = "arm_smmu"))]
+    {
+        cap_vspace_cap::new(asid as u64, base as u64, mapped as u64)
+    }
+    #[cfg(feature = "arm_smmu")]
+    {
+        cap_vspace_cap::new(asid as u64, base as u64, mapped as u64, CB_INVALID as u64)
+    }
+}
+
 #[cfg(target_arch = "aarch64")]
 unsafe fn rust_create_it_address_space(
     root_cnode_cap: &cap_cnode_cap,
@@ -714,7 +766,7 @@ unsafe fn rust_create_it_address_space(
 ) -> cap_vspace_cap {
     // create the PGD
 
-    let vspace_cap = cap_vspace_cap::new(IT_ASID as u64, rootserver.vspace as u64, 1);
+    let vspace_cap = new_vspace_cap(IT_ASID, rootserver.vspace, true);
     let ptr = root_cnode_cap.get_capCNodePtr() as *mut cte_t;
     let slot_pos_before = ndks_boot.slot_pos_cur;
     write_slot(
@@ -728,13 +780,13 @@ unsafe fn rust_create_it_address_space(
     // let PUD_INDEX_OFFSET = PAGE_BITS + PT_INDEX_BITS * 2;
     // let PD_INDEX_OFFSET = PAGE_BITS + PT_INDEX_BITS;
     let mut vptr = it_v_reg.start.align_down(PGD_INDEX_OFFSET);
-    // #[cfg(not(feature = "hypervisor"))]
-    while vptr < it_v_reg.end {
+    // stage-2 从 level 1 开始查找时根页表本身就是 PUD
+    while !AARCH64_VSPACE_S2_START_L1 && vptr < it_v_reg.end {
         if !provide_cap(
             root_cnode_cap,
             create_it_pud_cap(&vspace_cap, it_alloc_paging(), vptr, IT_ASID).unsplay(),
         ) {
-            return cap_vspace_cap::new(0, 0, 0);
+            return new_vspace_cap(0, 0, false);
         }
         vptr += bit!(PGD_INDEX_OFFSET);
     }
@@ -746,7 +798,7 @@ unsafe fn rust_create_it_address_space(
             root_cnode_cap,
             create_it_pd_cap(&vspace_cap, it_alloc_paging(), vptr, IT_ASID),
         ) {
-            return cap_vspace_cap::new(0, 0, 0);
+            return new_vspace_cap(0, 0, false);
         }
         vptr += bit!(PUD_INDEX_OFFSET);
     }
@@ -758,7 +810,7 @@ unsafe fn rust_create_it_address_space(
             root_cnode_cap,
             create_it_pt_cap(&vspace_cap, it_alloc_paging(), vptr, IT_ASID).unsplay(),
         ) {
-            return cap_vspace_cap::new(0, 0, 0);
+            return new_vspace_cap(0, 0, false);
         }
         vptr += bit!(PD_INDEX_OFFSET);
     }
@@ -11,6 +11,8 @@ use sel4_common::structures_gen::{cap, cap_cnode_cap, mdb_node};
 use sel4_cspace::interface::*;
 #[cfg(target_arch = "riscv64")]
 use sel4_vspace::riscv_get_lvl_pgsize_bits;
+#[cfg(target_arch = "aarch64")]
+use sel4_vspace::{upt_level_shift, UPT_LEVELS};
 // use sel4_vspace::
 
 pub fn ceiling_kernel_window(mut p: usize) -> usize {
@@ -42,9 +44,10 @@ pub fn arch_get_n_paging(it_v_reg: VRegion) -> usize {
 
 #[cfg(target_arch = "aarch64")]
 pub fn arch_get_n_paging(it_v_reg: VRegion) -> usize {
-    let n = get_n_paging(it_v_reg, 3 * PT_INDEX_BITS + PAGE_SIZE_BITS)
-        + get_n_paging(it_v_reg, PT_INDEX_BITS + PAGE_SIZE_BITS + PT_INDEX_BITS)
-        + get_n_paging(it_v_reg, PT_INDEX_BITS + PAGE_SIZE_BITS);
+    let mut n: usize = 0;
+    for i in 0..UPT_LEVELS - 1 {
+        n += get_n_paging(it_v_reg, upt_level_shift(i));
+    }
     n
 }
 
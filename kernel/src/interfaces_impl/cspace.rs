@@ -2,8 +2,11 @@ use core::usize;
 
 // use crate::ffi::tcbDebugRemove;
 use crate::arch::fpu::fpu_thread_delete;
+#[cfg(feature = "arm_smmu")]
+use crate::arch::smmu::smmu_finalise_cap;
+#[cfg(feature = "hypervisor")]
+use crate::arch::vcpu::{vcpu_thread_delete, VCpu};
 use crate::interrupt::{deleting_irq_handler, is_irq_pending, set_irq_state_by_index, IRQState};
-use crate::kernel::boot::current_lookup_fault;
 use crate::syscall::safe_unbind_notification;
 use sel4_common::sel4_config::{
     CONFIG_MAX_NUM_WORK_UNITS_PER_PREEMPTION, TCB_CNODE_ENTRIES, TCB_CTABLE, TCB_VTABLE,
@@ -41,15 +44,13 @@ pub fn arch_finalise_cap(capability: &cap, final_: bool) -> FinaliseCapRet {
     match capability.get_tag() {
         cap_tag::cap_frame_cap => {
             if cap::cap_frame_cap(capability).get_capFMappedASID() != 0 {
-                match unmap_page(
+                // the frame is gone either way, a lookup fault of the unmap is not reported
+                let _ = unmap_page(
                     cap::cap_frame_cap(capability).get_capFSize() as usize,
                     cap::cap_frame_cap(capability).get_capFMappedASID() as usize,
                     vptr!(cap::cap_frame_cap(capability).get_capFMappedAddress()),
                     pptr!(cap::cap_frame_cap(capability).get_capFBasePtr()),
-                ) {
-                    Err(lookup_fault) => unsafe { current_lookup_fault = lookup_fault },
-                    _ => {}
-                }
+                );
             }
         }
 
@@ -68,11 +69,6 @@ pub fn arch_finalise_cap(capability: &cap, final_: bool) -> FinaliseCapRet {
                         vptr!(cap::cap_page_table_cap(capability).get_capPTMappedAddress()),
                     );
                 }
-                if let Some(lookup_fault) = find_ret.lookup_fault {
-                    unsafe {
-                        current_lookup_fault = lookup_fault;
-                    }
-                }
             }
         }
 
@@ -99,18 +95,18 @@ pub fn arch_finalise_cap(capability: &cap, final_: bool) -> FinaliseCapRet {
         remainder: cap_null_cap::new().unsplay(),
         cleanupInfo: cap_null_cap::new().unsplay(),
     };
+    // Detach the SMMU first, a vspace may still be in use by a context bank.
+    #[cfg(feature = "arm_smmu")]
+    smmu_finalise_cap(capability, final_);
     match capability.get_tag() {
         cap_tag::cap_frame_cap => {
             if cap::cap_frame_cap(capability).get_capFMappedASID() != 0 {
-                match unmap_page(
+                let _ = unmap_page(
                     cap::cap_frame_cap(capability).get_capFSize() as usize,
                     cap::cap_frame_cap(capability).get_capFMappedASID() as usize,
                     vptr!(cap::cap_frame_cap(capability).get_capFMappedAddress()),
                     pptr!(cap::cap_frame_cap(capability).get_capFBasePtr()),
-                ) {
-                    Err(fault) => unsafe { current_lookup_fault = fault },
-                    _ => {}
-                }
+                );
             }
         }
         cap_tag::cap_vspace_cap => {
@@ -162,6 +158,15 @@ pub fn arch_finalise_cap(capability: &cap, final_: bool) -> FinaliseCapRet {
             }
         }
         cap_tag::cap_asid_control_cap => {}
+        #[cfg(feature = "hypervisor")]
+        cap_tag::cap_vcpu_cap => {
+            if final_ {
+                convert_to_mut_type_ref::<VCpu>(
+                    cap::cap_vcpu_cap(capability).get_capVCPUPtr() as usize
+                )
+                .finalise();
+            }
+        }
         _ => {}
     }
     fc_ret.remainder = cap_null_cap::new().unsplay();
@@ -246,6 +251,12 @@ pub fn finalise_cap(capability: &cap, _final: bool, _exposed: bool) -> FinaliseC
             fc_ret.cleanupInfo = cap_null_cap::new().unsplay();
             return fc_ret;
         }
+        #[cfg(feature = "cpu_hotplug")]
+        cap_tag::cap_cpu_control_cap => {
+            fc_ret.remainder = cap_null_cap::new().unsplay();
+            fc_ret.cleanupInfo = cap_null_cap::new().unsplay();
+            return fc_ret;
+        }
         _ => {
             if _exposed {
                 panic!("finalise_cap: failed to finalise immediately.");
@@ -292,6 +303,8 @@ pub fn finalise_cap(capability: &cap, _final: bool, _exposed: bool) -> FinaliseC
                 tcb.suspend();
                 #[cfg(feature = "have_fpu")]
                 fpu_thread_delete(tcb);
+                #[cfg(feature = "hypervisor")]
+                vcpu_thread_delete(tcb);
                 // #[cfg(feature="DEBUG_BUILD")]
                 // unsafe {
                 //     tcbDebugRemove(tcb as *mut tcb_t);
@@ -394,55 +407,40 @@ pub fn preemption_point() -> exception_t {
 #[no_mangle]
 #[cfg(target_arch = "riscv64")]
 pub fn deleteASID(asid: asid_t, vspace: *mut PTE) {
-    unsafe {
-        if let Err(lookup_fault) = delete_asid(
-            asid,
-            vspace,
-            &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
-        ) {
-            current_lookup_fault = lookup_fault;
-        }
-    }
+    // the ASID is unused afterwards either way, a lookup fault is not reported
+    let _ = delete_asid(
+        asid,
+        vspace,
+        &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
+    );
 }
 
 #[no_mangle]
 #[cfg(target_arch = "aarch64")]
 pub fn deleteASID(asid: asid_t, vspace: *mut PTE) {
-    unsafe {
-        if let Err(lookup_fault) = delete_asid(
-            asid,
-            vspace,
-            &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
-        ) {
-            current_lookup_fault = lookup_fault;
-        }
-    }
+    let _ = delete_asid(
+        asid,
+        vspace,
+        &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
+    );
 }
 
 #[no_mangle]
 #[cfg(target_arch = "aarch64")]
 pub fn deleteASIDPool(asid_base: asid_t, pool: *mut asid_pool_t) {
-    unsafe {
-        if let Err(lookup_fault) = delete_asid_pool(
-            asid_base,
-            pool,
-            &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
-        ) {
-            current_lookup_fault = lookup_fault;
-        }
-    }
+    let _ = delete_asid_pool(
+        asid_base,
+        pool,
+        &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
+    );
 }
 
 #[no_mangle]
 #[cfg(target_arch = "riscv64")]
 pub fn deleteASIDPool(asid_base: asid_t, pool: *mut asid_pool_t) {
-    unsafe {
-        if let Err(lookup_fault) = delete_asid_pool(
-            asid_base,
-            pool,
-            &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
-        ) {
-            current_lookup_fault = lookup_fault;
-        }
-    }
+    let _ = delete_asid_pool(
+        asid_base,
+        pool,
+        &get_currenct_thread().get_cspace(TCB_VTABLE).capability,
+    );
 }
@@ -1,6 +1,7 @@
 use crate::interrupt::*;
 use core::intrinsics::unlikely;
 use log::debug;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::platform::{timer, Timer_func};
 use sel4_common::platform::{IRQ_INVALID, MAX_IRQ};
 use sel4_common::structures::exception_t;
@@ -14,7 +15,7 @@ use sel4_task::{activateThread, schedule};
 use sel4_task::{check_budget, update_timestamp, SET_NODE_STATE};
 
 #[no_mangle]
-pub fn handle_interrupt_entry() -> exception_t {
+pub fn handle_interrupt_entry(ctx: &mut kernel_state_t) -> exception_t {
     #[cfg(feature = "kernel_mcs")]
     {
         cfg_if::cfg_if! {
@@ -32,7 +33,10 @@ pub fn handle_interrupt_entry() -> exception_t {
     let irq = get_active_irq();
 
     if irq != IRQ_INVALID {
-        handle_interrput(irq);
+        handle_interrput(irq, ctx);
+    } else {
+        #[cfg(feature = "irq_stats")]
+        stats::record_none_pending();
     }
 
     cfg_if::cfg_if! {
@@ -50,16 +54,22 @@ pub fn handle_interrupt_entry() -> exception_t {
 }
 
 #[no_mangle]
-pub fn handle_interrput(irq: usize) {
+pub fn handle_interrput(irq: usize, _ctx: &mut kernel_state_t) {
+    #[cfg(feature = "irq_stats")]
+    let stamp = timer.get_current_time() as usize;
     if unlikely(irq > MAX_IRQ) {
         debug!(
             "Received IRQ {}, which is above the platforms MAX_IRQ of {}\n",
             irq, MAX_IRQ
         );
+        #[cfg(feature = "irq_stats")]
+        stats::record_above_max();
         mask_interrupt(true, irq);
         ack_interrupt(irq);
         return;
     }
+    #[cfg(feature = "irq_stats")]
+    stats::record_delivery(irq, get_irq_state(irq));
     match get_irq_state(irq) {
         IRQState::IRQInactive => {
             debug!("IRQInactive");
@@ -76,9 +86,12 @@ pub fn handle_interrput(irq: usize) {
                 let nf = convert_to_mut_type_ref::<notification>(
                     cap::cap_notification_cap(handler_cap).get_capNtfnPtr() as usize,
                 );
+                #[cfg(feature = "irq_stats")]
+                stats::record_signal(irq, nf, stamp);
                 nf.send_signal(cap::cap_notification_cap(handler_cap).get_capNtfnBadge() as usize);
             }
-            #[cfg(not(target_arch = "riscv64"))]
+            // the PLIC holds a claimed source back by itself
+            #[cfg(any(not(target_arch = "riscv64"), feature = "riscv_aia"))]
             {
                 mask_interrupt(true, irq);
             }
@@ -98,6 +111,12 @@ pub fn handle_interrput(irq: usize) {
         #[cfg(feature = "enable_smp")]
         IRQState::IRQIPI => crate::smp::ipi::handle_ipi(irq, true),
         IRQState::IRQReserved => {
+            #[cfg(feature = "hypervisor")]
+            if irq == sel4_common::platform::INTERRUPT_VTIMER_EVENT {
+                crate::arch::vcpu::vppi_event(irq, _ctx);
+                ack_interrupt(irq);
+                return;
+            }
             debug!("Received unhandled reserved IRQ: {}\n", irq);
         }
     }
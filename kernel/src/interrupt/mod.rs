@@ -1,4 +1,6 @@
 pub mod handler;
+#[cfg(feature = "irq_stats")]
+pub mod stats;
 
 #[cfg(target_arch = "riscv64")]
 use core::arch::asm;
@@ -11,16 +13,19 @@ use sel4_common::utils::{convert_to_mut_type_ref, cpu_id};
 use sel4_common::utils::{global_ops, unsafe_ops};
 use sel4_cspace::interface::cte_t;
 
+#[cfg(all(target_arch = "riscv64", feature = "riscv_aia"))]
+use crate::arch::aia::{aplic_mask_irq, is_aplic_irq};
+#[cfg(target_arch = "riscv64")]
+use crate::arch::claim_external_irq;
+#[cfg(target_arch = "riscv64")]
+use crate::arch::plic::{is_plic_irq, plic_complete_claim, plic_mask_irq};
 #[cfg(target_arch = "riscv64")]
 use crate::arch::read_sip;
 #[cfg(all(target_arch = "riscv64", feature = "enable_smp"))]
 use crate::arch::{ipi_clear_irq, ipi_get_irq};
 
 #[cfg(target_arch = "aarch64")]
-use crate::arch::arm_gic::gic_v2::{
-    consts::{IRQ_MASK, IRQ_NONE},
-    gic_v2::gic_int_ack,
-};
+use crate::arch::arm_gic::{gic_int_ack, IRQ_MASK, IRQ_NONE};
 
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "enable_smp", target_arch = "aarch64"))] {
@@ -176,13 +181,19 @@ pub fn mask_interrupt(disable: bool, irq: usize) {
         } else {
             set_sie_mask(bit!(SIE_STIE));
         }
+    } else if is_plic_irq(irq) {
+        plic_mask_irq(disable, irq);
+    }
+    #[cfg(all(target_arch = "riscv64", feature = "riscv_aia"))]
+    if is_aplic_irq(irq) {
+        aplic_mask_irq(disable, irq);
     }
     #[cfg(target_arch = "aarch64")]
     {
         if disable {
-            crate::arch::arm_gic::gic_v2::irq_disable(irq);
+            crate::arch::arm_gic::irq_disable(irq);
         } else {
-            crate::arch::arm_gic::gic_v2::irq_enable(irq);
+            crate::arch::arm_gic::irq_enable(irq);
         }
     }
 }
@@ -203,10 +214,32 @@ pub fn is_irq_pending() -> bool {
     false
 }
 
+/// Whether the IRQ at `index` is raised at its controller, false for the private IRQs of
+/// other cores and for the IRQs whose pending state the kernel can't read.
+#[cfg(feature = "irq_stats")]
+pub fn is_irq_line_pending(index: usize) -> bool {
+    let irq = idx_to_irq(index);
+    #[cfg(target_arch = "aarch64")]
+    {
+        if irq < NUM_PPI && current_cpu_irq_to_idx(irq) != index {
+            return false;
+        }
+        crate::arch::arm_gic::irq_is_pending(irq)
+    }
+    #[cfg(all(target_arch = "riscv64", not(feature = "riscv_aia")))]
+    {
+        // a claimed source is held back in the gateway of the PLIC
+        is_plic_irq(irq) && crate::arch::plic::plic_pending_interrupt(irq)
+    }
+    #[cfg(all(target_arch = "riscv64", feature = "riscv_aia"))]
+    {
+        is_aplic_irq(irq) && crate::arch::aia::aplic_irq_pending(irq)
+    }
+}
+
 /// 毫无疑问，应该是 irq
 #[cfg(target_arch = "riscv64")]
 #[no_mangle]
-#[cfg_attr(not(feature = "enable_smp"), allow(unused_variables))]
 pub fn ack_interrupt(irq: usize) {
     unsafe {
         active_irq[cpu_id()] = IRQ_INVALID;
@@ -217,20 +250,68 @@ pub fn ack_interrupt(irq: usize) {
             ipi_clear_irq(irq);
         }
     }
+    // a signalled IRQ stays claimed until the handler acks it
+    if is_plic_irq(irq) && get_irq_state(irq) != IRQState::IRQSignal {
+        plic_complete_claim(irq);
+    }
     return;
 }
 
 #[cfg(target_arch = "aarch64")]
 #[no_mangle]
 pub fn ack_interrupt(irq: usize) {
-    if crate::arch::arm_gic::gic_v2::irq_is_edge_triggered(irq) {
-        crate::arch::arm_gic::gic_v2::dist_pending_clr(irq);
+    if crate::arch::arm_gic::irq_is_edge_triggered(irq) {
+        crate::arch::arm_gic::dist_pending_clr(irq);
     }
-    crate::arch::arm_gic::gic_v2::gic_v2::ack_irq(irq);
+    crate::arch::arm_gic::ack_irq(irq);
     global_ops!(active_irq[cpu_id()] = IRQ_NONE as usize);
     return;
 }
 
+/// Whether the IRQ at `index` is shared by the cores, so it can be routed to another.
+#[cfg(feature = "enable_smp")]
+pub fn irq_can_migrate(index: usize) -> bool {
+    #[cfg(target_arch = "aarch64")]
+    {
+        sel4_common::structures::idx_to_irqt(index).irq >= NUM_PPI
+    }
+    #[cfg(all(target_arch = "riscv64", not(feature = "riscv_aia")))]
+    {
+        is_plic_irq(index)
+    }
+    #[cfg(all(target_arch = "riscv64", feature = "riscv_aia"))]
+    {
+        is_aplic_irq(index)
+    }
+}
+
+/// The core each shared IRQ is routed to, indexed like `int_state_irq_table`. The
+/// shared IRQs start out on core 0.
+#[cfg(feature = "enable_smp")]
+static mut irq_target_core: [usize; INT_STATE_ARRAY_SIZE + 1] = [0; INT_STATE_ARRAY_SIZE + 1];
+
+/// Route the shared IRQ at `index` to `core`.
+#[cfg(feature = "enable_smp")]
+pub fn set_irq_target_core(index: usize, core: usize) {
+    #[cfg(target_arch = "aarch64")]
+    crate::arch::arm_gic::set_irq_target(idx_to_irq(index), core);
+    #[cfg(target_arch = "riscv64")]
+    crate::arch::set_irq_target(idx_to_irq(index), core);
+    unsafe {
+        irq_target_core[index] = core;
+    }
+}
+
+/// Route the shared IRQs that target `core` to `target` instead.
+#[cfg(feature = "enable_smp")]
+pub fn migrate_irqs(core: usize, target: usize) {
+    for index in 0..=INT_STATE_ARRAY_SIZE {
+        if irq_can_migrate(index) && unsafe { irq_target_core[index] } == core {
+            set_irq_target_core(index, target);
+        }
+    }
+}
+
 /// 同样的问题，decode_irq_control_invocation 中有用到，应该是 index
 #[inline]
 pub fn is_irq_active(index: usize) -> bool {
@@ -258,7 +339,7 @@ pub fn get_active_irq() -> usize {
     {
         use sel4_common::arch::riscv64::clear_ipi;
         if (sip & bit!(SIP_SEIP)) != 0 {
-            irq = 0;
+            irq = claim_external_irq();
         } else if (sip & bit!(SIP_SSIP)) != 0 {
             clear_ipi();
             irq = ipi_get_irq();
@@ -271,7 +352,7 @@ pub fn get_active_irq() -> usize {
     }
     #[cfg(not(feature = "enable_smp"))]
     if (sip & bit!(SIP_SEIP)) != 0 {
-        irq = 0;
+        irq = claim_external_irq();
     } else if (sip & bit!(SIP_STIP)) != 0 {
         irq = KERNEL_TIMER_IRQ;
     } else {
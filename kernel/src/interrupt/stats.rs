@@ -0,0 +1,218 @@
+//! Per-IRQ delivery and latency counters, read with the `SysDebugIRQStats` syscall.
+//!
+//! The counters are indexed like `int_state_irq_table` and only written with the big
+//! kernel lock held. Latencies are in timer ticks, measured from the kernel entry that
+//! took a signalled IRQ to the `IRQAckIRQ` on its handler cap, and to the first time the
+//! thread woken by the signal is activated.
+use log::info;
+use sel4_common::arch::ArchReg;
+use sel4_common::platform::{timer, Timer_func};
+use sel4_common::structures::current_cpu_irq_to_idx;
+use sel4_common::structures_gen::notification;
+use sel4_common::utils::convert_to_option_mut_type_ref;
+use sel4_ipc::{notification_func, NtfnState};
+use sel4_task::{tcb_t, ThreadState};
+
+use super::{is_irq_line_pending, IRQState, INT_STATE_ARRAY_SIZE};
+
+/// Number of words in a [irq_stats_t] snapshot, including the global counters.
+pub const IRQ_STATS_WORDS: usize = 11;
+
+#[derive(Clone, Copy)]
+pub struct irq_stats_t {
+    deliveries: usize,
+    spurious: usize,
+    masked_pending: usize,
+    acks: usize,
+    ack_ticks: usize,
+    ack_max: usize,
+    wakes: usize,
+    wake_ticks: usize,
+    wake_max: usize,
+    /// Entry time of the signal that is not acked yet, 0 if none.
+    signal_stamp: usize,
+}
+
+impl irq_stats_t {
+    const fn new() -> Self {
+        Self {
+            deliveries: 0,
+            spurious: 0,
+            masked_pending: 0,
+            acks: 0,
+            ack_ticks: 0,
+            ack_max: 0,
+            wakes: 0,
+            wake_ticks: 0,
+            wake_max: 0,
+            signal_stamp: 0,
+        }
+    }
+}
+
+static mut IRQ_STATS: [irq_stats_t; INT_STATE_ARRAY_SIZE + 1] =
+    [irq_stats_t::new(); INT_STATE_ARRAY_SIZE + 1];
+/// IRQs above `MAX_IRQ`, they have no entry in the table.
+static mut IRQ_ABOVE_MAX: usize = 0;
+/// Interrupt entries with nothing to claim.
+static mut IRQ_NONE_PENDING: usize = 0;
+
+#[inline]
+fn stats(index: usize) -> &'static mut irq_stats_t {
+    unsafe { &mut *(&raw mut IRQ_STATS[index]) }
+}
+
+#[inline]
+fn now() -> usize {
+    timer.get_current_time() as usize
+}
+
+/// The thread a signal on `nf` wakes, if any.
+fn signal_target(nf: &notification) -> Option<&'static mut tcb_t> {
+    match nf.get_ntfn_state() {
+        NtfnState::Idle => convert_to_option_mut_type_ref::<tcb_t>(nf.get_ntfnBoundTCB() as usize)
+            .filter(|tcb| tcb.get_state() == ThreadState::ThreadStateBlockedOnReceive),
+        NtfnState::Waiting => {
+            convert_to_option_mut_type_ref::<tcb_t>(nf.get_ntfnQueue_head() as usize)
+        }
+        NtfnState::Active => None,
+    }
+}
+
+#[inline]
+pub fn record_none_pending() {
+    unsafe { IRQ_NONE_PENDING += 1 };
+}
+
+#[inline]
+pub fn record_above_max() {
+    unsafe { IRQ_ABOVE_MAX += 1 };
+}
+
+/// Count a delivery of `irq`, it is spurious if nobody can take it.
+pub fn record_delivery(irq: usize, state: IRQState) {
+    let s = stats(current_cpu_irq_to_idx(irq));
+    s.deliveries += 1;
+    if matches!(state, IRQState::IRQInactive | IRQState::IRQReserved) {
+        s.spurious += 1;
+    }
+}
+
+/// Start the latencies of a signal of `irq` on `nf`, call before the signal is sent.
+pub fn record_signal(irq: usize, nf: &notification, stamp: usize) {
+    let index = current_cpu_irq_to_idx(irq);
+    stats(index).signal_stamp = stamp.max(1);
+    if let Some(tcb) = signal_target(nf) {
+        tcb.tcbIRQWake = index + 1;
+    }
+}
+
+/// End the ack latency of the IRQ at `index`, and count it if it was raised again while
+/// it was masked.
+pub fn record_ack(index: usize) {
+    let s = stats(index);
+    if is_irq_line_pending(index) {
+        s.masked_pending += 1;
+    }
+    if s.signal_stamp != 0 {
+        let ticks = now().wrapping_sub(s.signal_stamp);
+        s.acks += 1;
+        s.ack_ticks += ticks;
+        s.ack_max = s.ack_max.max(ticks);
+        s.signal_stamp = 0;
+    }
+}
+
+/// End the wake latency of the IRQ at `index`, called when the thread its signal woke
+/// is activated.
+#[no_mangle]
+pub fn irq_stats_woken(index: usize) {
+    let s = stats(index);
+    if s.signal_stamp != 0 {
+        let ticks = now().wrapping_sub(s.signal_stamp);
+        s.wakes += 1;
+        s.wake_ticks += ticks;
+        s.wake_max = s.wake_max.max(ticks);
+    }
+}
+
+/// Counters in the order: deliveries, spurious, masked while pending, then the count, total
+/// and maximum ticks of the ack and of the wake latency, then the global counts of IRQs
+/// above `MAX_IRQ` and of interrupt entries with nothing pending.
+pub fn snapshot(index: usize) -> [usize; IRQ_STATS_WORDS] {
+    let s = stats(index);
+    unsafe {
+        [
+            s.deliveries,
+            s.spurious,
+            s.masked_pending,
+            s.acks,
+            s.ack_ticks,
+            s.ack_max,
+            s.wakes,
+            s.wake_ticks,
+            s.wake_max,
+            IRQ_ABOVE_MAX,
+            IRQ_NONE_PENDING,
+        ]
+    }
+}
+
+pub fn reset() {
+    for index in 0..=INT_STATE_ARRAY_SIZE {
+        let s = stats(index);
+        *s = irq_stats_t {
+            signal_stamp: s.signal_stamp,
+            ..irq_stats_t::new()
+        };
+    }
+    unsafe {
+        IRQ_ABOVE_MAX = 0;
+        IRQ_NONE_PENDING = 0;
+    }
+}
+
+/// Print the counters of every IRQ that was delivered to the kernel log.
+///
+/// reL4 has no benchmark log buffer, the counters only go to `info!`.
+pub fn dump() {
+    for index in 0..=INT_STATE_ARRAY_SIZE {
+        let s = snapshot(index);
+        if s[0] == 0 {
+            continue;
+        }
+        info!(
+            "irq {}: delivered {} spurious {} masked pending {}, ack {} ticks {} max {}, wake {} ticks {} max {}",
+            index, s[0], s[1], s[2], s[3], s[4], s[5], s[6], s[7], s[8]
+        );
+    }
+    let s = snapshot(0);
+    info!("irq: above MAX_IRQ {}, none pending {}", s[9], s[10]);
+}
+
+/// Handle the `SysDebugIRQStats` syscall.
+///
+/// The `Cap` register selects the IRQ index, and the first message register holds the
+/// flags: bit 0 resets all counters after reading them, bit 1 also prints every delivered
+/// IRQ to the kernel log. The counters are returned in the message registers in the order
+/// of [snapshot], and the `Cap` register is set to the number of words, or 0 if the index
+/// is invalid.
+pub fn handle_debug_irq_stats(thread: &mut tcb_t) {
+    let index = thread.tcbArch.get_register(ArchReg::Cap);
+    let flags = thread.tcbArch.get_register(ArchReg::Msg(0));
+    if flags & bit!(1) != 0 {
+        dump();
+    }
+    if index > INT_STATE_ARRAY_SIZE {
+        thread.tcbArch.set_register(ArchReg::Cap, 0);
+        return;
+    }
+    let mut offset = 0;
+    for word in snapshot(index) {
+        offset = thread.set_mr(offset, word);
+    }
+    if flags & bit!(0) != 0 {
+        reset();
+    }
+    thread.tcbArch.set_register(ArchReg::Cap, offset);
+}
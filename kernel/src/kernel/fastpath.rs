@@ -1,9 +1,11 @@
 use crate::arch::fastpath_restore;
 use crate::syscall::{slowpath, SYS_CALL, SYS_REPLY_RECV};
 use core::intrinsics::{likely, unlikely};
+use core::sync::atomic::{AtomicBool, Ordering};
 #[cfg(feature = "kernel_mcs")]
 use sched_context::sched_context_t;
 use sel4_common::arch::MSG_REGISTER;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::message_info::seL4_MessageInfo_func;
 use sel4_common::shared_types_bf_gen::seL4_MessageInfo;
 #[cfg(feature = "kernel_mcs")]
@@ -24,6 +26,18 @@ use sel4_task::reply::reply_t;
 use sel4_task::*;
 use sel4_vspace::*;
 
+/// Cleared by the `nofastpath` boot option, calls and replies then always take the slowpath.
+static FASTPATH_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_fastpath_enabled(enabled: bool) {
+    FASTPATH_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+#[inline]
+fn fastpath_disabled() -> bool {
+    !FASTPATH_ENABLED.load(Ordering::Relaxed)
+}
+
 #[no_mangle]
 pub fn lookup_fp(_cap: &cap, cptr: usize) -> cap {
     let mut capability = _cap.clone();
@@ -84,8 +98,10 @@ pub fn switch_to_thread_fp(thread: *mut tcb_t, vroot: *mut PTE, stored_hw_asid:
     unsafe {
         #[cfg(target_arch = "riscv64")]
         set_vspace_root(pptr!(vroot).to_paddr(), asid);
+        #[cfg(feature = "hypervisor")]
+        crate::arch::vcpu::vcpu_switch((*thread).tcbArch.vcpu);
         #[cfg(target_arch = "aarch64")]
-        set_current_user_vspace_root(ttbr_new(asid, pptr!(vroot).to_paddr()));
+        set_current_user_vspace_root(ttbr_new(get_hw_asid(asid), pptr!(vroot).to_paddr()));
         // panic!("switch_to_thread_fp");
         // ksCurThread = thread as usize;
         set_current_thread(&*thread);
@@ -121,16 +137,17 @@ pub fn fastpath_copy_mrs(length: usize, src: &mut tcb_t, dest: &mut tcb_t) {
 }
 
 #[no_mangle]
-pub fn fastpath_call(cptr: usize, msgInfo: usize) {
+pub fn fastpath_call(cptr: usize, msgInfo: usize, ctx: &mut kernel_state_t) {
     // sel4_common::println!("fastpath call");
     let current = get_currenct_thread();
     let mut info = seL4_MessageInfo::from_word(msgInfo);
     let length = info.get_length() as usize;
 
-    if fastpath_mi_check(msgInfo)
+    if fastpath_disabled()
+        || fastpath_mi_check(msgInfo)
         || current.tcbFault.get_tag() != seL4_Fault_tag::seL4_Fault_NullFault
     {
-        slowpath(SYS_CALL as usize);
+        slowpath(SYS_CALL as usize, ctx);
     }
     let lookup_fp_ret = &lookup_fp(&current.get_cspace(TCB_CTABLE).capability, cptr);
 
@@ -138,13 +155,13 @@ pub fn fastpath_call(cptr: usize, msgInfo: usize) {
         !(lookup_fp_ret.clone().get_tag() == cap_tag::cap_endpoint_cap)
             || (cap::cap_endpoint_cap(lookup_fp_ret).get_capCanSend() == 0),
     ) {
-        slowpath(SYS_CALL as usize);
+        slowpath(SYS_CALL as usize, ctx);
     }
     let ep_cap = cap::cap_endpoint_cap(lookup_fp_ret);
     let ep = convert_to_mut_type_ref::<endpoint>(ep_cap.get_capEPPtr() as usize);
 
     if unlikely(ep.get_ep_state() != EPState::Recv) {
-        slowpath(SYS_CALL as usize);
+        slowpath(SYS_CALL as usize, ctx);
     }
 
     let dest = convert_to_mut_type_ref::<tcb_t>(ep.get_epQueue_head() as usize);
@@ -152,32 +169,32 @@ pub fn fastpath_call(cptr: usize, msgInfo: usize) {
     if unlikely(!isValidVTableRoot_fp(
         &dest.get_cspace(TCB_VTABLE).capability.clone(),
     )) {
-        slowpath(SYS_CALL as usize);
+        slowpath(SYS_CALL as usize, ctx);
     }
     let new_vtable = cap::cap_page_table_cap(&dest.get_cspace(TCB_VTABLE).capability);
 
     let dom = 0;
     if unlikely(dest.tcbPriority < current.tcbPriority && !is_highest_prio(dom, dest.tcbPriority)) {
-        slowpath(SYS_CALL as usize);
+        slowpath(SYS_CALL as usize, ctx);
     }
     if unlikely((ep_cap.get_capCanGrant() == 0) && (ep_cap.get_capCanGrantReply() == 0)) {
-        slowpath(SYS_CALL as usize);
+        slowpath(SYS_CALL as usize, ctx);
     }
     #[cfg(feature = "kernel_mcs")]
     {
         if unlikely(dest.tcbSchedContext != 0) {
-            slowpath(SYS_CALL as usize);
+            slowpath(SYS_CALL as usize, ctx);
         }
         assert!(dest.tcbState.get_tcbQueued() == 0);
         assert!(dest.tcbState.get_tcbInReleaseQueue() == 0);
         let reply = dest.tcbState.get_replyObject();
         if unlikely(reply == 0) {
-            slowpath(SYS_CALL as usize);
+            slowpath(SYS_CALL as usize, ctx);
         }
     }
     #[cfg(feature = "enable_smp")]
     if unlikely(get_currenct_thread().tcbAffinity != dest.tcbAffinity) {
-        slowpath(SYS_CALL as usize);
+        slowpath(SYS_CALL as usize, ctx);
     }
 
     // debug!("enter fast path");
@@ -249,15 +266,18 @@ pub fn fastpath_call(cptr: usize, msgInfo: usize) {
 
 #[no_mangle]
 #[cfg(not(feature = "kernel_mcs"))]
-pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize) {
+pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize, ctx: &mut kernel_state_t) {
     // sel4_common::println!("enter fastpath_reply_recv");
     let current = get_currenct_thread();
     let mut info = seL4_MessageInfo::from_word(msgInfo);
     let length = info.get_length() as usize;
     let fault_type = current.tcbFault.get_tag();
 
-    if fastpath_mi_check(msgInfo) || fault_type != seL4_Fault_tag::seL4_Fault_NullFault {
-        slowpath(SYS_REPLY_RECV as usize);
+    if fastpath_disabled()
+        || fastpath_mi_check(msgInfo)
+        || fault_type != seL4_Fault_tag::seL4_Fault_NullFault
+    {
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     let lookup_fp_ret = &lookup_fp(&current.get_cspace(TCB_CTABLE).capability, cptr);
 
@@ -265,20 +285,20 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize) {
         lookup_fp_ret.clone().get_tag() != cap_tag::cap_endpoint_cap
             || cap::cap_endpoint_cap(lookup_fp_ret).get_capCanSend() == 0,
     ) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     let ep_cap = cap::cap_endpoint_cap(lookup_fp_ret);
 
     if let Some(ntfn) = convert_to_option_mut_type_ref::<notification>(current.tcbBoundNotification)
     {
         if ntfn.get_ntfn_state() == NtfnState::Active {
-            slowpath(SYS_REPLY_RECV as usize);
+            slowpath(SYS_REPLY_RECV as usize, ctx);
         }
     }
 
     let ep = convert_to_mut_type_ref::<endpoint>(ep_cap.get_capEPPtr() as usize);
     if unlikely(ep.get_ep_state() == EPState::Send) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
 
     let caller_slot = current.get_cspace_mut_ref(TCB_CALLER);
@@ -290,24 +310,24 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize) {
             .get_tag()
             != cap_tag::cap_reply_cap,
     ) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
 
     let caller = convert_to_mut_type_ref::<tcb_t>(caller_cap.get_capTCBPtr() as usize);
     if unlikely(caller.tcbFault.get_tag() != seL4_Fault_tag::seL4_Fault_NullFault) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
 
     if unlikely(!isValidVTableRoot_fp(
         &caller.get_cspace(TCB_VTABLE).capability.clone(),
     )) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     let new_vtable = &cap::cap_page_table_cap(&caller.get_cspace(TCB_VTABLE).capability);
 
     let dom = 0;
     if unlikely(!is_highest_prio(dom, caller.tcbPriority)) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     thread_state_ptr_mset_blockingObject_tsType(
         &mut current.tcbState,
@@ -355,7 +375,7 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize) {
 #[inline]
 #[no_mangle]
 #[cfg(feature = "kernel_mcs")]
-pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize) {
+pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize, ctx: &mut kernel_state_t) {
     // sel4_common::println!("enter fastpath_reply_recv");
 
     let current = get_currenct_thread();
@@ -363,8 +383,11 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize) {
     let length = info.get_length() as usize;
     let fault_type = current.tcbFault.get_tag();
 
-    if fastpath_mi_check(msgInfo) || fault_type != seL4_Fault_tag::seL4_Fault_NullFault {
-        slowpath(SYS_REPLY_RECV as usize);
+    if fastpath_disabled()
+        || fastpath_mi_check(msgInfo)
+        || fault_type != seL4_Fault_tag::seL4_Fault_NullFault
+    {
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     let lookup_fp_ret = &lookup_fp(&current.get_cspace(TCB_CTABLE).capability, cptr);
 
@@ -372,7 +395,7 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize) {
         lookup_fp_ret.clone().get_tag() != cap_tag::cap_endpoint_cap
             || cap::cap_endpoint_cap(lookup_fp_ret).get_capCanSend() == 0,
     ) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     let ep_cap = cap::cap_endpoint_cap(lookup_fp_ret);
 
@@ -382,19 +405,19 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize) {
 
     /* check it's a reply object */
     if unlikely(reply_cap.clone().unsplay().get_tag() != cap_tag::cap_endpoint_cap) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
 
     if let Some(ntfn) = convert_to_option_mut_type_ref::<notification>(current.tcbBoundNotification)
     {
         if ntfn.get_ntfn_state() == NtfnState::Active {
-            slowpath(SYS_REPLY_RECV as usize);
+            slowpath(SYS_REPLY_RECV as usize, ctx);
         }
     }
 
     let ep = convert_to_mut_type_ref::<endpoint>(ep_cap.get_capEPPtr() as usize);
     if unlikely(ep.get_ep_state() == EPState::Send) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     /* Get the reply address */
     let reply_ptr = convert_to_mut_type_ref::<reply_t>(reply_cap.get_capReplyPtr() as usize);
@@ -405,28 +428,28 @@ pub fn fastpath_reply_recv(cptr: usize, msgInfo: usize, reply: usize) {
             || reply_ptr.replyNext.get_isHead() == 0
             || reply_ptr.replyNext.get_callStackPtr() as usize != current.tcbSchedContext,
     ) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     let caller = convert_to_mut_type_ref::<tcb_t>(reply_ptr.replyTCB);
 
     if unlikely(caller.tcbFault.get_tag() != seL4_Fault_tag::seL4_Fault_NullFault) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
 
     if unlikely(!isValidVTableRoot_fp(
         &caller.get_cspace(TCB_VTABLE).capability.clone(),
     )) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     let new_vtable = cap::cap_page_table_cap(&caller.get_cspace(TCB_VTABLE).capability);
 
     let dom = 0;
     if unlikely(!is_highest_prio(dom, caller.tcbPriority)) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
 
     if unlikely(caller.tcbSchedContext != 0) {
-        slowpath(SYS_REPLY_RECV as usize);
+        slowpath(SYS_REPLY_RECV as usize, ctx);
     }
     assert!(current.tcbState.get_replyObject() == 0);
 
@@ -1,6 +1,7 @@
 use crate::structures::{lookupCapAndSlot_ret_t, lookupCap_ret_t};
 use crate::syscall::handle_fault;
 use sel4_common::arch::MessageLabel;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::structures::exception_t;
 use sel4_common::structures_gen::{cap, cap_null_cap};
 use sel4_cspace::interface::cte_t;
@@ -35,10 +36,10 @@ pub fn setMR(
 }
 
 #[no_mangle]
-pub fn handleFault(tptr: *mut tcb_t) {
+pub fn handleFault(tptr: *mut tcb_t, ctx: &mut kernel_state_t) {
     //TODO: MCS
     unsafe {
-        handle_fault(&mut *tptr);
+        handle_fault(&mut *tptr, ctx);
     }
 }
 
@@ -0,0 +1,236 @@
+//! Fine-grained locking for the IPC syscalls.
+//!
+//! With the `fine_grained_lock` feature, `SysSend`, `SysNBSend`, `SysCall`, `SysRecv`,
+//! `SysNBRecv`, `SysReply` and `SysReplyRecv` on endpoints and notifications no longer
+//! take the big kernel lock. Instead the core registers itself as an IPC reader and
+//! takes the lock of the object it operates on, so IPC on distinct endpoints proceeds
+//! concurrently on different cores.
+//!
+//! Everything else (cap operations, faults, interrupts, fastpath) still takes the big
+//! kernel lock, and the holder of the big kernel lock waits until all IPC readers have
+//! left the kernel. Code running under the big kernel lock therefore never observes an
+//! IPC in flight and does not need to take any object lock.
+//!
+//! An IPC syscall falls back to the big kernel lock when it would have to do more than
+//! touch the invoked object, the threads queued on it and the ready queues:
+//!
+//! * the cap lookup fails or the cap rights are not sufficient (a fault is raised),
+//! * the message or the reply carries extra caps, or a blocked sender does (cap transfer),
+//! * the notification is bound to a TCB (signal may cancel an IPC elsewhere).
+//!
+//! The ready queues of each core are protected by `sel4_task::ksReadyQueuesLock`.
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use rel4_utils::spin_lock::RawSpinLock;
+use sel4_common::arch::ArchReg;
+use sel4_common::message_info::seL4_MessageInfo_func;
+use sel4_common::sel4_config::{CONFIG_MAX_NUM_NODES, SEL4_ENDPOINT_BITS};
+use sel4_common::shared_types_bf_gen::seL4_MessageInfo;
+use sel4_common::structures::exception_t;
+use sel4_common::structures_gen::{cap_Splayed, endpoint, notification};
+use sel4_common::utils::convert_to_type_ref;
+use sel4_ipc::{endpoint_func, EPState};
+use sel4_task::{get_currenct_thread, tcb_t};
+
+use crate::syscall::{
+    SYS_CALL, SYS_NB_RECV, SYS_NB_SEND, SYS_RECV, SYS_REPLY, SYS_REPLY_RECV, SYS_SEND,
+};
+
+/// Number of locks shared by all endpoints and notifications, must be a power of 2.
+const IPC_OBJECT_LOCK_NUM: usize = 64;
+
+/// An IPC syscall locks at most the invoked object and the bound notification.
+const MAX_HELD_LOCKS: usize = 2;
+
+static ipc_object_locks: [RawSpinLock; IPC_OBJECT_LOCK_NUM] = {
+    const LOCK: RawSpinLock = RawSpinLock::new();
+    [LOCK; IPC_OBJECT_LOCK_NUM]
+};
+
+/// Number of cores currently in the kernel on the IPC path.
+static ipc_readers: AtomicUsize = AtomicUsize::new(0);
+
+/// Set while a core holds the big kernel lock.
+static bkl_writer: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy)]
+struct ipc_lock_state {
+    active: bool,
+    locks: [usize; MAX_HELD_LOCKS],
+    count: usize,
+}
+
+impl ipc_lock_state {
+    const fn new() -> Self {
+        Self {
+            active: false,
+            locks: [0; MAX_HELD_LOCKS],
+            count: 0,
+        }
+    }
+}
+
+static mut ipc_lock_held: [ipc_lock_state; CONFIG_MAX_NUM_NODES] =
+    [ipc_lock_state::new(); CONFIG_MAX_NUM_NODES];
+
+#[inline]
+fn object_lock_index(obj: usize) -> usize {
+    (obj >> SEL4_ENDPOINT_BITS) & (IPC_OBJECT_LOCK_NUM - 1)
+}
+
+/// Called after acquiring the big kernel lock, wait until all IPC readers leave.
+#[inline]
+pub fn bkl_writer_enter() {
+    bkl_writer.store(true, Ordering::SeqCst);
+    while ipc_readers.load(Ordering::SeqCst) != 0 {
+        crate::arch::arch_pause();
+    }
+}
+
+/// Called before releasing the big kernel lock.
+#[inline]
+pub fn bkl_writer_exit() {
+    bkl_writer.store(false, Ordering::SeqCst);
+}
+
+fn reader_enter() -> bool {
+    ipc_readers.fetch_add(1, Ordering::SeqCst);
+    if bkl_writer.load(Ordering::SeqCst) {
+        ipc_readers.fetch_sub(1, Ordering::SeqCst);
+        return false;
+    }
+    true
+}
+
+fn lock_objects(cpu: usize, objs: &[usize]) {
+    let mut idx = [0; MAX_HELD_LOCKS];
+    let mut count = 0;
+    for obj in objs {
+        let i = object_lock_index(*obj);
+        if !idx[..count].contains(&i) {
+            idx[count] = i;
+            count += 1;
+        }
+    }
+    // Always lock in index order to avoid deadlock between two readers.
+    idx[..count].sort_unstable();
+    for i in &idx[..count] {
+        ipc_object_locks[*i].lock();
+    }
+    unsafe {
+        ipc_lock_held[cpu] = ipc_lock_state {
+            active: true,
+            locks: idx,
+            count,
+        };
+    }
+}
+
+/// Leave the IPC path if the current core is on it, called on kernel exit.
+#[inline]
+pub fn ipc_exit(cpu: usize) {
+    unsafe {
+        let state = &mut ipc_lock_held[cpu];
+        if !state.active {
+            return;
+        }
+        for i in state.locks[..state.count].iter().rev() {
+            ipc_object_locks[*i].unlock();
+        }
+        *state = ipc_lock_state::new();
+    }
+    ipc_readers.fetch_sub(1, Ordering::SeqCst);
+}
+
+#[inline]
+fn has_extra_caps(thread: &tcb_t) -> bool {
+    seL4_MessageInfo::from_word_security(thread.tcbArch.get_register(ArchReg::MsgInfo))
+        .get_extraCaps()
+        != 0
+}
+
+/// A blocked sender would transfer caps to the receiver.
+fn ep_has_cap_sender(ep: &endpoint) -> bool {
+    ep.get_ep_state() == EPState::Send
+        && has_extra_caps(convert_to_type_ref::<tcb_t>(ep.get_queue().head))
+}
+
+/// Collect the objects the syscall operates on, `None` means the big kernel lock is needed.
+///
+/// Return the number of objects and whether the first one is an endpoint.
+fn ipc_objects(
+    thread: &mut tcb_t,
+    syscall: isize,
+    objs: &mut [usize; MAX_HELD_LOCKS],
+) -> Option<(usize, bool)> {
+    let is_send = matches!(syscall, SYS_SEND | SYS_NB_SEND | SYS_CALL);
+    // the reply of SysReply and SysReplyRecv is the message of the current thread too
+    let is_reply = matches!(syscall, SYS_REPLY | SYS_REPLY_RECV);
+    if (is_send || is_reply) && has_extra_caps(thread) {
+        return None;
+    }
+    if syscall == SYS_REPLY {
+        return Some((0, false));
+    }
+    let lu_ret = thread.lookup_slot(thread.tcbArch.get_register(ArchReg::Cap));
+    if lu_ret.status != exception_t::EXCEPTION_NONE {
+        return None;
+    }
+    match unsafe { (*lu_ret.slot).capability.clone() }.splay() {
+        cap_Splayed::endpoint_cap(data) => {
+            if (is_send && data.get_capCanSend() == 0)
+                || (!is_send && data.get_capCanReceive() == 0)
+            {
+                return None;
+            }
+            objs[0] = data.get_capEPPtr() as usize;
+            if !is_send && thread.tcbBoundNotification != 0 {
+                objs[1] = thread.tcbBoundNotification;
+                return Some((2, true));
+            }
+            Some((1, true))
+        }
+        cap_Splayed::notification_cap(data) => {
+            let ntfn = convert_to_type_ref::<notification>(data.get_capNtfnPtr() as usize);
+            let bound_tcb = ntfn.get_ntfnBoundTCB() as usize;
+            if is_send && (data.get_capNtfnCanSend() == 0 || bound_tcb != 0) {
+                return None;
+            }
+            if !is_send
+                && (data.get_capNtfnCanReceive() == 0
+                    || (bound_tcb != 0 && bound_tcb != thread.get_ptr().raw()))
+            {
+                return None;
+            }
+            objs[0] = data.get_capNtfnPtr() as usize;
+            Some((1, false))
+        }
+        _ => None,
+    }
+}
+
+/// Try to enter the kernel on the IPC path without the big kernel lock.
+///
+/// Return `false` if the caller must take the big kernel lock instead.
+pub fn try_ipc_enter(cpu: usize, syscall: isize) -> bool {
+    if !matches!(
+        syscall,
+        SYS_SEND | SYS_NB_SEND | SYS_CALL | SYS_RECV | SYS_NB_RECV | SYS_REPLY | SYS_REPLY_RECV
+    ) {
+        return false;
+    }
+    if !reader_enter() {
+        return false;
+    }
+    let mut objs = [0; MAX_HELD_LOCKS];
+    let Some((count, is_ep)) = ipc_objects(get_currenct_thread(), syscall, &mut objs) else {
+        ipc_readers.fetch_sub(1, Ordering::SeqCst);
+        return false;
+    };
+    lock_objects(cpu, &objs[..count]);
+    let is_recv = matches!(syscall, SYS_RECV | SYS_NB_RECV | SYS_REPLY_RECV);
+    if is_recv && is_ep && ep_has_cap_sender(convert_to_type_ref::<endpoint>(objs[0])) {
+        ipc_exit(cpu);
+        return false;
+    }
+    true
+}
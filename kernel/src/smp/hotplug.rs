@@ -0,0 +1,93 @@
+//! Taking cores offline and bringing them back at runtime.
+//!
+//! `CPUControlOffline` removes a core from the active cores of the scheduler and sends it
+//! a reschedule IPI, after the shared IRQs routed to it have been moved to an active
+//! core. The core hands its threads to the active cores in `schedule`, and
+//! then parks in [`park_core`]: it saves the FPU state it holds, leaves the cores that
+//! take remote calls and sleeps in `CPU_SUSPEND` standby (aarch64) or HSM retentive
+//! suspend (riscv64) until `CPUControlOnline` makes it active again.
+//!
+//! A parked core misses the TLB shootdowns of the other cores, so it drops its whole
+//! TLB before it takes remote calls again. The cores are suspended rather than turned
+//! off with `CPU_OFF`/`hart_stop`, which would need a cold entry point that sets up the
+//! MMU again.
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use sel4_common::platform::KERNEL_TIMER_IRQ;
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+use sel4_common::utils::cpu_id;
+use sel4_task::{active_core_for, core_is_active, reschedule_required, set_core_active};
+
+use super::ipi::do_mask_reschedule;
+use super::{clh_is_self_in_queue, clh_lock_acquire, clh_lock_release};
+use crate::interrupt::{mask_interrupt, migrate_irqs};
+
+/// The cores that take remote calls, the barrier of [`super::ipi::do_remote_mask_op`]
+/// only counts these.
+///
+/// A core leaves the set when it parks, and joins it again once it is awake and holds
+/// the big kernel lock, so the set only changes under the lock.
+static ONLINE_CORES: AtomicUsize = AtomicUsize::new(bit!(CONFIG_MAX_NUM_NODES) - 1);
+
+#[inline]
+pub fn online_cores() -> usize {
+    ONLINE_CORES.load(Ordering::Acquire)
+}
+
+/// Stop scheduling threads on `core`, it parks at its next `schedule`.
+///
+/// The shared IRQs routed to `core` go to the lowest active core, which is core 0 as it
+/// never goes offline.
+pub fn core_offline(core: usize) {
+    set_core_active(core, false);
+    migrate_irqs(core, active_core_for(core));
+    if core == cpu_id() {
+        reschedule_required();
+    } else {
+        do_mask_reschedule(bit!(core));
+    }
+}
+
+/// Make `core` active again and wake it up if it is parked.
+pub fn core_online(core: usize) {
+    set_core_active(core, true);
+    do_mask_reschedule(bit!(core));
+}
+
+/// Drop `core`, which the platform doesn't have, from the active cores and from the
+/// cores that take remote calls.
+pub fn core_absent(core: usize) {
+    set_core_active(core, false);
+    ONLINE_CORES.fetch_and(!bit!(core), Ordering::AcqRel);
+}
+
+/// Park this core, which has been taken offline, until it is made active again.
+///
+/// Called at the end of `schedule`, once the core runs the idle thread. The big kernel
+/// lock is released while the core sleeps. An IPC syscall under `fine_grained_lock`
+/// does not hold the lock, the core then parks at its next kernel entry instead.
+#[no_mangle]
+pub fn park_core() {
+    let cpu = cpu_id();
+    if !clh_is_self_in_queue() {
+        return;
+    }
+    #[cfg(feature = "have_fpu")]
+    unsafe {
+        crate::arch::fpu::switch_local_fpu_owner(0)
+    };
+    mask_interrupt(true, KERNEL_TIMER_IRQ);
+    ONLINE_CORES.fetch_and(!bit!(cpu), Ordering::AcqRel);
+    log::info!("core {} offline", cpu);
+    clh_lock_release(cpu);
+
+    while !core_is_active(cpu) {
+        sel4_common::arch::cpu_standby();
+    }
+
+    clh_lock_acquire(cpu, false);
+    crate::arch::cpu_flush_local();
+    ONLINE_CORES.fetch_or(bit!(cpu), Ordering::AcqRel);
+    mask_interrupt(false, KERNEL_TIMER_IRQ);
+    log::info!("core {} online", cpu);
+}
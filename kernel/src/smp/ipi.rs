@@ -54,6 +54,8 @@ pub fn ipi_wait() {
 }
 
 pub fn ipi_stall_core_cb(irq_path: bool) {
+    #[cfg(feature = "clh_lock_stats")]
+    super::clh_stats_record_stall_ipi(cpu_id());
     let thread = sel4_task::get_currenct_thread();
     if super::clh_is_self_in_queue() && !irq_path {
         if thread.tcbState.get_tsType() == ThreadState::ThreadStateRunning as u64 {
@@ -161,6 +163,10 @@ pub fn do_remote_mask_op(
 ) {
     let mut mask2 = mask;
     mask2 &= !bit!(cpu_id());
+    #[cfg(feature = "cpu_hotplug")]
+    {
+        mask2 &= super::hotplug::online_cores();
+    }
     if mask2 != 0 {
         unsafe {
             ipi_args[0] = arg0;
@@ -1,5 +1,9 @@
+#[cfg(feature = "clh_lock_stats")]
+use core::sync::atomic::AtomicUsize;
 use core::sync::atomic::{fence, AtomicPtr, Ordering};
 use sel4_common::arch::config::IRQ_REMOTE_CALL_IPI;
+#[cfg(feature = "clh_lock_stats")]
+use sel4_common::arch::read_cycles;
 
 use sel4_common::sel4_config::*;
 
@@ -53,23 +57,109 @@ impl clh_qnode_p {
     }
 }
 
+/// Index of the syscall and exception path in [clh_lock_stats].
+#[cfg(feature = "clh_lock_stats")]
+pub const CLH_STATS_SYSCALL_PATH: usize = 0;
+/// Index of the irq path in [clh_lock_stats].
+#[cfg(feature = "clh_lock_stats")]
+pub const CLH_STATS_IRQ_PATH: usize = 1;
+/// Number of words in a [clh_lock_stats] snapshot.
+#[cfg(feature = "clh_lock_stats")]
+pub const CLH_STATS_WORDS: usize = 7;
+
+/// Contention counters of one core, only written by the owner core.
+///
+/// The waiting time is measured in cycles of the core, with the `cycle` CSR on riscv64
+/// and the PMU cycle counter on aarch64.
+#[cfg(feature = "clh_lock_stats")]
+#[repr(align(64))]
+pub struct clh_lock_stats {
+    acquisitions: [AtomicUsize; 2],
+    spins: [AtomicUsize; 2],
+    wait_cycles: [AtomicUsize; 2],
+    stall_ipis: AtomicUsize,
+}
+
+#[cfg(feature = "clh_lock_stats")]
+impl clh_lock_stats {
+    pub const fn new() -> Self {
+        Self {
+            acquisitions: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            spins: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            wait_cycles: [AtomicUsize::new(0), AtomicUsize::new(0)],
+            stall_ipis: AtomicUsize::new(0),
+        }
+    }
+
+    #[inline]
+    fn record_acquire(&self, irq_path: bool, spins: usize, cycles: usize) {
+        let path = match irq_path {
+            true => CLH_STATS_IRQ_PATH,
+            false => CLH_STATS_SYSCALL_PATH,
+        };
+        self.acquisitions[path].fetch_add(1, Ordering::Relaxed);
+        self.spins[path].fetch_add(spins, Ordering::Relaxed);
+        self.wait_cycles[path].fetch_add(cycles, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_stall_ipi(&self) {
+        self.stall_ipis.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counters in the order: acquisitions, spins and wait cycles of the syscall path and
+    /// the irq path, then the number of stall ipis.
+    pub fn snapshot(&self) -> [usize; CLH_STATS_WORDS] {
+        [
+            self.acquisitions[CLH_STATS_SYSCALL_PATH].load(Ordering::Relaxed),
+            self.acquisitions[CLH_STATS_IRQ_PATH].load(Ordering::Relaxed),
+            self.spins[CLH_STATS_SYSCALL_PATH].load(Ordering::Relaxed),
+            self.spins[CLH_STATS_IRQ_PATH].load(Ordering::Relaxed),
+            self.wait_cycles[CLH_STATS_SYSCALL_PATH].load(Ordering::Relaxed),
+            self.wait_cycles[CLH_STATS_IRQ_PATH].load(Ordering::Relaxed),
+            self.stall_ipis.load(Ordering::Relaxed),
+        ]
+    }
+
+    pub fn reset(&self) {
+        for path in [CLH_STATS_SYSCALL_PATH, CLH_STATS_IRQ_PATH] {
+            self.acquisitions[path].store(0, Ordering::Relaxed);
+            self.spins[path].store(0, Ordering::Relaxed);
+            self.wait_cycles[path].store(0, Ordering::Relaxed);
+        }
+        self.stall_ipis.store(0, Ordering::Relaxed);
+    }
+}
+
 #[repr(align(64))]
 pub struct clh_lock {
     nodes: [clh_qnode; CONFIG_MAX_NUM_NODES + 1],
     node_owners: [clh_qnode_p; CONFIG_MAX_NUM_NODES],
     head: AtomicPtr<clh_qnode>,
+    #[cfg(feature = "clh_lock_stats")]
+    stats: [clh_lock_stats; CONFIG_MAX_NUM_NODES],
 }
 
 impl clh_lock {
     pub const fn new() -> Self {
         const OWNER: clh_qnode_p = clh_qnode_p::new();
+        #[cfg(feature = "clh_lock_stats")]
+        const STATS: clh_lock_stats = clh_lock_stats::new();
         Self {
             nodes: [clh_qnode::new(); CONFIG_MAX_NUM_NODES + 1],
             node_owners: [OWNER; CONFIG_MAX_NUM_NODES],
             head: AtomicPtr::new(core::ptr::null_mut()),
+            #[cfg(feature = "clh_lock_stats")]
+            stats: [STATS; CONFIG_MAX_NUM_NODES],
         }
     }
 
+    #[cfg(feature = "clh_lock_stats")]
+    #[inline]
+    pub fn stats(&self, cpu: usize) -> &clh_lock_stats {
+        &self.stats[cpu]
+    }
+
     pub fn init(&mut self) {
         for i in 0..CONFIG_MAX_NUM_NODES {
             self.node_owners[i]
@@ -103,6 +193,10 @@ impl clh_lock {
 
     #[inline]
     pub fn acquire(&mut self, cpu: usize, irq_path: bool) {
+        #[cfg(feature = "clh_lock_stats")]
+        let start = read_cycles();
+        #[cfg(feature = "clh_lock_stats")]
+        let mut spins = 0;
         unsafe {
             self.node_owners[cpu]
                 .node
@@ -123,6 +217,10 @@ impl clh_lock {
                     super::ipi::handle_ipi(IRQ_REMOTE_CALL_IPI, irq_path);
                 }
                 crate::arch::arch_pause();
+                #[cfg(feature = "clh_lock_stats")]
+                {
+                    spins += 1;
+                }
             }
 
             while self.next_node_value(cpu) != clh_qnode_state::CLHState_Granted {
@@ -130,8 +228,14 @@ impl clh_lock {
                     super::ipi::handle_ipi(IRQ_REMOTE_CALL_IPI, irq_path);
                 }
                 crate::arch::arch_pause();
+                #[cfg(feature = "clh_lock_stats")]
+                {
+                    spins += 1;
+                }
             }
         }
+        #[cfg(feature = "clh_lock_stats")]
+        self.stats[cpu].record_acquire(irq_path, spins, read_cycles().wrapping_sub(start));
     }
 
     #[inline]
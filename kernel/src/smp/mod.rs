@@ -1,3 +1,7 @@
+#[cfg(all(feature = "fine_grained_lock", not(feature = "kernel_mcs")))]
+pub mod fine_lock;
+#[cfg(feature = "cpu_hotplug")]
+pub mod hotplug;
 pub mod ipi;
 pub mod lock;
 
@@ -31,14 +35,97 @@ pub fn clh_next_node_state(cpu: usize) -> lock::clh_qnode_state {
 
 pub fn clh_lock_acquire(cpu: usize, irq_path: bool) {
     assert!(cpu < CONFIG_MAX_NUM_NODES);
-    BIG_KERNEL_LOCK.no_lock().acquire(cpu, irq_path)
+    BIG_KERNEL_LOCK.no_lock().acquire(cpu, irq_path);
+    #[cfg(all(feature = "fine_grained_lock", not(feature = "kernel_mcs")))]
+    fine_lock::bkl_writer_enter();
 }
 
 pub fn clh_lock_release(cpu: usize) {
     assert!(cpu < CONFIG_MAX_NUM_NODES);
+    #[cfg(all(feature = "fine_grained_lock", not(feature = "kernel_mcs")))]
+    fine_lock::bkl_writer_exit();
     BIG_KERNEL_LOCK.no_lock().release(cpu)
 }
 
+/// Take the kernel lock for a syscall entry.
+///
+/// With `fine_grained_lock`, IPC syscalls only lock the invoked object, see [fine_lock].
+pub fn syscall_lock_acquire(cpu: usize, _syscall: usize) {
+    #[cfg(all(feature = "fine_grained_lock", not(feature = "kernel_mcs")))]
+    if fine_lock::try_ipc_enter(cpu, _syscall as isize) {
+        return;
+    }
+    clh_lock_acquire(cpu, false)
+}
+
+/// Release whatever kernel lock this core holds, called on kernel exit.
+pub fn kernel_lock_release_if_held(cpu: usize) {
+    if clh_is_self_in_queue() {
+        clh_lock_release(cpu);
+    }
+    #[cfg(all(feature = "fine_grained_lock", not(feature = "kernel_mcs")))]
+    fine_lock::ipc_exit(cpu);
+}
+
+#[cfg(feature = "clh_lock_stats")]
+pub fn clh_stats_record_stall_ipi(cpu: usize) {
+    assert!(cpu < CONFIG_MAX_NUM_NODES);
+    BIG_KERNEL_LOCK.stats(cpu).record_stall_ipi();
+}
+
+/// Print the contention counters of every core to the kernel log.
+///
+/// reL4 has no benchmark log buffer, the counters only go to `log::info!`.
+#[cfg(feature = "clh_lock_stats")]
+pub fn clh_stats_dump() {
+    for cpu in 0..CONFIG_MAX_NUM_NODES {
+        let s = BIG_KERNEL_LOCK.stats(cpu).snapshot();
+        log::info!(
+            "clh_lock cpu {}: syscall acq {} spin {} cycles {}, irq acq {} spin {} cycles {}, stall ipi {}",
+            cpu,
+            s[0],
+            s[2],
+            s[4],
+            s[1],
+            s[3],
+            s[5],
+            s[6]
+        );
+    }
+}
+
+/// Handle the `SysDebugLockStats` syscall.
+///
+/// The `Cap` register selects the core, and the first message register holds the flags:
+/// bit 0 resets the counters after reading them, bit 1 also prints all cores to the kernel
+/// log. The counters are returned in the message registers in the order of
+/// [lock::clh_lock_stats::snapshot], and the `Cap` register is set to the number of words,
+/// or 0 if the core is invalid.
+#[cfg(feature = "clh_lock_stats")]
+pub fn handle_debug_lock_stats(thread: &mut sel4_task::tcb_t) {
+    use sel4_common::arch::ArchReg;
+
+    let cpu = thread.tcbArch.get_register(ArchReg::Cap);
+    let flags = thread.tcbArch.get_register(ArchReg::Msg(0));
+    if flags & bit!(1) != 0 {
+        clh_stats_dump();
+    }
+    if cpu >= CONFIG_MAX_NUM_NODES {
+        thread.tcbArch.set_register(ArchReg::Cap, 0);
+        return;
+    }
+    let stats = BIG_KERNEL_LOCK.stats(cpu);
+    let mut offset = 0;
+    for word in stats.snapshot() {
+        offset = thread.set_mr(offset, word);
+    }
+    if flags & bit!(0) != 0 {
+        stats.reset();
+    }
+    thread.tcbArch.set_register(ArchReg::Cap, offset);
+}
+
+/// Move `tcb` to `new_core`, the FPU state it owns on the old core is saved there first.
 #[no_mangle]
 pub fn migrate_tcb(tcb: &mut sel4_task::tcb_t, new_core: usize) {
     #[cfg(feature = "have_fpu")]
@@ -1,5 +1,6 @@
-use rel4_arch::basic::{PAddr, PPtr, PRegion, Region};
+use rel4_arch::basic::{PAddr, PRegion, Region};
 use sel4_common::sel4_config::*;
+pub use sel4_common::structures::extra_caps_t;
 use sel4_common::structures::{exception_t, seL4_IPCBuffer};
 use sel4_common::structures_gen::{cap, cap_null_cap};
 use sel4_cspace::interface::cte_t;
@@ -117,21 +118,3 @@ impl Default for lookupCapAndSlot_ret_t {
         }
     }
 }
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct syscall_error_t {
-    pub invalidArgumentNumber: usize,
-    pub invalidCapNumber: usize,
-    pub rangeErrorMin: usize,
-    pub rangeErrorMax: usize,
-    pub memoryLeft: usize,
-    pub failedLookupWasSource: usize,
-    pub _type: usize,
-}
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub struct extra_caps_t {
-    pub excaprefs: [PPtr; SEL4_MSG_MAX_EXTRA_CAPS],
-}
@@ -0,0 +1,81 @@
+//! The errors of the invocation layer.
+//!
+//! Every `decode_*` and `invoke_*` function returns `Result<_, SyscallError>`. The error
+//! carries its own payload, and `syscall_reply::reply_error_from_kernel` is the only place
+//! that turns it into the seL4 error registers.
+use sel4_common::sel4_config::*;
+use sel4_common::structures::exception_t;
+use sel4_common::structures_gen::lookup_fault;
+
+/// An invocation that did not complete.
+#[derive(Clone)]
+pub enum SyscallError {
+    /// The message register `index` holds an invalid argument.
+    InvalidArgument {
+        index: usize,
+    },
+    /// The extra cap `index` is invalid, 0 is the invoked cap when it is the only one.
+    InvalidCapability {
+        index: usize,
+    },
+    IllegalOperation,
+    /// An argument is out of `min..=max`.
+    RangeError {
+        min: usize,
+        max: usize,
+    },
+    AlignmentError,
+    /// A cap lookup failed, `source` tells whether it was the source or the destination.
+    FailedLookup {
+        source: bool,
+        fault: lookup_fault,
+    },
+    TruncatedMessage,
+    DeleteFirst,
+    RevokeFirst,
+    /// The untyped only has `left` bytes left.
+    NotEnoughMemory {
+        left: usize,
+    },
+    /// The invocation was preempted and will restart, it is never reported to the user.
+    Preempted,
+}
+
+impl SyscallError {
+    /// The seL4 error number, the label of the error reply. `None` for a preemption,
+    /// which restarts the invocation instead of replying.
+    pub fn error_type(&self) -> Option<usize> {
+        Some(match self {
+            SyscallError::InvalidArgument { .. } => SEL4_INVALID_ARGUMENT,
+            SyscallError::InvalidCapability { .. } => SEL4_INVALID_CAPABILITY,
+            SyscallError::IllegalOperation => SEL4_ILLEGAL_OPERATION,
+            SyscallError::RangeError { .. } => SEL4_RANGE_ERROR,
+            SyscallError::AlignmentError => SEL4_ALIGNMENT_ERROR,
+            SyscallError::FailedLookup { .. } => SEL4_FAILED_LOOKUP,
+            SyscallError::TruncatedMessage => SEL4_TRUNCATED_MESSAGE,
+            SyscallError::DeleteFirst => SEL4_DELETE_FIRST,
+            SyscallError::RevokeFirst => SEL4_REVOKE_FIRST,
+            SyscallError::NotEnoughMemory { .. } => SEL4_NOT_ENOUGH_MEMORY,
+            SyscallError::Preempted => return None,
+        })
+    }
+}
+
+impl From<SyscallError> for exception_t {
+    fn from(error: SyscallError) -> Self {
+        match error {
+            SyscallError::Preempted => exception_t::EXCEPTION_PREEMTED,
+            _ => exception_t::EXCEPTION_SYSCALL_ERROR,
+        }
+    }
+}
+
+/// Convert the status of an operation that either completes or is preempted,
+/// e.g. `cte_t::revoke` or `cte_t::delete_all`.
+#[inline]
+pub fn check_preempted(status: exception_t) -> Result<(), SyscallError> {
+    match status {
+        exception_t::EXCEPTION_PREEMTED => Err(SyscallError::Preempted),
+        _ => Ok(()),
+    }
+}
@@ -1,5 +1,11 @@
+#[cfg(feature = "hypervisor")]
+use crate::arch::vcpu::VCpu;
 use rel4_arch::basic::PPtr;
+#[cfg(feature = "arm_smmu")]
+use sel4_common::sel4_config::CB_INVALID;
 use sel4_common::structures_gen::cap;
+#[cfg(feature = "hypervisor")]
+use sel4_common::structures_gen::cap_vcpu_cap;
 use sel4_common::{
     arch::{vm_rights_t, ObjectType},
     sel4_config::{ARM_HUGE_PAGE, ARM_LARGE_PAGE, ARM_SMALL_PAGE, ASID_INVALID},
@@ -39,12 +45,26 @@ pub fn arch_create_object(
             device_mem as u64,
         )
         .unsplay(),
+        #[cfg(not(feature = "arm_smmu"))]
         ObjectType::seL4_ARM_VSpaceObject => {
             cap_vspace_cap::new(ASID_INVALID as u64, region_base.raw() as u64, 0).unsplay()
         }
+        #[cfg(feature = "arm_smmu")]
+        ObjectType::seL4_ARM_VSpaceObject => cap_vspace_cap::new(
+            ASID_INVALID as u64,
+            region_base.raw() as u64,
+            0,
+            CB_INVALID as u64,
+        )
+        .unsplay(),
         ObjectType::seL4_ARM_PageTableObject => {
             cap_page_table_cap::new(ASID_INVALID as u64, region_base.raw() as u64, 0, 0).unsplay()
         }
+        #[cfg(feature = "hypervisor")]
+        ObjectType::seL4_ARM_VCPUObject => {
+            region_base.get_mut_ref::<VCpu>().init();
+            cap_vcpu_cap::new(region_base.raw() as u64).unsplay()
+        }
         _ => {
             unimplemented!(
                 "create object: {:?} region: {:#x} - {:#x}",
@@ -27,6 +27,16 @@ pub fn arch_create_object(
             )
             .unsplay()
         }
+        #[cfg(feature = "riscv_ext_svnapot")]
+        ObjectType::NapotPageObject => cap_frame_cap::new(
+            ASID_INVALID as u64,
+            region_base.as_u64(),
+            obj_type.get_frame_type() as u64,
+            vm_rights_t::VMReadWrite as u64,
+            device_mem as u64,
+            0,
+        )
+        .unsplay(),
         _ => {
             unimplemented!(
                 "create object: {:?} region: {:#x} - {:#x}",
@@ -1,30 +1,24 @@
-use crate::arch::set_vm_root_for_flush;
-use crate::kernel::boot::{current_extra_caps, get_extra_cap_by_index};
-use crate::syscall::invocation::decode::current_syscall_error;
+use crate::syscall::error::SyscallError;
+#[cfg(feature = "enable_smc")]
+use crate::syscall::get_syscall_arg;
 use crate::syscall::ThreadState;
-use crate::syscall::{current_lookup_fault, get_syscall_arg, set_thread_state, unlikely};
 use crate::syscall::{ensure_empty_slot, get_currenct_thread, lookup_slot_for_cnode_op};
+use crate::syscall::{set_thread_state, unlikely};
 use log::debug;
 use rel4_arch::basic::{PAddr, VPtr};
-use sel4_common::arch::maskVMRights;
+use rel4_utils::invocation::Skip;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::platform::MAX_IRQ;
-use sel4_common::sel4_bitfield_types::Bitfield;
-use sel4_common::sel4_config::{
-    ASID_INVALID, ASID_LOW_BITS, N_ASID_POOLS, SEL4_ALIGNMENT_ERROR, SEL4_ASID_POOL_BITS,
-    SEL4_FAILED_LOOKUP, SEL4_PAGE_BITS, SEL4_RANGE_ERROR, USER_TOP,
-};
-use sel4_common::sel4_config::{SEL4_DELETE_FIRST, SEL4_INVALID_ARGUMENT};
 use sel4_common::sel4_config::{
-    SEL4_ILLEGAL_OPERATION, SEL4_INVALID_CAPABILITY, SEL4_REVOKE_FIRST, SEL4_TRUNCATED_MESSAGE,
+    ASID_INVALID, ASID_LOW_BITS, N_ASID_POOLS, SEL4_ASID_POOL_BITS, SEL4_PAGE_BITS, USER_TOP,
 };
-use sel4_common::shared_types_bf_gen::seL4_CapRights;
 use sel4_common::structures_gen::{
     asid_map_asid_map_vspace, cap, cap_Splayed, cap_asid_pool_cap, cap_tag, cap_vspace_cap,
 };
 use sel4_common::structures_gen::{lookup_fault_invalid_root, lookup_fault_missing_capability};
 use sel4_common::utils::{
-    convert_ref_type_to_usize, convert_to_mut_type_ref, global_ops, max_free_index,
-    pageBitsForSize, ptr_to_mut, ptr_to_ref,
+    convert_ref_type_to_usize, convert_to_mut_type_ref, max_free_index, pageBitsForSize,
+    ptr_to_mut, ptr_to_ref,
 };
 use sel4_common::{
     arch::MessageLabel,
@@ -38,11 +32,27 @@ use sel4_vspace::{
     pte_tag_t, set_asid_pool_by_index, vm_attributes_t, PTE,
 };
 
+use super::super::args::{CapSlot, InvocationMessage, TypedCap, VmRights};
+#[cfg(feature = "arm_smmu")]
+use crate::arch::arm_smmu::consts::{SMMU_MAX_CB, SMMU_MAX_SID};
+#[cfg(not(feature = "hypervisor"))]
+use crate::arch::set_vm_root_for_flush;
+#[cfg(feature = "arm_smmu")]
+use crate::arch::smmu::{cb_asid, cb_is_assigned, sid_is_assigned, sid_node_slot};
+#[cfg(feature = "hypervisor")]
+use crate::arch::vcpu::{
+    gic_vcpu_num_list_regs, irq_vppi_event_index, virq_is_active, virq_pending_new, VCpu,
+    VCPU_REG_NUM,
+};
 #[cfg(feature = "enable_smp")]
-use crate::arch::arm_gic::gic_v2::gic_v2::set_irq_target;
+use crate::interrupt::set_irq_target_core;
 use crate::syscall::invocation::invoke_mmu_op::{
     invoke_page_get_address, invoke_page_map, invoke_page_table_unmap, invoke_page_unmap,
 };
+#[cfg(feature = "arm_smmu")]
+use crate::syscall::invocation::invoke_smmu::*;
+#[cfg(feature = "hypervisor")]
+use crate::syscall::invocation::invoke_vcpu::*;
 use crate::{
     interrupt::is_irq_active,
     syscall::{invocation::invoke_irq::invoke_irq_control, lookupSlotForCNodeOp},
@@ -55,11 +65,17 @@ use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
 use sel4_common::sel4_config::NUM_SMC_REGS;
 #[cfg(feature = "enable_smp")]
 use sel4_common::structures::{irq_to_idx, irqt_to_irq, to_irqt};
+#[cfg(feature = "hypervisor")]
+use sel4_common::structures_gen::cap_vcpu_cap;
+#[cfg(feature = "arm_smmu")]
+use sel4_common::structures_gen::{cap_cb_cap, cap_sid_cap};
 #[cfg(feature = "enable_smc")]
 use sel4_common::{
     arch::ArchReg, arch::MessageLabel::ARMSMCCall, arch::MSG_REGISTER_NUM,
     structures_gen::cap_smc_cap,
 };
+#[cfg(feature = "hypervisor")]
+use sel4_task::tcb_t;
 
 pub fn decode_mmu_invocation(
     label: MessageLabel,
@@ -67,25 +83,97 @@ pub fn decode_mmu_invocation(
     slot: &mut cte_t,
     call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     match slot.capability.clone().splay() {
-        cap_Splayed::vspace_cap(_) => decode_vspace_root_invocation(label, length, slot, buffer),
-        cap_Splayed::page_table_cap(_) => decode_page_table_invocation(label, length, slot, buffer),
-        cap_Splayed::frame_cap(_) => decode_frame_invocation(label, length, slot, call, buffer),
-        cap_Splayed::asid_control_cap(_) => decode_asid_control(label, length, buffer),
-        cap_Splayed::asid_pool_cap(_) => decode_asid_pool(label, slot),
+        cap_Splayed::vspace_cap(_) => {
+            decode_vspace_root_invocation(label, length, slot, buffer, ctx)
+        }
+        cap_Splayed::page_table_cap(_) => {
+            decode_page_table_invocation(label, length, slot, buffer, ctx)
+        }
+        cap_Splayed::frame_cap(_) => {
+            decode_frame_invocation(label, length, slot, call, buffer, ctx)
+        }
+        cap_Splayed::asid_control_cap(_) => decode_asid_control(label, length, buffer, ctx),
+        cap_Splayed::asid_pool_cap(_) => decode_asid_pool(label, length, slot, buffer, ctx),
+        #[cfg(feature = "arm_smmu")]
+        cap_Splayed::sid_control_cap(_) => {
+            decode_sid_control_invocation(label, length, slot, call, buffer, ctx)
+        }
+        #[cfg(feature = "arm_smmu")]
+        cap_Splayed::sid_cap(data) => decode_sid_invocation(label, length, &data, buffer, ctx),
+        #[cfg(feature = "arm_smmu")]
+        cap_Splayed::cb_control_cap(_) => {
+            decode_cb_control_invocation(label, length, slot, buffer, ctx)
+        }
+        #[cfg(feature = "arm_smmu")]
+        cap_Splayed::cb_cap(data) => decode_cb_invocation(label, length, &data, call, buffer, ctx),
         _ => {
             panic!("Invalid arch cap type");
         }
     }
 }
 
+invocation_args! {
+    /// The arguments of `ARMPageTableMap`, the vspace is checked after the page table.
+    struct PageTableMapArgs {
+        vaddr: usize,
+        _attr: usize,
+        _vspace: CapSlot,
+    }
+}
+
+invocation_args! {
+    /// The vspace of `ARMPageTableMap`.
+    struct PageTableMapVSpaceArgs {
+        _args: Skip<2>,
+        vspace: TypedCap<cap_vspace_cap>,
+    }
+}
+
+invocation_args! {
+    /// The range of a flush of a frame or vspace, e.g. `ARMPageClean_Data`.
+    struct FlushArgs {
+        start: usize,
+        end: usize,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `ARMASIDControlMakePool`.
+    struct ASIDControlMakePoolArgs {
+        index: usize,
+        depth: usize,
+        untyped: CapSlot,
+        root: CapSlot,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `ARMASIDPoolAssign`.
+    struct ASIDPoolAssignArgs {
+        vspace: TypedCap<cap_vspace_cap>,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `ARMPageMap`.
+    struct PageMapArgs {
+        vaddr: usize,
+        rights: VmRights,
+        attr: usize,
+        vspace: TypedCap<cap_vspace_cap>,
+    }
+}
+
 fn decode_page_table_invocation(
     label: MessageLabel,
     length: usize,
     cte: &mut cte_t,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     /*
         if (invLabel == ARMPageTableUnmap) {
             if (unlikely(!isFinalCapability(cte))) {
@@ -99,8 +187,7 @@ fn decode_page_table_invocation(
 
     if label == MessageLabel::ARMPageTableUnmap {
         if unlikely(!cte.is_final_cap()) {
-            global_ops!(current_syscall_error._type = SEL4_REVOKE_FIRST);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::RevokeFirst);
         }
         // log::warn!("Need to check is FinalCapability here");
         get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
@@ -109,49 +196,37 @@ fn decode_page_table_invocation(
     }
 
     if unlikely(label != MessageLabel::ARMPageTableMap) {
-        global_ops!(current_syscall_error._type = SEL4_ILLEGAL_OPERATION);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    if unlikely(length < 2 || global_ops!(current_extra_caps.excaprefs[0].is_null())) {
-        global_ops!(current_syscall_error._type = SEL4_TRUNCATED_MESSAGE);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
+    let message = InvocationMessage::new(length, buffer, ctx);
+    let vaddr = PageTableMapArgs::decode(&message)?.vaddr;
     if unlikely(cap::cap_page_table_cap(&cte.capability).get_capPTIsMapped() == 1) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidArgumentNumber = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 0 });
     }
 
-    let vaddr = get_syscall_arg(0, buffer);
-    let vspace_root_cap =
-        global_ops!(current_extra_caps.excaprefs[0].get_mut_ref::<cap_vspace_cap>());
+    let vspace_root_cap = PageTableMapVSpaceArgs::decode(&message)?.vspace.cap;
 
-    if unlikely(!vspace_root_cap.clone().unsplay().is_valid_native_root()) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidCapNumber = 1);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    if unlikely(vspace_root_cap.get_capVSIsMapped() == 0) {
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
 
     let vspace_root = vspace_root_cap.get_capVSBasePtr() as usize;
     let asid = vspace_root_cap.get_capVSMappedASID() as usize;
 
     if unlikely(vaddr > USER_TOP) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_ARGUMENT);
-        global_ops!(current_syscall_error.invalidArgumentNumber = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 0 });
     }
 
     let find_ret = find_vspace_for_asid(asid);
 
     if unlikely(find_ret.status != exception_t::EXCEPTION_NONE) {
-        global_ops!(current_syscall_error._type = SEL4_FAILED_LOOKUP);
-        global_ops!(current_syscall_error.failedLookupWasSource = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: find_ret.lookup_fault.unwrap(),
+        });
     }
     if unlikely(find_ret.vspace_root.unwrap() as usize != vspace_root) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidCapNumber = 1);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
 
     let pd_slot = PTE(vspace_root).lookup_pt_slot(vptr!(vaddr));
@@ -160,8 +235,7 @@ fn decode_page_table_invocation(
         pd_slot.ptBitsLeft == SEL4_PAGE_BITS
             || (ptr_to_ref(pd_slot.ptSlot).get_type() != (pte_tag_t::pte_invalid) as usize),
     ) {
-        global_ops!(current_syscall_error._type = SEL4_DELETE_FIRST);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
     let pte = PTE::pte_new_table(
         pptr!(cap::cap_page_table_cap(&cte.capability).get_capPTBasePtr()).to_paddr(),
@@ -178,7 +252,7 @@ fn decode_page_table_invocation(
         convert_ref_type_to_usize(ptr_to_mut(pd_slot.ptSlot)),
         pptr!(convert_ref_type_to_usize(ptr_to_mut(pd_slot.ptSlot))).to_paddr(),
     );
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 fn decode_page_clean_invocation(
@@ -187,86 +261,52 @@ fn decode_page_clean_invocation(
     cte: &mut cte_t,
     _call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 2 {
-        log::error!("[User] Page Flush: Truncated message.");
-        global_ops!(current_syscall_error._type = SEL4_TRUNCATED_MESSAGE);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
+    ctx: &kernel_state_t,
+) -> Result<(), SyscallError> {
+    let args = FlushArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| log::error!("[User] Page Flush: Truncated message."))?;
     if unlikely(cap::cap_frame_cap(&cte.capability).get_capFMappedASID() == 0) {
         log::error!("[User] Page Flush: Frame is not mapped.");
-        global_ops!(current_syscall_error._type = SEL4_ILLEGAL_OPERATION);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    let _vaddr = cap::cap_frame_cap(&cte.capability).get_capFMappedAddress();
+    let vaddr = cap::cap_frame_cap(&cte.capability).get_capFMappedAddress() as usize;
     let asid = cap::cap_frame_cap(&cte.capability).get_capFMappedASID() as usize;
     let find_ret = find_vspace_for_asid(asid);
 
     if unlikely(find_ret.status != exception_t::EXCEPTION_NONE) {
         log::error!("[User] Page Flush: No PGD for ASID");
-        global_ops!(current_syscall_error._type = SEL4_FAILED_LOOKUP);
-        global_ops!(current_syscall_error.failedLookupWasSource = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: find_ret.lookup_fault.unwrap(),
+        });
     }
 
-    let start = get_syscall_arg(0, buffer);
-    let end = get_syscall_arg(1, buffer);
+    let (start, end) = (args.start, args.end);
 
     if end <= start {
         log::error!("[User] Page Flush: Invalid range");
-        global_ops!(current_syscall_error._type = SEL4_INVALID_ARGUMENT);
-        global_ops!(current_syscall_error.invalidArgumentNumber = 1);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 1 });
     }
 
+    /* start and end are currently relative inside this page */
     let page_size = bit!(pageBitsForSize(
         cap::cap_frame_cap(&cte.capability).get_capFSize() as usize
     ));
     if start >= page_size || end > page_size {
         log::error!("[User] Page Flush: Requested range not inside page");
-        global_ops!(current_syscall_error._type = SEL4_INVALID_ARGUMENT);
-        global_ops!(current_syscall_error.invalidArgumentNumber = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 0 });
     }
     let pstart = (pptr!(cap::cap_frame_cap(&cte.capability).get_capFBasePtr()) + start).to_paddr();
     get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
-
-    if start < end {
-        let root_switched = set_vm_root_for_flush(find_ret.vspace_root.unwrap() as _, asid);
-        // log::warn!(
-        //     "need to flush cache for decode_page_clean_invocation label: {:?}",
-        //     label
-        // );
-
-        do_flush(label, start, end, pstart);
-        if root_switched {
-            get_currenct_thread()
-                .set_vm_root()
-                .expect("can't set vm root for decode_page_clean_invocation");
-        }
-    }
-    exception_t::EXCEPTION_NONE
-
-    /*
-        static exception_t performPageFlush(int invLabel, vspace_root_t *vspaceRoot, asid_t asid,
-                                    vptr_t start, vptr_t end, paddr_t pstart)
-        {
-            bool_t root_switched;
-                if (start < end) {
-                    root_switched = setVMRootForFlush(vspaceRoot, asid);
-                    do_flush(invLabel, start, end, pstart);
-                    if (root_switched) {
-                        setVMRoot(NODE_STATE(ksCurThread));
-                    }
-                }
-            return EXCEPTION_NONE;
-        }
-    */
-    /*
-        return performPageFlush(invLabel, find_ret.vspace_root, asid, vaddr + start, vaddr + end - 1,
-                                pstart);
-    */
+    perform_flush(
+        label,
+        find_ret.vspace_root.unwrap() as usize,
+        asid,
+        vptr!(vaddr + start),
+        vptr!(vaddr + end - 1),
+        pstart,
+    )
 }
 
 fn decode_frame_invocation(
@@ -275,19 +315,20 @@ fn decode_frame_invocation(
     frame_slot: &mut cte_t,
     call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     // sel4_common::println!("decode frame invocation {}",label as usize);
     match label {
-        MessageLabel::ARMPageMap => decode_frame_map(length, frame_slot, buffer),
+        MessageLabel::ARMPageMap => decode_frame_map(length, frame_slot, buffer, ctx),
         MessageLabel::ARMPageUnmap => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-            invoke_page_unmap(frame_slot)
+            invoke_page_unmap(frame_slot, ctx)
         }
         MessageLabel::ARMPageClean_Data
         | MessageLabel::ARMPageInvalidate_Data
         | MessageLabel::ARMPageCleanInvalidate_Data
         | MessageLabel::ARMPageUnify_Instruction => {
-            decode_page_clean_invocation(label, length, frame_slot, call, buffer)
+            decode_page_clean_invocation(label, length, frame_slot, call, buffer, ctx)
         }
         MessageLabel::ARMPageGetAddress => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -298,32 +339,24 @@ fn decode_frame_invocation(
         }
         _ => {
             debug!("invalid operation label:{:?}", label);
-            unsafe {
-                current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            }
-            exception_t::EXCEPTION_SYSCALL_ERROR
+            Err(SyscallError::IllegalOperation)
         }
     }
 }
 
-fn decode_asid_control(label: MessageLabel, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
+fn decode_asid_control(
+    label: MessageLabel,
+    length: usize,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if unlikely(label != MessageLabel::ARMASIDControlMakePool) {
-        global_ops!(current_syscall_error._type = SEL4_ILLEGAL_OPERATION);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
-    if unlikely(
-        length < 2
-            || global_ops!(current_extra_caps.excaprefs[0].is_null())
-            || global_ops!(current_extra_caps.excaprefs[1].is_null()),
-    ) {
-        global_ops!(current_syscall_error._type = SEL4_TRUNCATED_MESSAGE);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let index = get_syscall_arg(0, buffer);
-    let depth = get_syscall_arg(1, buffer);
-    let parent_slot = global_ops!(current_extra_caps.excaprefs[0].get_mut_ref::<cte_t>());
+    let args = ASIDControlMakePoolArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+    let parent_slot = args.untyped.0;
     let untyped = cap::cap_untyped_cap(&parent_slot.capability);
-    let root = &global_ops!(current_extra_caps.excaprefs[1].get_ref::<cte_t>()).capability;
+    let root = &args.root.0.capability;
 
     let mut i = 0;
     loop {
@@ -334,8 +367,7 @@ fn decode_asid_control(label: MessageLabel, length: usize, buffer: &seL4_IPCBuff
     }
     if unlikely(i == N_ASID_POOLS) {
         /* If no unallocated pool is found */
-        global_ops!(current_syscall_error._type = SEL4_DELETE_FIRST);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
     let asid_base = i << ASID_LOW_BITS;
     if unlikely(
@@ -343,24 +375,14 @@ fn decode_asid_control(label: MessageLabel, length: usize, buffer: &seL4_IPCBuff
             || untyped.get_capBlockSize() as usize != SEL4_ASID_POOL_BITS
             || untyped.get_capIsDevice() == 1,
     ) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidCapNumber = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
-    let status = parent_slot.ensure_no_children();
-    if unlikely(status != exception_t::EXCEPTION_NONE) {
-        return status;
+    if parent_slot.ensure_no_children() != exception_t::EXCEPTION_NONE {
+        return Err(SyscallError::RevokeFirst);
     }
     let frame = untyped.get_capPtr() as usize;
-    let lu_ret = lookup_slot_for_cnode_op(false, root, index, depth);
-    if unlikely(lu_ret.status != exception_t::EXCEPTION_NONE) {
-        return lu_ret.status;
-    }
-    let dest_slot = ptr_to_mut(lu_ret.slot);
-    let status = ensure_empty_slot(dest_slot);
-    if unlikely(status != exception_t::EXCEPTION_NONE) {
-        return status;
-    }
+    let dest_slot = lookup_slot_for_cnode_op(false, root, args.index, args.depth, ctx)?;
+    ensure_empty_slot(dest_slot)?;
     get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
     cap::cap_untyped_cap(&parent_slot.capability).set_capFreeIndex(max_free_index(
         cap::cap_untyped_cap(&parent_slot.capability).get_capBlockSize() as usize,
@@ -375,46 +397,41 @@ fn decode_asid_control(label: MessageLabel, length: usize, buffer: &seL4_IPCBuff
     );
     assert!(asid_base & mask_bits!(ASID_LOW_BITS) == 0);
     set_asid_pool_by_index(asid_base >> ASID_LOW_BITS, frame);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
-fn decode_asid_pool(label: MessageLabel, cte: &mut cte_t) -> exception_t {
+fn decode_asid_pool(
+    label: MessageLabel,
+    length: usize,
+    cte: &mut cte_t,
+    buffer: &seL4_IPCBuffer,
+    ctx: &kernel_state_t,
+) -> Result<(), SyscallError> {
     if unlikely(label != MessageLabel::ARMASIDPoolAssign) {
-        global_ops!(current_syscall_error._type = SEL4_ILLEGAL_OPERATION);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    if unlikely(global_ops!(current_extra_caps.excaprefs[0].is_null())) {
-        global_ops!(current_syscall_error._type = SEL4_TRUNCATED_MESSAGE);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    let vspace_cap_slot = global_ops!(current_extra_caps.excaprefs[0]);
-    let vspace_cap = vspace_cap_slot.get_mut_ref::<cap_vspace_cap>();
-    if unlikely(
-        !vspace_cap.clone().unsplay().is_vtable_root() || vspace_cap.get_capVSIsMapped() == 1,
-    ) {
+    let vspace = ASIDPoolAssignArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| log::debug!("is not a valid vtable root"))?
+        .vspace;
+    let vspace_cap = cap::cap_vspace_cap(&vspace.slot.capability);
+    if unlikely(vspace_cap.get_capVSIsMapped() == 1) {
         log::debug!("is not a valid vtable root");
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidArgumentNumber = 1);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
     let pool = get_asid_pool_by_index(
         cap::cap_asid_pool_cap(&cte.capability).get_capASIDBase() as usize >> ASID_LOW_BITS,
     );
 
     if unlikely(pool == 0) {
-        global_ops!(current_syscall_error._type = SEL4_FAILED_LOOKUP);
-        global_ops!(current_syscall_error.failedLookupWasSource = 0);
-        unsafe {
-            current_lookup_fault = lookup_fault_invalid_root::new().unsplay();
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: lookup_fault_invalid_root::new().unsplay(),
+        });
     }
 
     if unlikely(pool != cap::cap_asid_pool_cap(&cte.capability).get_capASIDPool() as usize) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidCapNumber = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 0 });
     }
 
     let mut asid = cap::cap_asid_pool_cap(&cte.capability).get_capASIDBase() as usize;
@@ -427,10 +444,7 @@ fn decode_asid_pool(label: MessageLabel, cte: &mut cte_t) -> exception_t {
     }
 
     if i == bit!(ASID_LOW_BITS) {
-        unsafe {
-            current_syscall_error._type = SEL4_DELETE_FIRST;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
 
     asid += i;
@@ -438,87 +452,74 @@ fn decode_asid_pool(label: MessageLabel, cte: &mut cte_t) -> exception_t {
     get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
     vspace_cap.set_capVSMappedASID(asid as u64);
     vspace_cap.set_capVSIsMapped(1);
+    #[cfg(not(any(feature = "hypervisor", feature = "arm_smmu")))]
     let asidmap = asid_map_asid_map_vspace::new(vspace_cap.get_capVSBasePtr() as u64).unsplay();
+    #[cfg(feature = "arm_smmu")]
+    let asidmap = asid_map_asid_map_vspace::new(0, vspace_cap.get_capVSBasePtr() as u64).unsplay();
+    #[cfg(feature = "hypervisor")]
+    let asidmap =
+        asid_map_asid_map_vspace::new(vspace_cap.get_capVSBasePtr() as u64, 0, 0).unsplay();
     pool[asid & mask_bits!(ASID_LOW_BITS)] = asidmap;
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
-fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuffer) -> exception_t {
-    if length < 3 || get_extra_cap_by_index(0).is_none() {
-        debug!("ARMPageMap: Truncated message.");
-        global_ops!(current_syscall_error._type = SEL4_TRUNCATED_MESSAGE);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let vaddr = get_syscall_arg(0, buffer);
-    let attr = vm_attributes_t::from_word(get_syscall_arg(2, buffer));
-    let vspace_root_cap = cap::cap_vspace_cap(&get_extra_cap_by_index(0).unwrap().capability);
+fn decode_frame_map(
+    length: usize,
+    frame_slot: &mut cte_t,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let args = PageMapArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("ARMPageMap: Invalid arguments."))?;
+    let vaddr = args.vaddr;
+    let attr = vm_attributes_t::from_word(args.attr);
+    let vspace_root_cap = args.vspace.cap;
     let frame_size = cap::cap_frame_cap(&frame_slot.capability).get_capFSize() as usize;
-    let frame_vm_rights = unsafe {
-        core::mem::transmute(cap::cap_frame_cap(&frame_slot.capability).get_capFVMRights())
-    };
-    let vm_rights = maskVMRights(
-        frame_vm_rights,
-        seL4_CapRights(Bitfield {
-            arr: [get_syscall_arg(1, buffer) as u64; 1],
-        }),
-    );
-    if unlikely(!vspace_root_cap.clone().unsplay().is_valid_native_root()) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidCapNumber = 1);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+    let vm_rights = args.rights.mask(cap::cap_frame_cap(&frame_slot.capability));
+    if unlikely(vspace_root_cap.get_capVSIsMapped() == 0) {
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
     let vspace_root = vspace_root_cap.get_capVSBasePtr() as usize;
     let asid = vspace_root_cap.get_capVSMappedASID() as usize;
     let find_ret = find_vspace_for_asid(asid);
     if unlikely(find_ret.status != exception_t::EXCEPTION_NONE) {
-        global_ops!(current_syscall_error._type = SEL4_FAILED_LOOKUP);
-        global_ops!(current_syscall_error.failedLookupWasSource = 0);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: find_ret.lookup_fault.unwrap(),
+        });
     }
     if unlikely(find_ret.vspace_root.unwrap() as usize != vspace_root) {
-        global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-        global_ops!(current_syscall_error.invalidCapNumber = 1);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
     if unlikely(!is_aligned!(vaddr, pageBitsForSize(frame_size))) {
-        // global_var!(current_syscall_error)._type = SEL4_ALIGNMENT_ERROR;
+        // global_var!(ctx.syscall_error)._type = SEL4_ALIGNMENT_ERROR;
         // Use unsafe here will cause the _type error.
-        global_ops!(current_syscall_error._type = SEL4_ALIGNMENT_ERROR);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::AlignmentError);
     }
     let frame_asid = cap::cap_frame_cap(&frame_slot.capability).get_capFMappedASID() as usize;
     if frame_asid != ASID_INVALID {
         if frame_asid != asid {
             log::error!("[User] ARMPageMap: Attempting to remap a frame that does not belong to the passed address space");
-            global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-            global_ops!(current_syscall_error.invalidArgumentNumber = 0);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidCapability { index: 0 });
         } else if cap::cap_frame_cap(&frame_slot.capability).get_capFMappedAddress() as usize
             != vaddr
         {
             log::error!("[User] ARMPageMap: Attempting to map frame into multiple addresses");
-            global_ops!(current_syscall_error._type = SEL4_INVALID_ARGUMENT);
-            global_ops!(current_syscall_error.invalidArgumentNumber = 2);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidArgument { index: 2 });
         }
     } else {
         if unlikely(vaddr + bit!(pageBitsForSize(frame_size)) - 1 > USER_TOP) {
-            global_ops!(current_syscall_error._type = SEL4_INVALID_ARGUMENT);
-            global_ops!(current_syscall_error.invalidArgumentNumber = 0);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidArgument { index: 0 });
         }
     }
     let mut vspace_root_pte = PTE::new_from_pte(vspace_root);
     let base = pptr!(cap::cap_frame_cap(&frame_slot.capability).get_capFBasePtr()).to_paddr();
     let lu_ret = vspace_root_pte.lookup_pt_slot(vptr!(vaddr));
     if unlikely(lu_ret.ptBitsLeft != pageBitsForSize(frame_size)) {
-        unsafe {
-            current_lookup_fault =
-                lookup_fault_missing_capability::new(lu_ret.ptBitsLeft as u64).unsplay();
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = 0;
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: lookup_fault_missing_capability::new(lu_ret.ptBitsLeft as u64).unsplay(),
+        });
     }
     let pt_slot = convert_to_mut_type_ref::<PTE>(lu_ret.ptSlot as usize);
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -535,8 +536,8 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //         let lu_ret = vspace_root.lookup_pt_slot(vaddr);
     //         if lu_ret.status != exception_t::EXCEPTION_NONE {
     //             unsafe {
-    //                 current_syscall_error._type = SEL4_FAILED_LOOKUP;
-    //                 current_syscall_error.failedLookupWasSource = 0;
+    //                 ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+    //                 ctx.syscall_error.failedLookupWasSource = 0;
     //             }
     //             return exception_t::EXCEPTION_SYSCALL_ERROR;
     //         }
@@ -554,8 +555,8 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //         let lu_ret = vspace_root.lookup_pd_slot(vaddr);
     //         if lu_ret.status != exception_t::EXCEPTION_NONE {
     //             unsafe {
-    //                 current_syscall_error._type = SEL4_FAILED_LOOKUP;
-    //                 current_syscall_error.failedLookupWasSource = 0;
+    //                 ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+    //                 ctx.syscall_error.failedLookupWasSource = 0;
     //             }
     //             return exception_t::EXCEPTION_SYSCALL_ERROR;
     //         }
@@ -573,8 +574,8 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //         let lu_ret = vspace_root.lookup_pud_slot(vaddr);
     //         if lu_ret.status != exception_t::EXCEPTION_NONE {
     //             unsafe {
-    //                 current_syscall_error._type = SEL4_FAILED_LOOKUP;
-    //                 current_syscall_error.failedLookupWasSource = 0;
+    //                 ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+    //                 ctx.syscall_error.failedLookupWasSource = 0;
     //             }
     //             return exception_t::EXCEPTION_SYSCALL_ERROR;
     //         }
@@ -590,17 +591,17 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //     }
     // _ => exception_t::EXCEPTION_SYSCALL_ERROR,
     // }
-    // if length < 3 || get_extra_cap_by_index(0).is_none() {
+    // if length < 3 || get_extra_cap_by_index(ctx, 0).is_none() {
     //     debug!("ARMPageMap: Truncated message.");
     //     unsafe {
-    //         current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
+    //         ctx.syscall_error._type = SEL4_TRUNCATED_MESSAGE;
     //     }
     //     return exception_t::EXCEPTION_SYSCALL_ERROR;
     // }
     // let vaddr = get_syscall_arg(0, buffer);
     // log::debug!("map frame: {:#x?}  frame: {:#x?}", frame_slot.cap.get_frame_mapped_address(), vaddr);
     // let attr = vm_attributes_t::from_word(get_syscall_arg(2, buffer));
-    // let lvl1pt_cap = get_extra_cap_by_index(0).unwrap().cap;
+    // let lvl1pt_cap = get_extra_cap_by_index(ctx, 0).unwrap().cap;
     // let frame_size = frame_slot.cap.get_frame_size();
     // let frame_vm_rights = unsafe { core::mem::transmute(frame_slot.cap.get_frame_vm_rights()) };
     // let vm_rights = maskVMRights(
@@ -613,7 +614,7 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     // };
     // if unlikely(!check_vp_alignment(frame_size, vaddr)) {
     //     unsafe {
-    //         current_syscall_error._type = SEL4_ALIGNMENT_ERROR;
+    //         ctx.syscall_error._type = SEL4_ALIGNMENT_ERROR;
     //     }
     //     return exception_t::EXCEPTION_SYSCALL_ERROR;
     // }
@@ -623,16 +624,16 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //     if frame_asid != asid {
     //         debug!("ARMPageMap: Attempting to remap a frame that does not belong to the passed address space");
     //         unsafe {
-    //             current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-    //             current_syscall_error.invalidArgumentNumber = 0;
+    //             ctx.syscall_error._type = SEL4_INVALID_CAPABILITY;
+    //             ctx.syscall_error.invalidArgumentNumber = 0;
     //         }
     //         return exception_t::EXCEPTION_SYSCALL_ERROR;
     //     }
     //     if frame_slot.cap.get_frame_mapped_address() != vaddr {
     //         debug!("ARMPageMap: attempting to map frame into multiple addresses");
     //         unsafe {
-    //             current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-    //             current_syscall_error.invalidArgumentNumber = 2;
+    //             ctx.syscall_error._type = SEL4_INVALID_ARGUMENT;
+    //             ctx.syscall_error.invalidArgumentNumber = 2;
     //         }
     //         return exception_t::EXCEPTION_SYSCALL_ERROR;
     //     }
@@ -640,8 +641,8 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //     let vtop = vaddr + bit!(pageBitsForSize(frame_size)) - 1;
     //     if unlikely(vtop >= USER_TOP) {
     //         unsafe {
-    //             current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-    //             current_syscall_error.invalidArgumentNumber = 0;
+    //             ctx.syscall_error._type = SEL4_INVALID_ARGUMENT;
+    //             ctx.syscall_error.invalidArgumentNumber = 0;
     //         }
     //         return exception_t::EXCEPTION_SYSCALL_ERROR;
     //     }
@@ -655,8 +656,8 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //     let lu_ret = vspace_root.lookup_pt_slot(vaddr);
     //     if lu_ret.status != exception_t::EXCEPTION_NONE {
     //         unsafe {
-    //             current_syscall_error._type = SEL4_FAILED_LOOKUP;
-    //             current_syscall_error.failedLookupWasSource = 0;
+    //             ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+    //             ctx.syscall_error.failedLookupWasSource = 0;
     //         }
     //         return exception_t::EXCEPTION_SYSCALL_ERROR;
     //     }
@@ -673,8 +674,8 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //     let lu_ret = vspace_root.lookup_pd_slot(vaddr);
     //     if lu_ret.status != exception_t::EXCEPTION_NONE {
     //         unsafe {
-    //             current_syscall_error._type = SEL4_FAILED_LOOKUP;
-    //             current_syscall_error.failedLookupWasSource = 0;
+    //             ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+    //             ctx.syscall_error.failedLookupWasSource = 0;
     //         }
     //         return exception_t::EXCEPTION_SYSCALL_ERROR;
     //     }
@@ -691,8 +692,8 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
     //     let lu_ret = vspace_root.lookup_pud_slot(vaddr);
     //     if lu_ret.status != exception_t::EXCEPTION_NONE {
     //         unsafe {
-    //             current_syscall_error._type = SEL4_FAILED_LOOKUP;
-    //             current_syscall_error.failedLookupWasSource = 0;
+    //             ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+    //             ctx.syscall_error.failedLookupWasSource = 0;
     //         }
     //         return exception_t::EXCEPTION_SYSCALL_ERROR;
     //     }
@@ -711,11 +712,10 @@ fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuff
 }
 
 #[allow(unused)]
-fn decode_page_table_unmap(pt_cte: &mut cte_t) -> exception_t {
+fn decode_page_table_unmap(pt_cte: &mut cte_t) -> Result<(), SyscallError> {
     if !pt_cte.is_final_cap() {
         debug!("PageTableUnmap: cannot unmap if more than once cap exists");
-        global_ops!(current_syscall_error._type = SEL4_REVOKE_FIRST);
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::RevokeFirst);
     }
     let capability = &mut cap::cap_page_table_cap(&pt_cte.capability);
     // todo: in riscv here exists some more code ,but I don't know what it means and cannot find it in sel4,need check
@@ -756,112 +756,111 @@ fn decode_vspace_root_invocation(
     length: usize,
     cte: &mut cte_t,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &kernel_state_t,
+) -> Result<(), SyscallError> {
     match label {
         MessageLabel::ARMVSpaceClean_Data
         | MessageLabel::ARMVSpaceInvalidate_Data
         | MessageLabel::ARMVSpaceCleanInvalidate_Data
         | MessageLabel::ARMVSpaceUnify_Instruction => {
-            if length < 2 {
-                debug!("VSpaceRoot Flush: Truncated message.");
-                unsafe {
-                    current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-                    return exception_t::EXCEPTION_SYSCALL_ERROR;
-                }
-            }
-            let start = get_syscall_arg(0, buffer);
-            let end = get_syscall_arg(1, buffer);
+            let args = FlushArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("VSpaceRoot Flush: Truncated message."))?;
+            let (start, end) = (args.start, args.end);
             if end <= start {
                 debug!("VSpaceRoot Flush: Invalid range.");
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-                    current_syscall_error.invalidArgumentNumber = 1;
-                    return exception_t::EXCEPTION_SYSCALL_ERROR;
-                }
+                return Err(SyscallError::InvalidArgument { index: 1 });
             }
             if end > USER_TOP {
                 debug!("VSpaceRoot Flush: Exceed the user addressable region.");
-                unsafe { current_syscall_error._type = SEL4_ILLEGAL_OPERATION };
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::IllegalOperation);
             }
             if !cte.capability.is_valid_native_root() {
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0
-                };
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
-            let vspace_root = cap::cap_vspace_cap(&cte.capability).get_capVSBasePtr() as *mut PTE;
-            let asid = cap::cap_asid_pool_cap(&cte.capability).get_capASIDBase() as usize;
+            let vspace_root = cap::cap_vspace_cap(&cte.capability).get_capVSBasePtr() as usize;
+            let asid = cap::cap_vspace_cap(&cte.capability).get_capVSMappedASID() as usize;
             let find_ret = find_vspace_for_asid(asid);
             if find_ret.status != exception_t::EXCEPTION_NONE {
                 debug!("VSpaceRoot Flush: No VSpace for ASID");
-                unsafe {
-                    current_syscall_error._type = SEL4_FAILED_LOOKUP;
-                    current_syscall_error.failedLookupWasSource = 0;
-                    return exception_t::EXCEPTION_SYSCALL_ERROR;
-                }
+                return Err(SyscallError::FailedLookup {
+                    source: false,
+                    fault: find_ret.lookup_fault.unwrap(),
+                });
             }
-            if find_ret.vspace_root.unwrap() as usize != ptr_to_ref(vspace_root).get_ptr() {
+            if find_ret.vspace_root.unwrap() as usize != vspace_root {
                 debug!("VSpaceRoot Flush: Invalid VSpace Cap");
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
-            let resolve_ret = ptr_to_mut(vspace_root).lookup_pt_slot(vptr!(start));
-            let pte = resolve_ret.ptSlot;
-            if ptr_to_ref(pte).get_type() != (pte_tag_t::pte_page) as usize {
+            let resolve_ret = ptr_to_mut(vspace_root as *mut PTE).lookup_pt_slot(vptr!(start));
+            let pte = ptr_to_ref(resolve_ret.ptSlot);
+            if !pte.pte_is_page_type() {
+                /*
+                 * Fail silently, as there can't be any stale cached data (for the given
+                 * address space), and getting a syscall error because the relevant page
+                 * is non-resident would be 'astonishing'.
+                 */
                 get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
-                return exception_t::EXCEPTION_NONE;
+                return Ok(());
             }
-            let page_base_start = start & !mask_bits!(pageBitsForSize(resolve_ret.ptBitsLeft));
-            let page_base_end = (end - 1) & !mask_bits!(pageBitsForSize(resolve_ret.ptBitsLeft));
+            /* Make sure the vaddr range is within one page */
+            let page_mask = mask_bits!(resolve_ret.ptBitsLeft);
+            let page_base_start = start & !page_mask;
+            let page_base_end = (end - 1) & !page_mask;
             if page_base_start != page_base_end {
-                unsafe {
-                    current_syscall_error._type = SEL4_RANGE_ERROR;
-                    current_syscall_error.rangeErrorMin = start;
-                    current_syscall_error.rangeErrorMax =
-                        page_base_start + mask_bits!(pageBitsForSize(resolve_ret.ptBitsLeft));
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                debug!("VSpaceRoot Flush: Only flush in a single page.");
+                return Err(SyscallError::RangeError {
+                    min: start,
+                    max: page_base_start + page_mask,
+                });
             }
-            let pstart = ptr_to_ref(pte).get_page_base_address().raw() + start
-                & mask_bits!(pageBitsForSize(resolve_ret.ptBitsLeft));
+            let pstart = pte.get_page_base_address() + (start & page_mask);
             get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
-            return decode_vspace_flush_invocation(
+            perform_flush(
                 label,
-                find_ret.vspace_root.unwrap() as usize,
+                vspace_root,
                 asid,
                 vptr!(start),
-                vptr!(end),
-                paddr!(pstart),
-            );
-        }
-        _ => {
-            unsafe { current_syscall_error._type = SEL4_ILLEGAL_OPERATION };
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+                vptr!(end - 1),
+                pstart,
+            )
         }
+        _ => Err(SyscallError::IllegalOperation),
     }
 }
 
-fn decode_vspace_flush_invocation(
+/// Flush the cache of `[start, end]` in the vspace of `asid`, `pstart` is the physical
+/// address of `start`.
+#[cfg(not(feature = "hypervisor"))]
+fn perform_flush(
     label: MessageLabel,
     vspace: usize,
     asid: asid_t,
     start: VPtr,
     end: VPtr,
     pstart: PAddr,
-) -> exception_t {
-    if start < end {
-        let root_switched = set_vm_root_for_flush(vspace, asid);
-        do_flush(label, start.raw(), end.raw(), pstart);
-        if root_switched {
-            let _ = get_currenct_thread().set_vm_root();
-        }
+) -> Result<(), SyscallError> {
+    let root_switched = set_vm_root_for_flush(vspace, asid);
+    do_flush(label, start.raw(), end.raw(), pstart);
+    if root_switched {
+        let _ = get_currenct_thread().set_vm_root();
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
+}
+
+/// Flush the cache of `[start, end]` through the kernel window, as the user virtual
+/// addresses are not valid in EL2 and there is no need to switch the vm root.
+#[cfg(feature = "hypervisor")]
+fn perform_flush(
+    label: MessageLabel,
+    _vspace: usize,
+    _asid: asid_t,
+    start: VPtr,
+    end: VPtr,
+    pstart: PAddr,
+) -> Result<(), SyscallError> {
+    let kstart = pstart.to_pptr().raw();
+    do_flush(label, kstart, kstart + (end.raw() - start.raw()), pstart);
+    Ok(())
 }
 
 // fn decode_page_upper_directory_invocation(
@@ -875,7 +874,7 @@ fn decode_vspace_flush_invocation(
 //         findVSpaceForASID_ret_t find_ret;
 //         if (invLabel == ARMPageUpperDirectoryUnmap) {
 //             if (unlikely(!isFinalCapability(cte))) {
-//                 current_syscall_error.type = SEL4_REVOKE_FIRST;
+//                 ctx.syscall_error.type = SEL4_REVOKE_FIRST;
 //                 return EXCEPTION_SYSCALL_ERROR;
 //             }
 //             set_thread_state(NODE_STATE(ksCurThread), ThreadState_Restart);
@@ -885,7 +884,7 @@ fn decode_vspace_flush_invocation(
 //     if label == MessageLabel::ARMPageUpperDirectoryUnmap {
 //         // log::warn!("Need to check is FinalCapability here");
 //         if unlikely(!cte.is_final_cap()) {
-//             global_ops!(current_syscall_error._type = SEL4_REVOKE_FIRST);
+//             ctx.syscall_error._type = SEL4_REVOKE_FIRST;
 //             return exception_t::EXCEPTION_SYSCALL_ERROR;
 //         }
 //         get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
@@ -895,25 +894,25 @@ fn decode_vspace_flush_invocation(
 
 //     // Return SYSCALL_ERROR if message is not ARMPageUpperDirectoryUnmap
 //     if unlikely(label != MessageLabel::ARMPageUpperDirectoryMap) {
-//         global_ops!(current_syscall_error._type = SEL4_ILLEGAL_OPERATION);
+//         ctx.syscall_error._type = SEL4_ILLEGAL_OPERATION;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 
-//     if unlikely(length < 2 || unsafe { current_extra_caps.excaprefs[0] == 0 }) {
-//         global_ops!(current_syscall_error._type = SEL4_TRUNCATED_MESSAGE);
+//     if unlikely(length < 2 || unsafe { ctx.extra_caps.excaprefs[0] == 0 }) {
+//         ctx.syscall_error._type = SEL4_TRUNCATED_MESSAGE;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     if unlikely(cte.cap.get_pud_is_mapped() == 1) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-//         global_ops!(current_syscall_error.invalidCapNumber = 0);
+//         ctx.syscall_error._type = SEL4_INVALID_CAPABILITY;
+//         ctx.syscall_error.invalidCapNumber = 0;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     let vaddr = get_syscall_arg(0, buffer) & (!MASK!(PGD_INDEX_OFFSET));
-//     let pgd_cap = convert_to_mut_type_ref::<cap_t>(global_ops!(current_extra_caps.excaprefs[0]));
+//     let pgd_cap = convert_to_mut_type_ref::<cap_t>(ctx.extra_caps.excaprefs[0]);
 
 //     if unlikely(!pgd_cap.is_valid_native_root()) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-//         global_ops!(current_syscall_error.invalidCapNumber = 1);
+//         ctx.syscall_error._type = SEL4_INVALID_CAPABILITY;
+//         ctx.syscall_error.invalidCapNumber = 1;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 
@@ -921,22 +920,22 @@ fn decode_vspace_flush_invocation(
 //     let asid = pgd_cap.get_pgd_mapped_asid();
 
 //     if unlikely(vaddr > USER_TOP) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_ARGUMENT);
-//         global_ops!(current_syscall_error.failedLookupWasSource = 0);
+//         ctx.syscall_error._type = SEL4_INVALID_ARGUMENT;
+//         ctx.syscall_error.failedLookupWasSource = 0;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 
 //     let find_ret = find_vspace_for_asid(asid);
 
 //     if unlikely(find_ret.status != exception_t::EXCEPTION_NONE) {
-//         global_ops!(current_syscall_error._type = SEL4_FAILED_LOOKUP);
-//         global_ops!(current_syscall_error.failedLookupWasSource = 0);
+//         ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+//         ctx.syscall_error.failedLookupWasSource = 0;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     // vspace_root is Some(_) when Exception is NONE
 //     if unlikely(find_ret.vspace_root.unwrap() as usize != pgd) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-//         global_ops!(current_syscall_error.invalidCapNumber = 1);
+//         ctx.syscall_error._type = SEL4_INVALID_CAPABILITY;
+//         ctx.syscall_error.invalidCapNumber = 1;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     // Ensure that pgd is aligned 4K.
@@ -945,7 +944,7 @@ fn decode_vspace_flush_invocation(
 //     let pgd_slot = PGDE::new_from_pte(pgd).lookup_pgd_slot(vaddr);
 
 //     if unlikely(ptr_to_ref(pgd_slot.pgdSlot).get_present()) {
-//         global_ops!(current_syscall_error._type = SEL4_DELETE_FIRST);
+//         ctx.syscall_error._type = SEL4_DELETE_FIRST;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     // TODO: make 0x3 in a pagetable-specific position
@@ -971,7 +970,7 @@ fn decode_vspace_flush_invocation(
 //     /*
 //         if (invLabel == ARMPageDirectoryUnmap) {
 //             if (unlikely(!isFinalCapability(cte))) {
-//                 current_syscall_error.type = SEL4_REVOKE_FIRST;
+//                 ctx.syscall_error.type = SEL4_REVOKE_FIRST;
 //                 return EXCEPTION_SYSCALL_ERROR;
 //             }
 //             set_thread_state(NODE_STATE(ksCurThread), ThreadState_Restart);
@@ -982,7 +981,7 @@ fn decode_vspace_flush_invocation(
 //     if label == MessageLabel::ARMPageDirectoryUnmap {
 //         // log::warn!("Need to check is FinalCapability here");
 //         if unlikely(!cte.is_final_cap()) {
-//             global_ops!(current_syscall_error._type = SEL4_REVOKE_FIRST);
+//             ctx.syscall_error._type = SEL4_REVOKE_FIRST;
 //             return exception_t::EXCEPTION_SYSCALL_ERROR;
 //         }
 //         get_currenct_thread().set_state(ThreadState::ThreadStateRestart);
@@ -992,26 +991,26 @@ fn decode_vspace_flush_invocation(
 
 //     // Return SYSCALL_ERROR if message is not ARMPageDirectoryUnmap
 //     if unlikely(label != MessageLabel::ARMPageDirectoryMap) {
-//         global_ops!(current_syscall_error._type = SEL4_ILLEGAL_OPERATION);
+//         ctx.syscall_error._type = SEL4_ILLEGAL_OPERATION;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
-//     if unlikely(length < 2 || global_ops!(current_extra_caps.excaprefs[0] == 0)) {
-//         global_ops!(current_syscall_error._type = SEL4_TRUNCATED_MESSAGE);
+//     if unlikely(length < 2 || global_ops!(ctx.extra_caps.excaprefs[0] == 0)) {
+//         ctx.syscall_error._type = SEL4_TRUNCATED_MESSAGE;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     if unlikely(cte.cap.get_pd_is_mapped() == 1) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-//         global_ops!(current_syscall_error.invalidCapNumber = 0);
+//         ctx.syscall_error._type = SEL4_INVALID_CAPABILITY;
+//         ctx.syscall_error.invalidCapNumber = 0;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 
 //     let vaddr = get_syscall_arg(0, buffer) & (!MASK!(PUD_INDEX_OFFSET));
 //     let vspace_root_cap =
-//         convert_to_mut_type_ref::<cap_t>(global_ops!(current_extra_caps.excaprefs[0]));
+//         convert_to_mut_type_ref::<cap_t>(ctx.extra_caps.excaprefs[0]);
 
 //     if unlikely(!vspace_root_cap.is_valid_native_root()) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-//         global_ops!(current_syscall_error.invalidCapNumber = 1);
+//         ctx.syscall_error._type = SEL4_INVALID_CAPABILITY;
+//         ctx.syscall_error.invalidCapNumber = 1;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 
@@ -1019,35 +1018,35 @@ fn decode_vspace_flush_invocation(
 //     let asid = vspace_root_cap.get_pgd_mapped_asid();
 
 //     if unlikely(vaddr > USER_TOP) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_ARGUMENT);
-//         global_ops!(current_syscall_error.failedLookupWasSource = 0);
+//         ctx.syscall_error._type = SEL4_INVALID_ARGUMENT;
+//         ctx.syscall_error.failedLookupWasSource = 0;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 
 //     let find_ret = find_vspace_for_asid(asid);
 
 //     if unlikely(find_ret.status != exception_t::EXCEPTION_NONE) {
-//         global_ops!(current_syscall_error._type = SEL4_FAILED_LOOKUP);
-//         global_ops!(current_syscall_error.failedLookupWasSource = 0);
+//         ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+//         ctx.syscall_error.failedLookupWasSource = 0;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     if unlikely(find_ret.vspace_root.unwrap() as usize != vspace_root) {
-//         global_ops!(current_syscall_error._type = SEL4_INVALID_CAPABILITY);
-//         global_ops!(current_syscall_error.invalidCapNumber = 1);
+//         ctx.syscall_error._type = SEL4_INVALID_CAPABILITY;
+//         ctx.syscall_error.invalidCapNumber = 1;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 
 //     let pud_slot = PGDE::new_from_pte(vspace_root).lookup_pud_slot(vaddr);
 
 //     if pud_slot.status != exception_t::EXCEPTION_NONE {
-//         global_ops!(current_syscall_error._type = SEL4_FAILED_LOOKUP);
-//         global_ops!(current_syscall_error.failedLookupWasSource = 0);
+//         ctx.syscall_error._type = SEL4_FAILED_LOOKUP;
+//         ctx.syscall_error.failedLookupWasSource = 0;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     if unlikely(
 //         ptr_to_ref(pud_slot.pudSlot).get_present() || ptr_to_ref(pud_slot.pudSlot).is_1g_page(),
 //     ) {
-//         global_ops!(current_syscall_error._type = SEL4_DELETE_FIRST);
+//         ctx.syscall_error._type = SEL4_DELETE_FIRST;
 //         return exception_t::EXCEPTION_SYSCALL_ERROR;
 //     }
 //     // TODO: make 0x3 in a pagetable-specific position
@@ -1065,67 +1064,68 @@ fn decode_vspace_flush_invocation(
 //     exception_t::EXCEPTION_NONE
 // }
 
-pub(crate) fn check_irq(irq: usize) -> exception_t {
+invocation_args! {
+    /// The arguments of `ARMIRQIssueIRQHandlerTrigger`, the trigger is not configured.
+    struct IssueIRQHandlerTriggerArgs {
+        irq: usize,
+        _trigger: bool,
+        index: usize,
+        depth: usize,
+        root: CapSlot,
+    }
+}
+
+#[cfg(feature = "enable_smp")]
+invocation_args! {
+    /// The arguments of `ARMIRQIssueIRQHandlerTriggerCore`.
+    struct IssueIRQHandlerTriggerCoreArgs {
+        irq: usize,
+        _trigger: bool,
+        index: usize,
+        depth: usize,
+        target: usize,
+        root: CapSlot,
+    }
+}
+
+pub(crate) fn check_irq(irq: usize) -> Result<(), SyscallError> {
     if irq > MAX_IRQ {
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 0;
-            current_syscall_error.rangeErrorMax = MAX_IRQ;
-            debug!(
-                "Rejecting request for IRQ {}. IRQ is out of range [1..MAX_IRQ].",
-                irq
-            );
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
+        debug!(
+            "Rejecting request for IRQ {}. IRQ is out of range [1..MAX_IRQ].",
+            irq
+        );
+        return Err(SyscallError::RangeError {
+            min: 0,
+            max: MAX_IRQ,
+        });
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 #[cfg(not(feature = "enable_smp"))]
 pub fn arch_decode_irq_control_invocation(
     label: MessageLabel,
     length: usize,
     src_slot: &mut cte_t,
+    _call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if label == MessageLabel::ARMIRQIssueIRQHandlerTrigger {
-        if length < 4 || get_extra_cap_by_index(0).is_none() {
-            unsafe {
-                current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        let irq = get_syscall_arg(0, buffer);
-        let _trigger = get_syscall_arg(1, buffer) != 0;
-        let index = get_syscall_arg(2, buffer);
-        let depth = get_syscall_arg(3, buffer);
-        let cnode_cap = &get_extra_cap_by_index(0).unwrap().capability;
-        let status = check_irq(irq);
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
+        let args =
+            IssueIRQHandlerTriggerArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+        let irq = args.irq;
+        let cnode_cap = &args.root.0.capability;
+        check_irq(irq)?;
         if is_irq_active(irq) {
-            unsafe {
-                current_syscall_error._type = SEL4_REVOKE_FIRST;
-            }
             debug!("Rejecting request for IRQ {}. Already active.", irq);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        let lu_ret = lookupSlotForCNodeOp(false, cnode_cap, index, depth);
-        if lu_ret.status != exception_t::EXCEPTION_NONE {
-            debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq);
-            return lu_ret.status;
+            return Err(SyscallError::RevokeFirst);
         }
+        let dest_slot = lookupSlotForCNodeOp(false, cnode_cap, args.index, args.depth, ctx)
+            .inspect_err(|_| debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq))?;
         set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-        invoke_irq_control(
-            irq,
-            convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize),
-            src_slot,
-        )
+        invoke_irq_control(irq, dest_slot, src_slot)
     } else {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 }
 #[cfg(feature = "enable_smp")]
@@ -1133,114 +1133,86 @@ pub fn arch_decode_irq_control_invocation(
     label: MessageLabel,
     length: usize,
     src_slot: &mut cte_t,
+    _call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if label == MessageLabel::ARMIRQIssueIRQHandlerTrigger {
-        if length < 4 || get_extra_cap_by_index(0).is_none() {
-            unsafe {
-                current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        let irq = get_syscall_arg(0, buffer);
-        let _trigger = get_syscall_arg(1, buffer) != 0;
-        let index = get_syscall_arg(2, buffer);
-        let depth = get_syscall_arg(3, buffer);
-        let cnode_cap = &get_extra_cap_by_index(0).unwrap().capability;
-        let status = check_irq(irq);
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
+        let args =
+            IssueIRQHandlerTriggerArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+        let irq = args.irq;
+        let cnode_cap = &args.root.0.capability;
+        check_irq(irq)?;
         if irq < NUM_PPI {
-            unsafe {
-                current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            }
             debug!("Trying to get a handler on a PPI: use GetTriggerCore.");
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::IllegalOperation);
         }
         if is_irq_active(irq) {
-            unsafe {
-                current_syscall_error._type = SEL4_REVOKE_FIRST;
-            }
             debug!("Rejecting request for IRQ {}. Already active.", irq);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        let lu_ret = lookupSlotForCNodeOp(false, cnode_cap, index, depth);
-        if lu_ret.status != exception_t::EXCEPTION_NONE {
-            debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq);
-            return lu_ret.status;
+            return Err(SyscallError::RevokeFirst);
         }
+        let dest_slot = lookupSlotForCNodeOp(false, cnode_cap, args.index, args.depth, ctx)
+            .inspect_err(|_| debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq))?;
         set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-        invoke_irq_control(
-            irq,
-            convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize),
-            src_slot,
-        )
+        invoke_irq_control(irq, dest_slot, src_slot)
     } else if label == MessageLabel::ARMIRQIssueIRQHandlerTriggerCore {
-        let irq_w = get_syscall_arg(0, buffer);
-        let _trigger = get_syscall_arg(1, buffer) != 0;
-        let index = get_syscall_arg(2, buffer);
-        let depth = get_syscall_arg(3, buffer) & 0xff;
-        let target = get_syscall_arg(4, buffer);
-        let cnode_cap = &get_extra_cap_by_index(0).unwrap().capability;
-        let status = check_irq(irq_w);
+        let args =
+            IssueIRQHandlerTriggerCoreArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+        let (irq_w, target) = (args.irq, args.target);
+        let depth = args.depth & 0xff;
+        let cnode_cap = &args.root.0.capability;
+        check_irq(irq_w)?;
         // let irq = to_irqt(irq_w, target);
         let irq_index = irq_to_idx(to_irqt(irq_w, target));
         let irq_irq = irqt_to_irq(to_irqt(irq_w, target));
 
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
         if target >= CONFIG_MAX_NUM_NODES {
-            unsafe {
-                current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            }
             debug!("Target core {} is invalid.", target);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidArgument { index: 4 });
+        }
+        #[cfg(feature = "cpu_hotplug")]
+        if !sel4_task::core_is_active(target) {
+            debug!("Target core {} is offline.", target);
+            return Err(SyscallError::IllegalOperation);
         }
         if is_irq_active(irq_index) {
-            unsafe {
-                current_syscall_error._type = SEL4_REVOKE_FIRST;
-            }
             debug!("Rejecting request for IRQ {}. Already active.", irq_irq);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-
-        let lu_ret = lookupSlotForCNodeOp(false, cnode_cap, index, depth);
-        if lu_ret.status != exception_t::EXCEPTION_NONE {
-            debug!(
-                "Target slot for new IRQ Handler cap invalid: IRQ {}.",
-                irq_irq
-            );
-            return lu_ret.status;
+            return Err(SyscallError::RevokeFirst);
         }
 
-        let status = ensure_empty_slot(convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize));
-        if status != exception_t::EXCEPTION_NONE {
+        let dest_slot = lookupSlotForCNodeOp(false, cnode_cap, args.index, depth, ctx)
+            .inspect_err(|_| {
+                debug!(
+                    "Target slot for new IRQ Handler cap invalid: IRQ {}.",
+                    irq_irq
+                )
+            })?;
+        ensure_empty_slot(dest_slot).inspect_err(|_| {
             debug!(
                 "Target slot for new IRQ Handler cap not empty: IRQ {}.",
                 irq_irq
-            );
-            return status;
-        }
+            )
+        })?;
         set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
 
         if irq_w >= NUM_PPI {
-            set_irq_target(irq_irq, target);
+            set_irq_target_core(irq_index, target);
         }
 
-        invoke_irq_control(
-            irq_index,
-            convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize),
-            src_slot,
-        )
+        invoke_irq_control(irq_index, dest_slot, src_slot)
     } else {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
+    }
+}
+#[cfg(feature = "enable_smc")]
+invocation_args! {
+    /// The arguments of `ARMSMCCall`, the registers of the call starting with its function.
+    struct SMCCallArgs {
+        smc_func_id: usize,
+        _args: Skip<{ NUM_SMC_REGS - 1 }>,
     }
 }
+
 #[cfg(feature = "enable_smc")]
 pub fn decode_arm_smc_invocation(
     label: MessageLabel,
@@ -1248,36 +1220,26 @@ pub fn decode_arm_smc_invocation(
     capability: &cap_smc_cap,
     call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &kernel_state_t,
+) -> Result<(), SyscallError> {
     if label != ARMSMCCall {
         debug!("ARMSMCInvocation: Illegal operation.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    if length < NUM_SMC_REGS {
-        debug!("ARMSMCCall: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
+    let args = SMCCallArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("ARMSMCCall: Truncated message."))?;
     let badge = capability.get_capSMCBadge();
-    let smc_func_id = get_syscall_arg(0, buffer);
+    let smc_func_id = args.smc_func_id;
     if badge != 0 && badge != smc_func_id as u64 {
         debug!("ARMSMCCall: Illegal operation.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
 
     invoke_smc_call(buffer, call)
 }
 #[cfg(feature = "enable_smc")]
-fn invoke_smc_call(buffer: &seL4_IPCBuffer, call: bool) -> exception_t {
+fn invoke_smc_call(buffer: &seL4_IPCBuffer, call: bool) -> Result<(), SyscallError> {
     use core::arch::asm;
 
     let thread = get_currenct_thread();
@@ -1333,5 +1295,372 @@ fn invoke_smc_call(buffer: &seL4_IPCBuffer, call: bool) -> exception_t {
         thread.tcbArch.set_register(ArchReg::MsgInfo, 0);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRunning);
-    exception_t::EXCEPTION_NONE
+    Ok(())
+}
+
+#[cfg(feature = "hypervisor")]
+pub fn decode_vcpu_invocation(
+    label: MessageLabel,
+    length: usize,
+    capability: &cap_vcpu_cap,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+    ctx: &kernel_state_t,
+) -> Result<(), SyscallError> {
+    let message = InvocationMessage::new(length, buffer, ctx);
+    let vcpu = convert_to_mut_type_ref::<VCpu>(capability.get_capVCPUPtr() as usize);
+    match label {
+        MessageLabel::ARMVCPUSetTCB => decode_vcpu_set_tcb(vcpu, &message),
+        MessageLabel::ARMVCPUInjectIRQ => decode_vcpu_inject_irq(vcpu, &message),
+        MessageLabel::ARMVCPUReadReg => decode_vcpu_read_reg(vcpu, &message, call),
+        MessageLabel::ARMVCPUWriteReg => decode_vcpu_write_reg(vcpu, &message),
+        MessageLabel::ARMVCPUAckVPPI => decode_vcpu_ack_vppi(vcpu, &message),
+        _ => {
+            debug!("VCPU: Illegal operation.");
+            Err(SyscallError::IllegalOperation)
+        }
+    }
+}
+
+#[cfg(feature = "hypervisor")]
+invocation_args! {
+    /// The arguments of `ARMVCPUSetTCB`.
+    struct VCPUSetTCBArgs {
+        tcb: CapSlot,
+    }
+}
+
+#[cfg(feature = "hypervisor")]
+fn decode_vcpu_set_tcb(vcpu: &mut VCpu, message: &InvocationMessage) -> Result<(), SyscallError> {
+    let args = VCPUSetTCBArgs::decode(message)
+        .inspect_err(|_| debug!("VCPU SetTCB: Truncated message."))?;
+    let cap_Splayed::thread_cap(tcb_cap) = args.tcb.0.capability.clone().splay() else {
+        debug!("VCPU SetTCB: TCB cap is not a TCB cap.");
+        return Err(SyscallError::IllegalOperation);
+    };
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_set_tcb(
+        vcpu,
+        convert_to_mut_type_ref::<tcb_t>(tcb_cap.get_capTCBPtr() as usize),
+    )
+}
+
+#[cfg(feature = "hypervisor")]
+invocation_args! {
+    /// The arguments of `ARMVCPUInjectIRQ`, packed into one word.
+    struct VCPUInjectIRQArgs {
+        mr0: usize,
+    }
+}
+
+#[cfg(feature = "hypervisor")]
+fn decode_vcpu_inject_irq(
+    vcpu: &mut VCpu,
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = VCPUInjectIRQArgs::decode(message)
+        .inspect_err(|_| debug!("VCPU InjectIRQ: Truncated message."))?;
+    let vid = args.mr0 & 0xffff;
+    let priority = (args.mr0 >> 16) & 0xff;
+    let group = (args.mr0 >> 24) & 0xff;
+    let index = (args.mr0 >> 32) & 0xff;
+
+    // Check for 10 bit invalid
+    if vid >= bit!(10) {
+        debug!("VCPU InjectIRQ: Invalid virtual IRQ {}.", vid);
+        return Err(SyscallError::RangeError {
+            min: 0,
+            max: bit!(10) - 1,
+        });
+    }
+    if priority >= bit!(5) {
+        debug!("VCPU InjectIRQ: Invalid priority {}.", priority);
+        return Err(SyscallError::RangeError {
+            min: 0,
+            max: bit!(5) - 1,
+        });
+    }
+    if group > 1 {
+        debug!("VCPU InjectIRQ: Invalid group {}.", group);
+        return Err(SyscallError::RangeError { min: 0, max: 1 });
+    }
+    let num_list_regs = gic_vcpu_num_list_regs();
+    if index >= num_list_regs {
+        debug!("VCPU InjectIRQ: Invalid list register index {}.", index);
+        return Err(SyscallError::RangeError {
+            min: 0,
+            max: num_list_regs - 1,
+        });
+    }
+    if virq_is_active(vcpu.get_lr(index)) {
+        debug!("VCPU InjectIRQ: List register {} is in use.", index);
+        return Err(SyscallError::DeleteFirst);
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_inject_irq(vcpu, index, virq_pending_new(group, priority, vid))
+}
+
+#[cfg(feature = "hypervisor")]
+invocation_args! {
+    /// The arguments of `ARMVCPUReadReg`.
+    struct VCPUReadRegArgs {
+        field: usize,
+    }
+}
+
+#[cfg(feature = "hypervisor")]
+fn decode_vcpu_read_reg(
+    vcpu: &mut VCpu,
+    message: &InvocationMessage,
+    call: bool,
+) -> Result<(), SyscallError> {
+    let args = VCPUReadRegArgs::decode(message)
+        .inspect_err(|_| debug!("VCPU ReadReg: Truncated message."))?;
+    if args.field >= VCPU_REG_NUM {
+        debug!("VCPU ReadReg: Invalid field {:#x}.", args.field);
+        return Err(SyscallError::InvalidArgument { index: 1 });
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_read_reg(vcpu, args.field, call)
+}
+
+#[cfg(feature = "hypervisor")]
+invocation_args! {
+    /// The arguments of `ARMVCPUWriteReg`.
+    struct VCPUWriteRegArgs {
+        field: usize,
+        value: usize,
+    }
+}
+
+#[cfg(feature = "hypervisor")]
+fn decode_vcpu_write_reg(vcpu: &mut VCpu, message: &InvocationMessage) -> Result<(), SyscallError> {
+    let args = VCPUWriteRegArgs::decode(message)
+        .inspect_err(|_| debug!("VCPU WriteReg: Truncated message."))?;
+    if args.field >= VCPU_REG_NUM {
+        debug!("VCPU WriteReg: Invalid field {:#x}.", args.field);
+        return Err(SyscallError::InvalidArgument { index: 1 });
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_write_reg(vcpu, args.field, args.value)
+}
+
+#[cfg(feature = "hypervisor")]
+invocation_args! {
+    /// The arguments of `ARMVCPUAckVPPI`.
+    struct VCPUAckVPPIArgs {
+        irq: usize,
+    }
+}
+
+#[cfg(feature = "hypervisor")]
+fn decode_vcpu_ack_vppi(vcpu: &mut VCpu, message: &InvocationMessage) -> Result<(), SyscallError> {
+    let args = VCPUAckVPPIArgs::decode(message)
+        .inspect_err(|_| debug!("VCPU AckVPPI: Truncated message."))?;
+    check_irq(args.irq)?;
+    let vppi = irq_vppi_event_index(args.irq).ok_or_else(|| {
+        debug!("VCPU AckVPPI: Invalid IRQ number {}.", args.irq);
+        SyscallError::InvalidArgument { index: 0 }
+    })?;
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_vcpu_ack_vppi(vcpu, vppi)
+}
+
+#[cfg(feature = "arm_smmu")]
+invocation_args! {
+    /// The arguments of `ARMSIDIssueSIDManager` and `ARMCBIssueCBManager`.
+    struct SMMUIssueArgs {
+        id: usize,
+        index: usize,
+        depth: usize,
+        root: CapSlot,
+    }
+}
+
+#[cfg(feature = "arm_smmu")]
+fn decode_sid_control_invocation(
+    label: MessageLabel,
+    length: usize,
+    slot: &mut cte_t,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    match label {
+        MessageLabel::ARMSIDIssueSIDManager => {
+            let args = SMMUIssueArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("SIDControl IssueSIDManager: Truncated message."))?;
+            if args.id >= SMMU_MAX_SID {
+                debug!("SIDControl IssueSIDManager: Invalid SID {}.", args.id);
+                return Err(SyscallError::RangeError {
+                    min: 0,
+                    max: SMMU_MAX_SID - 1,
+                });
+            }
+            if sid_is_assigned(args.id) {
+                debug!("SIDControl IssueSIDManager: SID {} is in use.", args.id);
+                return Err(SyscallError::RevokeFirst);
+            }
+            let root = args.root.0.capability.clone();
+            let dest_slot = lookup_slot_for_cnode_op(false, &root, args.index, args.depth, ctx)?;
+            ensure_empty_slot(dest_slot)?;
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_sid_control_issue_sid(args.id, slot, dest_slot)
+        }
+        MessageLabel::ARMSIDGetFault => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_sid_control_get_fault(call)
+        }
+        MessageLabel::ARMSIDClearFault => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_sid_control_clear_fault()
+        }
+        _ => {
+            debug!("SIDControl: Illegal operation.");
+            Err(SyscallError::IllegalOperation)
+        }
+    }
+}
+
+#[cfg(feature = "arm_smmu")]
+invocation_args! {
+    /// The arguments of `ARMSIDBindCB`.
+    struct SIDBindCBArgs {
+        cb: TypedCap<cap_cb_cap>,
+    }
+}
+
+#[cfg(feature = "arm_smmu")]
+fn decode_sid_invocation(
+    label: MessageLabel,
+    length: usize,
+    capability: &cap_sid_cap,
+    buffer: &seL4_IPCBuffer,
+    ctx: &kernel_state_t,
+) -> Result<(), SyscallError> {
+    let sid = capability.get_capSID() as usize;
+    match label {
+        MessageLabel::ARMSIDBindCB => {
+            let args = SIDBindCBArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("SID BindCB: Invalid arguments."))?;
+            if sid_node_slot(sid).capability.get_tag() != cap_tag::cap_null_cap {
+                debug!("SID BindCB: SID {} is already bound.", sid);
+                return Err(SyscallError::DeleteFirst);
+            }
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_sid_bind_cb(sid, args.cb.slot)
+        }
+        MessageLabel::ARMSIDUnbindCB => {
+            if sid_node_slot(sid).capability.get_tag() == cap_tag::cap_null_cap {
+                debug!("SID UnbindCB: SID {} is not bound.", sid);
+                return Err(SyscallError::IllegalOperation);
+            }
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_sid_unbind_cb(sid)
+        }
+        _ => {
+            debug!("SID: Illegal operation.");
+            Err(SyscallError::IllegalOperation)
+        }
+    }
+}
+
+#[cfg(feature = "arm_smmu")]
+fn decode_cb_control_invocation(
+    label: MessageLabel,
+    length: usize,
+    slot: &mut cte_t,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    match label {
+        MessageLabel::ARMCBIssueCBManager => {
+            let args = SMMUIssueArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("CBControl IssueCBManager: Truncated message."))?;
+            if args.id >= SMMU_MAX_CB {
+                debug!("CBControl IssueCBManager: Invalid CB {}.", args.id);
+                return Err(SyscallError::RangeError {
+                    min: 0,
+                    max: SMMU_MAX_CB - 1,
+                });
+            }
+            if cb_is_assigned(args.id) {
+                debug!("CBControl IssueCBManager: CB {} is in use.", args.id);
+                return Err(SyscallError::RevokeFirst);
+            }
+            let root = args.root.0.capability.clone();
+            let dest_slot = lookup_slot_for_cnode_op(false, &root, args.index, args.depth, ctx)?;
+            ensure_empty_slot(dest_slot)?;
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_cb_control_issue_cb(args.id, slot, dest_slot)
+        }
+        MessageLabel::ARMCBTLBInvalidateAll => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_cb_control_tlb_invalidate_all()
+        }
+        _ => {
+            debug!("CBControl: Illegal operation.");
+            Err(SyscallError::IllegalOperation)
+        }
+    }
+}
+
+#[cfg(feature = "arm_smmu")]
+invocation_args! {
+    /// The arguments of `ARMCBAssignVspace`.
+    struct CBAssignVspaceArgs {
+        vspace: TypedCap<cap_vspace_cap>,
+    }
+}
+
+#[cfg(feature = "arm_smmu")]
+fn decode_cb_invocation(
+    label: MessageLabel,
+    length: usize,
+    capability: &cap_cb_cap,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+    ctx: &kernel_state_t,
+) -> Result<(), SyscallError> {
+    let cb = capability.get_capCB() as usize;
+    match label {
+        MessageLabel::ARMCBTLBInvalidate => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_cb_tlb_invalidate(cb)
+        }
+        MessageLabel::ARMCBAssignVspace => {
+            let args = CBAssignVspaceArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("CB AssignVspace: Invalid arguments."))?;
+            if args.vspace.cap.get_capVSIsMapped() == 0 {
+                debug!("CB AssignVspace: Invalid vspace cap.");
+                return Err(SyscallError::InvalidCapability { index: 1 });
+            }
+            if cb_asid(cb).is_some() {
+                debug!("CB AssignVspace: CB {} already has a vspace.", cb);
+                return Err(SyscallError::DeleteFirst);
+            }
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_cb_assign_vspace(cb, args.vspace.slot)
+        }
+        MessageLabel::ARMCBUnassignVspace => {
+            if cb_asid(cb).is_none() {
+                debug!("CB UnassignVspace: CB {} has no vspace.", cb);
+                return Err(SyscallError::IllegalOperation);
+            }
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_cb_unassign_vspace(cb)
+        }
+        MessageLabel::ARMCBGetFault => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_cb_get_fault(cb, call)
+        }
+        MessageLabel::ARMCBClearFault => {
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_cb_clear_fault(cb)
+        }
+        _ => {
+            debug!("CB: Illegal operation.");
+            Err(SyscallError::IllegalOperation)
+        }
+    }
 }
@@ -7,54 +7,150 @@ use core::intrinsics::unlikely;
 
 use log::debug;
 use sel4_common::arch::MessageLabel;
-use sel4_common::sel4_config::{
-    ASID_INVALID, ASID_LOW_BITS, N_ASID_POOLS, SEL4_ALIGNMENT_ERROR, SEL4_DELETE_FIRST,
-    SEL4_FAILED_LOOKUP, SEL4_ILLEGAL_OPERATION, SEL4_INVALID_ARGUMENT, SEL4_INVALID_CAPABILITY,
-    SEL4_PAGE_BITS, SEL4_REVOKE_FIRST, SEL4_TRUNCATED_MESSAGE,
-};
+use sel4_common::kernel_state::kernel_state_t;
+use sel4_common::sel4_config::{ASID_INVALID, ASID_LOW_BITS, N_ASID_POOLS, SEL4_PAGE_BITS};
 use sel4_common::structures::{exception_t, seL4_IPCBuffer};
-use sel4_common::structures_gen::{cap, cap_tag};
+use sel4_common::structures_gen::{cap, cap_page_table_cap, cap_tag};
 use sel4_common::structures_gen::{lookup_fault_invalid_root, lookup_fault_missing_capability};
 use sel4_common::utils::{convert_to_mut_type_ref, pageBitsForSize};
 use sel4_cspace::interface::cte_t;
 use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 use sel4_vspace::{
-    check_vp_alignment, find_vspace_for_asid, get_asid_pool_by_index, vm_attributes_t, PTE,
+    check_vp_alignment, find_vspace_for_asid, get_asid_pool_by_index, pt_bits_for_frame_size,
+    vm_attributes_t, PTE,
 };
 
-use crate::{
-    kernel::boot::{current_lookup_fault, current_syscall_error, get_extra_cap_by_index},
-    syscall::{
-        get_syscall_arg,
-        invocation::invoke_mmu_op::{
-            invoke_asid_control, invoke_asid_pool, invoke_page_get_address, invoke_page_map,
-            invoke_page_table_map, invoke_page_table_unmap, invoke_page_unmap,
-        },
-        lookup_slot_for_cnode_op,
+use rel4_utils::invocation::Skip;
+
+use super::super::args::{CapSlot, InvocationMessage, TypedCap, VmRights};
+use crate::syscall::{
+    error::SyscallError,
+    invocation::invoke_mmu_op::{
+        invoke_asid_control, invoke_asid_pool, invoke_page_get_address, invoke_page_map,
+        invoke_page_table_map, invoke_page_table_unmap, invoke_page_unmap,
     },
+    lookup_slot_for_cnode_op,
 };
 
-use sel4_common::sel4_config::{SEL4_ASID_POOL_BITS, SEL4_RANGE_ERROR, USER_TOP};
+use sel4_common::sel4_config::{SEL4_ASID_POOL_BITS, USER_TOP};
 
+#[cfg(feature = "riscv_aia")]
+use sel4_common::platform::{AIA_MAX_IRQ, APLIC_MAX_IRQ};
 use sel4_common::platform::{IRQ_INVALID, MAX_IRQ};
+#[cfg(any(feature = "enable_smp", feature = "riscv_aia"))]
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+
+use crate::arch::set_irq_trigger;
+#[cfg(feature = "enable_smp")]
+use crate::interrupt::set_irq_target_core;
+#[cfg(any(feature = "enable_smp", feature = "riscv_aia"))]
+use crate::syscall::ensure_empty_slot;
+#[cfg(feature = "riscv_aia")]
+use crate::{
+    arch::aia::{imsic_msi_address, is_msi_irq},
+    syscall::invocation::invoke_irq::invoke_irq_control_msi,
+};
 
 use crate::{
     interrupt::is_irq_active,
     syscall::{invocation::invoke_irq::invoke_irq_control, lookupSlotForCNodeOp},
 };
 
+invocation_args! {
+    /// The arguments of `RISCVASIDControlMakePool`.
+    struct ASIDControlMakePoolArgs {
+        index: usize,
+        depth: usize,
+        untyped: CapSlot,
+        root: CapSlot,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `RISCVASIDPoolAssign`.
+    struct ASIDPoolAssignArgs {
+        vspace: TypedCap<cap_page_table_cap>,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `RISCVPageMap`.
+    struct PageMapArgs {
+        vaddr: usize,
+        rights: VmRights,
+        attr: usize,
+        vspace: TypedCap<cap_page_table_cap>,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `RISCVPageTableMap`, the vspace is checked after the address.
+    struct PageTableMapArgs {
+        vaddr: usize,
+        _attr: usize,
+        _vspace: CapSlot,
+    }
+}
+
+invocation_args! {
+    /// The vspace of `RISCVPageTableMap`.
+    struct PageTableMapVSpaceArgs {
+        _args: Skip<2>,
+        vspace: TypedCap<cap_page_table_cap>,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `RISCVIRQIssueIRQHandlerTrigger`.
+    struct IssueIRQHandlerTriggerArgs {
+        irq: usize,
+        trigger: bool,
+        index: usize,
+        depth: usize,
+        root: CapSlot,
+    }
+}
+
+#[cfg(feature = "enable_smp")]
+invocation_args! {
+    /// The arguments of `RISCVIRQIssueIRQHandlerTriggerCore`.
+    struct IssueIRQHandlerTriggerCoreArgs {
+        irq: usize,
+        trigger: bool,
+        index: usize,
+        depth: usize,
+        target: usize,
+        root: CapSlot,
+    }
+}
+
+#[cfg(feature = "riscv_aia")]
+invocation_args! {
+    /// The arguments of `RISCVIRQIssueIRQHandlerMSI`.
+    struct IssueIRQHandlerMSIArgs {
+        irq: usize,
+        index: usize,
+        depth: usize,
+        target: usize,
+        root: CapSlot,
+    }
+}
+
 pub fn decode_mmu_invocation(
     label: MessageLabel,
     length: usize,
     slot: &mut cte_t,
     call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     match slot.capability.get_tag() {
-        cap_tag::cap_page_table_cap => decode_page_table_invocation(label, length, slot, buffer),
-        cap_tag::cap_frame_cap => decode_frame_invocation(label, length, slot, call, buffer),
-        cap_tag::cap_asid_control_cap => decode_asid_control(label, length, buffer),
-        cap_tag::cap_asid_pool_cap => decode_asid_pool(label, slot),
+        cap_tag::cap_page_table_cap => {
+            decode_page_table_invocation(label, length, slot, buffer, ctx)
+        }
+        cap_tag::cap_frame_cap => decode_frame_invocation(label, length, slot, call, buffer, ctx),
+        cap_tag::cap_asid_control_cap => decode_asid_control(label, length, buffer, ctx),
+        cap_tag::cap_asid_pool_cap => decode_asid_pool(label, length, slot, buffer, ctx),
         _ => {
             panic!("Invalid arch cap type");
         }
@@ -66,17 +162,15 @@ fn decode_page_table_invocation(
     length: usize,
     cte: &mut cte_t,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     match label {
         MessageLabel::RISCVPageTableUnmap => decode_page_table_unmap(cte),
 
-        MessageLabel::RISCVPageTableMap => decode_page_table_map(length, cte, buffer),
+        MessageLabel::RISCVPageTableMap => decode_page_table_map(length, cte, buffer, ctx),
         _ => {
             debug!("RISCVPageTable: Illegal Operation");
-            unsafe {
-                current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::IllegalOperation);
         }
     }
 }
@@ -87,12 +181,13 @@ fn decode_frame_invocation(
     frame_slot: &mut cte_t,
     call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     match label {
-        MessageLabel::RISCVPageMap => decode_frame_map(length, frame_slot, buffer),
+        MessageLabel::RISCVPageMap => decode_frame_map(length, frame_slot, buffer, ctx),
         MessageLabel::RISCVPageUnmap => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-            invoke_page_unmap(frame_slot)
+            invoke_page_unmap(frame_slot, ctx)
         }
         MessageLabel::RISCVPageGetAddress => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -103,35 +198,25 @@ fn decode_frame_invocation(
         }
         _ => {
             debug!("invalid operation label:{:?}", label);
-            unsafe {
-                current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            }
-            exception_t::EXCEPTION_SYSCALL_ERROR
+            Err(SyscallError::IllegalOperation)
         }
     }
 }
 
-fn decode_asid_control(label: MessageLabel, length: usize, buffer: &seL4_IPCBuffer) -> exception_t {
+fn decode_asid_control(
+    label: MessageLabel,
+    length: usize,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if label != MessageLabel::RISCVASIDControlMakePool {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    if unlikely(
-        length < 2 || get_extra_cap_by_index(0).is_none() || get_extra_cap_by_index(1).is_none(),
-    ) {
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let index = get_syscall_arg(0, buffer);
-    let depth = get_syscall_arg(1, buffer);
-    let parent_slot = get_extra_cap_by_index(0).unwrap();
+    let args = ASIDControlMakePoolArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+    let parent_slot = args.untyped.0;
     let untyped_cap = &parent_slot.capability;
-    let root = &get_extra_cap_by_index(1).unwrap().capability;
+    let root = &args.root.0.capability;
 
     let mut i = 0;
     while get_asid_pool_by_index(i).is_some() {
@@ -139,10 +224,7 @@ fn decode_asid_control(label: MessageLabel, length: usize, buffer: &seL4_IPCBuff
     }
 
     if i == N_ASID_POOLS {
-        unsafe {
-            current_syscall_error._type = SEL4_DELETE_FIRST;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
 
     let asid_base = i << ASID_LOW_BITS;
@@ -150,78 +232,48 @@ fn decode_asid_control(label: MessageLabel, length: usize, buffer: &seL4_IPCBuff
         || cap::cap_untyped_cap(&untyped_cap).get_capBlockSize() as usize != SEL4_ASID_POOL_BITS
         || cap::cap_untyped_cap(&untyped_cap).get_capIsDevice() != 0
     {
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
 
-    let status = parent_slot.ensure_no_children();
-    if status != exception_t::EXCEPTION_NONE {
-        unsafe {
-            current_syscall_error._type = SEL4_REVOKE_FIRST;
-        }
-        return status;
+    if parent_slot.ensure_no_children() != exception_t::EXCEPTION_NONE {
+        return Err(SyscallError::RevokeFirst);
     }
 
     let frame = cap::cap_untyped_cap(&untyped_cap).get_capPtr();
-    let lu_ret = lookup_slot_for_cnode_op(false, root, index, depth);
-    if lu_ret.status != exception_t::EXCEPTION_NONE {
-        return lu_ret.status;
-    }
-
-    let dest_slot = convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize);
+    let dest_slot = lookup_slot_for_cnode_op(false, root, args.index, args.depth, ctx)?;
 
     if dest_slot.capability.get_tag() != cap_tag::cap_null_cap {
-        unsafe {
-            current_syscall_error._type = SEL4_DELETE_FIRST;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_asid_control(pptr!(frame), dest_slot, parent_slot, asid_base)
 }
 
-fn decode_asid_pool(label: MessageLabel, cte: &mut cte_t) -> exception_t {
+fn decode_asid_pool(
+    label: MessageLabel,
+    length: usize,
+    cte: &mut cte_t,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     // debug!("in cap_asid_pool_cap");
     if label != MessageLabel::RISCVASIDPoolAssign {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    if unlikely(get_extra_cap_by_index(0).is_none()) {
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
+    let args = ASIDPoolAssignArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("RISCVASIDPool: Invalid vspace root."))?;
+    let vspace_slot = args.vspace.slot;
 
-    let vspace_slot = get_extra_cap_by_index(0).unwrap();
-    let vspace_cap = &vspace_slot.capability;
-
-    if unlikely(
-        vspace_cap.get_tag() != cap_tag::cap_page_table_cap
-            || cap::cap_page_table_cap(&vspace_cap).get_capPTIsMapped() != 0,
-    ) {
+    if unlikely(args.vspace.cap.get_capPTIsMapped() != 0) {
         debug!("RISCVASIDPool: Invalid vspace root.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
 
     let asid = cap::cap_asid_pool_cap(&cte.capability).get_capASIDBase() as usize;
     if let Some(pool) = get_asid_pool_by_index(asid >> ASID_LOW_BITS) {
         if pool.get_ptr().as_u64() != cap::cap_asid_pool_cap(&cte.capability).get_capASIDPool() {
-            unsafe {
-                current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                current_syscall_error.invalidCapNumber = 0;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidCapability { index: 0 });
         }
 
         let mut i = 0;
@@ -230,126 +282,87 @@ fn decode_asid_pool(label: MessageLabel, cte: &mut cte_t) -> exception_t {
         }
 
         if i == bit!(ASID_LOW_BITS) {
-            unsafe {
-                current_syscall_error._type = SEL4_DELETE_FIRST;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::DeleteFirst);
         }
 
         set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
         // performASIDPoolInvocation(asid + i, pool as *mut asid_pool_t, vspace_slot as *mut cte_t)
         invoke_asid_pool(asid + i, pool, vspace_slot)
     } else {
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = 0;
-            current_lookup_fault = lookup_fault_invalid_root::new().unsplay();
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: lookup_fault_invalid_root::new().unsplay(),
+        });
     }
 }
 
-fn decode_frame_map(length: usize, frame_slot: &mut cte_t, buffer: &seL4_IPCBuffer) -> exception_t {
-    if length < 3 || get_extra_cap_by_index(0).is_none() {
-        debug!("RISCVPageMap: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let vaddr = get_syscall_arg(0, buffer);
-    let w_rights_mask = get_syscall_arg(1, buffer);
-    let attr = vm_attributes_t::from_word(get_syscall_arg(2, buffer));
-    let lvl1pt_cap = &get_extra_cap_by_index(0).unwrap().capability;
-    if let Some((lvl1pt, asid)) = get_vspace(lvl1pt_cap) {
-        let frame_size = cap::cap_frame_cap(&frame_slot.capability).get_capFSize() as usize;
-        let vtop = vaddr + bit!(pageBitsForSize(frame_size)) - 1;
-        if unlikely(vtop >= USER_TOP) {
-            unsafe {
-                current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-                current_syscall_error.invalidCapNumber = 0;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
+fn decode_frame_map(
+    length: usize,
+    frame_slot: &mut cte_t,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let args = PageMapArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("RISCVPageMap: Invalid arguments."))?;
+    let vaddr = args.vaddr;
+    let attr = vm_attributes_t::from_word(args.attr);
+    let (lvl1pt, asid) = get_vspace(&args.vspace.cap)?;
+    let frame_size = cap::cap_frame_cap(&frame_slot.capability).get_capFSize() as usize;
+    let vtop = vaddr + bit!(pageBitsForSize(frame_size)) - 1;
+    if unlikely(vtop >= USER_TOP) {
+        return Err(SyscallError::InvalidArgument { index: 0 });
+    }
+
+    if unlikely(!check_vp_alignment(frame_size, vaddr)) {
+        return Err(SyscallError::AlignmentError);
+    }
+
+    let lu_ret = lvl1pt.lookup_pt_slot(vptr!(vaddr));
+    if lu_ret.ptBitsLeft != pt_bits_for_frame_size(frame_size) {
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: lookup_fault_missing_capability::new(lu_ret.ptBitsLeft as u64).unsplay(),
+        });
+    }
 
-        if unlikely(!check_vp_alignment(frame_size, vaddr)) {
-            unsafe {
-                current_syscall_error._type = SEL4_ALIGNMENT_ERROR;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+    let pt_slot = convert_to_mut_type_ref::<PTE>(lu_ret.ptSlot as usize);
+    let frame_asid = cap::cap_frame_cap(&frame_slot.capability).get_capFMappedASID() as usize;
+    if frame_asid != ASID_INVALID {
+        if frame_asid != asid {
+            debug!("RISCVPageMap: Attempting to remap a frame that does not belong to the passed address space");
+            return Err(SyscallError::InvalidCapability { index: 1 });
         }
 
-        let lu_ret = lvl1pt.lookup_pt_slot(vptr!(vaddr));
-        if lu_ret.ptBitsLeft != pageBitsForSize(frame_size) {
-            unsafe {
-                current_lookup_fault =
-                    lookup_fault_missing_capability::new(lu_ret.ptBitsLeft as u64).unsplay();
-                current_syscall_error._type = SEL4_FAILED_LOOKUP;
-                current_syscall_error.failedLookupWasSource = false as usize;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+        if cap::cap_frame_cap(&frame_slot.capability).get_capFMappedAddress() as usize != vaddr {
+            debug!("RISCVPageMap: attempting to map frame into multiple addresses");
+            return Err(SyscallError::InvalidArgument { index: 0 });
         }
 
-        let pt_slot = convert_to_mut_type_ref::<PTE>(lu_ret.ptSlot as usize);
-        let frame_asid = cap::cap_frame_cap(&frame_slot.capability).get_capFMappedASID() as usize;
-        if frame_asid != ASID_INVALID {
-            if frame_asid != asid {
-                debug!("RISCVPageMap: Attempting to remap a frame that does not belong to the passed address space");
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 1;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
-
-            if cap::cap_frame_cap(&frame_slot.capability).get_capFMappedAddress() as usize != vaddr
-            {
-                debug!("RISCVPageMap: attempting to map frame into multiple addresses");
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-                    current_syscall_error.invalidArgumentNumber = 0;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
-
-            if pt_slot.is_pte_table() {
-                debug!("RISCVPageMap: no mapping to remap.");
-                unsafe {
-                    current_syscall_error._type = SEL4_DELETE_FIRST;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
-        } else {
-            if pt_slot.get_valid() != 0 {
-                debug!("Virtual address already mapped");
-                unsafe {
-                    current_syscall_error._type = SEL4_DELETE_FIRST;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
+        if pt_slot.is_pte_table() {
+            debug!("RISCVPageMap: no mapping to remap.");
+            return Err(SyscallError::DeleteFirst);
         }
-        invoke_page_map(
-            cap::cap_frame_cap(&frame_slot.clone().capability),
-            w_rights_mask,
-            vaddr,
-            asid,
-            attr,
-            pt_slot,
-            frame_slot,
-        )
     } else {
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        if pt_slot.frame_slots_valid(frame_size) {
+            debug!("Virtual address already mapped");
+            return Err(SyscallError::DeleteFirst);
+        }
     }
+    invoke_page_map(
+        cap::cap_frame_cap(&frame_slot.clone().capability),
+        args.rights.mask(cap::cap_frame_cap(&frame_slot.capability)),
+        vaddr,
+        asid,
+        attr,
+        pt_slot,
+        frame_slot,
+    )
 }
 
-fn decode_page_table_unmap(pt_cte: &mut cte_t) -> exception_t {
+fn decode_page_table_unmap(pt_cte: &mut cte_t) -> Result<(), SyscallError> {
     if !pt_cte.is_final_cap() {
         debug!("RISCVPageTableUnmap: cannot unmap if more than once cap exists");
-        unsafe {
-            current_syscall_error._type = SEL4_REVOKE_FIRST;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::RevokeFirst);
     }
     let capability = cap::cap_page_table_cap(&pt_cte.capability);
     if capability.get_capPTIsMapped() != 0 {
@@ -360,14 +373,7 @@ fn decode_page_table_unmap(pt_cte: &mut cte_t) -> exception_t {
             && find_ret.vspace_root.unwrap() == pte_ptr
         {
             debug!("RISCVPageTableUnmap: cannot call unmap on top level PageTable");
-            unsafe {
-                current_syscall_error._type = SEL4_REVOKE_FIRST;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        } else {
-            unsafe {
-                current_lookup_fault = find_ret.lookup_fault.unwrap();
-            }
+            return Err(SyscallError::RevokeFirst);
         }
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -378,70 +384,48 @@ fn decode_page_table_map(
     length: usize,
     pt_cte: &mut cte_t,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if unlikely(length < 2 || get_extra_cap_by_index(0).is_none()) {
-        debug!("RISCVPageTableMap: truncated message");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let message = InvocationMessage::new(length, buffer, ctx);
+    let args = PageTableMapArgs::decode(&message)
+        .inspect_err(|_| debug!("RISCVPageTableMap: truncated message"))?;
     let capability = cap::cap_page_table_cap(&pt_cte.capability);
     if unlikely(capability.get_capPTIsMapped() != 0) {
         debug!("RISCVPageTable: PageTable is already mapped.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 0;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 0 });
     }
 
-    let vaddr = get_syscall_arg(0, buffer);
+    let vaddr = args.vaddr;
     if unlikely(vaddr >= USER_TOP) {
         debug!("RISCVPageTableMap: Virtual address cannot be in kernel window.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidCapNumber = 0;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let lvl1pt_cap = &get_extra_cap_by_index(0).unwrap().capability;
-
-    if let Some((lvl1pt, asid)) = get_vspace(lvl1pt_cap) {
-        let lu_ret = lvl1pt.lookup_pt_slot(vptr!(vaddr));
-        let lu_slot = convert_to_mut_type_ref::<PTE>(lu_ret.ptSlot as usize);
-        #[cfg(target_arch = "riscv64")]
-        if lu_ret.ptBitsLeft == SEL4_PAGE_BITS || lu_slot.get_valid() != 0 {
-            debug!("RISCVPageTableMap: All objects mapped at this address");
-            unsafe {
-                current_syscall_error._type = SEL4_DELETE_FIRST;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-        return invoke_page_table_map(
-            capability,
-            lu_slot,
-            asid,
-            vaddr & !mask_bits!(lu_ret.ptBitsLeft),
-        );
-    } else {
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 0 });
     }
+    let vspace = PageTableMapVSpaceArgs::decode(&message)
+        .inspect_err(|_| debug!("RISCVMMUInvocation: Invalid top-level PageTable."))?
+        .vspace;
+
+    let (lvl1pt, asid) = get_vspace(&vspace.cap)?;
+    let lu_ret = lvl1pt.lookup_pt_slot(vptr!(vaddr));
+    let lu_slot = convert_to_mut_type_ref::<PTE>(lu_ret.ptSlot as usize);
+    #[cfg(target_arch = "riscv64")]
+    if lu_ret.ptBitsLeft == SEL4_PAGE_BITS || lu_slot.get_valid() != 0 {
+        debug!("RISCVPageTableMap: All objects mapped at this address");
+        return Err(SyscallError::DeleteFirst);
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    return invoke_page_table_map(
+        capability,
+        lu_slot,
+        asid,
+        vaddr & !mask_bits!(lu_ret.ptBitsLeft),
+    );
 }
 
-fn get_vspace(lvl1pt_cap: &cap) -> Option<(&mut PTE, usize)> {
-    if lvl1pt_cap.clone().get_tag() != cap_tag::cap_page_table_cap
-        || cap::cap_page_table_cap(lvl1pt_cap).get_capPTIsMapped() as usize == ASID_INVALID
-    {
+fn get_vspace(lvl1pt_capability: &cap_page_table_cap) -> Result<(&mut PTE, usize), SyscallError> {
+    if lvl1pt_capability.get_capPTIsMapped() as usize == ASID_INVALID {
         debug!("RISCVMMUInvocation: Invalid top-level PageTable.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return None;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
-    let lvl1pt_capability = cap::cap_page_table_cap(lvl1pt_cap);
 
     let lvl1pt = convert_to_mut_type_ref::<PTE>(lvl1pt_capability.get_capPTBasePtr() as usize);
     let asid = lvl1pt_capability.get_capPTMappedASID() as usize;
@@ -449,85 +433,151 @@ fn get_vspace(lvl1pt_cap: &cap) -> Option<(&mut PTE, usize)> {
     let find_ret = find_vspace_for_asid(asid);
     if find_ret.status != exception_t::EXCEPTION_NONE {
         debug!("RISCVMMUInvocation: ASID lookup failed");
-        unsafe {
-            current_lookup_fault = find_ret.lookup_fault.unwrap();
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = 0;
-        }
-        return None;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: find_ret.lookup_fault.unwrap(),
+        });
     }
 
     if find_ret.vspace_root.unwrap() as usize != lvl1pt.get_ptr() {
         debug!("RISCVMMUInvocation: ASID lookup failed");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return None;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
-    Some((lvl1pt, asid))
+    Ok((lvl1pt, asid))
 }
 
-pub(crate) fn check_irq(irq: usize) -> exception_t {
+pub(crate) fn check_irq(irq: usize) -> Result<(), SyscallError> {
     if irq > MAX_IRQ || irq == IRQ_INVALID {
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 1;
-            current_syscall_error.rangeErrorMax = MAX_IRQ;
-            debug!(
-                "Rejecting request for IRQ {}. IRQ is out of range [1..MAX_IRQ].",
-                irq
-            );
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
+        debug!(
+            "Rejecting request for IRQ {}. IRQ is out of range [1..MAX_IRQ].",
+            irq
+        );
+        return Err(SyscallError::RangeError {
+            min: 1,
+            max: MAX_IRQ,
+        });
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
+#[cfg_attr(not(feature = "riscv_aia"), allow(unused_variables))]
 pub fn arch_decode_irq_control_invocation(
     label: MessageLabel,
     length: usize,
     src_slot: &mut cte_t,
+    call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if label == MessageLabel::RISCVIRQIssueIRQHandlerTrigger {
-        if length < 4 || get_extra_cap_by_index(0).is_none() {
-            unsafe {
-                current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        let irq = get_syscall_arg(0, buffer);
-        let _trigger = get_syscall_arg(1, buffer) != 0;
-        let index = get_syscall_arg(2, buffer);
-        let depth = get_syscall_arg(3, buffer);
-        let cnode_cap = &get_extra_cap_by_index(0).unwrap().capability;
-        let status = check_irq(irq);
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
+        let args =
+            IssueIRQHandlerTriggerArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+        let irq = args.irq;
+        let cnode_cap = &args.root.0.capability;
+        check_irq(irq)?;
         if is_irq_active(irq) {
-            unsafe {
-                current_syscall_error._type = SEL4_REVOKE_FIRST;
-            }
             debug!("Rejecting request for IRQ {}. Already active.", irq);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        let lu_ret = lookupSlotForCNodeOp(false, cnode_cap, index, depth);
-        if lu_ret.status != exception_t::EXCEPTION_NONE {
-            debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq);
-            return lu_ret.status;
+            return Err(SyscallError::RevokeFirst);
         }
+        let dest_slot = lookupSlotForCNodeOp(false, cnode_cap, args.index, args.depth, ctx)
+            .inspect_err(|_| debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq))?;
         set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-        invoke_irq_control(
-            irq,
-            convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize),
-            src_slot,
-        )
+        set_irq_trigger(irq, args.trigger);
+        invoke_irq_control(irq, dest_slot, src_slot)
     } else {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
+        #[cfg(feature = "enable_smp")]
+        if label == MessageLabel::RISCVIRQIssueIRQHandlerTriggerCore {
+            return decode_irq_issue_handler_trigger_core(length, src_slot, buffer, ctx);
         }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        #[cfg(feature = "riscv_aia")]
+        if label == MessageLabel::RISCVIRQIssueIRQHandlerMSI {
+            return decode_irq_issue_handler_msi(length, src_slot, call, buffer, ctx);
+        }
+        return Err(SyscallError::IllegalOperation);
+    }
+}
+
+/// Issue a handler for an external interrupt that is routed to the hart of `target`.
+#[cfg(feature = "enable_smp")]
+fn decode_irq_issue_handler_trigger_core(
+    length: usize,
+    src_slot: &mut cte_t,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let args =
+        IssueIRQHandlerTriggerCoreArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+    let (irq, target) = (args.irq, args.target);
+    let cnode_cap = &args.root.0.capability;
+    check_irq(irq)?;
+    if target >= CONFIG_MAX_NUM_NODES {
+        debug!("Target core {} is invalid.", target);
+        return Err(SyscallError::InvalidArgument { index: 4 });
     }
+    #[cfg(feature = "cpu_hotplug")]
+    if !sel4_task::core_is_active(target) {
+        debug!("Target core {} is offline.", target);
+        return Err(SyscallError::IllegalOperation);
+    }
+    if is_irq_active(irq) {
+        debug!("Rejecting request for IRQ {}. Already active.", irq);
+        return Err(SyscallError::RevokeFirst);
+    }
+    let dest_slot = lookupSlotForCNodeOp(false, cnode_cap, args.index, args.depth, ctx)
+        .inspect_err(|_| debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq))?;
+    ensure_empty_slot(dest_slot).inspect_err(|_| {
+        debug!(
+            "Target slot for new IRQ Handler cap not empty: IRQ {}.",
+            irq
+        )
+    })?;
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    set_irq_trigger(irq, args.trigger);
+    set_irq_target_core(irq, target);
+    invoke_irq_control(irq, dest_slot, src_slot)
+}
+
+/// Issue a handler for an MSI identity, the reply holds the address a device on the
+/// bus writes the identity to for it to reach the hart of `target`.
+#[cfg(feature = "riscv_aia")]
+fn decode_irq_issue_handler_msi(
+    length: usize,
+    src_slot: &mut cte_t,
+    call: bool,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let args = IssueIRQHandlerMSIArgs::decode(&InvocationMessage::new(length, buffer, ctx))?;
+    let (irq, target) = (args.irq, args.target);
+    let cnode_cap = &args.root.0.capability;
+    if !is_msi_irq(irq) {
+        debug!("Rejecting request for IRQ {}. Not an MSI.", irq);
+        return Err(SyscallError::RangeError {
+            min: APLIC_MAX_IRQ + 1,
+            max: AIA_MAX_IRQ,
+        });
+    }
+    if target >= CONFIG_MAX_NUM_NODES {
+        debug!("Target core {} is invalid.", target);
+        return Err(SyscallError::InvalidArgument { index: 3 });
+    }
+    #[cfg(feature = "cpu_hotplug")]
+    if !sel4_task::core_is_active(target) {
+        debug!("Target core {} is offline.", target);
+        return Err(SyscallError::IllegalOperation);
+    }
+    if is_irq_active(irq) {
+        debug!("Rejecting request for IRQ {}. Already active.", irq);
+        return Err(SyscallError::RevokeFirst);
+    }
+    let dest_slot = lookupSlotForCNodeOp(false, cnode_cap, args.index, args.depth, ctx)
+        .inspect_err(|_| debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq))?;
+    ensure_empty_slot(dest_slot).inspect_err(|_| {
+        debug!(
+            "Target slot for new IRQ Handler cap not empty: IRQ {}.",
+            irq
+        )
+    })?;
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_irq_control_msi(irq, imsic_msi_address(target), dest_slot, src_slot, call)
 }
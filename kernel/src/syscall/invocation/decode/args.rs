@@ -0,0 +1,216 @@
+//! The message of an invocation and the argument types of `invocation_args!`.
+use log::debug;
+use rel4_utils::invocation::{Arg, ArgSource};
+use sel4_common::arch::{maskVMRights, vm_rights_t};
+use sel4_common::kernel_state::kernel_state_t;
+use sel4_common::sel4_bitfield_types::Bitfield;
+use sel4_common::shared_types_bf_gen::seL4_CapRights;
+use sel4_common::structures::seL4_IPCBuffer;
+#[cfg(feature = "arm_smmu")]
+use sel4_common::structures_gen::cap_cb_cap;
+#[cfg(feature = "kernel_mcs")]
+use sel4_common::structures_gen::cap_sched_context_cap;
+#[cfg(target_arch = "aarch64")]
+use sel4_common::structures_gen::cap_vspace_cap;
+use sel4_common::structures_gen::{
+    cap, cap_Splayed, cap_frame_cap, cap_page_table_cap, cap_thread_cap,
+};
+use sel4_common::utils::convert_to_mut_type_ref;
+use sel4_common::vm_rights::vm_rights_from_word;
+use sel4_cspace::interface::cte_t;
+use sel4_task::tcb_t;
+
+use crate::kernel::boot::get_extra_cap_by_index;
+use crate::syscall::error::SyscallError;
+use crate::syscall::utils::{check_prio, get_syscall_arg};
+
+/// The message registers and extra caps of the current invocation.
+pub struct InvocationMessage<'a> {
+    length: usize,
+    buffer: &'a seL4_IPCBuffer,
+    ctx: &'a kernel_state_t,
+}
+
+impl<'a> InvocationMessage<'a> {
+    pub fn new(length: usize, buffer: &'a seL4_IPCBuffer, ctx: &'a kernel_state_t) -> Self {
+        Self {
+            length,
+            buffer,
+            ctx,
+        }
+    }
+}
+
+impl ArgSource for InvocationMessage<'_> {
+    type Error = SyscallError;
+    type Slot = &'static mut cte_t;
+
+    fn length(&self) -> usize {
+        self.length
+    }
+
+    fn mr(&self, index: usize) -> usize {
+        get_syscall_arg(index, self.buffer)
+    }
+
+    fn extra_cap(&self, index: usize) -> Option<Self::Slot> {
+        get_extra_cap_by_index(self.ctx, index)
+    }
+
+    fn truncated(&self) -> SyscallError {
+        SyscallError::TruncatedMessage
+    }
+}
+
+/// A priority or maximum controlled priority. The authority of a priority change is the
+/// thread cap in the first extra cap, so like seL4 the decode fails with an invalid cap
+/// if that is not a thread, and with a range error if the priority is above its maximum
+/// controlled priority.
+#[derive(Clone, Copy)]
+pub struct Priority(pub usize);
+
+impl Arg<InvocationMessage<'_>> for Priority {
+    const MRS: usize = 1;
+
+    fn decode(
+        source: &InvocationMessage<'_>,
+        mr: usize,
+        _cap: usize,
+    ) -> Result<Self, SyscallError> {
+        let auth = <ThreadCap as Arg<InvocationMessage<'_>>>::decode(source, 0, 0)?.tcb();
+        let prio = source.mr(mr);
+        check_prio(prio, auth).inspect_err(|_| {
+            debug!(
+                "Invocation: Requested priority {} too high (max {}).",
+                prio, auth.tcbMCP
+            )
+        })?;
+        Ok(Self(prio))
+    }
+}
+
+/// A word of cap rights, e.g. the rights of the cap `CNodeMint` creates.
+pub struct CapRights(pub seL4_CapRights);
+
+impl Arg<InvocationMessage<'_>> for CapRights {
+    const MRS: usize = 1;
+
+    fn decode(
+        source: &InvocationMessage<'_>,
+        mr: usize,
+        _cap: usize,
+    ) -> Result<Self, SyscallError> {
+        Ok(Self(seL4_CapRights(Bitfield {
+            arr: [source.mr(mr) as u64; 1],
+        })))
+    }
+}
+
+/// The rights mask of a frame mapping.
+pub struct VmRights(pub seL4_CapRights);
+
+impl VmRights {
+    /// The rights `frame` is mapped with, the rights of the frame cap masked by these.
+    pub fn mask(self, frame: &cap_frame_cap) -> vm_rights_t {
+        maskVMRights(
+            vm_rights_from_word(frame.get_capFVMRights() as usize),
+            self.0,
+        )
+    }
+}
+
+impl Arg<InvocationMessage<'_>> for VmRights {
+    const MRS: usize = 1;
+
+    fn decode(source: &InvocationMessage<'_>, mr: usize, cap: usize) -> Result<Self, SyscallError> {
+        CapRights::decode(source, mr, cap).map(|rights| Self(rights.0))
+    }
+}
+
+/// An extra cap of any type.
+pub struct CapSlot(pub &'static mut cte_t);
+
+impl Arg<InvocationMessage<'_>> for CapSlot {
+    const CAPS: usize = 1;
+
+    fn decode(
+        source: &InvocationMessage<'_>,
+        _mr: usize,
+        cap: usize,
+    ) -> Result<Self, SyscallError> {
+        Ok(Self(source.extra_cap(cap).unwrap()))
+    }
+}
+
+/// The type of the extra cap of a [`TypedCap`].
+pub trait CapType: Sized {
+    /// The cap as this type, `None` if it has another type.
+    fn from_cap(capability: &cap) -> Option<Self>;
+}
+
+macro_rules! cap_types {
+    ($($(#[$attr:meta])* $ty:ident => $variant:ident,)*) => {
+        $(
+            $(#[$attr])*
+            impl CapType for $ty {
+                fn from_cap(capability: &cap) -> Option<Self> {
+                    match capability.clone().splay() {
+                        cap_Splayed::$variant(data) => Some(data),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+cap_types! {
+    cap_thread_cap => thread_cap,
+    cap_page_table_cap => page_table_cap,
+    #[cfg(feature = "kernel_mcs")]
+    cap_sched_context_cap => sched_context_cap,
+    #[cfg(target_arch = "aarch64")]
+    cap_vspace_cap => vspace_cap,
+    #[cfg(feature = "arm_smmu")]
+    cap_cb_cap => cb_cap,
+}
+
+/// An extra cap that must have the type `T`. seL4 reports a cap of another type as an
+/// invalid cap, counting the invoked cap as cap 0. Invocations that report it with
+/// another error, or only after other checks, take a [`CapSlot`] and check it.
+pub struct TypedCap<T> {
+    pub cap: T,
+    pub slot: &'static mut cte_t,
+}
+
+impl<T: CapType> Arg<InvocationMessage<'_>> for TypedCap<T> {
+    const CAPS: usize = 1;
+
+    fn decode(
+        source: &InvocationMessage<'_>,
+        _mr: usize,
+        index: usize,
+    ) -> Result<Self, SyscallError> {
+        let slot = source.extra_cap(index).unwrap();
+        match T::from_cap(&slot.capability) {
+            Some(cap) => Ok(Self { cap, slot }),
+            None => {
+                debug!(
+                    "Invocation: Extra cap {} has the wrong type {:?}.",
+                    index,
+                    slot.capability.get_tag()
+                );
+                Err(SyscallError::InvalidCapability { index: index + 1 })
+            }
+        }
+    }
+}
+
+/// An extra cap that must be a thread cap, e.g. the source of `TCBCopyRegisters`.
+pub type ThreadCap = TypedCap<cap_thread_cap>;
+
+impl ThreadCap {
+    pub fn tcb(&self) -> &'static mut tcb_t {
+        convert_to_mut_type_ref::<tcb_t>(self.cap.get_capTCBPtr() as usize)
+    }
+}
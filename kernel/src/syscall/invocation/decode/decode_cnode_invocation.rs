@@ -1,69 +1,110 @@
 use log::debug;
+use rel4_utils::invocation::Skip;
 use sel4_common::arch::CNODE_LAST_INVOCATION;
-use sel4_common::sel4_bitfield_types::Bitfield;
-use sel4_common::shared_types_bf_gen::seL4_CapRights;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::structures_gen::cap_cnode_cap;
 use sel4_common::structures_gen::cap_tag;
 use sel4_common::structures_gen::lookup_fault_missing_capability;
-use sel4_common::{
-    arch::MessageLabel,
-    sel4_config::{
-        SEL4_DELETE_FIRST, SEL4_FAILED_LOOKUP, SEL4_ILLEGAL_OPERATION, SEL4_TRUNCATED_MESSAGE,
-    },
-    structures::{exception_t, seL4_IPCBuffer},
-    utils::convert_to_mut_type_ref,
-};
+use sel4_common::{arch::MessageLabel, structures::seL4_IPCBuffer};
 use sel4_cspace::interface::cte_t;
 
-use crate::{
-    kernel::boot::{current_lookup_fault, current_syscall_error, get_extra_cap_by_index},
-    syscall::{get_syscall_arg, invocation::invoke_cnode::*, lookup_slot_for_cnode_op},
-};
+use super::args::{CapRights, CapSlot, InvocationMessage};
+use crate::syscall::{error::SyscallError, invocation::invoke_cnode::*, lookup_slot_for_cnode_op};
+
+invocation_args! {
+    /// The destination slot every CNode invocation starts with.
+    struct CNodeDestArgs {
+        index: usize,
+        depth: usize,
+    }
+}
+
+invocation_args! {
+    /// The source slot of `CNodeCopy`, `CNodeMint`, `CNodeMove` and `CNodeMutate`.
+    struct CNodeSourceArgs {
+        _dest: Skip<2>,
+        index: usize,
+        depth: usize,
+        root: CapSlot,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `CNodeCopy` after the slots.
+    struct CNodeCopyArgs {
+        _slots: Skip<4>,
+        rights: CapRights,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `CNodeMint` after the slots.
+    struct CNodeMintArgs {
+        _slots: Skip<4>,
+        rights: CapRights,
+        data: usize,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `CNodeMutate` after the slots.
+    struct CNodeMutateArgs {
+        _slots: Skip<4>,
+        data: usize,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `CNodeRotate`.
+    struct CNodeRotateArgs {
+        _dest: Skip<2>,
+        pivot_data: usize,
+        pivot_index: usize,
+        pivot_depth: usize,
+        src_data: usize,
+        src_index: usize,
+        src_depth: usize,
+        pivot_root: CapSlot,
+        src_root: CapSlot,
+    }
+}
 
 pub fn decode_cnode_invocation(
     invLabel: MessageLabel,
     length: usize,
     capability: &cap_cnode_cap,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     // sel4_common::println!("decode cnode invocation {}", invLabel as usize);
     if invLabel < MessageLabel::CNodeRevoke || invLabel as usize > CNODE_LAST_INVOCATION {
         debug!("CNodeCap: Illegal Operation attempted.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    if length < 2 {
-        debug!("CNode operation: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let index = get_syscall_arg(0, buffer);
-    let w_bits = get_syscall_arg(1, buffer);
-    let lu_ret = lookup_slot_for_cnode_op(false, &capability.clone().unsplay(), index, w_bits);
-
-    if lu_ret.status != exception_t::EXCEPTION_NONE {
-        debug!("CNode operation: Target slot invalid.");
-        return lu_ret.status;
-    }
-    let dest_slot = convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize);
+        return Err(SyscallError::IllegalOperation);
+    }
+
+    let dest = CNodeDestArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("CNode operation: Truncated message."))?;
+    let dest_slot = lookup_slot_for_cnode_op(
+        false,
+        &capability.clone().unsplay(),
+        dest.index,
+        dest.depth,
+        ctx,
+    )
+    .inspect_err(|_| debug!("CNode operation: Target slot invalid."))?;
     match invLabel {
         MessageLabel::CNodeCopy
         | MessageLabel::CNodeMint
         | MessageLabel::CNodeMove
         | MessageLabel::CNodeMutate => {
-            return decode_cnode_invoke_with_two_slot(invLabel, dest_slot, length, buffer);
+            return decode_cnode_invoke_with_two_slot(invLabel, dest_slot, length, buffer, ctx);
         }
         MessageLabel::CNodeRevoke => invoke_cnode_revoke(dest_slot),
         MessageLabel::CNodeDelete => invoke_cnode_delete(dest_slot),
         #[cfg(not(feature = "kernel_mcs"))]
         MessageLabel::CNodeSaveCaller => invoke_cnode_save_caller(dest_slot),
         MessageLabel::CNodeCancelBadgedSends => invoke_cnode_cancel_badged_sends(dest_slot),
-        MessageLabel::CNodeRotate => decode_cnode_rotate(dest_slot, length, buffer),
+        MessageLabel::CNodeRotate => decode_cnode_rotate(dest_slot, length, buffer, ctx),
         _ => panic!("invalid invlabel: {:?}", invLabel),
     }
 }
@@ -73,69 +114,37 @@ fn decode_cnode_invoke_with_two_slot(
     dest_slot: &mut cte_t,
     length: usize,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 4 || get_extra_cap_by_index(0).is_none() {
-        debug!("CNode Copy/Mint/Move/Mutate: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let src_index = get_syscall_arg(2, buffer);
-    let src_depth = get_syscall_arg(3, buffer);
-    let src_root = &get_extra_cap_by_index(0).unwrap().capability;
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let src = CNodeSourceArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("CNode Copy/Mint/Move/Mutate: Truncated message."))?;
+    let src_depth = src.depth;
+    let src_root = &src.root.0.capability;
     if dest_slot.capability.get_tag() != cap_tag::cap_null_cap {
         debug!("CNode Copy/Mint/Move/Mutate: Destination not empty.");
-        unsafe {
-            current_syscall_error._type = SEL4_DELETE_FIRST;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
 
-    let lu_ret = lookup_slot_for_cnode_op(true, &src_root, src_index, src_depth);
-    if lu_ret.status != exception_t::EXCEPTION_NONE {
-        debug!("CNode Copy/Mint/Move/Mutate: Invalid source slot.");
-        return lu_ret.status;
-    }
-    let src_slot = convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize);
+    let src_slot = lookup_slot_for_cnode_op(true, &src_root, src.index, src_depth, ctx)
+        .inspect_err(|_| debug!("CNode Copy/Mint/Move/Mutate: Invalid source slot."))?;
     if src_slot.capability.get_tag() == cap_tag::cap_null_cap {
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = 1;
-            current_lookup_fault = lookup_fault_missing_capability::new(src_depth as u64).unsplay();
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: true,
+            fault: lookup_fault_missing_capability::new(src_depth as u64).unsplay(),
+        });
     }
 
     match label {
         MessageLabel::CNodeCopy => {
-            if length < 5 {
-                debug!("Truncated message for CNode Copy operation.");
-                unsafe {
-                    current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
-            let cap_right = seL4_CapRights(Bitfield {
-                arr: [get_syscall_arg(4, buffer) as u64; 1],
-            });
-            return invoke_cnode_copy(src_slot, dest_slot, cap_right);
+            let args = CNodeCopyArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("Truncated message for CNode Copy operation."))?;
+            return invoke_cnode_copy(src_slot, dest_slot, args.rights.0);
         }
 
         MessageLabel::CNodeMint => {
-            if length < 6 {
-                debug!("Truncated message for CNode Mint operation.");
-                unsafe {
-                    current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
-            let cap_right = seL4_CapRights(Bitfield {
-                arr: [get_syscall_arg(4, buffer) as u64; 1],
-            });
-            let cap_data = get_syscall_arg(5, buffer);
-            return invoke_cnode_mint(src_slot, dest_slot, cap_right, cap_data);
+            let args = CNodeMintArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("Truncated message for CNode Mint operation."))?;
+            return invoke_cnode_mint(src_slot, dest_slot, args.rights.0, args.data);
         }
 
         MessageLabel::CNodeMove => {
@@ -143,15 +152,9 @@ fn decode_cnode_invoke_with_two_slot(
         }
 
         MessageLabel::CNodeMutate => {
-            if length < 5 {
-                debug!("Truncated message for CNode Mutate operation.");
-                unsafe {
-                    current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
-            let cap_data = get_syscall_arg(4, buffer);
-            return invoke_cnode_mutate(src_slot, dest_slot, cap_data);
+            let args = CNodeMutateArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("Truncated message for CNode Mutate operation."))?;
+            return invoke_cnode_mutate(src_slot, dest_slot, args.data);
         }
         _ => {
             panic!("invalid invLabel:{:?}", label);
@@ -163,76 +166,47 @@ fn decode_cnode_rotate(
     dest_slot: &mut cte_t,
     length: usize,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 8 || get_extra_cap_by_index(0).is_none() || get_extra_cap_by_index(1).is_none() {
-        debug!("CNode Rotate: Target cap invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let pivot_new_data = get_syscall_arg(2, buffer);
-    let pivot_index = get_syscall_arg(3, buffer);
-    let pivot_depth = get_syscall_arg(4, buffer);
-    let src_new_data = get_syscall_arg(5, buffer);
-    let src_idnex = get_syscall_arg(6, buffer);
-    let src_depth = get_syscall_arg(7, buffer);
-
-    let pivot_root = &get_extra_cap_by_index(0).unwrap().capability;
-    let src_root = &get_extra_cap_by_index(1).unwrap().capability;
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let args = CNodeRotateArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("CNode Rotate: Target cap invalid."))?;
+    let (src_depth, pivot_depth) = (args.src_depth, args.pivot_depth);
+    let pivot_root = &args.pivot_root.0.capability;
+    let src_root = &args.src_root.0.capability;
 
-    let lu_ret = lookup_slot_for_cnode_op(true, src_root, src_idnex, src_depth);
-    if lu_ret.status != exception_t::EXCEPTION_NONE {
-        return lu_ret.status;
-    }
-    let src_slot = convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize);
+    let src_slot = lookup_slot_for_cnode_op(true, src_root, args.src_index, src_depth, ctx)?;
 
-    let lu_ret = lookup_slot_for_cnode_op(true, pivot_root, pivot_index, pivot_depth);
-    if lu_ret.status != exception_t::EXCEPTION_NONE {
-        return lu_ret.status;
-    }
-    let pivot_slot = convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize);
+    let pivot_slot =
+        lookup_slot_for_cnode_op(true, pivot_root, args.pivot_index, pivot_depth, ctx)?;
 
     if pivot_slot.get_ptr() == src_slot.get_ptr() || pivot_slot.get_ptr() == dest_slot.get_ptr() {
         debug!("CNode Rotate: Pivot slot the same as source or dest slot.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     if src_slot.get_ptr() != dest_slot.get_ptr() {
         if dest_slot.capability.get_tag() != cap_tag::cap_null_cap {
-            unsafe {
-                current_syscall_error._type = SEL4_DELETE_FIRST;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::DeleteFirst);
         }
     }
 
     if src_slot.capability.get_tag() == cap_tag::cap_null_cap {
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = 1;
-            current_lookup_fault = lookup_fault_missing_capability::new(src_depth as u64).unsplay();
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: true,
+            fault: lookup_fault_missing_capability::new(src_depth as u64).unsplay(),
+        });
     }
 
     if pivot_slot.capability.get_tag() == cap_tag::cap_null_cap {
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = 0;
-            current_lookup_fault =
-                lookup_fault_missing_capability::new(pivot_depth as u64).unsplay();
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: lookup_fault_missing_capability::new(pivot_depth as u64).unsplay(),
+        });
     }
     return invoke_cnode_rotate(
         src_slot,
         pivot_slot,
         dest_slot,
-        src_new_data,
-        pivot_new_data,
+        args.src_data,
+        args.pivot_data,
     );
 }
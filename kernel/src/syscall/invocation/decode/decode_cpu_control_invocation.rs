@@ -0,0 +1,70 @@
+use log::debug;
+use sel4_common::arch::MessageLabel;
+use sel4_common::kernel_state::kernel_state_t;
+use sel4_common::structures::seL4_IPCBuffer;
+use sel4_task::{core_is_active, get_currenct_thread, set_thread_state, ThreadState};
+
+use super::super::invoke_cpu_control::{invoke_cpu_control_offline, invoke_cpu_control_online};
+use super::args::InvocationMessage;
+use crate::syscall::error::SyscallError;
+
+invocation_args! {
+    /// The arguments of `CPUControlOffline` and `CPUControlOnline`.
+    struct CoreArgs {
+        core: usize,
+    }
+}
+
+pub fn decode_cpu_control_invocation(
+    label: MessageLabel,
+    length: usize,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
+    let message = InvocationMessage::new(length, buffer, ctx);
+    match label {
+        MessageLabel::CPUControlOffline => decode_offline(&message),
+        MessageLabel::CPUControlOnline => decode_online(&message),
+        _ => {
+            debug!("CPUControl: Illegal operation.");
+            Err(SyscallError::IllegalOperation)
+        }
+    }
+}
+
+fn decode_core(message: &InvocationMessage) -> Result<usize, SyscallError> {
+    let core = CoreArgs::decode(message)
+        .inspect_err(|_| debug!("CPUControl: Truncated message."))?
+        .core;
+    if core >= crate::boot::num_boot_cpus() {
+        debug!("CPUControl: Requested CPU does not exist.");
+        return Err(SyscallError::InvalidArgument { index: 0 });
+    }
+    Ok(core)
+}
+
+/// Core 0 takes the device interrupts, so it is never taken offline, which also keeps
+/// one core active.
+fn decode_offline(message: &InvocationMessage) -> Result<(), SyscallError> {
+    let core = decode_core(message)?;
+    if core == 0 {
+        debug!("CPUControl Offline: CPU 0 can not go offline.");
+        return Err(SyscallError::InvalidArgument { index: 0 });
+    }
+    if !core_is_active(core) {
+        debug!("CPUControl Offline: CPU {} is already offline.", core);
+        return Err(SyscallError::IllegalOperation);
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_cpu_control_offline(core)
+}
+
+fn decode_online(message: &InvocationMessage) -> Result<(), SyscallError> {
+    let core = decode_core(message)?;
+    if core_is_active(core) {
+        debug!("CPUControl Online: CPU {} is already online.", core);
+        return Err(SyscallError::IllegalOperation);
+    }
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_cpu_control_online(core)
+}
@@ -1,65 +1,57 @@
 use core::intrinsics::unlikely;
 
 use log::debug;
+use rel4_utils::invocation::Skip;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::structures_gen::{cap, cap_tag};
-use sel4_common::{
-    arch::MessageLabel,
-    sel4_config::*,
-    structures::{exception_t, seL4_IPCBuffer},
-    utils::convert_to_mut_type_ref,
-};
+use sel4_common::{arch::MessageLabel, structures::seL4_IPCBuffer, utils::convert_to_mut_type_ref};
 use sel4_task::{get_currenct_thread, set_thread_state, tcb_t, ThreadState};
 
-use crate::{
-    kernel::boot::{current_syscall_error, get_extra_cap_by_index},
-    syscall::get_syscall_arg,
-};
+use super::args::{CapSlot, InvocationMessage};
+use crate::syscall::error::SyscallError;
+
+invocation_args! {
+    /// The domain of `DomainSetSet`.
+    struct DomainArgs {
+        domain: usize,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `DomainSetSet`, the thread cap is only checked for a valid
+    /// domain.
+    struct DomainSetArgs {
+        _domain: Skip<1>,
+        thread: CapSlot,
+    }
+}
 
 pub fn decode_domain_invocation(
     invLabel: MessageLabel,
     length: usize,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if invLabel != MessageLabel::DomainSetSet {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
-    if length == 0 {
-        debug!("Domain Configure: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let domain = get_syscall_arg(0, buffer);
+    let message = InvocationMessage::new(length, buffer, ctx);
+    let domain = DomainArgs::decode(&message)
+        .inspect_err(|_| debug!("Domain Configure: Truncated message."))?
+        .domain;
     if domain >= 1 {
         debug!("Domain Configure: invalid domain ({} >= 1).", domain);
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidArgumentNumber = 0;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    if get_extra_cap_by_index(0).is_none() {
-        debug!("Domain Configure: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 0 });
     }
-    let thread_cap = cap::cap_thread_cap(&get_extra_cap_by_index(0).unwrap().capability);
+    let args = DomainSetArgs::decode(&message)
+        .inspect_err(|_| debug!("Domain Configure: Truncated message."))?;
+    let thread_cap = cap::cap_thread_cap(&args.thread.0.capability);
     if unlikely(thread_cap.clone().unsplay().get_tag() != cap_tag::cap_thread_cap) {
         debug!("Domain Configure: thread cap required.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidArgumentNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 1 });
     }
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     convert_to_mut_type_ref::<tcb_t>(thread_cap.get_capTCBPtr() as usize).set_domain(domain);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
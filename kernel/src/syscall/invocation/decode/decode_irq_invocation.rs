@@ -1,81 +1,96 @@
 use crate::interrupt::mask_interrupt;
 use log::debug;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::structures_gen::{cap_Splayed, cap_tag};
-use sel4_common::{
-    arch::MessageLabel,
-    sel4_config::*,
-    structures::{exception_t, seL4_IPCBuffer},
-    utils::convert_to_mut_type_ref,
-};
+use sel4_common::{arch::MessageLabel, structures::seL4_IPCBuffer};
 use sel4_cspace::interface::cte_t;
 use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
 use super::arch::{arch_decode_irq_control_invocation, check_irq};
+use super::args::{CapSlot, InvocationMessage};
+#[cfg(feature = "enable_smp")]
+use crate::interrupt::irq_can_migrate;
+#[cfg(feature = "enable_smp")]
+use crate::syscall::invocation::invoke_irq::invoke_set_irq_target_core;
 use crate::syscall::invocation::invoke_irq::{invoke_clear_irq_handler, invoke_set_irq_handler};
 use crate::{
     interrupt::is_irq_active,
-    kernel::boot::{current_syscall_error, get_extra_cap_by_index},
-    syscall::{get_syscall_arg, invocation::invoke_irq::invoke_irq_control, lookupSlotForCNodeOp},
+    syscall::{
+        error::SyscallError, invocation::invoke_irq::invoke_irq_control, lookupSlotForCNodeOp,
+    },
 };
+#[cfg(feature = "enable_smp")]
+use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
+
+invocation_args! {
+    /// The arguments of `IRQIssueIRQHandler`.
+    struct IssueIRQHandlerArgs {
+        irq: usize,
+        index: usize,
+        depth: usize,
+        root: CapSlot,
+    }
+}
+
+invocation_args! {
+    /// The arguments of `IRQSetIRQHandler`.
+    struct SetIRQHandlerArgs {
+        ntfn: CapSlot,
+    }
+}
+
+#[cfg(feature = "enable_smp")]
+invocation_args! {
+    /// The arguments of `IRQSetTargetCore`.
+    struct SetTargetCoreArgs {
+        target: usize,
+    }
+}
 
 pub fn decode_irq_control_invocation(
     label: MessageLabel,
     length: usize,
     src_slot: &mut cte_t,
+    call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if label == MessageLabel::IRQIssueIRQHandler {
-        if length < 3 || get_extra_cap_by_index(0).is_none() {
-            unsafe {
-                current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-        let irq = get_syscall_arg(0, buffer);
-        let index = get_syscall_arg(1, buffer);
-        let depth = get_syscall_arg(2, buffer);
-
-        let cnode_cap = &get_extra_cap_by_index(0).unwrap().capability;
-        let status = check_irq(irq);
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
+        let args = IssueIRQHandlerArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+            .inspect_err(|_| debug!("IRQControl IssueIRQHandler: Truncated message."))?;
+        let irq = args.irq;
+        let cnode_cap = &args.root.0.capability;
+        check_irq(irq)?;
         if is_irq_active(irq) {
-            unsafe {
-                current_syscall_error._type = SEL4_REVOKE_FIRST;
-            }
             debug!("Rejecting request for IRQ {}. Already active.", irq);
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::RevokeFirst);
         }
-        let lu_ret = lookupSlotForCNodeOp(false, cnode_cap, index, depth);
-        if lu_ret.status != exception_t::EXCEPTION_NONE {
-            debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq);
-            return lu_ret.status;
-        }
-        let dest_slot = convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize);
+        let dest_slot = lookupSlotForCNodeOp(false, cnode_cap, args.index, args.depth, ctx)
+            .inspect_err(|_| debug!("Target slot for new IRQ Handler cap invalid: IRQ {}.", irq))?;
         if dest_slot.capability.get_tag() != cap_tag::cap_null_cap {
-            unsafe {
-                current_syscall_error._type = SEL4_DELETE_FIRST;
-            }
             debug!("Target slot for new IRQ Handler cap not empty");
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::DeleteFirst);
         }
         set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-        invoke_irq_control(
-            irq,
-            convert_to_mut_type_ref::<cte_t>(lu_ret.slot as usize),
-            src_slot,
-        )
+        invoke_irq_control(irq, dest_slot, src_slot)
     } else {
-        arch_decode_irq_control_invocation(label, length, src_slot, buffer)
+        arch_decode_irq_control_invocation(label, length, src_slot, call, buffer, ctx)
     }
 }
 
-pub fn decode_irq_handler_invocation(label: MessageLabel, index: usize) -> exception_t {
+pub fn decode_irq_handler_invocation(
+    label: MessageLabel,
+    length: usize,
+    index: usize,
+    buffer: &seL4_IPCBuffer,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     let irq = sel4_common::structures::idx_to_irq(index);
     return match label {
         MessageLabel::IRQAckIRQ => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            #[cfg(feature = "irq_stats")]
+            crate::interrupt::stats::record_ack(index);
             #[cfg(all(feature = "enable_smp", target_arch = "aarch64"))]
             {
                 use crate::arch::remote_mask_private_interrupt;
@@ -85,49 +100,70 @@ pub fn decode_irq_handler_invocation(label: MessageLabel, index: usize) -> excep
                 let irq = idx_to_irqt(index);
                 if irq.irq < NUM_PPI && irq.core != cpu_id() {
                     remote_mask_private_interrupt(irq.core, false, irq.irq);
-                    return exception_t::EXCEPTION_NONE;
+                    return Ok(());
                 }
             }
+            // the PLIC holds a signalled source back until its claim completes
+            #[cfg(target_arch = "riscv64")]
+            if crate::arch::plic::is_plic_irq(irq) {
+                crate::arch::plic::plic_complete_claim(irq);
+            }
             mask_interrupt(false, irq);
-            exception_t::EXCEPTION_NONE
+            Ok(())
         }
 
         MessageLabel::IRQSetIRQHandler => {
-            if get_extra_cap_by_index(0).is_none() {
-                unsafe {
-                    current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
-            }
-            let slot = get_extra_cap_by_index(0).unwrap();
+            let slot = SetIRQHandlerArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("IRQSetHandler: Truncated message."))?
+                .ntfn
+                .0;
             let ntfn_cap = slot.capability.clone();
             match ntfn_cap.clone().splay() {
                 cap_Splayed::notification_cap(data) => {
                     if data.get_capNtfnCanSend() == 0 {
-                        unsafe {
-                            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                            current_syscall_error.invalidCapNumber = 0;
-                        }
-                        return exception_t::EXCEPTION_SYSCALL_ERROR;
+                        debug!("IRQSetHandler: caller does not have send rights on the endpoint.");
+                        return Err(SyscallError::InvalidCapability { index: 0 });
                     }
                 }
-                _ => {}
+                _ => {
+                    debug!("IRQSetHandler: provided cap is not an notification capability.");
+                    return Err(SyscallError::InvalidCapability { index: 0 });
+                }
             }
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             invoke_set_irq_handler(irq, &ntfn_cap, slot);
-            exception_t::EXCEPTION_NONE
+            Ok(())
         }
         MessageLabel::IRQClearIRQHandler => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             invoke_clear_irq_handler(irq);
-            exception_t::EXCEPTION_NONE
+            Ok(())
+        }
+        #[cfg(feature = "enable_smp")]
+        MessageLabel::IRQSetTargetCore => {
+            let target = SetTargetCoreArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+                .inspect_err(|_| debug!("IRQSetTargetCore: Truncated message."))?
+                .target;
+            if target >= CONFIG_MAX_NUM_NODES {
+                debug!("IRQSetTargetCore: Target core {} is invalid.", target);
+                return Err(SyscallError::InvalidArgument { index: 0 });
+            }
+            #[cfg(feature = "cpu_hotplug")]
+            if !sel4_task::core_is_active(target) {
+                debug!("IRQSetTargetCore: Target core {} is offline.", target);
+                return Err(SyscallError::IllegalOperation);
+            }
+            if !irq_can_migrate(index) {
+                debug!("IRQSetTargetCore: IRQ {} is private to a core.", irq);
+                return Err(SyscallError::IllegalOperation);
+            }
+            set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+            invoke_set_irq_target_core(index, target);
+            Ok(())
         }
         _ => {
             debug!("IRQHandler: Illegal operation.");
-            unsafe {
-                current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            }
-            exception_t::EXCEPTION_SYSCALL_ERROR
+            Err(SyscallError::IllegalOperation)
         }
     };
 }
@@ -4,13 +4,14 @@
 }, BIT};*/
 
 use log::debug;
+use rel4_utils::invocation::ArgSource;
+#[cfg(feature = "arm_sve")]
+use rel4_utils::invocation::Skip;
 use sel4_common::arch::MessageLabel;
 use sel4_common::arch::{FRAME_REG_NUM, GP_REG_NUM};
-use sel4_common::sel4_config::{
-    SEL4_ILLEGAL_OPERATION, SEL4_INVALID_CAPABILITY, SEL4_RANGE_ERROR, SEL4_TRUNCATED_MESSAGE,
-    TCB_CTABLE, TCB_VTABLE,
-};
-use sel4_common::structures::{exception_t, seL4_IPCBuffer};
+use sel4_common::kernel_state::kernel_state_t;
+use sel4_common::sel4_config::{TCB_CTABLE, TCB_VTABLE};
+use sel4_common::structures::seL4_IPCBuffer;
 use sel4_common::structures_gen::{cap, cap_null_cap, cap_tag, cap_thread_cap, notification};
 use sel4_common::utils::convert_to_mut_type_ref;
 #[cfg(target_arch = "aarch64")]
@@ -20,8 +21,8 @@ use sel4_cspace::interface::cte_t;
 use sel4_task::{get_currenct_thread, set_thread_state, tcb_t, ThreadState};
 
 use crate::{
-    kernel::boot::{current_syscall_error, get_extra_cap_by_index},
-    syscall::utils::{check_ipc_buffer_vaild, check_prio, get_syscall_arg},
+    syscall::error::SyscallError,
+    syscall::utils::{check_ipc_buffer_vaild, derive_cap},
 };
 #[cfg(feature = "kernel_mcs")]
 use sel4_common::sel4_config::{
@@ -33,6 +34,7 @@ use sel4_common::sel4_config::{
 use crate::syscall::is_valid_vtable_root;
 
 use super::super::invoke_tcb::*;
+use super::args::{CapSlot, InvocationMessage, Priority, ThreadCap};
 
 pub const COPY_REGISTERS_SUSPEND_SOURCE: usize = 0;
 pub const COPY_REGISTERS_RESUME_TARGET: usize = 1;
@@ -77,7 +79,7 @@ pub const READ_REGISTERS_SUSPEND: usize = 0;
 //         MessageLabel::TCBSetTLSBase => decode_set_tls_base(cap, length, buffer),
 //         _ => unsafe {
 //             debug!("TCB: Illegal operation invLabel :{:?}", invLabel);
-//             current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
+//             ctx.syscall_error._type = SEL4_ILLEGAL_OPERATION;
 //             exception_t::EXCEPTION_SYSCALL_ERROR
 //         },
 //     }
@@ -92,15 +94,17 @@ pub fn decode_tcb_invocation(
     slot: &mut cte_t,
     call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     #[cfg(feature = "enable_smp")]
     crate::smp::ipi::remote_tcb_stall(convert_to_mut_type_ref::<tcb_t>(
         capability.get_capTCBPtr() as usize
     ));
+    let message = InvocationMessage::new(length, buffer, ctx);
     match invLabel {
-        MessageLabel::TCBReadRegisters => decode_read_registers(capability, length, call, buffer),
-        MessageLabel::TCBWriteRegisters => decode_write_registers(capability, length, buffer),
-        MessageLabel::TCBCopyRegisters => decode_copy_registers(capability, length, buffer),
+        MessageLabel::TCBReadRegisters => decode_read_registers(capability, &message, call),
+        MessageLabel::TCBWriteRegisters => decode_write_registers(capability, &message, buffer),
+        MessageLabel::TCBCopyRegisters => decode_copy_registers(capability, &message),
         MessageLabel::TCBSuspend => {
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             invoke_tcb_suspend(convert_to_mut_type_ref::<tcb_t>(
@@ -113,198 +117,183 @@ pub fn decode_tcb_invocation(
                 capability.get_capTCBPtr() as usize
             ))
         }
-        MessageLabel::TCBConfigure => decode_tcb_configure(capability, length, slot, buffer),
-        MessageLabel::TCBSetPriority => decode_set_priority(capability, length, buffer),
-        MessageLabel::TCBSetMCPriority => decode_set_mc_priority(capability, length, buffer),
+        MessageLabel::TCBConfigure => decode_tcb_configure(capability, slot, &message),
+        MessageLabel::TCBSetPriority => decode_set_priority(capability, &message),
+        MessageLabel::TCBSetMCPriority => decode_set_mc_priority(capability, &message),
         #[cfg(not(feature = "kernel_mcs"))]
-        MessageLabel::TCBSetSchedParams => decode_set_sched_params(capability, length, buffer),
+        MessageLabel::TCBSetSchedParams => decode_set_sched_params(capability, &message),
         #[cfg(feature = "kernel_mcs")]
-        MessageLabel::TCBSetSchedParams => {
-            decode_set_sched_params(capability, length, slot, buffer)
-        }
-        MessageLabel::TCBSetIPCBuffer => decode_set_ipc_buffer(capability, length, slot, buffer),
-        MessageLabel::TCBSetSpace => decode_set_space(capability, length, slot, buffer),
-        MessageLabel::TCBBindNotification => decode_bind_notification(capability),
+        MessageLabel::TCBSetSchedParams => decode_set_sched_params(capability, slot, &message),
+        MessageLabel::TCBSetIPCBuffer => decode_set_ipc_buffer(capability, slot, &message),
+        MessageLabel::TCBSetSpace => decode_set_space(capability, slot, &message),
+        MessageLabel::TCBBindNotification => decode_bind_notification(capability, &message),
         MessageLabel::TCBUnbindNotification => decode_unbind_notification(capability),
         #[cfg(feature = "kernel_mcs")]
-        MessageLabel::TCBSetTimeoutEndpoint => decode_set_timeout_endpoint(capability, slot),
-        MessageLabel::TCBSetTLSBase => decode_set_tls_base(capability, length, buffer),
+        MessageLabel::TCBSetTimeoutEndpoint => {
+            decode_set_timeout_endpoint(capability, slot, &message)
+        }
+        MessageLabel::TCBSetTLSBase => decode_set_tls_base(capability, &message),
         #[cfg(all(feature = "enable_smp", not(feature = "kernel_mcs")))]
-        MessageLabel::TCBSetAffinity => decode_set_affinity(capability, length, buffer),
-        _ => unsafe {
+        MessageLabel::TCBSetAffinity => decode_set_affinity(capability, &message),
+        #[cfg(feature = "arm_sve")]
+        MessageLabel::ARMTCBSetSVE => decode_set_sve(capability, slot, &message),
+        _ => {
             debug!("TCB: Illegal operation invLabel :{:?}", invLabel);
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            exception_t::EXCEPTION_SYSCALL_ERROR
-        },
+            Err(SyscallError::IllegalOperation)
+        }
+    }
+}
+
+invocation_args! {
+    /// The arguments of `TCBReadRegisters`.
+    struct ReadRegistersArgs {
+        flags: usize,
+        count: usize,
     }
 }
 
 fn decode_read_registers(
     capability: &cap_thread_cap,
-    length: usize,
+    message: &InvocationMessage,
     call: bool,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 2 {
-        debug!("TCB CopyRegisters: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let flags = get_syscall_arg(0, buffer);
-    let n = get_syscall_arg(1, buffer);
+) -> Result<(), SyscallError> {
+    let args = ReadRegistersArgs::decode(message)
+        .inspect_err(|_| debug!("TCB ReadRegisters: Truncated message."))?;
+    let n = args.count;
     if n < 1 || n > FRAME_REG_NUM + GP_REG_NUM {
         debug!(
             "TCB ReadRegisters: Attempted to read an invalid number of registers:{}",
             n
         );
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 1;
-            current_syscall_error.rangeErrorMax = FRAME_REG_NUM + GP_REG_NUM;
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
+        return Err(SyscallError::RangeError {
+            min: 1,
+            max: FRAME_REG_NUM + GP_REG_NUM,
+        });
     }
     let thread = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
     if thread.is_current() {
         debug!("TCB ReadRegisters: Attempted to read our own registers.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-    invoke_tcb_read_registers(thread, flags & bit!(READ_REGISTERS_SUSPEND), n, 0, call)
+    invoke_tcb_read_registers(
+        thread,
+        args.flags & bit!(READ_REGISTERS_SUSPEND),
+        n,
+        0,
+        call,
+    )
+}
+
+invocation_args! {
+    /// The arguments of `TCBWriteRegisters`, the values of the registers follow them.
+    struct WriteRegistersArgs {
+        flags: usize,
+        count: usize,
+    }
 }
 
 fn decode_write_registers(
     capability: &cap_thread_cap,
-    length: usize,
+    message: &InvocationMessage,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 2 {
-        unsafe {
-            debug!("TCB CopyRegisters: Truncated message.");
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
-        }
-    }
-    let flags = get_syscall_arg(0, buffer);
-    let w = get_syscall_arg(1, buffer);
-
-    if length - 2 < w {
+) -> Result<(), SyscallError> {
+    let args = WriteRegistersArgs::decode(message)
+        .inspect_err(|_| debug!("TCB WriteRegisters: Truncated message."))?;
+    let w = args.count;
+    if message.length() - 2 < w {
         debug!(
             "TCB WriteRegisters: Message too short for requested write size {}/{}",
-            length - 2,
+            message.length() - 2,
             w
         );
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::TruncatedMessage);
     }
 
     let thread = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
     if thread.is_current() {
         debug!("TCB WriteRegisters: Attempted to write our own registers.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-    invoke_tcb_write_registers(thread, flags & bit!(0), w, 0, buffer)
+    invoke_tcb_write_registers(thread, args.flags & bit!(0), w, 0, buffer)
 }
 
-fn decode_copy_registers(
-    capability: &cap_thread_cap,
-    _length: usize,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    let flags = get_syscall_arg(0, buffer);
-
-    let source_cap = cap::cap_thread_cap(&get_extra_cap_by_index(0).unwrap().capability);
-
-    if capability.clone().unsplay().get_tag() != cap_tag::cap_thread_cap {
-        debug!("TCB CopyRegisters: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+invocation_args! {
+    /// The arguments of `TCBCopyRegisters`.
+    struct CopyRegistersArgs {
+        flags: usize,
+        source: ThreadCap,
     }
+}
 
-    let src_tcb = convert_to_mut_type_ref::<tcb_t>(source_cap.get_capTCBPtr() as usize);
-    return invoke_tcb_copy_registers(
+fn decode_copy_registers(
+    capability: &cap_thread_cap,
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = CopyRegistersArgs::decode(message)
+        .inspect_err(|_| debug!("TCB CopyRegisters: Invalid arguments."))?;
+    let flags = args.flags;
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_tcb_copy_registers(
         convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize),
-        src_tcb,
+        args.source.tcb(),
         flags & bit!(COPY_REGISTERS_SUSPEND_SOURCE),
         flags & bit!(COPY_REGISTERS_RESUME_TARGET),
         flags & bit!(COPY_REGISTERS_TRANSFER_FRAME),
         flags & bit!(COPY_REGISTERS_TRANSFER_INTEGER),
         0,
-    );
+    )
 }
 
-fn decode_tcb_configure(
-    target_thread_cap: &cap_thread_cap,
-    msg_length: usize,
-    target_thread_slot: &mut cte_t,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    #[cfg(not(feature = "kernel_mcs"))]
-    let TCBCONFIGURE_ARGS = 3;
-    #[cfg(feature = "kernel_mcs")]
-    let TCBCONFIGURE_ARGS = 4;
-    if msg_length < TCBCONFIGURE_ARGS
-        || get_extra_cap_by_index(0).is_none()
-        || get_extra_cap_by_index(1).is_none()
-        || get_extra_cap_by_index(2).is_none()
-    {
-        debug!("TCB CopyRegisters: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+#[cfg(not(feature = "kernel_mcs"))]
+invocation_args! {
+    /// The arguments of `TCBConfigure`.
+    struct ConfigureArgs {
+        fault_ep: usize,
+        croot_data: usize,
+        vroot_data: usize,
+        buffer_addr: usize,
+        croot: CapSlot,
+        vroot: CapSlot,
+        buffer: CapSlot,
     }
-    #[cfg(not(feature = "kernel_mcs"))]
-    let fault_ep = get_syscall_arg(0, buffer);
-    #[cfg(not(feature = "kernel_mcs"))]
-    let croot_data = get_syscall_arg(1, buffer);
-    #[cfg(not(feature = "kernel_mcs"))]
-    let vroot_data = get_syscall_arg(2, buffer);
-    #[cfg(not(feature = "kernel_mcs"))]
-    let new_buffer_addr = get_syscall_arg(3, buffer);
+}
 
-    #[cfg(feature = "kernel_mcs")]
-    let croot_data = get_syscall_arg(0, buffer);
-    #[cfg(feature = "kernel_mcs")]
-    let vroot_data = get_syscall_arg(1, buffer);
-    #[cfg(feature = "kernel_mcs")]
-    let new_buffer_addr = get_syscall_arg(2, buffer);
+#[cfg(feature = "kernel_mcs")]
+invocation_args! {
+    /// The arguments of `TCBConfigure`.
+    struct ConfigureArgs {
+        croot_data: usize,
+        vroot_data: usize,
+        buffer_addr: usize,
+        croot: CapSlot,
+        vroot: CapSlot,
+        buffer: CapSlot,
+    }
+}
 
-    let croot_slot = get_extra_cap_by_index(0).unwrap();
+fn decode_tcb_configure(
+    target_thread_cap: &cap_thread_cap,
+    target_thread_slot: &mut cte_t,
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = ConfigureArgs::decode(message)
+        .inspect_err(|_| debug!("TCB Configure: Truncated message."))?;
+    let new_buffer_addr = args.buffer_addr;
+    let croot_slot = args.croot.0;
     let mut croot_cap = &croot_slot.clone().capability;
-    let vroot_slot = get_extra_cap_by_index(1).unwrap();
+    let vroot_slot = args.vroot.0;
     let mut vroot_cap = &vroot_slot.clone().capability;
 
     let (buffer_slot, buffer_cap) = if new_buffer_addr == 0 {
         (None, cap_null_cap::new().unsplay())
     } else {
-        let slot = get_extra_cap_by_index(2).unwrap();
+        let slot = args.buffer.0;
         let capability = &slot.capability;
-        let dc_ret = slot.derive_cap(&capability);
-        if dc_ret.status != exception_t::EXCEPTION_NONE {
-            unsafe {
-                current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            }
-            return dc_ret.status;
-        }
-        let status = check_ipc_buffer_vaild(new_buffer_addr, &dc_ret.capability);
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
-        (Some(slot), dc_ret.capability)
+        let derived = derive_cap(slot, capability)?;
+        check_ipc_buffer_vaild(new_buffer_addr, &derived)?;
+        (Some(slot), derived)
     };
     let target_thread =
         convert_to_mut_type_ref::<tcb_t>(target_thread_cap.get_capTCBPtr() as usize);
@@ -316,58 +305,38 @@ fn decode_tcb_configure(
             .is_long_running_delete()
     {
         debug!("TCB Configure: CSpace or VSpace currently being deleted.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let decode_croot_cap = decode_set_space_args(croot_data, croot_cap, croot_slot);
-    let binding = decode_croot_cap.clone().unwrap();
-    match decode_croot_cap {
-        Ok(_) => croot_cap = &binding,
-        Err(status) => return status,
+        return Err(SyscallError::IllegalOperation);
     }
+    let binding = decode_set_space_args(args.croot_data, croot_cap, croot_slot)?;
+    croot_cap = &binding;
     if croot_cap.get_tag() != cap_tag::cap_cnode_cap {
         debug!("TCB Configure: CSpace cap is invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let decode_vroot_cap_ret = decode_set_space_args(vroot_data, vroot_cap, vroot_slot);
-    let binding = decode_vroot_cap_ret.clone().unwrap();
-    match decode_vroot_cap_ret {
-        Ok(_) => vroot_cap = &binding,
-        Err(status) => return status,
+        return Err(SyscallError::IllegalOperation);
     }
+    let binding = decode_set_space_args(args.vroot_data, vroot_cap, vroot_slot)?;
+    vroot_cap = &binding;
     #[cfg(target_arch = "riscv64")]
     if !is_valid_vtable_root(&vroot_cap) {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     #[cfg(target_arch = "aarch64")]
     if !vroot_cap.is_valid_vtable_root() {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     #[cfg(not(feature = "kernel_mcs"))]
-    let status = invoke_tcb_set_space(
+    invoke_tcb_set_space(
         target_thread,
         target_thread_slot,
-        fault_ep,
+        args.fault_ep,
         croot_cap,
         croot_slot,
         vroot_cap,
         vroot_slot,
-    );
+    )?;
     #[cfg(feature = "kernel_mcs")]
-    let status = invoke_tcb_thread_control_caps(
+    invoke_tcb_thread_control_caps(
         target_thread,
         target_thread_slot,
         &cap_null_cap::new().unsplay(),
@@ -379,10 +348,7 @@ fn decode_tcb_configure(
         vroot_cap,
         Some(vroot_slot),
         THREAD_CONTROL_CAPS_UPDATE_SPACE | THREAD_CONTROL_CAPS_UPDATE_IPC_BUFFER,
-    );
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    )?;
 
     invoke_tcb_set_ipc_buffer(
         target_thread,
@@ -393,182 +359,90 @@ fn decode_tcb_configure(
     )
 }
 
+invocation_args! {
+    /// The arguments of `TCBSetPriority` and `TCBSetMCPriority`.
+    struct SetPriorityArgs {
+        prio: Priority,
+        /// The authority, checked by the priority.
+        _auth: CapSlot,
+    }
+}
+
 fn decode_set_priority(
     capability: &cap_thread_cap,
-    length: usize,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 1 || get_extra_cap_by_index(0).is_none() {
-        debug!("TCB SetPriority: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let new_prio = get_syscall_arg(0, buffer);
-    let auth_cap = &get_extra_cap_by_index(0).unwrap().capability;
-    if auth_cap.get_tag() != cap_tag::cap_thread_cap {
-        debug!("Set priority: authority cap not a TCB.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let auth_tcb =
-        convert_to_mut_type_ref::<tcb_t>(cap::cap_thread_cap(auth_cap).get_capTCBPtr() as usize);
-    let status = check_prio(new_prio, auth_tcb);
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = SetPriorityArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetPriority: Invalid arguments."))?;
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_tcb_set_priority(
         convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize),
-        new_prio,
+        args.prio.0,
     )
 }
 
 fn decode_set_mc_priority(
     capability: &cap_thread_cap,
-    length: usize,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 1 || get_extra_cap_by_index(0).is_none() {
-        debug!("TCB SetMCPPriority: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let new_mcp = get_syscall_arg(0, buffer);
-    let auth_cap = &get_extra_cap_by_index(0).unwrap().capability;
-    if auth_cap.get_tag() != cap_tag::cap_thread_cap {
-        debug!("SetMCPriority: authority cap not a TCB.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let auth_tcb =
-        convert_to_mut_type_ref::<tcb_t>(cap::cap_thread_cap(auth_cap).get_capTCBPtr() as usize);
-    let status = check_prio(new_mcp, auth_tcb);
-    if status != exception_t::EXCEPTION_NONE {
-        debug!(
-            "TCB SetMCPriority: Requested maximum controlled priority {} too high (max {}).",
-            new_mcp, auth_tcb.tcbMCP
-        );
-        return status;
-    }
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = SetPriorityArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetMCPriority: Invalid arguments."))?;
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_tcb_set_mcp(
         convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize),
-        new_mcp,
+        args.prio.0,
     )
 }
 #[cfg(not(feature = "kernel_mcs"))]
-fn decode_set_sched_params(
-    capability: &cap_thread_cap,
-    length: usize,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 2 || get_extra_cap_by_index(0).is_none() {
-        debug!("TCB SetSchedParams: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let new_mcp = get_syscall_arg(0, buffer);
-    let new_prio = get_syscall_arg(1, buffer);
-    let auth_cap = cap::cap_thread_cap(&get_extra_cap_by_index(0).unwrap().capability);
-    if auth_cap.clone().unsplay().get_tag() != cap_tag::cap_thread_cap {
-        debug!("SetSchedParams: authority cap not a TCB.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+invocation_args! {
+    /// The arguments of `TCBSetSchedParams`.
+    struct SetSchedParamsArgs {
+        mcp: Priority,
+        prio: Priority,
+        /// The authority, checked by the priorities.
+        _auth: CapSlot,
     }
+}
 
-    let auth_tcb = convert_to_mut_type_ref::<tcb_t>(auth_cap.get_capTCBPtr() as usize);
-    let status = check_prio(new_mcp, auth_tcb);
-    if status != exception_t::EXCEPTION_NONE {
-        debug!(
-            "TCB SetSchedParams: Requested maximum controlled priority {} too high (max {}).",
-            new_mcp, auth_tcb.tcbMCP
-        );
-        return status;
-    }
-    let status = check_prio(new_prio, auth_tcb);
-    if status != exception_t::EXCEPTION_NONE {
-        debug!(
-            "TCB SetSchedParams: Requested priority {} too high (max {}).",
-            new_prio, auth_tcb.tcbMCP
-        );
-        return status;
+#[cfg(feature = "kernel_mcs")]
+invocation_args! {
+    /// The arguments of `TCBSetSchedParams`.
+    struct SetSchedParamsArgs {
+        mcp: Priority,
+        prio: Priority,
+        /// The authority, checked by the priorities.
+        _auth: CapSlot,
+        sc: CapSlot,
+        fault_handler: CapSlot,
     }
+}
 
+#[cfg(not(feature = "kernel_mcs"))]
+fn decode_set_sched_params(
+    capability: &cap_thread_cap,
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = SetSchedParamsArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetSchedParams: Invalid arguments."))?;
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     let target = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
-    invoke_tcb_set_mcp(target, new_mcp);
-    invoke_tcb_set_priority(target, new_prio)
+    invoke_tcb_set_mcp(target, args.mcp.0)?;
+    invoke_tcb_set_priority(target, args.prio.0)
 }
 #[cfg(feature = "kernel_mcs")]
 fn decode_set_sched_params(
     capability: &cap_thread_cap,
-    length: usize,
     slot: &mut cte_t,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
     use sel4_common::{sel4_config::TCB_FAULT_HANDLER, structures_gen::cap_Splayed};
     use sel4_task::sched_context::sched_context_t;
-    if length < 2
-        || get_extra_cap_by_index(0).is_none()
-        || get_extra_cap_by_index(1).is_none()
-        || get_extra_cap_by_index(2).is_none()
-    {
-        debug!("TCB SetSchedParams: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let new_mcp = get_syscall_arg(0, buffer);
-    let new_prio = get_syscall_arg(1, buffer);
-    let auth_cap = cap::cap_thread_cap(&get_extra_cap_by_index(0).unwrap().capability);
-
-    let sc_cap = &get_extra_cap_by_index(1).unwrap().capability;
-    let fh_slot = get_extra_cap_by_index(2);
-    let fh_cap = &get_extra_cap_by_index(2).unwrap().capability;
-
-    if auth_cap.clone().unsplay().get_tag() != cap_tag::cap_thread_cap {
-        debug!("SetSchedParams: authority cap not a TCB.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let auth_tcb = convert_to_mut_type_ref::<tcb_t>(auth_cap.get_capTCBPtr() as usize);
-    let status = check_prio(new_mcp, auth_tcb);
-    if status != exception_t::EXCEPTION_NONE {
-        debug!(
-            "TCB SetSchedParams: Requested maximum controlled priority {} too high (max {}).",
-            new_mcp, auth_tcb.tcbMCP
-        );
-        return status;
-    }
-    let status = check_prio(new_prio, auth_tcb);
-    if status != exception_t::EXCEPTION_NONE {
-        debug!(
-            "TCB SetSchedParams: Requested priority {} too high (max {}).",
-            new_prio, auth_tcb.tcbMCP
-        );
-        return status;
-    }
+    let args = SetSchedParamsArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetSchedParams: Invalid arguments."))?;
+    let (new_mcp, new_prio) = (args.mcp.0, args.prio.0);
+    let sc_cap = &args.sc.0.capability;
+    let fh_cap = &args.fault_handler.0.capability.clone();
+    let fh_slot = args.fault_handler.0;
     let tcb = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
     let mut have_sc = false;
     match sc_cap.clone().splay() {
@@ -577,69 +451,39 @@ fn decode_set_sched_params(
             have_sc = true;
             if tcb.tcbSchedContext != 0 {
                 debug!("TCB Configure: tcb already has a scheduling context.");
-                unsafe {
-                    current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::IllegalOperation);
             }
             if sc.scTcb != 0 {
                 debug!("TCB Configure: sched contextext already bound.");
-                unsafe {
-                    current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::IllegalOperation);
             }
             if tcb.is_blocked() && !sc.sc_released() {
                 debug!("TCB Configure: tcb blocked and scheduling context not schedulable.");
-                unsafe {
-                    current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::IllegalOperation);
             }
         }
         cap_Splayed::null_cap(_) => {
             if tcb.is_current() {
                 debug!("TCB SetSchedParams: Cannot change sched_context of current thread");
-                unsafe {
-                    current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::IllegalOperation);
             }
         }
         _ => {
             debug!("TCB Configure: sched context cap invalid.");
-            unsafe {
-                current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                current_syscall_error.invalidCapNumber = 2;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidCapability { index: 2 });
         }
     }
     if !valid_fault_handler(fh_cap) {
         debug!("TCB Configure: fault endpoint cap invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            current_syscall_error.invalidCapNumber = 3;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 3 });
     }
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     let t_cap = cap_thread_cap::new(tcb.get_ptr().as_u64()).unsplay();
 
-    let status = install_tcb_cap(
-        tcb,
-        &t_cap,
-        slot,
-        TCB_FAULT_HANDLER,
-        fh_cap,
-        fh_slot.unwrap(),
-    );
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
-    invoke_tcb_set_mcp(tcb, new_mcp);
-    invoke_tcb_set_priority(tcb, new_prio);
+    install_tcb_cap(tcb, &t_cap, slot, TCB_FAULT_HANDLER, fh_cap, fh_slot)?;
+    invoke_tcb_set_mcp(tcb, new_mcp)?;
+    invoke_tcb_set_priority(tcb, new_prio)?;
     if have_sc && cap::cap_sched_context_cap(sc_cap).get_capSCPtr() as usize != tcb.tcbSchedContext
     {
         let sc = convert_to_mut_type_ref::<sched_context_t>(
@@ -650,41 +494,33 @@ fn decode_set_sched_params(
         convert_to_mut_type_ref::<sched_context_t>(tcb.tcbSchedContext)
             .sched_context_unbind_tcb(tcb);
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
+}
+
+invocation_args! {
+    /// The arguments of `TCBSetIPCBuffer`.
+    struct SetIPCBufferArgs {
+        buffer_addr: usize,
+        buffer: CapSlot,
+    }
 }
 
 fn decode_set_ipc_buffer(
     capability: &cap_thread_cap,
-    length: usize,
     slot: &mut cte_t,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 1 || get_extra_cap_by_index(0).is_none() {
-        debug!("TCB SetIPCBuffer: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let buffer_addr = get_syscall_arg(0, buffer);
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = SetIPCBufferArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetIPCBuffer: Truncated message."))?;
+    let buffer_addr = args.buffer_addr;
     let (buffer_slot, buffer_cap) = if buffer_addr == 0 {
         (None, cap_null_cap::new().unsplay())
     } else {
-        let slot = get_extra_cap_by_index(0).unwrap();
+        let slot = args.buffer.0;
         let capability = &slot.capability;
-        let dc_ret = slot.derive_cap(capability);
-        if dc_ret.status != exception_t::EXCEPTION_NONE {
-            unsafe {
-                current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-            }
-            return dc_ret.status;
-        }
-        let status = check_ipc_buffer_vaild(buffer_addr, &dc_ret.capability);
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
-        (Some(slot), dc_ret.capability)
+        let derived = derive_cap(slot, capability)?;
+        check_ipc_buffer_vaild(buffer_addr, &derived)?;
+        (Some(slot), derived)
     };
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -697,26 +533,29 @@ fn decode_set_ipc_buffer(
     )
 }
 
+#[cfg(not(feature = "kernel_mcs"))]
+invocation_args! {
+    /// The arguments of `TCBSetSpace`.
+    struct SetSpaceArgs {
+        fault_ep: usize,
+        croot_data: usize,
+        vroot_data: usize,
+        croot: CapSlot,
+        vroot: CapSlot,
+    }
+}
+
 #[cfg(not(feature = "kernel_mcs"))]
 fn decode_set_space(
     capability: &cap_thread_cap,
-    length: usize,
     slot: &mut cte_t,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 3 || get_extra_cap_by_index(0).is_none() || get_extra_cap_by_index(1).is_none() {
-        debug!("TCB SetSpace: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let fault_ep = get_syscall_arg(0, buffer);
-    let croot_data = get_syscall_arg(1, buffer);
-    let vroot_data = get_syscall_arg(2, buffer);
-    let croot_slot = get_extra_cap_by_index(0).unwrap();
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = SetSpaceArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetSpace: Truncated message."))?;
+    let croot_slot = args.croot.0;
     let mut croot_cap = &croot_slot.capability;
-    let vroot_slot = get_extra_cap_by_index(1).unwrap();
+    let vroot_slot = args.vroot.0;
     let mut vroot_cap = &vroot_slot.capability;
     let target_thread = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
     if target_thread
@@ -727,52 +566,32 @@ fn decode_set_space(
             .is_long_running_delete()
     {
         debug!("TCB Configure: CSpace or VSpace currently being deleted.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    let decode_croot_cap = decode_set_space_args(croot_data, croot_cap, croot_slot);
-    let binding = decode_croot_cap.clone().unwrap();
-    match decode_croot_cap {
-        Ok(_) => croot_cap = &binding,
-        Err(status) => return status,
-    }
+    let binding = decode_set_space_args(args.croot_data, croot_cap, croot_slot)?;
+    croot_cap = &binding;
     if croot_cap.get_tag() != cap_tag::cap_cnode_cap {
         debug!("TCB Configure: CSpace cap is invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    let decode_vroot_cap_ret = decode_set_space_args(vroot_data, vroot_cap, vroot_slot);
-    let binding = decode_vroot_cap_ret.clone().unwrap();
-    match decode_vroot_cap_ret {
-        Ok(_) => vroot_cap = &binding,
-        Err(status) => return status,
-    }
+    let binding = decode_set_space_args(args.vroot_data, vroot_cap, vroot_slot)?;
+    vroot_cap = &binding;
     #[cfg(target_arch = "riscv64")]
     if !is_valid_vtable_root(&vroot_cap) {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     #[cfg(target_arch = "aarch64")]
     if !vroot_cap.is_valid_vtable_root() {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_tcb_set_space(
         target_thread,
         slot,
-        fault_ep,
+        args.fault_ep,
         &croot_cap,
         croot_slot,
         &vroot_cap,
@@ -788,9 +607,6 @@ pub fn valid_fault_handler(capability: &cap) -> bool {
             if data.get_capCanSend() == 0
                 || (data.get_capCanGrant() == 0 && data.get_capCanGrantReply() == 0)
             {
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                }
                 return false;
             }
             return true;
@@ -799,42 +615,39 @@ pub fn valid_fault_handler(capability: &cap) -> bool {
             return true;
         }
         _ => {
-            unsafe {
-                current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-            }
             return false;
         }
     }
 }
+#[cfg(feature = "kernel_mcs")]
+invocation_args! {
+    /// The arguments of `TCBSetSpace`.
+    struct SetSpaceArgs {
+        croot_data: usize,
+        vroot_data: usize,
+        fault_handler: CapSlot,
+        croot: CapSlot,
+        vroot: CapSlot,
+    }
+}
+
 #[cfg(feature = "kernel_mcs")]
 fn decode_set_space(
     capability: &cap_thread_cap,
-    length: usize,
     slot: &mut cte_t,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 2
-        || get_extra_cap_by_index(0).is_none()
-        || get_extra_cap_by_index(1).is_none()
-        || get_extra_cap_by_index(2).is_none()
-    {
-        log::debug!("TCB SetSpace: Truncated message. {}", length);
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let croot_data = get_syscall_arg(0, buffer);
-    let vroot_data = get_syscall_arg(1, buffer);
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let args = SetSpaceArgs::decode(message)
+        .inspect_err(|_| log::debug!("TCB SetSpace: Truncated message."))?;
+    let (croot_data, vroot_data) = (args.croot_data, args.vroot_data);
 
-    let fh_slot = get_extra_cap_by_index(0).unwrap();
+    let fh_slot = args.fault_handler.0;
     let fh_cap = &fh_slot.clone().capability;
 
-    let croot_slot = get_extra_cap_by_index(1).unwrap();
+    let croot_slot = args.croot.0;
     let mut croot_cap = &croot_slot.capability;
 
-    let vroot_slot = get_extra_cap_by_index(2).unwrap();
+    let vroot_slot = args.vroot.0;
     let mut vroot_cap = &vroot_slot.capability;
 
     let target_thread = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
@@ -846,52 +659,29 @@ fn decode_set_space(
             .is_long_running_delete()
     {
         log::debug!("TCB Configure: CSpace or VSpace currently being deleted.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    let decode_croot_cap = decode_set_space_args(croot_data, croot_cap, croot_slot);
-    let binding = decode_croot_cap.clone().unwrap();
-    match decode_croot_cap {
-        Ok(_) => croot_cap = &binding,
-        Err(status) => return status,
-    }
+    let binding = decode_set_space_args(croot_data, croot_cap, croot_slot)?;
+    croot_cap = &binding;
     if croot_cap.get_tag() != cap_tag::cap_cnode_cap {
         log::debug!("TCB Configure: CSpace cap is invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    let decode_vroot_cap_ret = decode_set_space_args(vroot_data, vroot_cap, vroot_slot);
-    let binding = decode_vroot_cap_ret.clone().unwrap();
-    match decode_vroot_cap_ret {
-        Ok(_) => vroot_cap = &binding,
-        Err(status) => return status,
-    }
+    let binding = decode_set_space_args(vroot_data, vroot_cap, vroot_slot)?;
+    vroot_cap = &binding;
     #[cfg(target_arch = "riscv64")]
     if !is_valid_vtable_root(&vroot_cap) {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     #[cfg(target_arch = "aarch64")]
     if !vroot_cap.is_valid_vtable_root() {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     if !valid_fault_handler(fh_cap) {
         log::debug!("TCB SetSpace: fault endpoint cap invalid.");
-        unsafe {
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -910,35 +700,34 @@ fn decode_set_space(
     )
 }
 
-fn decode_bind_notification(capability: &cap_thread_cap) -> exception_t {
-    // println!("decode_bind_notification");
-    if get_extra_cap_by_index(0).is_none() {
-        debug!("TCB BindNotification: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+invocation_args! {
+    /// The arguments of `TCBBindNotification`.
+    struct BindNotificationArgs {
+        ntfn: CapSlot,
     }
+}
+
+fn decode_bind_notification(
+    capability: &cap_thread_cap,
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    // println!("decode_bind_notification");
+    let args = BindNotificationArgs::decode(message)
+        .inspect_err(|_| debug!("TCB BindNotification: Truncated message."))?;
 
     // println!("decode_bind_notification1");
 
     let tcb = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
     if tcb.tcbBoundNotification != 0 {
         debug!("TCB BindNotification: TCB already has a bound notification.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     // println!("decode_bind_notification2");
-    let ntfn_cap = cap::cap_notification_cap(&get_extra_cap_by_index(0).unwrap().capability);
+    let ntfn_cap = cap::cap_notification_cap(&args.ntfn.0.capability);
     if ntfn_cap.clone().unsplay().get_tag() != cap_tag::cap_notification_cap {
         debug!("TCB BindNotification: Notification is invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     // println!("decode_bind_notification3");
@@ -947,20 +736,14 @@ fn decode_bind_notification(capability: &cap_thread_cap) -> exception_t {
 
     if ntfn_cap.get_capNtfnCanReceive() == 0 {
         debug!("TCB BindNotification: Insufficient access rights");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     // println!("decode_bind_notification4");
 
     if ntfn.get_ntfnQueue_head() != 0 || ntfn.get_ntfnQueue_tail() != 0 {
         debug!("TCB BindNotification: Notification cannot be bound.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -968,34 +751,39 @@ fn decode_bind_notification(capability: &cap_thread_cap) -> exception_t {
     invoke_tcb_bind_notification(tcb, ntfn)
 }
 
-fn decode_unbind_notification(capability: &cap_thread_cap) -> exception_t {
+fn decode_unbind_notification(capability: &cap_thread_cap) -> Result<(), SyscallError> {
     let tcb = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
     if tcb.tcbBoundNotification == 0 {
         debug!("TCB BindNotification: TCB already has no bound Notification.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_tcb_unbind_notification(tcb)
 }
 #[cfg(feature = "kernel_mcs")]
-pub fn decode_set_timeout_endpoint(capability: &cap_thread_cap, slot: &mut cte_t) -> exception_t {
+invocation_args! {
+    /// The arguments of `TCBSetTimeoutEndpoint`.
+    struct SetTimeoutEndpointArgs {
+        endpoint: CapSlot,
+    }
+}
+
+#[cfg(feature = "kernel_mcs")]
+pub fn decode_set_timeout_endpoint(
+    capability: &cap_thread_cap,
+    slot: &mut cte_t,
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
     use sel4_common::sel4_config::THREAD_CONTROL_CAPS_UPDATE_TIMEOUT;
 
-    if get_extra_cap_by_index(0).is_none() {
-        debug!("TCB SetSchedParams: Truncated message.");
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let mut thSlot = get_extra_cap_by_index(0).unwrap();
+    let mut thSlot = SetTimeoutEndpointArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetTimeoutEndpoint: Truncated message."))?
+        .endpoint
+        .0;
     let thCap = &thSlot.clone().capability;
     if !valid_fault_handler(&thCap) {
         debug!("TCB SetTimeoutEndpoint: timeout endpoint cap invalid.");
-        unsafe {
-            current_syscall_error.invalidCapNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidCapability { index: 1 });
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_tcb_thread_control_caps(
@@ -1013,48 +801,52 @@ pub fn decode_set_timeout_endpoint(capability: &cap_thread_cap, slot: &mut cte_t
     )
 }
 
+#[cfg(all(feature = "enable_smp", not(feature = "kernel_mcs")))]
+invocation_args! {
+    /// The arguments of `TCBSetAffinity`.
+    struct SetAffinityArgs {
+        affinity: usize,
+    }
+}
+
 #[cfg(all(feature = "enable_smp", not(feature = "kernel_mcs")))]
 fn decode_set_affinity(
     capability: &cap_thread_cap,
-    length: usize,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
     use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
 
-    if length < 1 {
-        debug!("TCB SetAffinity: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-
-    let affinity = get_syscall_arg(0, buffer);
-    if affinity > CONFIG_MAX_NUM_NODES {
+    let affinity = SetAffinityArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetAffinity: Truncated message."))?
+        .affinity;
+    if affinity >= CONFIG_MAX_NUM_NODES {
         debug!("TCB SetAffinity: Requested CPU does not exist.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
+    }
+    #[cfg(feature = "cpu_hotplug")]
+    if !sel4_task::core_is_active(affinity) {
+        debug!("TCB SetAffinity: Requested CPU is offline.");
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     let tcb = convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize);
     invoke_tcb_set_affinity(tcb, affinity)
 }
 
+invocation_args! {
+    /// The arguments of `TCBSetTLSBase`.
+    struct SetTlsBaseArgs {
+        base: usize,
+    }
+}
+
 fn decode_set_tls_base(
     capability: &cap_thread_cap,
-    length: usize,
-    buffer: &seL4_IPCBuffer,
-) -> exception_t {
-    if length < 1 {
-        debug!("TCB SetTLSBase: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
-    let base = get_syscall_arg(0, buffer);
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    let base = SetTlsBaseArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetTLSBase: Truncated message."))?
+        .base;
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     invoke_tcb_set_tls_base(
         convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize),
@@ -1062,23 +854,83 @@ fn decode_set_tls_base(
     )
 }
 
+#[cfg(feature = "arm_sve")]
+invocation_args! {
+    /// The arguments of `ARMTCBSetSVE`.
+    struct SetSVEArgs {
+        vl: usize,
+    }
+}
+
+#[cfg(feature = "arm_sve")]
+invocation_args! {
+    /// The arguments of `ARMTCBSetSVE` with a vector length, which also takes the frame
+    /// of the SVE registers.
+    struct SetSVEStateArgs {
+        _vl: Skip<1>,
+        state: CapSlot,
+    }
+}
+
+/// Set the SVE vector length of the thread in bytes, with a frame large enough for its
+/// SVE registers as the extra cap. A length of 0 stops the thread from using SVE.
+#[cfg(feature = "arm_sve")]
+fn decode_set_sve(
+    capability: &cap_thread_cap,
+    slot: &mut cte_t,
+    message: &InvocationMessage,
+) -> Result<(), SyscallError> {
+    use sel4_common::arch::arch_tcb::{sve_state_size, SVE_MAX_VL};
+    use sel4_common::utils::pageBitsForSize;
+
+    let vl = SetSVEArgs::decode(message)
+        .inspect_err(|_| debug!("TCB SetSVE: Truncated message."))?
+        .vl;
+    if vl % 16 != 0 || vl > SVE_MAX_VL {
+        debug!("TCB SetSVE: Invalid vector length {}.", vl);
+        return Err(SyscallError::InvalidArgument { index: 0 });
+    }
+
+    let (state_slot, state_cap) = if vl == 0 {
+        (None, cap_null_cap::new().unsplay())
+    } else {
+        let state_slot = SetSVEStateArgs::decode(message)
+            .inspect_err(|_| debug!("TCB SetSVE: Truncated message."))?
+            .state
+            .0;
+        let state_cap = &state_slot.capability;
+        if state_cap.get_tag() != cap_tag::cap_frame_cap
+            || cap::cap_frame_cap(state_cap).get_capFIsDevice() != 0
+            || bit!(pageBitsForSize(
+                cap::cap_frame_cap(state_cap).get_capFSize() as usize
+            )) < sve_state_size(vl)
+        {
+            debug!("TCB SetSVE: The SVE state cap is not a large enough frame.");
+            return Err(SyscallError::InvalidCapability { index: 1 });
+        }
+        let derived = derive_cap(state_slot, state_cap)?;
+        (Some(state_slot), derived)
+    };
+
+    set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
+    invoke_tcb_set_sve(
+        convert_to_mut_type_ref::<tcb_t>(capability.get_capTCBPtr() as usize),
+        slot,
+        vl,
+        state_cap,
+        state_slot,
+    )
+}
+
 #[inline]
 fn decode_set_space_args(
     root_data: usize,
     root_cap: &cap,
     root_slot: &cte_t,
-) -> Result<cap, exception_t> {
+) -> Result<cap, SyscallError> {
     let mut ret_root_cap = root_cap.clone();
     if root_data != 0 {
         ret_root_cap = root_cap.update_data(false, root_data as u64);
     }
-    let dc_ret = root_slot.derive_cap(&ret_root_cap);
-    if dc_ret.status != exception_t::EXCEPTION_NONE {
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return Err(dc_ret.status);
-    }
-    ret_root_cap = dc_ret.capability;
-    return Ok(ret_root_cap);
+    derive_cap(root_slot, &ret_root_cap)
 }
@@ -1,5 +1,6 @@
 use log::debug;
 use sel4_common::arch::config::MAX_UNTYPED_BITS;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::structures_gen::cap;
 use sel4_common::structures_gen::cap_cnode_cap;
 use sel4_common::structures_gen::cap_tag;
@@ -14,100 +15,83 @@ use sel4_common::{
 use sel4_cspace::interface::cte_t;
 use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
+use super::args::{CapSlot, InvocationMessage};
 use crate::syscall::{alignUp, FREE_INDEX_TO_OFFSET, GET_FREE_REF};
-use crate::{
-    kernel::boot::{current_lookup_fault, current_syscall_error, get_extra_cap_by_index},
-    syscall::{
-        get_syscall_arg, invocation::invoke_untyped::invoke_untyped_retype,
-        lookup_slot_for_cnode_op,
-    },
+use crate::syscall::{
+    error::SyscallError, invocation::invoke_untyped::invoke_untyped_retype,
+    lookup_slot_for_cnode_op,
 };
 
+invocation_args! {
+    /// The arguments of `UntypedRetype`.
+    struct RetypeArgs {
+        new_type: usize,
+        user_obj_size: usize,
+        node_index: usize,
+        node_depth: usize,
+        node_offset: usize,
+        node_window: usize,
+        root: CapSlot,
+    }
+}
+
 pub fn decode_untyed_invocation(
     inv_label: MessageLabel,
     length: usize,
     slot: &mut cte_t,
     capability: &cap_untyped_cap,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     if inv_label != MessageLabel::UntypedRetype {
         debug!("Untyped cap: Illegal operation attempted.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
-    if length < 6 || get_extra_cap_by_index(0).is_none() {
-        debug!("Untyped invocation: Truncated message.");
-        unsafe {
-            current_syscall_error._type = SEL4_TRUNCATED_MESSAGE;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
-    }
+    let args = RetypeArgs::decode(&InvocationMessage::new(length, buffer, ctx))
+        .inspect_err(|_| debug!("Untyped invocation: Truncated message."))?;
 
-    let op_new_type = ObjectType::from_usize(get_syscall_arg(0, buffer));
+    let op_new_type = ObjectType::from_usize(args.new_type);
     if op_new_type.is_none() {
-        debug!(
-            "Untyped Retype: Invalid object type. {}",
-            get_syscall_arg(0, buffer)
-        );
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidArgumentNumber = 0;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        debug!("Untyped Retype: Invalid object type. {}", args.new_type);
+        return Err(SyscallError::InvalidArgument { index: 0 });
     }
     let new_type = op_new_type.unwrap();
-    let user_obj_size = get_syscall_arg(1, buffer);
-    let node_index = get_syscall_arg(2, buffer);
-    let node_depth = get_syscall_arg(3, buffer);
-    let node_offset = get_syscall_arg(4, buffer);
-    let node_window = get_syscall_arg(5, buffer);
+    let user_obj_size = args.user_obj_size;
+    let node_offset = args.node_offset;
+    let node_window = args.node_window;
     let obj_size = new_type.get_object_size(user_obj_size);
     if user_obj_size >= WORD_BITS || obj_size > MAX_UNTYPED_BITS {
         debug!(
             "Untyped Retype: Invalid object size. {} : {}",
             user_obj_size, obj_size
         );
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 0;
-            current_syscall_error.rangeErrorMax = MAX_UNTYPED_BITS;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::RangeError {
+            min: 0,
+            max: MAX_UNTYPED_BITS,
+        });
     }
 
-    let status = check_object_type(new_type, user_obj_size);
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    check_object_type(new_type, user_obj_size)?;
     #[cfg(feature = "kernel_mcs")]
     if new_type == ObjectType::SchedContextObject && user_obj_size < SEL4_MIN_SCHED_CONTEXT_BITS {
         debug!("Untyped retype: Requested a scheduling context too small.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidArgumentNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 1 });
     }
     let node_cap = &mut cap_cnode_cap::new(0, 0, 0, 0);
-    let status = get_target_cnode(node_index, node_depth, node_cap);
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    get_target_cnode(
+        &args.root.0.capability,
+        args.node_index,
+        args.node_depth,
+        node_cap,
+        ctx,
+    )?;
 
-    let status = check_cnode_slot(node_cap, node_offset, node_window);
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    check_cnode_slot(node_cap, node_offset, node_window)?;
 
     let status = slot.ensure_no_children();
     let (free_index, reset) = if status != exception_t::EXCEPTION_NONE {
         // 原始 untype 有子节点
-        unsafe {
-            current_syscall_error._type = SEL4_REVOKE_FIRST;
-        }
         (capability.get_capFreeIndex() as usize, false)
     } else {
         (0, true)
@@ -126,21 +110,15 @@ pub fn decode_untyed_invocation(
             },
             untyped_free_bytes
         );
-        unsafe {
-            current_syscall_error._type = SEL4_NOT_ENOUGH_MEMORY;
-            current_syscall_error.memoryLeft = untyped_free_bytes;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::NotEnoughMemory {
+            left: untyped_free_bytes,
+        });
     }
 
     let device_mem = capability.get_capIsDevice() != 0;
     if device_mem && !new_type.is_arch_type() && new_type != ObjectType::UnytpedObject {
         debug!("Untyped Retype: Creating kernel objects with device untyped");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidArgumentNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 1 });
     }
     let aligned_free_ref = alignUp(free_ref, obj_size);
 
@@ -159,57 +137,44 @@ pub fn decode_untyed_invocation(
 }
 
 #[inline]
-fn check_object_type(new_type: ObjectType, user_obj_size: usize) -> exception_t {
+fn check_object_type(new_type: ObjectType, user_obj_size: usize) -> Result<(), SyscallError> {
     if new_type == ObjectType::CapTableObject && user_obj_size == 0 {
         debug!("Untyped Retype: Requested CapTable size too small.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidArgumentNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 1 });
     }
 
     if new_type == ObjectType::UnytpedObject && user_obj_size < SEL4_MIN_UNTYPED_BITS {
         debug!("Untyped Retype: Requested UntypedItem size too small.");
-        unsafe {
-            current_syscall_error._type = SEL4_INVALID_ARGUMENT;
-            current_syscall_error.invalidArgumentNumber = 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::InvalidArgument { index: 1 });
     }
-    return exception_t::EXCEPTION_NONE;
+    return Ok(());
 }
 
 #[inline]
 fn get_target_cnode(
+    root_cap: &cap,
     node_index: usize,
     node_depth: usize,
     node_cap: &mut cap_cnode_cap,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     let target_node_cap = if node_depth == 0 {
-        &get_extra_cap_by_index(0).unwrap().capability
+        root_cap
     } else {
-        let root_cap = &get_extra_cap_by_index(0).unwrap().capability;
-        let lu_ret = lookup_slot_for_cnode_op(false, root_cap, node_index, node_depth);
-        if lu_ret.status != exception_t::EXCEPTION_NONE {
-            debug!("Untyped Retype: Invalid destination address.");
-            return lu_ret.status;
-        }
-        unsafe { &(*lu_ret.slot).capability }
+        let lu_slot = lookup_slot_for_cnode_op(false, root_cap, node_index, node_depth, ctx)
+            .inspect_err(|_| debug!("Untyped Retype: Invalid destination address."))?;
+        &lu_slot.capability
     };
 
     if target_node_cap.get_tag() != cap_tag::cap_cnode_cap {
         debug!("Untyped Retype: Destination cap invalid or read-only.");
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = 0;
-            current_lookup_fault =
-                lookup_fault_missing_capability::new(node_depth as u64).unsplay();
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::FailedLookup {
+            source: false,
+            fault: lookup_fault_missing_capability::new(node_depth as u64).unsplay(),
+        });
     }
     *node_cap = cap::cap_cnode_cap(&target_node_cap).clone();
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
@@ -217,19 +182,17 @@ fn check_cnode_slot(
     node_cap: &cap_cnode_cap,
     node_offset: usize,
     node_window: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let node_size = 1 << node_cap.get_capCNodeRadix();
     if node_offset > (node_size - 1) {
         debug!(
             "Untyped Retype: Destination node offset {} too large.",
             node_offset
         );
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 0;
-            current_syscall_error.rangeErrorMax = node_size - 1;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::RangeError {
+            min: 0,
+            max: node_size - 1,
+        });
     }
 
     if node_window < 1 || node_window > CONFIG_RETYPE_FAN_OUT_LIMIT {
@@ -237,22 +200,18 @@ fn check_cnode_slot(
             "Untyped Retype: Number of requested objects {} too small or large.",
             node_window
         );
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 1;
-            current_syscall_error.rangeErrorMax = CONFIG_RETYPE_FAN_OUT_LIMIT;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::RangeError {
+            min: 1,
+            max: CONFIG_RETYPE_FAN_OUT_LIMIT,
+        });
     }
 
     if node_window > node_size - node_offset {
         debug!("Untyped Retype: Requested destination window overruns size of node.");
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 1;
-            current_syscall_error.rangeErrorMax = node_size - node_offset;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::RangeError {
+            min: 1,
+            max: node_size - node_offset,
+        });
     }
 
     let dest_cnode = convert_to_mut_type_ref::<cte_t>(node_cap.get_capCNodePtr() as usize);
@@ -262,11 +221,8 @@ fn check_cnode_slot(
                 "Untyped Retype: Slot {:#x} in destination window non-empty.",
                 i
             );
-            unsafe {
-                current_syscall_error._type = SEL4_DELETE_FIRST;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::DeleteFirst);
         }
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
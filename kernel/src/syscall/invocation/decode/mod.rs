@@ -1,34 +1,37 @@
 mod decode_cnode_invocation;
+#[cfg(feature = "cpu_hotplug")]
+mod decode_cpu_control_invocation;
 mod decode_domain_invocation;
 pub mod decode_irq_invocation;
 #[cfg(feature = "kernel_mcs")]
 pub mod decode_sched_invocation;
 
 pub mod arch;
+mod args;
 mod decode_tcb_invocation;
 mod decode_untyped_invocation;
 
 use core::intrinsics::unlikely;
 
 use log::debug;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::structures_gen::{cap, cap_Splayed, endpoint, notification};
-use sel4_common::{
-    arch::MessageLabel,
-    sel4_config::SEL4_INVALID_CAPABILITY,
-    structures::{exception_t, seL4_IPCBuffer},
-    utils::convert_to_mut_type_ref,
-};
+use sel4_common::{arch::MessageLabel, structures::seL4_IPCBuffer, utils::convert_to_mut_type_ref};
 use sel4_cspace::interface::cte_t;
 use sel4_ipc::{endpoint_func, notification_func, Transfer};
 #[cfg(not(feature = "kernel_mcs"))]
 use sel4_task::tcb_t;
 use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
-use crate::kernel::boot::current_syscall_error;
+use crate::syscall::error::SyscallError;
 use crate::syscall::invocation::decode::decode_irq_invocation::decode_irq_handler_invocation;
 
 #[cfg(feature = "enable_smc")]
 use self::arch::decode_arm_smc_invocation;
+#[cfg(feature = "hypervisor")]
+use self::arch::decode_vcpu_invocation;
+#[cfg(feature = "cpu_hotplug")]
+use self::decode_cpu_control_invocation::decode_cpu_control_invocation;
 #[cfg(feature = "kernel_mcs")]
 use self::decode_sched_invocation::{
     decode_sched_context_invocation, decode_sched_control_invocation,
@@ -50,7 +53,8 @@ pub fn decode_invocation(
     block: bool,
     call: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     // sel4_common::println!("decode invocation {}", capability.get_tag());
     match capability.clone().splay() {
         cap_Splayed::null_cap(_) | cap_Splayed::zombie_cap(_) => {
@@ -59,11 +63,7 @@ pub fn decode_invocation(
                 cap_index,
                 capability.get_tag()
             );
-            unsafe {
-                current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                current_syscall_error.invalidCapNumber = 0;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidCapability { index: 0 });
         }
 
         cap_Splayed::endpoint_cap(data) => {
@@ -72,11 +72,7 @@ pub fn decode_invocation(
                     "Attempted to invoke a read-only endpoint cap {}.",
                     cap_index
                 );
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             convert_to_mut_type_ref::<endpoint>(data.get_capEPPtr() as usize).send_ipc(
@@ -87,7 +83,7 @@ pub fn decode_invocation(
                 data.get_capEPBadge() as usize,
                 data.get_capCanGrantReply() != 0,
             );
-            return exception_t::EXCEPTION_NONE;
+            Ok(())
         }
 
         cap_Splayed::notification_cap(data) => {
@@ -96,26 +92,18 @@ pub fn decode_invocation(
                     "Attempted to invoke a read-only notification cap {}.",
                     cap_index
                 );
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             convert_to_mut_type_ref::<notification>(data.get_capNtfnPtr() as usize)
                 .send_signal(data.get_capNtfnBadge() as usize);
-            exception_t::EXCEPTION_NONE
+            Ok(())
         }
 
         cap_Splayed::reply_cap(data) => {
             if unlikely(data.get_capReplyMaster() != 0) {
                 debug!("Attempted to invoke an invalid reply cap {}.", cap_index);
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                    return exception_t::EXCEPTION_SYSCALL_ERROR;
-                }
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             get_currenct_thread().do_reply(
@@ -123,25 +111,35 @@ pub fn decode_invocation(
                 slot,
                 data.get_capReplyCanGrant() != 0,
             );
-            exception_t::EXCEPTION_NONE
+            Ok(())
         }
         cap_Splayed::thread_cap(data) => {
-            decode_tcb_invocation(label, length, &data, slot, call, buffer)
+            decode_tcb_invocation(label, length, &data, slot, call, buffer, ctx)
         }
-        cap_Splayed::domain_cap(_) => decode_domain_invocation(label, length, buffer),
-        cap_Splayed::cnode_cap(data) => decode_cnode_invocation(label, length, &data, buffer),
+        cap_Splayed::domain_cap(_) => decode_domain_invocation(label, length, buffer, ctx),
+        #[cfg(feature = "cpu_hotplug")]
+        cap_Splayed::cpu_control_cap(_) => {
+            decode_cpu_control_invocation(label, length, buffer, ctx)
+        }
+        cap_Splayed::cnode_cap(data) => decode_cnode_invocation(label, length, &data, buffer, ctx),
         cap_Splayed::untyped_cap(data) => {
-            decode_untyed_invocation(label, length, slot, &data, buffer)
+            decode_untyed_invocation(label, length, slot, &data, buffer, ctx)
         }
         cap_Splayed::irq_control_cap(_) => {
-            decode_irq_control_invocation(label, length, slot, buffer)
+            decode_irq_control_invocation(label, length, slot, call, buffer, ctx)
         }
         cap_Splayed::irq_handler_cap(data) => {
-            decode_irq_handler_invocation(label, data.get_capIRQ() as usize)
+            decode_irq_handler_invocation(label, length, data.get_capIRQ() as usize, buffer, ctx)
         }
         #[cfg(feature = "enable_smc")]
-        cap_Splayed::smc_cap(data) => decode_arm_smc_invocation(label, length, &data, call, buffer),
-        _ => decode_mmu_invocation(label, length, slot, call, buffer),
+        cap_Splayed::smc_cap(data) => {
+            decode_arm_smc_invocation(label, length, &data, call, buffer, ctx)
+        }
+        #[cfg(feature = "hypervisor")]
+        cap_Splayed::vcpu_cap(data) => {
+            decode_vcpu_invocation(label, length, &data, call, buffer, ctx)
+        }
+        _ => decode_mmu_invocation(label, length, slot, call, buffer, ctx),
     }
 }
 #[cfg(feature = "kernel_mcs")]
@@ -156,7 +154,8 @@ pub fn decode_invocation(
     canDonate: bool,
     firstPhase: bool,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     // TODO: MCS , in this function, there's lot's of mcs codes
     // sel4_common::println!("decode invocation {}", capability.get_tag());
 
@@ -169,11 +168,7 @@ pub fn decode_invocation(
                 cap_index,
                 capability.get_tag()
             );
-            unsafe {
-                current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                current_syscall_error.invalidCapNumber = 0;
-            }
-            return exception_t::EXCEPTION_SYSCALL_ERROR;
+            return Err(SyscallError::InvalidCapability { index: 0 });
         }
 
         cap_Splayed::endpoint_cap(data) => {
@@ -182,11 +177,7 @@ pub fn decode_invocation(
                     "Attempted to invoke a read-only endpoint cap {}.",
                     cap_index
                 );
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             convert_to_mut_type_ref::<endpoint>(data.get_capEPPtr() as usize).send_ipc(
@@ -198,7 +189,7 @@ pub fn decode_invocation(
                 data.get_capCanGrantReply() != 0,
                 canDonate,
             );
-            return exception_t::EXCEPTION_NONE;
+            Ok(())
         }
 
         cap_Splayed::notification_cap(data) => {
@@ -207,16 +198,12 @@ pub fn decode_invocation(
                     "Attempted to invoke a read-only notification cap {}.",
                     cap_index
                 );
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_SYSCALL_ERROR;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
             set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
             convert_to_mut_type_ref::<notification>(data.get_capNtfnPtr() as usize)
                 .send_signal(data.get_capNtfnBadge() as usize);
-            exception_t::EXCEPTION_NONE
+            Ok(())
         }
 
         cap_Splayed::reply_cap(data) => {
@@ -225,68 +212,58 @@ pub fn decode_invocation(
                 convert_to_mut_type_ref::<reply_t>(data.get_capReplyPtr() as usize),
                 data.get_capReplyCanGrant() != 0,
             );
-            exception_t::EXCEPTION_NONE
+            Ok(())
         }
         cap_Splayed::thread_cap(data) => {
             if unlikely(firstPhase) {
                 debug!("Cannot invoke thread capabilities in the first phase of an invocation");
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_NONE;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
-            decode_tcb_invocation(label, length, &data, slot, call, buffer)
+            decode_tcb_invocation(label, length, &data, slot, call, buffer, ctx)
         }
         cap_Splayed::domain_cap(_) => {
             if unlikely(firstPhase) {
                 debug!("Cannot invoke cnode capabilities in the first phase of an invocation");
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_NONE;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
-            decode_domain_invocation(label, length, buffer)
+            decode_domain_invocation(label, length, buffer, ctx)
         }
-        cap_Splayed::cnode_cap(data) => decode_cnode_invocation(label, length, &data, buffer),
+        cap_Splayed::cnode_cap(data) => decode_cnode_invocation(label, length, &data, buffer, ctx),
         cap_Splayed::untyped_cap(data) => {
-            decode_untyed_invocation(label, length, slot, &data, buffer)
+            decode_untyed_invocation(label, length, slot, &data, buffer, ctx)
         }
         cap_Splayed::irq_control_cap(_) => {
-            decode_irq_control_invocation(label, length, slot, buffer)
+            decode_irq_control_invocation(label, length, slot, call, buffer, ctx)
         }
         cap_Splayed::irq_handler_cap(data) => {
-            decode_irq_handler_invocation(label, data.get_capIRQ() as usize)
+            decode_irq_handler_invocation(label, length, data.get_capIRQ() as usize, buffer, ctx)
         }
         cap_Splayed::sched_control_cap(data) => {
             if unlikely(firstPhase) {
                 debug!(
                     "Cannot invoke sched control capabilities in the first phase of an invocation"
                 );
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_NONE;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
-            decode_sched_control_invocation(label, length, &data, buffer)
+            decode_sched_control_invocation(label, length, &data, buffer, ctx)
         }
         cap_Splayed::sched_context_cap(data) => {
             if unlikely(firstPhase) {
                 debug!(
                     "Cannot invoke sched context capabilities in the first phase of an invocation"
                 );
-                unsafe {
-                    current_syscall_error._type = SEL4_INVALID_CAPABILITY;
-                    current_syscall_error.invalidCapNumber = 0;
-                }
-                return exception_t::EXCEPTION_NONE;
+                return Err(SyscallError::InvalidCapability { index: 0 });
             }
-            decode_sched_context_invocation(label, &data)
+            decode_sched_context_invocation(label, length, &data, buffer, ctx)
         }
         #[cfg(feature = "enable_smc")]
-        cap_Splayed::smc_cap(data) => decode_arm_smc_invocation(label, length, &data, call, buffer),
-        _ => decode_mmu_invocation(label, length, slot, call, buffer),
+        cap_Splayed::smc_cap(data) => {
+            decode_arm_smc_invocation(label, length, &data, call, buffer, ctx)
+        }
+        #[cfg(feature = "hypervisor")]
+        cap_Splayed::vcpu_cap(data) => {
+            decode_vcpu_invocation(label, length, &data, call, buffer, ctx)
+        }
+        _ => decode_mmu_invocation(label, length, slot, call, buffer, ctx),
     }
 }
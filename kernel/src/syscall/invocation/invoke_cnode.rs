@@ -1,41 +1,33 @@
 use log::debug;
 #[cfg(not(feature = "kernel_mcs"))]
-use sel4_common::sel4_config::{SEL4_DELETE_FIRST, TCB_CALLER};
+use sel4_common::sel4_config::TCB_CALLER;
 use sel4_common::structures_gen::{cap, cap_tag, endpoint};
-use sel4_common::{
-    sel4_config::SEL4_ILLEGAL_OPERATION, shared_types_bf_gen::seL4_CapRights,
-    structures::exception_t, utils::convert_to_mut_type_ref,
-};
+use sel4_common::{shared_types_bf_gen::seL4_CapRights, utils::convert_to_mut_type_ref};
 use sel4_cspace::capability::cap_func;
 use sel4_cspace::interface::{cte_insert, cte_move, cte_swap, cte_t};
 use sel4_ipc::endpoint_func;
 use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
-use crate::{kernel::boot::current_syscall_error, syscall::mask_cap_rights};
+use crate::syscall::error::{check_preempted, SyscallError};
+use crate::syscall::{derive_cap, mask_cap_rights};
 
 #[inline]
 pub fn invoke_cnode_copy(
     src_slot: &mut cte_t,
     dest_slot: &mut cte_t,
     cap_right: seL4_CapRights,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let src_cap = mask_cap_rights(cap_right, &src_slot.capability);
-    let dc_ret = src_slot.derive_cap(&src_cap);
-    if dc_ret.status != exception_t::EXCEPTION_NONE {
-        debug!("Error deriving cap for CNode Copy operation.");
-        return dc_ret.status;
-    }
-    if dc_ret.capability.get_tag() == cap_tag::cap_null_cap {
+    let derived = derive_cap(src_slot, &src_cap)
+        .inspect_err(|_| debug!("Error deriving cap for CNode Copy operation."))?;
+    if derived.get_tag() == cap_tag::cap_null_cap {
         debug!("CNode Copy:Copy cap would be invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-    cte_insert(&dc_ret.capability, src_slot, dest_slot);
+    cte_insert(&derived, src_slot, dest_slot);
 
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
@@ -44,25 +36,19 @@ pub fn invoke_cnode_mint(
     dest_slot: &mut cte_t,
     cap_right: seL4_CapRights,
     cap_data: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let src_cap = mask_cap_rights(cap_right, &src_slot.capability);
     let new_cap = src_cap.update_data(false, cap_data as u64);
-    let dc_ret = src_slot.derive_cap(&new_cap);
-    if dc_ret.status != exception_t::EXCEPTION_NONE {
-        debug!("Error deriving cap for CNode Copy operation.");
-        return dc_ret.status;
-    }
-    if dc_ret.capability.get_tag() == cap_tag::cap_null_cap {
+    let derived = derive_cap(src_slot, &new_cap)
+        .inspect_err(|_| debug!("Error deriving cap for CNode Copy operation."))?;
+    if derived.get_tag() == cap_tag::cap_null_cap {
         debug!("CNode Mint:Mint cap would be invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-    cte_insert(&dc_ret.capability, src_slot, dest_slot);
+    cte_insert(&derived, src_slot, dest_slot);
 
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
@@ -70,29 +56,23 @@ pub fn invoke_cnode_mutate(
     src_slot: &mut cte_t,
     dest_slot: &mut cte_t,
     cap_data: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let new_cap = src_slot.capability.update_data(true, cap_data as u64);
     if new_cap.get_tag() == cap_tag::cap_null_cap {
         debug!("CNode Mint:Mint cap would be invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     cte_move(&new_cap, src_slot, dest_slot);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
 #[cfg(not(feature = "kernel_mcs"))]
-pub fn invoke_cnode_save_caller(dest_slot: &mut cte_t) -> exception_t {
+pub fn invoke_cnode_save_caller(dest_slot: &mut cte_t) -> Result<(), SyscallError> {
     if dest_slot.capability.get_tag() != cap_tag::cap_null_cap {
         debug!("CNode SaveCaller: Destination slot not empty.");
-        unsafe {
-            current_syscall_error._type = SEL4_DELETE_FIRST;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     let src_slot = get_currenct_thread().get_cspace_mut_ref(TCB_CALLER);
@@ -106,7 +86,7 @@ pub fn invoke_cnode_save_caller(dest_slot: &mut cte_t) -> exception_t {
         }
         _ => panic!("caller capability must be null or reply"),
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
@@ -116,24 +96,18 @@ pub fn invoke_cnode_rotate(
     slot3: &mut cte_t,
     src_new_data: usize,
     pivot_new_data: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let new_src_cap = slot1.capability.update_data(true, src_new_data as u64);
     let new_pivot_cap = slot2.capability.update_data(true, pivot_new_data as u64);
 
     if new_src_cap.get_tag() == cap_tag::cap_null_cap {
         debug!("CNode Rotate: Source cap invalid");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     if new_pivot_cap.get_tag() == cap_tag::cap_null_cap {
         debug!("CNode Rotate: Pivot cap invalid");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
@@ -145,33 +119,27 @@ pub fn invoke_cnode_rotate(
         cte_move(&new_src_cap, slot1, slot2);
     }
 
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_cnode_move(src_slot: &mut cte_t, dest_slot: &mut cte_t) -> exception_t {
+pub fn invoke_cnode_move(src_slot: &mut cte_t, dest_slot: &mut cte_t) -> Result<(), SyscallError> {
     let src_cap = &src_slot.clone().capability;
     if src_cap.get_tag() == cap_tag::cap_null_cap {
         debug!("CNode Copy/Mint/Move/Mutate: Mutated cap would be invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     cte_move(&src_cap, src_slot, dest_slot);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_cnode_cancel_badged_sends(dest_slot: &mut cte_t) -> exception_t {
+pub fn invoke_cnode_cancel_badged_sends(dest_slot: &mut cte_t) -> Result<(), SyscallError> {
     let dest_cap = &dest_slot.capability;
     if !has_cancel_send_right(&dest_cap) {
         debug!("CNode CancelBadgedSends: Target cap invalid.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
     let badge = cap::cap_endpoint_cap(&dest_cap).get_capEPBadge() as usize;
@@ -181,19 +149,19 @@ pub fn invoke_cnode_cancel_badged_sends(dest_slot: &mut cte_t) -> exception_t {
         )
         .cancel_badged_sends(badge);
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_cnode_revoke(dest_slot: &mut cte_t) -> exception_t {
+pub fn invoke_cnode_revoke(dest_slot: &mut cte_t) -> Result<(), SyscallError> {
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-    dest_slot.revoke()
+    check_preempted(dest_slot.revoke())
 }
 
 #[inline]
-pub fn invoke_cnode_delete(dest_slot: &mut cte_t) -> exception_t {
+pub fn invoke_cnode_delete(dest_slot: &mut cte_t) -> Result<(), SyscallError> {
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-    dest_slot.delete_all(true)
+    check_preempted(dest_slot.delete_all(true))
 }
 
 fn has_cancel_send_right(capability: &cap) -> bool {
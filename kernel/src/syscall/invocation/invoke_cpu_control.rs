@@ -0,0 +1,12 @@
+use crate::smp::hotplug::{core_offline, core_online};
+use crate::syscall::error::SyscallError;
+
+pub fn invoke_cpu_control_offline(core: usize) -> Result<(), SyscallError> {
+    core_offline(core);
+    Ok(())
+}
+
+pub fn invoke_cpu_control_online(core: usize) -> Result<(), SyscallError> {
+    core_online(core);
+    Ok(())
+}
@@ -1,23 +1,49 @@
+use sel4_common::structures_gen::{cap, cap_irq_handler_cap};
+#[cfg(feature = "riscv_aia")]
 use sel4_common::{
-    structures::exception_t,
-    structures_gen::{cap, cap_irq_handler_cap},
+    arch::ArchReg, message_info::seL4_MessageInfo_func, shared_types_bf_gen::seL4_MessageInfo,
 };
 use sel4_cspace::interface::{cte_insert, cte_t};
+#[cfg(feature = "riscv_aia")]
+use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
 use crate::interrupt::{get_irq_handler_slot, set_irq_state_by_index, IRQState};
+use crate::syscall::error::SyscallError;
 
 pub fn invoke_irq_control(
     irq: usize,
     handler_slot: &mut cte_t,
     control_slot: &mut cte_t,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     set_irq_state_by_index(IRQState::IRQSignal, irq);
     cte_insert(
         &cap_irq_handler_cap::new(irq as u64).unsplay(),
         control_slot,
         handler_slot,
     );
-    exception_t::EXCEPTION_NONE
+    Ok(())
+}
+
+/// Issue the handler of an MSI and reply with the address the device writes it to.
+#[cfg(feature = "riscv_aia")]
+pub fn invoke_irq_control_msi(
+    irq: usize,
+    msi_address: usize,
+    handler_slot: &mut cte_t,
+    control_slot: &mut cte_t,
+    call: bool,
+) -> Result<(), SyscallError> {
+    let thread = get_currenct_thread();
+    if call {
+        thread.tcbArch.set_register(ArchReg::Badge, 0);
+        let length = thread.set_mr(0, msi_address) as u64;
+        thread.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo::new(0, 0, 0, length).to_word(),
+        );
+    }
+    set_thread_state(thread, ThreadState::ThreadStateRunning);
+    invoke_irq_control(irq, handler_slot, control_slot)
 }
 
 #[inline]
@@ -31,3 +57,9 @@ pub fn invoke_set_irq_handler(irq: usize, capability: &cap, slot: &mut cte_t) {
 pub fn invoke_clear_irq_handler(irq: usize) {
     get_irq_handler_slot(irq).delete_one();
 }
+
+/// Route a shared IRQ to `core`, its handler cap and state stay as they are.
+#[cfg(feature = "enable_smp")]
+pub fn invoke_set_irq_target_core(index: usize, core: usize) {
+    crate::interrupt::set_irq_target_core(index, core);
+}
@@ -1,5 +1,6 @@
 #[cfg(target_arch = "aarch64")]
 use core::intrinsics::unlikely;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::{
     arch::ArchReg,
     message_info::seL4_MessageInfo_func,
@@ -12,12 +13,10 @@ use sel4_common::structures_gen::cap_frame_cap;
 use sel4_common::utils::convert_ref_type_to_usize;
 #[cfg(target_arch = "riscv64")]
 use sel4_common::{
-    arch::maskVMRights,
-    sel4_bitfield_types::Bitfield,
-    shared_types_bf_gen::seL4_CapRights,
+    arch::vm_rights_t,
     utils::{max_free_index, pageBitsForSize},
 };
-use sel4_common::{sel4_config::*, structures::exception_t, utils::convert_to_mut_type_ref};
+use sel4_common::{sel4_config::*, utils::convert_to_mut_type_ref};
 
 #[cfg(target_arch = "riscv64")]
 use sel4_cspace::interface::cte_insert;
@@ -31,9 +30,10 @@ use sel4_vspace::{
 use sel4_vspace::{clean_by_va_pou, invalidate_tlb_by_asid_va, pte_tag_t};
 use sel4_vspace::{unmap_page, unmap_page_table, PTE};
 
-use crate::{kernel::boot::current_lookup_fault, utils::clear_memory};
+use crate::syscall::error::SyscallError;
+use crate::utils::clear_memory;
 
-pub fn invoke_page_table_unmap(capability: &mut cap_page_table_cap) -> exception_t {
+pub fn invoke_page_table_unmap(capability: &mut cap_page_table_cap) -> Result<(), SyscallError> {
     if capability.get_capPTIsMapped() != 0 {
         let pt = convert_to_mut_type_ref::<PTE>(capability.get_capPTBasePtr() as usize);
         unmap_page_table(
@@ -44,7 +44,7 @@ pub fn invoke_page_table_unmap(capability: &mut cap_page_table_cap) -> exception
         clear_memory(pt.get_mut_ptr() as *mut u8, SEL4_PAGE_TABLE_BITS)
     }
     capability.set_capPTIsMapped(0);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 #[cfg(target_arch = "riscv64")]
 pub fn invoke_page_table_map(
@@ -52,7 +52,7 @@ pub fn invoke_page_table_map(
     pt_slot: &mut PTE,
     asid: usize,
     vaddr: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let paddr = pptr!(pt_cap.get_capPTBasePtr()).to_paddr();
     let pte = PTE::new(paddr.raw() >> SEL4_PAGE_BITS, PTEFlags::V);
     *pt_slot = pte;
@@ -60,7 +60,7 @@ pub fn invoke_page_table_map(
     pt_cap.set_capPTMappedASID(asid as u64);
     pt_cap.set_capPTMappedAddress(vaddr as u64);
     sfence();
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 // #[allow(unused)]
 // #[cfg(target_arch = "aarch64")]
@@ -86,7 +86,7 @@ pub fn invoke_page_table_map(
 //     exception_t::EXCEPTION_NONE
 // }
 
-pub fn invoke_page_get_address(vbase_ptr: usize, call: bool) -> exception_t {
+pub fn invoke_page_get_address(vbase_ptr: usize, call: bool) -> Result<(), SyscallError> {
     let thread = get_currenct_thread();
     if call {
         thread.tcbArch.set_register(ArchReg::Badge, 0);
@@ -97,10 +97,13 @@ pub fn invoke_page_get_address(vbase_ptr: usize, call: bool) -> exception_t {
         );
     }
     set_thread_state(thread, ThreadState::ThreadStateRestart);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
-pub fn invoke_page_unmap(frame_slot: &mut cte_t) -> exception_t {
+pub fn invoke_page_unmap(
+    frame_slot: &mut cte_t,
+    ctx: &mut kernel_state_t,
+) -> Result<(), SyscallError> {
     let page_cap = cap::cap_frame_cap(&frame_slot.capability);
     if page_cap.get_capFMappedASID() as usize != ASID_INVALID {
         match unmap_page(
@@ -110,50 +113,47 @@ pub fn invoke_page_unmap(frame_slot: &mut cte_t) -> exception_t {
             vptr!(page_cap.get_capFMappedAddress()),
             pptr!(page_cap.get_capFBasePtr() as usize),
         ) {
-            Err(lookup_fault) => unsafe {
-                current_lookup_fault = lookup_fault;
-            },
+            Err(lookup_fault) => {
+                ctx.lookup_fault = lookup_fault;
+            }
             _ => {}
         }
     }
     page_cap.set_capFMappedAddress(0);
     page_cap.set_capFMappedASID(ASID_INVALID as u64);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[cfg(target_arch = "riscv64")]
 pub fn invoke_page_map(
     _frame_cap: &mut cap_frame_cap,
-    w_rights_mask: usize,
+    vm_rights: vm_rights_t,
     vaddr: usize,
     asid: usize,
     attr: vm_attributes_t,
     pt_slot: &mut PTE,
     frame_slot: &mut cte_t,
-) -> exception_t {
-    let frame_vm_rights = unsafe {
-        core::mem::transmute(cap::cap_frame_cap(&frame_slot.capability).get_capFVMRights())
-    };
-    let vm_rights = maskVMRights(
-        frame_vm_rights,
-        seL4_CapRights(Bitfield {
-            arr: [w_rights_mask as u64; 1],
-        }),
-    );
+) -> Result<(), SyscallError> {
     let frame_addr = pptr!(cap::cap_frame_cap(&frame_slot.capability).get_capFBasePtr()).to_paddr();
     // let frame_addr =
     //     pptr_to_paddr(cap::cap_frame_cap(&frame_slot.capability).get_capFBasePtr() as usize);
     cap::cap_frame_cap(&frame_slot.capability).set_capFMappedAddress(vaddr as u64);
     cap::cap_frame_cap(&frame_slot.capability).set_capFMappedASID(asid as u64);
     #[cfg(target_arch = "riscv64")]
-    let executable = attr.get_execute_never() == 0;
-    #[cfg(target_arch = "riscv64")]
-    let pte = PTE::make_user_pte(frame_addr, executable, vm_rights);
+    let pte = PTE::make_user_pte(
+        frame_addr,
+        vm_rights,
+        attr,
+        cap::cap_frame_cap(&frame_slot.capability).get_capFIsDevice() != 0,
+    );
     #[cfg(target_arch = "aarch64")]
     let pte = PTE::make_user_pte(frame_addr, vm_rights, attr, frame_slot.cap.get_frame_size());
     set_thread_state(get_currenct_thread(), ThreadState::ThreadStateRestart);
-    pt_slot.update(pte);
-    exception_t::EXCEPTION_NONE
+    pt_slot.update_frame(
+        pte,
+        cap::cap_frame_cap(&frame_slot.capability).get_capFSize() as usize,
+    );
+    Ok(())
 }
 #[cfg(target_arch = "aarch64")]
 pub fn invoke_page_map(
@@ -161,7 +161,7 @@ pub fn invoke_page_map(
     capability: cap_frame_cap,
     pte: PTE,
     pt_slot: &mut PTE,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let tlbflush_required: bool = pt_slot.get_type() != (pte_tag_t::pte_invalid) as usize;
     pt_slot.update(pte);
 
@@ -173,7 +173,7 @@ pub fn invoke_page_map(
         assert!(asid < bit!(16));
         invalidate_tlb_by_asid_va(asid, vptr!(capability.get_capFMappedAddress()));
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 // #[cfg(target_arch = "aarch64")]
 // pub fn invoke_huge_page_map(
@@ -259,7 +259,7 @@ pub fn invoke_asid_control(
     slot: &mut cte_t,
     parent_slot: &mut cte_t,
     asid_base: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     use sel4_common::structures_gen::cap_asid_pool_cap;
 
     cap::cap_untyped_cap(&parent_slot.capability).set_capFreeIndex(max_free_index(
@@ -273,7 +273,7 @@ pub fn invoke_asid_control(
     );
     assert_eq!(asid_base & mask_bits!(ASID_LOW_BITS), 0);
     set_asid_pool_by_index(asid_base >> ASID_LOW_BITS, frame_ptr);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[cfg(target_arch = "riscv64")]
@@ -281,7 +281,7 @@ pub fn invoke_asid_pool(
     asid: usize,
     pool: &mut asid_pool_t,
     vspace_slot: &mut cte_t,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let region_base = pptr!(cap::cap_page_table_cap(&vspace_slot.capability).get_capPTBasePtr());
     cap::cap_page_table_cap(&vspace_slot.capability).set_capPTIsMapped(1);
     cap::cap_page_table_cap(&vspace_slot.capability).set_capPTMappedAddress(0);
@@ -289,5 +289,5 @@ pub fn invoke_asid_pool(
 
     copyGlobalMappings(region_base);
     pool.set_vspace_by_index(asid & mask_bits!(ASID_LOW_BITS), region_base);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
@@ -2,7 +2,6 @@
 use sel4_common::arch::get_current_cpu_index;
 use sel4_common::{
     platform::time_def::ticks_t,
-    structures::exception_t,
     structures_gen::{call_stack, cap, cap_Splayed, cap_tag, notification_t},
     utils::{convert_to_mut_type_ref, convert_to_option_mut_type_ref},
 };
@@ -14,7 +13,12 @@ use sel4_task::{
     tcb_t, NODE_STATE, SCHED_CONTEXT_SPORADIC,
 };
 
-pub fn invokeSchedContext_UnbindObject(sc: &mut sched_context, capability: cap) -> exception_t {
+use crate::syscall::error::SyscallError;
+
+pub fn invokeSchedContext_UnbindObject(
+    sc: &mut sched_context,
+    capability: cap,
+) -> Result<(), SyscallError> {
     match capability.get_tag() {
         cap_tag::cap_thread_cap => {
             sc.sched_context_unbind_tcb(convert_to_mut_type_ref::<tcb_t>(sc.scTcb));
@@ -26,10 +30,13 @@ pub fn invokeSchedContext_UnbindObject(sc: &mut sched_context, capability: cap)
             panic!("invalid cap type");
         }
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
-pub fn invoke_sched_context_bind(sc: &mut sched_context, capability: &cap) -> exception_t {
+pub fn invoke_sched_context_bind(
+    sc: &mut sched_context,
+    capability: &cap,
+) -> Result<(), SyscallError> {
     match capability.clone().splay() {
         cap_Splayed::thread_cap(data) => sc.sched_context_bind_tcb(
             convert_to_mut_type_ref::<tcb_t>(data.get_capTCBPtr() as usize),
@@ -41,23 +48,23 @@ pub fn invoke_sched_context_bind(sc: &mut sched_context, capability: &cap) -> ex
             panic!("invalid cap type of invoke sched context bind")
         }
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
-pub fn invoke_sched_context_unbind(sc: &mut sched_context) -> exception_t {
+pub fn invoke_sched_context_unbind(sc: &mut sched_context) -> Result<(), SyscallError> {
     sc.sched_context_unbind_all_tcbs();
     sc.sched_context_unbind_ntfn();
     if sc.scReply != 0 {
         convert_to_mut_type_ref::<reply_t>(sc.scReply).replyNext = call_stack::new(0, 0);
         sc.scReply = 0;
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
-pub fn invoke_sched_context_consumed(sc: &mut sched_context) -> exception_t {
+pub fn invoke_sched_context_consumed(sc: &mut sched_context) -> Result<(), SyscallError> {
     // TODO: MCS
     sc.set_consumed();
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
-pub fn invoke_sched_context_yield_to(sc: &mut sched_context) -> exception_t {
+pub fn invoke_sched_context_yield_to(sc: &mut sched_context) -> Result<(), SyscallError> {
     if sc.scYieldFrom != 0 {
         convert_to_mut_type_ref::<tcb_t>(sc.scYieldFrom).schedContext_completeYieldTo();
         assert!(sc.scYieldFrom == 0);
@@ -101,7 +108,7 @@ pub fn invoke_sched_context_yield_to(sc: &mut sched_context) -> exception_t {
     if return_now == true {
         sc.set_consumed();
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 pub fn invoke_sched_control_configure_flags(
     target: &mut sched_context,
@@ -111,7 +118,7 @@ pub fn invoke_sched_control_configure_flags(
     max_refills: usize,
     badge: usize,
     flags: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     target.scBadge = badge;
     target.scSporadic = (flags & SCHED_CONTEXT_SPORADIC) != 0;
 
@@ -187,5 +194,5 @@ pub fn invoke_sched_control_configure_flags(
             reschedule_required();
         }
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
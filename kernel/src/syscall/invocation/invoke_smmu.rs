@@ -0,0 +1,115 @@
+use sel4_common::arch::ArchReg;
+use sel4_common::message_info::seL4_MessageInfo_func;
+use sel4_common::sel4_config::SID_INVALID;
+use sel4_common::shared_types_bf_gen::seL4_MessageInfo;
+use sel4_common::structures_gen::{cap_cb_cap, cap_sid_cap};
+use sel4_cspace::interface::{cte_insert, cte_t};
+use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
+
+use crate::arch::smmu::{
+    assign_cb, assign_sid, assign_vspace, bind_sid, cb_fault, cb_tlb_invalidate, clear_cb_fault,
+    clear_global_fault, global_fault, smmu_tlb_invalidate_all, unassign_vspace, unbind_sid,
+    SmmuFault,
+};
+use crate::syscall::error::SyscallError;
+
+pub fn invoke_sid_control_issue_sid(
+    sid: usize,
+    control_slot: &mut cte_t,
+    dest_slot: &mut cte_t,
+) -> Result<(), SyscallError> {
+    assign_sid(sid);
+    cte_insert(
+        &cap_sid_cap::new(sid as u64).unsplay(),
+        control_slot,
+        dest_slot,
+    );
+    Ok(())
+}
+
+pub fn invoke_sid_control_get_fault(call: bool) -> Result<(), SyscallError> {
+    let (fault, gerror) = global_fault();
+    reply_fault(&fault, Some(gerror), call);
+    Ok(())
+}
+
+pub fn invoke_sid_control_clear_fault() -> Result<(), SyscallError> {
+    clear_global_fault();
+    Ok(())
+}
+
+pub fn invoke_sid_bind_cb(sid: usize, cb_slot: &mut cte_t) -> Result<(), SyscallError> {
+    bind_sid(sid, cb_slot);
+    Ok(())
+}
+
+pub fn invoke_sid_unbind_cb(sid: usize) -> Result<(), SyscallError> {
+    unbind_sid(sid);
+    Ok(())
+}
+
+pub fn invoke_cb_control_issue_cb(
+    cb: usize,
+    control_slot: &mut cte_t,
+    dest_slot: &mut cte_t,
+) -> Result<(), SyscallError> {
+    assign_cb(cb);
+    cte_insert(
+        &cap_cb_cap::new(SID_INVALID as u64, cb as u64).unsplay(),
+        control_slot,
+        dest_slot,
+    );
+    Ok(())
+}
+
+pub fn invoke_cb_control_tlb_invalidate_all() -> Result<(), SyscallError> {
+    smmu_tlb_invalidate_all();
+    Ok(())
+}
+
+pub fn invoke_cb_tlb_invalidate(cb: usize) -> Result<(), SyscallError> {
+    cb_tlb_invalidate(cb);
+    Ok(())
+}
+
+pub fn invoke_cb_assign_vspace(cb: usize, vspace_slot: &mut cte_t) -> Result<(), SyscallError> {
+    assign_vspace(cb, vspace_slot);
+    Ok(())
+}
+
+pub fn invoke_cb_unassign_vspace(cb: usize) -> Result<(), SyscallError> {
+    unassign_vspace(cb);
+    Ok(())
+}
+
+pub fn invoke_cb_get_fault(cb: usize, call: bool) -> Result<(), SyscallError> {
+    reply_fault(&cb_fault(cb), None, call);
+    Ok(())
+}
+
+pub fn invoke_cb_clear_fault(cb: usize) -> Result<(), SyscallError> {
+    clear_cb_fault(cb);
+    Ok(())
+}
+
+/// Reply with the event ID, stream ID, input address and info of `fault`, followed by
+/// the global errors for `ARMSIDGetFault`. An event ID of zero means no fault.
+fn reply_fault(fault: &SmmuFault, gerror: Option<usize>, call: bool) {
+    let thread = get_currenct_thread();
+    if call {
+        thread.tcbArch.set_register(ArchReg::Badge, 0);
+        let mut length = 0;
+        for (i, value) in [fault.event, fault.sid, fault.address, fault.info]
+            .into_iter()
+            .chain(gerror)
+            .enumerate()
+        {
+            length = thread.set_mr(i, value);
+        }
+        thread.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo::new(0, 0, 0, length as u64).to_word(),
+        );
+    }
+    set_thread_state(thread, ThreadState::ThreadStateRunning);
+}
@@ -4,12 +4,13 @@ use sel4_common::shared_types_bf_gen::seL4_MessageInfo;
 use sel4_common::structures_gen::{cap, cap_thread_cap, notification};
 use sel4_common::{
     sel4_config::{TCB_BUFFER, TCB_CTABLE, TCB_VTABLE},
-    structures::{exception_t, seL4_IPCBuffer},
+    structures::seL4_IPCBuffer,
 };
 use sel4_cspace::interface::{cte_insert, cte_t, same_object_as};
 use sel4_ipc::Transfer;
 use sel4_task::{get_currenct_thread, reschedule_required, set_thread_state, tcb_t, ThreadState};
 
+use crate::syscall::error::{check_preempted, SyscallError};
 use crate::syscall::{do_bind_notification, safe_unbind_notification, utils::get_syscall_arg};
 
 pub fn invoke_tcb_read_registers(
@@ -18,7 +19,7 @@ pub fn invoke_tcb_read_registers(
     n: usize,
     _arch: usize,
     call: bool,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let thread = get_currenct_thread();
     if suspend_source != 0 {
         // cancel_ipc(src);
@@ -66,7 +67,7 @@ pub fn invoke_tcb_read_registers(
         );
     }
     set_thread_state(thread, ThreadState::ThreadStateRunning);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 pub fn invoke_tcb_write_registers(
@@ -75,7 +76,7 @@ pub fn invoke_tcb_write_registers(
     mut n: usize,
     _arch: usize,
     buffer: &seL4_IPCBuffer,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     if n > FRAME_REG_NUM + GP_REG_NUM {
         n = FRAME_REG_NUM + GP_REG_NUM;
     }
@@ -108,7 +109,7 @@ pub fn invoke_tcb_write_registers(
     if dest.is_current() {
         reschedule_required();
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 pub fn invoke_tcb_copy_registers(
@@ -119,7 +120,7 @@ pub fn invoke_tcb_copy_registers(
     transferFrame: usize,
     _transferInteger: usize,
     _transferArch: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     if suspendSource != 0 {
         // cancel_ipc(src);
         src.cancel_ipc();
@@ -139,37 +140,37 @@ pub fn invoke_tcb_copy_registers(
     if dest.is_current() {
         reschedule_required();
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_tcb_suspend(thread: &mut tcb_t) -> exception_t {
+pub fn invoke_tcb_suspend(thread: &mut tcb_t) -> Result<(), SyscallError> {
     // cancel_ipc(thread);
     thread.cancel_ipc();
     thread.suspend();
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_tcb_resume(thread: &mut tcb_t) -> exception_t {
+pub fn invoke_tcb_resume(thread: &mut tcb_t) -> Result<(), SyscallError> {
     // cancel_ipc(thread);
     if thread.is_stopped() {
         thread.cancel_ipc();
     }
     thread.restart();
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_tcb_set_mcp(target: &mut tcb_t, mcp: usize) -> exception_t {
+pub fn invoke_tcb_set_mcp(target: &mut tcb_t, mcp: usize) -> Result<(), SyscallError> {
     target.set_mc_priority(mcp);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_tcb_set_priority(target: &mut tcb_t, prio: usize) -> exception_t {
+pub fn invoke_tcb_set_priority(target: &mut tcb_t, prio: usize) -> Result<(), SyscallError> {
     target.set_priority(prio);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 #[cfg(not(feature = "kernel_mcs"))]
 pub fn invoke_tcb_set_space(
@@ -180,14 +181,11 @@ pub fn invoke_tcb_set_space(
     croot_src_slot: &mut cte_t,
     vroot_new_cap: &cap,
     vroot_src_slot: &mut cte_t,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let target_cap = cap_thread_cap::new(target.get_ptr().raw() as u64).unsplay();
     target.TCB_FAULT_HANDLER = fault_ep;
     let root_slot = target.get_cspace_mut_ref(TCB_CTABLE);
-    let status = root_slot.delete_all(true);
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    check_preempted(root_slot.delete_all(true))?;
     if same_object_as(croot_new_cap, &croot_src_slot.capability)
         && same_object_as(&target_cap, &slot.capability)
     {
@@ -195,16 +193,13 @@ pub fn invoke_tcb_set_space(
     }
 
     let root_vslot = target.get_cspace_mut_ref(TCB_VTABLE);
-    let status = root_vslot.delete_all(true);
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    check_preempted(root_vslot.delete_all(true))?;
     if same_object_as(vroot_new_cap, &vroot_src_slot.capability)
         && same_object_as(&target_cap, &slot.capability)
     {
         cte_insert(vroot_new_cap, vroot_src_slot, root_vslot);
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 #[cfg(feature = "kernel_mcs")]
 #[no_mangle]
@@ -215,16 +210,13 @@ pub fn install_tcb_cap(
     index: usize,
     newCap: &cap,
     srcSlot: &mut cte_t,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let mut rootSlot = target.get_cspace_mut_ref(index);
-    let e = rootSlot.delete_all(true);
-    if e != exception_t::EXCEPTION_NONE {
-        return e;
-    }
+    check_preempted(rootSlot.delete_all(true))?;
     if same_object_as(newCap, &srcSlot.capability) && same_object_as(tCap, &slot.capability) {
         cte_insert(newCap, srcSlot, &mut rootSlot);
     }
-    return e;
+    Ok(())
 }
 #[cfg(feature = "kernel_mcs")]
 pub fn invoke_tcb_thread_control_caps(
@@ -239,7 +231,7 @@ pub fn invoke_tcb_thread_control_caps(
     vroot_new_cap: &cap,
     vroot_src_slot: Option<&mut cte_t>,
     updateFlags: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     use sel4_common::sel4_config::{
         TCB_FAULT_HANDLER, TCB_TIMEOUT_HANDLER, THREAD_CONTROL_CAPS_UPDATE_FAULT,
         THREAD_CONTROL_CAPS_UPDATE_SPACE, THREAD_CONTROL_CAPS_UPDATE_TIMEOUT,
@@ -247,60 +239,48 @@ pub fn invoke_tcb_thread_control_caps(
     let target_cap = cap_thread_cap::new(target.get_ptr().as_u64()).unsplay();
     if updateFlags & THREAD_CONTROL_CAPS_UPDATE_FAULT != 0 {
         if let Some(fh_slot) = fh_srcSlot {
-            let e = install_tcb_cap(
+            install_tcb_cap(
                 target,
                 &target_cap,
                 slot,
                 TCB_FAULT_HANDLER,
                 fh_newCap,
                 fh_slot,
-            );
-            if e != exception_t::EXCEPTION_NONE {
-                return e;
-            }
+            )?;
         }
     }
     if updateFlags & THREAD_CONTROL_CAPS_UPDATE_TIMEOUT != 0 {
         if let Some(th_slot) = th_srcSlot {
-            let e = install_tcb_cap(
+            install_tcb_cap(
                 target,
                 &target_cap,
                 slot,
                 TCB_TIMEOUT_HANDLER,
                 th_newCap,
                 th_slot,
-            );
-            if e != exception_t::EXCEPTION_NONE {
-                return e;
-            }
+            )?;
         }
     }
     if updateFlags & THREAD_CONTROL_CAPS_UPDATE_SPACE != 0 {
         if let Some(croot_slot) = croot_src_slot {
-            let e = install_tcb_cap(
+            install_tcb_cap(
                 target,
                 &target_cap,
                 slot,
                 TCB_CTABLE,
                 croot_new_cap,
                 croot_slot,
-            );
-            if e != exception_t::EXCEPTION_NONE {
-                return e;
-            }
+            )?;
         }
         if let Some(vroot_slot) = vroot_src_slot {
-            let e = install_tcb_cap(
+            install_tcb_cap(
                 target,
                 &target_cap,
                 slot,
                 TCB_VTABLE,
                 vroot_new_cap,
                 vroot_slot,
-            );
-            if e != exception_t::EXCEPTION_NONE {
-                return e;
-            }
+            )?;
         }
     }
 
@@ -326,7 +306,7 @@ pub fn invoke_tcb_thread_control_caps(
     // {
     //     cte_insert(vroot_new_cap, vroot_src_slot, root_vslot);
     // }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 pub fn invoke_tcb_set_ipc_buffer(
@@ -335,13 +315,10 @@ pub fn invoke_tcb_set_ipc_buffer(
     buffer_addr: usize,
     buffer_cap: cap,
     buffer_src_slot: Option<&mut cte_t>,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let target_cap = cap_thread_cap::new(target.get_ptr().raw() as u64).unsplay();
     let buffer_slot = target.get_cspace_mut_ref(TCB_BUFFER);
-    let status = buffer_slot.delete_all(true);
-    if status != exception_t::EXCEPTION_NONE {
-        return status;
-    }
+    check_preempted(buffer_slot.delete_all(true))?;
     target.tcbIPCBuffer = vptr!(buffer_addr);
     if let Some(mut buffer_src_slot) = buffer_src_slot {
         if same_object_as(&buffer_cap, &buffer_src_slot.capability)
@@ -353,33 +330,64 @@ pub fn invoke_tcb_set_ipc_buffer(
     if target.is_current() {
         reschedule_required();
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
+}
+
+/// Save and release the FPU of `target` before its SVE state is replaced, the new state
+/// is loaded from the frame on its next use of the FPU.
+#[cfg(feature = "arm_sve")]
+pub fn invoke_tcb_set_sve(
+    target: &mut tcb_t,
+    slot: &mut cte_t,
+    vl: usize,
+    state_cap: cap,
+    state_src_slot: Option<&mut cte_t>,
+) -> Result<(), SyscallError> {
+    use sel4_common::sel4_config::TCB_SVE_STATE;
+
+    let target_cap = cap_thread_cap::new(target.get_ptr().raw() as u64).unsplay();
+    crate::arch::fpu::fpu_thread_delete(target);
+    let state_slot = target.get_cspace_mut_ref(TCB_SVE_STATE);
+    check_preempted(state_slot.delete_all(true))?;
+    target.tcbArch.set_sve_vl(0);
+    if let Some(state_src_slot) = state_src_slot {
+        if same_object_as(&state_cap, &state_src_slot.capability)
+            && same_object_as(&target_cap, &slot.capability)
+        {
+            cte_insert(&state_cap, state_src_slot, state_slot);
+            target.tcbArch.set_sve_vl(vl);
+        }
+    }
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_tcb_bind_notification(tcb: &mut tcb_t, ntfn: &mut notification) -> exception_t {
+pub fn invoke_tcb_bind_notification(
+    tcb: &mut tcb_t,
+    ntfn: &mut notification,
+) -> Result<(), SyscallError> {
     do_bind_notification(tcb, ntfn);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_tcb_unbind_notification(tcb: &mut tcb_t) -> exception_t {
+pub fn invoke_tcb_unbind_notification(tcb: &mut tcb_t) -> Result<(), SyscallError> {
     safe_unbind_notification(tcb);
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn invoke_tcb_set_tls_base(thread: &mut tcb_t, base: usize) -> exception_t {
+pub fn invoke_tcb_set_tls_base(thread: &mut tcb_t, base: usize) -> Result<(), SyscallError> {
     thread.tcbArch.set_register(ArchReg::TlsBase, base);
     if thread.is_current() {
         reschedule_required();
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[cfg(all(feature = "enable_smp", not(feature = "kernel_mcs")))]
 #[inline]
-pub fn invoke_tcb_set_affinity(thread: &mut tcb_t, affinitiy: usize) -> exception_t {
+pub fn invoke_tcb_set_affinity(thread: &mut tcb_t, affinitiy: usize) -> Result<(), SyscallError> {
     thread.sched_dequeue();
     crate::smp::migrate_tcb(thread, affinitiy);
     thread.tcbAffinity = affinitiy;
@@ -391,5 +399,5 @@ pub fn invoke_tcb_set_affinity(thread: &mut tcb_t, affinitiy: usize) -> exceptio
     if thread.is_current() {
         reschedule_required();
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
@@ -1,16 +1,17 @@
 // use crate::ffi::tcbDebugAppend;
 use super::arch::arch_create_object;
+use crate::syscall::error::{check_preempted, SyscallError};
 use crate::syscall::{
     FREE_INDEX_TO_OFFSET, GET_FREE_INDEX, GET_OFFSET_FREE_PTR, OFFSET_TO_FREE_IDNEX,
 };
 use rel4_arch::basic::PPtr;
 use sel4_common::arch::ObjectType;
+use sel4_common::sel4_config::*;
 use sel4_common::structures_gen::{
     cap, cap_cnode_cap, cap_endpoint_cap, cap_notification_cap, cap_thread_cap, cap_untyped_cap,
 };
 #[cfg(feature = "kernel_mcs")]
 use sel4_common::structures_gen::{cap_reply_cap, cap_sched_context_cap};
-use sel4_common::{sel4_config::*, structures::exception_t};
 use sel4_cspace::deps::preemption_point;
 use sel4_cspace::interface::{cte_t, insert_new_cap};
 use sel4_task::{get_current_domain, tcb_t};
@@ -125,7 +126,7 @@ fn create_object(
     }
 }
 
-pub fn reset_untyped_cap(srcSlot: &mut cte_t) -> exception_t {
+pub fn reset_untyped_cap(srcSlot: &mut cte_t) -> Result<(), SyscallError> {
     let prev_cap = cap::cap_untyped_cap(&(*srcSlot).capability);
     let block_size = prev_cap.get_capBlockSize() as usize;
     let region_base = prev_cap.get_capPtr() as usize;
@@ -133,7 +134,7 @@ pub fn reset_untyped_cap(srcSlot: &mut cte_t) -> exception_t {
     let offset = FREE_INDEX_TO_OFFSET(prev_cap.get_capFreeIndex() as usize);
     let device_mem = prev_cap.get_capIsDevice();
     if offset == 0 {
-        return exception_t::EXCEPTION_NONE;
+        return Ok(());
     }
 
     if device_mem != 0 || block_size < chunk {
@@ -149,14 +150,11 @@ pub fn reset_untyped_cap(srcSlot: &mut cte_t) -> exception_t {
                 chunk,
             );
             prev_cap.set_capFreeIndex(OFFSET_TO_FREE_IDNEX(offset as usize) as u64);
-            let status = unsafe { preemption_point() };
-            if status != exception_t::EXCEPTION_NONE {
-                return status;
-            }
+            check_preempted(unsafe { preemption_point() })?;
             offset -= bit!(chunk) as isize;
         }
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 pub fn invoke_untyped_retype(
@@ -169,13 +167,10 @@ pub fn invoke_untyped_retype(
     dest_offset: usize,
     dest_length: usize,
     device_mem: usize,
-) -> exception_t {
+) -> Result<(), SyscallError> {
     let region_base = cap::cap_untyped_cap(&src_slot.capability).get_capPtr() as usize;
     if reset {
-        let status = reset_untyped_cap(src_slot);
-        if status != exception_t::EXCEPTION_NONE {
-            return status;
-        }
+        reset_untyped_cap(src_slot)?;
     }
     let total_object_size = dest_length << new_type.get_object_size(user_size);
     let free_ref = retype_base + total_object_size;
@@ -191,5 +186,5 @@ pub fn invoke_untyped_retype(
         user_size,
         device_mem,
     );
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
@@ -0,0 +1,50 @@
+use sel4_common::arch::ArchReg;
+use sel4_common::message_info::seL4_MessageInfo_func;
+use sel4_common::shared_types_bf_gen::seL4_MessageInfo;
+use sel4_task::{get_currenct_thread, set_thread_state, tcb_t, ThreadState};
+
+use crate::arch::vcpu::VCpu;
+use crate::syscall::error::SyscallError;
+
+pub fn invoke_vcpu_set_tcb(vcpu: &mut VCpu, tcb: &mut tcb_t) -> Result<(), SyscallError> {
+    vcpu.associate(tcb);
+    Ok(())
+}
+
+pub fn invoke_vcpu_inject_irq(
+    vcpu: &mut VCpu,
+    index: usize,
+    virq: usize,
+) -> Result<(), SyscallError> {
+    vcpu.inject_irq(index, virq);
+    Ok(())
+}
+
+pub fn invoke_vcpu_read_reg(vcpu: &VCpu, field: usize, call: bool) -> Result<(), SyscallError> {
+    let thread = get_currenct_thread();
+    let value = vcpu.read_reg(field);
+    if call {
+        thread.tcbArch.set_register(ArchReg::Badge, 0);
+        let length = thread.set_mr(0, value) as u64;
+        thread.tcbArch.set_register(
+            ArchReg::MsgInfo,
+            seL4_MessageInfo::new(0, 0, 0, length).to_word(),
+        );
+    }
+    set_thread_state(thread, ThreadState::ThreadStateRunning);
+    Ok(())
+}
+
+pub fn invoke_vcpu_write_reg(
+    vcpu: &mut VCpu,
+    field: usize,
+    value: usize,
+) -> Result<(), SyscallError> {
+    vcpu.write_reg(field, value);
+    Ok(())
+}
+
+pub fn invoke_vcpu_ack_vppi(vcpu: &mut VCpu, vppi: usize) -> Result<(), SyscallError> {
+    vcpu.vppi_masked[vppi] = false;
+    Ok(())
+}
@@ -1,52 +1,57 @@
 pub(crate) mod arch;
 pub mod decode;
 mod invoke_cnode;
+#[cfg(feature = "cpu_hotplug")]
+mod invoke_cpu_control;
 pub mod invoke_irq;
 mod invoke_mmu_op;
 #[cfg(feature = "kernel_mcs")]
 mod invoke_sched;
+#[cfg(feature = "arm_smmu")]
+mod invoke_smmu;
 mod invoke_tcb;
 mod invoke_untyped;
+#[cfg(feature = "hypervisor")]
+mod invoke_vcpu;
 
 use core::intrinsics::unlikely;
 
 use log::debug;
 use sel4_common::arch::{ArchReg, MSG_REGISTER_NUM};
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::message_info::seL4_MessageInfo_func;
 use sel4_common::shared_types_bf_gen::seL4_MessageInfo;
 use sel4_common::structures::exception_t;
 use sel4_common::structures_gen::seL4_Fault_CapFault;
 use sel4_task::{get_currenct_thread, set_thread_state, ThreadState};
 
+use crate::syscall::error::SyscallError;
 use crate::syscall::invocation::decode::decode_invocation;
 use crate::syscall::syscall_reply::{reply_error_from_kernel, reply_success_from_kernel};
 use crate::syscall::{handle_fault, lookup_extra_caps_with_buf};
-use sel4_common::ffi::current_fault;
 
 #[no_mangle]
 #[cfg(not(feature = "kernel_mcs"))]
-pub fn handle_invocation(isCall: bool, isBlocking: bool) -> exception_t {
+pub fn handle_invocation(isCall: bool, isBlocking: bool, ctx: &mut kernel_state_t) -> exception_t {
     let thread = get_currenct_thread();
     let info = seL4_MessageInfo::from_word_security(thread.tcbArch.get_register(ArchReg::MsgInfo));
     let cptr = thread.tcbArch.get_register(ArchReg::Cap);
     let lu_ret = thread.lookup_slot(cptr);
     if unlikely(lu_ret.status != exception_t::EXCEPTION_NONE) {
         debug!("Invocation of invalid cap {:#x}.", cptr);
-        unsafe {
-            current_fault = seL4_Fault_CapFault::new(cptr as u64, 0).unsplay();
-        }
+        ctx.fault = seL4_Fault_CapFault::new(cptr as u64, 0).unsplay();
         if isBlocking {
-            handle_fault(thread);
+            handle_fault(thread, ctx);
         }
         return exception_t::EXCEPTION_NONE;
     }
     let buffer = thread.lookup_ipc_buffer(false);
-    let status = lookup_extra_caps_with_buf(thread, buffer);
+    let status = lookup_extra_caps_with_buf(thread, buffer, ctx);
     if unlikely(status != exception_t::EXCEPTION_NONE) {
         debug!("Lookup of extra caps failed.");
         if isBlocking {
             // handleFault(thread);
-            handle_fault(thread);
+            handle_fault(thread, ctx);
         }
         return exception_t::EXCEPTION_NONE;
     }
@@ -57,7 +62,7 @@ pub fn handle_invocation(isCall: bool, isBlocking: bool) -> exception_t {
     }
 
     let capability = unsafe { (*(lu_ret.slot)).capability.clone() };
-    let status = decode_invocation(
+    let result = decode_invocation(
         info.get_message_label(),
         length,
         unsafe { &mut *lu_ret.slot },
@@ -66,16 +71,17 @@ pub fn handle_invocation(isCall: bool, isBlocking: bool) -> exception_t {
         isBlocking,
         isCall,
         buffer.unwrap(),
+        ctx,
     );
-    if status == exception_t::EXCEPTION_PREEMTED {
-        return status;
-    }
-
-    if status == exception_t::EXCEPTION_SYSCALL_ERROR {
-        if isCall {
-            reply_error_from_kernel(thread);
+    match result {
+        Ok(()) => {}
+        Err(SyscallError::Preempted) => return exception_t::EXCEPTION_PREEMTED,
+        Err(error) => {
+            if isCall {
+                reply_error_from_kernel(thread, &error);
+            }
+            return exception_t::EXCEPTION_NONE;
         }
-        return exception_t::EXCEPTION_NONE;
     }
 
     if unlikely(thread.get_state() == ThreadState::ThreadStateRestart) {
@@ -95,27 +101,26 @@ pub fn handle_invocation(
     canDonate: bool,
     firstPhase: bool,
     cptr: usize,
+    ctx: &mut kernel_state_t,
 ) -> exception_t {
     let thread = get_currenct_thread();
     let info = seL4_MessageInfo::from_word_security(thread.tcbArch.get_register(ArchReg::MsgInfo));
     let lu_ret = thread.lookup_slot(cptr);
     if unlikely(lu_ret.status != exception_t::EXCEPTION_NONE) {
         debug!("Invocation of invalid cap {:#x}.", cptr);
-        unsafe {
-            current_fault = seL4_Fault_CapFault::new(cptr as u64, 0).unsplay();
-        }
+        ctx.fault = seL4_Fault_CapFault::new(cptr as u64, 0).unsplay();
         if isBlocking {
-            handle_fault(thread);
+            handle_fault(thread, ctx);
         }
         return exception_t::EXCEPTION_NONE;
     }
     let buffer = thread.lookup_ipc_buffer(false);
-    let status = lookup_extra_caps_with_buf(thread, buffer);
+    let status = lookup_extra_caps_with_buf(thread, buffer, ctx);
     if unlikely(status != exception_t::EXCEPTION_NONE) {
         debug!("Lookup of extra caps failed.");
         if isBlocking {
             // handleFault(thread);
-            handle_fault(thread);
+            handle_fault(thread, ctx);
         }
         return exception_t::EXCEPTION_NONE;
     }
@@ -131,7 +136,7 @@ pub fn handle_invocation(
     //                               cptr, lu_ret.slot, lu_ret.cap,
     //                               isBlocking, isCall,
     //                               canDonate, firstPhase, buffer);
-    let status = decode_invocation(
+    let result = decode_invocation(
         info.get_message_label(),
         length,
         unsafe { &mut *lu_ret.slot },
@@ -142,16 +147,17 @@ pub fn handle_invocation(
         canDonate,
         firstPhase,
         buffer.unwrap(),
+        ctx,
     );
-    if status == exception_t::EXCEPTION_PREEMTED {
-        return status;
-    }
-
-    if status == exception_t::EXCEPTION_SYSCALL_ERROR {
-        if isCall {
-            reply_error_from_kernel(thread);
+    match result {
+        Ok(()) => {}
+        Err(SyscallError::Preempted) => return exception_t::EXCEPTION_PREEMTED,
+        Err(error) => {
+            if isCall {
+                reply_error_from_kernel(thread, &error);
+            }
+            return exception_t::EXCEPTION_NONE;
         }
-        return exception_t::EXCEPTION_NONE;
     }
 
     if unlikely(thread.get_state() == ThreadState::ThreadStateRestart) {
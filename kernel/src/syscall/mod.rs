@@ -1,3 +1,4 @@
+pub mod error;
 pub mod invocation;
 pub mod syscall_reply;
 pub mod utils;
@@ -60,6 +61,12 @@ pub const SYS_DEBUG_NAME_THREAD: isize = SYS_DEBUG_SNAPSHOT - 1;
 pub const SYS_GET_CLOCK: isize = -30;
 #[cfg(feature = "kernel_mcs")]
 pub const SYS_GET_CLOCK: isize = -33;
+/// `SysDebugLockStats`, numbered below the reL4-only `SysGetClock`.
+#[cfg(feature = "clh_lock_stats")]
+pub const SYS_DEBUG_LOCK_STATS: isize = SYS_GET_CLOCK - 1;
+/// `SysDebugIRQStats`, numbered below `SysDebugLockStats`.
+#[cfg(feature = "irq_stats")]
+pub const SYS_DEBUG_IRQ_STATS: isize = SYS_GET_CLOCK - 2;
 #[cfg(feature = "kernel_mcs")]
 use crate::structures::lookupCap_ret_t;
 use sel4_common::structures::exception_t;
@@ -85,27 +92,26 @@ pub use utils::*;
 use crate::arch::restore_user_context;
 use crate::interrupt::get_active_irq;
 use crate::interrupt::handler::handle_interrput;
-use crate::kernel::boot::current_lookup_fault;
-use sel4_common::ffi::current_fault;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::platform::IRQ_INVALID;
 
 use self::invocation::handle_invocation;
 
 #[no_mangle]
-pub fn slowpath(syscall: usize) {
+pub fn slowpath(syscall: usize, ctx: &mut kernel_state_t) {
     if (syscall as isize) < SYSCALL_MIN || (syscall as isize) > SYSCALL_MAX {
         // using ffi_call! macro to call c function
-        handle_unknown_syscall(syscall as isize);
+        handle_unknown_syscall(syscall as isize, ctx);
         // ffi_call!(handle_unknown_syscall(id: usize => syscall));
     } else {
-        handlesyscall(syscall);
+        handlesyscall(syscall, ctx);
     }
     restore_user_context();
 }
 
 #[no_mangle]
 #[cfg(not(feature = "kernel_mcs"))]
-pub fn handlesyscall(_syscall: usize) -> exception_t {
+pub fn handlesyscall(_syscall: usize, ctx: &mut kernel_state_t) -> exception_t {
     let syscall: isize = _syscall as isize;
     // if hart_id() == 0 {
     //     debug!("handle syscall: {}", syscall);
@@ -113,41 +119,41 @@ pub fn handlesyscall(_syscall: usize) -> exception_t {
     // sel4_common::println!("handle syscall {}", syscall);
     match syscall {
         SYS_SEND => {
-            let ret = handle_invocation(false, true);
+            let ret = handle_invocation(false, true, ctx);
             if unlikely(ret != exception_t::EXCEPTION_NONE) {
                 let irq = get_active_irq();
                 if irq != IRQ_INVALID {
-                    handle_interrput(irq);
+                    handle_interrput(irq, ctx);
                 }
             }
         }
         SYS_NB_SEND => {
-            let ret = handle_invocation(false, false);
+            let ret = handle_invocation(false, false, ctx);
             if unlikely(ret != exception_t::EXCEPTION_NONE) {
                 let irq = get_active_irq();
                 if irq != IRQ_INVALID {
-                    handle_interrput(irq);
+                    handle_interrput(irq, ctx);
                 }
             }
         }
         SYS_CALL => {
-            let ret = handle_invocation(true, true);
+            let ret = handle_invocation(true, true, ctx);
             if unlikely(ret != exception_t::EXCEPTION_NONE) {
                 let irq = get_active_irq();
                 if irq != IRQ_INVALID {
-                    handle_interrput(irq);
+                    handle_interrput(irq, ctx);
                 }
             }
         }
         SYS_RECV => {
-            handle_recv(true);
+            handle_recv(true, ctx);
         }
         SYS_REPLY => handle_reply(),
         SYS_REPLY_RECV => {
             handle_reply();
-            handle_recv(true);
+            handle_recv(true, ctx);
         }
-        SYS_NB_RECV => handle_recv(false),
+        SYS_NB_RECV => handle_recv(false, ctx),
         SYS_YIELD => handle_yield(),
         _ => panic!("Invalid syscall"),
     }
@@ -157,7 +163,7 @@ pub fn handlesyscall(_syscall: usize) -> exception_t {
 }
 #[no_mangle]
 #[cfg(feature = "kernel_mcs")]
-pub fn handlesyscall(_syscall: usize) -> exception_t {
+pub fn handlesyscall(_syscall: usize, ctx: &mut kernel_state_t) -> exception_t {
     use core::intrinsics::likely;
     use sel4_task::{check_budget_restart, update_timestamp};
 
@@ -176,12 +182,13 @@ pub fn handlesyscall(_syscall: usize) -> exception_t {
                     false,
                     false,
                     get_currenct_thread().tcbArch.get_register(Cap),
+                    ctx,
                 );
                 if unlikely(ret != exception_t::EXCEPTION_NONE) {
                     mcs_preemption_point();
                     let irq = get_active_irq();
                     if irq != IRQ_INVALID {
-                        handle_interrput(irq);
+                        handle_interrput(irq, ctx);
                     }
                 }
             }
@@ -192,12 +199,13 @@ pub fn handlesyscall(_syscall: usize) -> exception_t {
                     false,
                     false,
                     get_currenct_thread().tcbArch.get_register(Cap),
+                    ctx,
                 );
                 if unlikely(ret != exception_t::EXCEPTION_NONE) {
                     mcs_preemption_point();
                     let irq = get_active_irq();
                     if irq != IRQ_INVALID {
-                        handle_interrput(irq);
+                        handle_interrput(irq, ctx);
                     }
                 }
             }
@@ -208,58 +216,59 @@ pub fn handlesyscall(_syscall: usize) -> exception_t {
                     true,
                     false,
                     get_currenct_thread().tcbArch.get_register(Cap),
+                    ctx,
                 );
                 if unlikely(ret != exception_t::EXCEPTION_NONE) {
                     mcs_preemption_point();
                     let irq = get_active_irq();
                     if irq != IRQ_INVALID {
-                        handle_interrput(irq);
+                        handle_interrput(irq, ctx);
                     }
                 }
             }
             SYS_RECV => {
-                handle_recv(true, true);
+                handle_recv(true, true, ctx);
             }
             SYS_WAIT => {
-                handle_recv(true, false);
+                handle_recv(true, false, ctx);
             }
             SYS_NB_WAIT => {
-                handle_recv(false, false);
+                handle_recv(false, false, ctx);
             }
             SYS_REPLY_RECV => {
                 let reply = get_currenct_thread().tcbArch.get_register(Reply);
-                let ret = handle_invocation(false, false, true, true, reply);
+                let ret = handle_invocation(false, false, true, true, reply, ctx);
                 assert!(ret == exception_t::EXCEPTION_NONE);
-                handle_recv(true, true);
+                handle_recv(true, true, ctx);
             }
             SYS_NB_SEND_RECV => {
                 // TODO: MCS
                 let dest = get_currenct_thread().tcbArch.get_register(nbsRecvDest);
-                let ret = handle_invocation(false, false, true, true, dest);
+                let ret = handle_invocation(false, false, true, true, dest, ctx);
                 if unlikely(ret != exception_t::EXCEPTION_NONE) {
                     mcs_preemption_point();
                     let irq = get_active_irq();
                     if irq != IRQ_INVALID {
-                        handle_interrput(irq);
+                        handle_interrput(irq, ctx);
                     }
                 } else {
-                    handle_recv(true, true);
+                    handle_recv(true, true, ctx);
                 }
             }
             SYS_NB_SEND_WAIT => {
                 let reply = get_currenct_thread().tcbArch.get_register(Reply);
-                let ret = handle_invocation(false, false, true, true, reply);
+                let ret = handle_invocation(false, false, true, true, reply, ctx);
                 if unlikely(ret != exception_t::EXCEPTION_NONE) {
                     mcs_preemption_point();
                     let irq = get_active_irq();
                     if irq != IRQ_INVALID {
-                        handle_interrput(irq);
+                        handle_interrput(irq, ctx);
                     }
                 } else {
-                    handle_recv(true, false);
+                    handle_recv(true, false, ctx);
                 }
             }
-            SYS_NB_RECV => handle_recv(false, true),
+            SYS_NB_RECV => handle_recv(false, true, ctx),
             SYS_YIELD => handle_yield(),
             _ => panic!("Invalid syscall"),
         }
@@ -269,7 +278,12 @@ pub fn handlesyscall(_syscall: usize) -> exception_t {
     exception_t::EXCEPTION_NONE
 }
 #[cfg(feature = "kernel_mcs")]
-fn send_fault_ipc(thread: &mut tcb_t, handlerCap: &cap, can_donate: bool) -> bool {
+fn send_fault_ipc(
+    thread: &mut tcb_t,
+    handlerCap: &cap,
+    can_donate: bool,
+    ctx: &mut kernel_state_t,
+) -> bool {
     // TODO: MCS
     if handlerCap.get_tag() == cap_tag::cap_endpoint_cap {
         assert!(cap::cap_endpoint_cap(&handlerCap).get_capCanSend() != 0);
@@ -277,7 +291,7 @@ fn send_fault_ipc(thread: &mut tcb_t, handlerCap: &cap, can_donate: bool) -> boo
             cap::cap_endpoint_cap(&handlerCap).get_capCanGrant() != 0
                 || cap::cap_endpoint_cap(&handlerCap).get_capCanGrantReply() != 0
         );
-        thread.tcbFault = unsafe { current_fault.clone() };
+        thread.tcbFault = ctx.fault.clone();
         convert_to_mut_type_ref::<endpoint>(
             cap::cap_endpoint_cap(&handlerCap).get_capEPPtr() as usize
         )
@@ -297,15 +311,13 @@ fn send_fault_ipc(thread: &mut tcb_t, handlerCap: &cap, can_donate: bool) -> boo
     }
 }
 #[cfg(not(feature = "kernel_mcs"))]
-fn send_fault_ipc(thread: &mut tcb_t) -> exception_t {
+fn send_fault_ipc(thread: &mut tcb_t, ctx: &mut kernel_state_t) -> exception_t {
     use sel4_common::structures_gen::seL4_Fault_tag;
 
-    let origin_lookup_fault = unsafe { current_lookup_fault.clone() };
+    let origin_lookup_fault = ctx.lookup_fault.clone();
     let lu_ret = thread.lookup_slot(thread.TCB_FAULT_HANDLER);
     if lu_ret.status != exception_t::EXCEPTION_NONE {
-        unsafe {
-            current_fault = seL4_Fault_CapFault::new(thread.TCB_FAULT_HANDLER as u64, 0).unsplay();
-        }
+        ctx.fault = seL4_Fault_CapFault::new(thread.TCB_FAULT_HANDLER as u64, 0).unsplay();
         return exception_t::EXCEPTION_FAULT;
     }
 
@@ -315,7 +327,7 @@ fn send_fault_ipc(thread: &mut tcb_t) -> exception_t {
                 != 0)
     {
         let handler_cap = cap::cap_endpoint_cap(&ptr_to_mut(lu_ret.slot).capability);
-        thread.tcbFault = unsafe { current_fault.clone() };
+        thread.tcbFault = ctx.fault.clone();
         if thread.tcbFault.get_tag() == seL4_Fault_tag::seL4_Fault_CapFault {
             thread.tcbLookupFailure = origin_lookup_fault;
         }
@@ -328,10 +340,8 @@ fn send_fault_ipc(thread: &mut tcb_t) -> exception_t {
             true,
         );
     } else {
-        unsafe {
-            current_fault = seL4_Fault_CapFault::new(thread.TCB_FAULT_HANDLER as u64, 0).unsplay();
-            current_lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
-        }
+        ctx.fault = seL4_Fault_CapFault::new(thread.TCB_FAULT_HANDLER as u64, 0).unsplay();
+        ctx.lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
         return exception_t::EXCEPTION_FAULT;
     }
     exception_t::EXCEPTION_NONE
@@ -339,17 +349,17 @@ fn send_fault_ipc(thread: &mut tcb_t) -> exception_t {
 
 #[inline]
 #[cfg(not(feature = "kernel_mcs"))]
-pub fn handle_fault(thread: &mut tcb_t) {
-    if send_fault_ipc(thread) != exception_t::EXCEPTION_NONE {
+pub fn handle_fault(thread: &mut tcb_t, ctx: &mut kernel_state_t) {
+    if send_fault_ipc(thread, ctx) != exception_t::EXCEPTION_NONE {
         set_thread_state(thread, ThreadState::ThreadStateInactive);
     }
 }
 #[inline]
 #[cfg(feature = "kernel_mcs")]
-pub fn handle_fault(thread: &mut tcb_t) {
+pub fn handle_fault(thread: &mut tcb_t, ctx: &mut kernel_state_t) {
     use sel4_common::sel4_config::TCB_FAULT_HANDLER;
     let cte = thread.get_cspace(TCB_FAULT_HANDLER);
-    let hasFaultHandler = send_fault_ipc(thread, &cte.capability, thread.tcbSchedContext != 0);
+    let hasFaultHandler = send_fault_ipc(thread, &cte.capability, thread.tcbSchedContext != 0, ctx);
     if !hasFaultHandler {
         set_thread_state(thread, ThreadState::ThreadStateInactive);
     }
@@ -357,25 +367,28 @@ pub fn handle_fault(thread: &mut tcb_t) {
 #[inline]
 #[cfg(feature = "kernel_mcs")]
 #[no_mangle]
-pub fn handleTimeout(tptr: &mut tcb_t) {
+pub fn handleTimeout(tptr: &mut tcb_t, badge: usize) {
     use sel4_common::sel4_config::TCB_TIMEOUT_HANDLER;
+    use sel4_common::structures_gen::seL4_Fault_Timeout;
 
     assert!(tptr.valid_timeout_handler());
     let cte = tptr.get_cspace(TCB_TIMEOUT_HANDLER);
-    send_fault_ipc(tptr, &cte.capability, false);
+    // the timeout fault is not the fault of the current kernel entry, keep it apart
+    let mut ctx = kernel_state_t {
+        fault: seL4_Fault_Timeout::new(badge as u64).unsplay(),
+        ..kernel_state_t::new()
+    };
+    send_fault_ipc(tptr, &cte.capability, false, &mut ctx);
 }
 #[inline]
 #[cfg(feature = "kernel_mcs")]
 #[no_mangle]
 pub fn endTimeslice(can_timeout_fault: bool) {
-    use sel4_common::structures_gen::seL4_Fault_Timeout;
-
     unsafe {
         let thread = get_currenct_thread();
         let sched_context = get_current_sc();
         if can_timeout_fault && !sched_context.is_round_robin() && thread.valid_timeout_handler() {
-            current_fault = seL4_Fault_Timeout::new(sched_context.scBadge as u64).unsplay();
-            handleTimeout(thread);
+            handleTimeout(thread, sched_context.scBadge);
         } else if sched_context.refill_ready() && sched_context.refill_sufficient(0) {
             /* apply round robin */
             assert!(sched_context.refill_sufficient(0));
@@ -389,7 +402,7 @@ pub fn endTimeslice(can_timeout_fault: bool) {
 }
 #[cfg(feature = "kernel_mcs")]
 #[inline]
-pub fn lookup_reply() -> lookupCap_ret_t {
+pub fn lookup_reply(ctx: &mut kernel_state_t) -> lookupCap_ret_t {
     use log::debug;
 
     use crate::object::lookup_cap;
@@ -399,15 +412,15 @@ pub fn lookup_reply() -> lookupCap_ret_t {
 
     if unlikely(lu_ret.status != exception_t::EXCEPTION_NONE) {
         debug!("Reply cap lookup failed");
-        unsafe { current_fault = seL4_Fault_CapFault::new(reply_ptr as u64, 1).unsplay() };
-        handle_fault(get_currenct_thread());
+        ctx.fault = seL4_Fault_CapFault::new(reply_ptr as u64, 1).unsplay();
+        handle_fault(get_currenct_thread(), ctx);
         return lu_ret;
     }
 
     if unlikely(lu_ret.capability.get_tag() != cap_tag::cap_reply_cap) {
         debug!("Cap in reply slot is not a reply");
-        unsafe { current_fault = seL4_Fault_CapFault::new(reply_ptr as u64, 1).unsplay() };
-        handle_fault(get_currenct_thread());
+        ctx.fault = seL4_Fault_CapFault::new(reply_ptr as u64, 1).unsplay();
+        handle_fault(get_currenct_thread(), ctx);
         lu_ret.status = exception_t::EXCEPTION_FAULT;
         return lu_ret;
     }
@@ -433,29 +446,25 @@ fn handle_reply() {
     }
 }
 #[cfg(feature = "kernel_mcs")]
-fn handle_recv(block: bool, canReply: bool) {
+fn handle_recv(block: bool, canReply: bool, ctx: &mut kernel_state_t) {
     let current_thread = get_currenct_thread();
     let ep_cptr = current_thread.tcbArch.get_register(ArchReg::Cap);
     let lu_ret = current_thread.lookup_slot(ep_cptr);
     if lu_ret.status != exception_t::EXCEPTION_NONE {
-        unsafe {
-            current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-        }
-        return handle_fault(current_thread);
+        ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+        return handle_fault(current_thread, ctx);
     }
     let ipc_cap = unsafe { (*lu_ret.slot).capability.clone() };
     match ipc_cap.splay() {
         cap_Splayed::endpoint_cap(data) => {
             if unlikely(data.get_capCanReceive() == 0) {
-                unsafe {
-                    current_lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
-                    current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-                }
-                return handle_fault(current_thread);
+                ctx.lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
+                ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+                return handle_fault(current_thread, ctx);
             }
             // TODO: MCS
             if canReply {
-                let lu_ret = lookup_reply();
+                let lu_ret = lookup_reply(ctx);
                 if lu_ret.status != exception_t::EXCEPTION_NONE {
                     return;
                 } else {
@@ -483,44 +492,36 @@ fn handle_recv(block: bool, canReply: bool) {
                     || (bound_tcb_ptr != 0
                         && bound_tcb_ptr != current_thread.get_ptr().raw() as u64),
             ) {
-                unsafe {
-                    current_lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
-                    current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-                }
-                return handle_fault(current_thread);
+                ctx.lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
+                ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+                return handle_fault(current_thread, ctx);
             }
             return ntfn.receive_signal(current_thread, block);
         }
         _ => {
-            unsafe {
-                current_lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
-                current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-            }
-            return handle_fault(current_thread);
+            ctx.lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
+            ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+            return handle_fault(current_thread, ctx);
         }
     }
 }
 
 #[cfg(not(feature = "kernel_mcs"))]
-fn handle_recv(block: bool) {
+fn handle_recv(block: bool, ctx: &mut kernel_state_t) {
     let current_thread = get_currenct_thread();
     let ep_cptr = current_thread.tcbArch.get_register(ArchReg::Cap);
     let lu_ret = current_thread.lookup_slot(ep_cptr);
     if lu_ret.status != exception_t::EXCEPTION_NONE {
-        unsafe {
-            current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-        }
-        return handle_fault(current_thread);
+        ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+        return handle_fault(current_thread, ctx);
     }
     let ipc_cap = unsafe { (*lu_ret.slot).capability.clone() };
     match ipc_cap.splay() {
         cap_Splayed::endpoint_cap(data) => {
             if unlikely(data.get_capCanReceive() == 0) {
-                unsafe {
-                    current_lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
-                    current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-                }
-                return handle_fault(current_thread);
+                ctx.lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
+                ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+                return handle_fault(current_thread, ctx);
             }
             current_thread.delete_caller_cap();
             convert_to_mut_type_ref::<endpoint>(data.get_capEPPtr() as usize).receive_ipc(
@@ -538,20 +539,16 @@ fn handle_recv(block: bool) {
                     || (bound_tcb_ptr != 0
                         && bound_tcb_ptr != current_thread.get_ptr().raw() as u64),
             ) {
-                unsafe {
-                    current_lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
-                    current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-                }
-                return handle_fault(current_thread);
+                ctx.lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
+                ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+                return handle_fault(current_thread, ctx);
             }
             return ntfn.receive_signal(current_thread, block);
         }
         _ => {
-            unsafe {
-                current_lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
-                current_fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
-            }
-            return handle_fault(current_thread);
+            ctx.lookup_fault = lookup_fault_missing_capability::new(0).unsplay();
+            ctx.fault = seL4_Fault_CapFault::new(ep_cptr as u64, 1).unsplay();
+            return handle_fault(current_thread, ctx);
         }
     }
 }
@@ -1,10 +1,8 @@
 use core::intrinsics::unlikely;
 
-use crate::kernel::boot::current_extra_caps;
-use crate::kernel::boot::{current_lookup_fault, current_syscall_error};
 use log::debug;
 use sel4_common::arch::{maskVMRights, ArchReg, MSG_REGISTER_NUM};
-use sel4_common::ffi::current_fault;
+use sel4_common::kernel_state::kernel_state_t;
 use sel4_common::sel4_config::SEL4_MIN_UNTYPED_BITS;
 use sel4_common::shared_types_bf_gen::seL4_CapRights;
 use sel4_common::structures_gen::{
@@ -15,17 +13,16 @@ use sel4_common::{
     structures::{exception_t, seL4_IPCBuffer},
 };
 use sel4_common::{
-    sel4_config::{
-        SEL4_ALIGNMENT_ERROR, SEL4_DELETE_FIRST, SEL4_FAILED_LOOKUP, SEL4_IPC_BUFFER_SIZE_BITS,
-        WORD_BITS,
-    },
+    sel4_config::{SEL4_IPC_BUFFER_SIZE_BITS, WORD_BITS},
     utils::convert_to_mut_type_ref,
 };
 use sel4_cspace::arch::arch_mask_cap_rights;
 use sel4_cspace::capability::cap_func;
 use sel4_cspace::interface::{cte_t, resolve_address_bits};
 use sel4_ipc::notification_func;
-use sel4_task::{get_currenct_thread, lookupSlot_ret_t, tcb_t};
+use sel4_task::{get_currenct_thread, tcb_t};
+
+use crate::syscall::error::SyscallError;
 
 pub fn alignUp(baseValue: usize, alignment: usize) -> usize {
     (baseValue + bit!(alignment) - 1) & !mask_bits!(alignment)
@@ -63,14 +60,16 @@ pub fn OFFSET_TO_FREE_IDNEX(offset: usize) -> usize {
 // }
 
 #[inline]
-pub fn lookup_extra_caps_with_buf(thread: &mut tcb_t, buf: Option<&seL4_IPCBuffer>) -> exception_t {
-    unsafe {
-        match thread.lookup_extra_caps_with_buf(&mut current_extra_caps.excaprefs, buf) {
-            Ok(()) => {}
-            Err(fault) => {
-                current_fault = fault;
-                return exception_t::EXCEPTION_LOOKUP_FAULT;
-            }
+pub fn lookup_extra_caps_with_buf(
+    thread: &mut tcb_t,
+    buf: Option<&seL4_IPCBuffer>,
+    ctx: &mut kernel_state_t,
+) -> exception_t {
+    match thread.lookup_extra_caps_with_buf(&mut ctx.extra_caps.excaprefs, buf) {
+        Ok(()) => {}
+        Err(fault) => {
+            ctx.fault = fault;
+            return exception_t::EXCEPTION_LOOKUP_FAULT;
         }
     }
     return exception_t::EXCEPTION_NONE;
@@ -86,44 +85,33 @@ pub fn get_syscall_arg(i: usize, ipc_buffer: &seL4_IPCBuffer) -> usize {
 }
 
 #[inline]
-pub fn check_prio(prio: usize, auth_tcb: &tcb_t) -> exception_t {
+pub fn check_prio(prio: usize, auth_tcb: &tcb_t) -> Result<(), SyscallError> {
     if prio > auth_tcb.tcbMCP {
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = SEL4_MIN_PRIO;
-            current_syscall_error.rangeErrorMax = auth_tcb.tcbMCP;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::RangeError {
+            min: SEL4_MIN_PRIO,
+            max: auth_tcb.tcbMCP,
+        });
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
-pub fn check_ipc_buffer_vaild(vptr: usize, capability: &cap) -> exception_t {
+pub fn check_ipc_buffer_vaild(vptr: usize, capability: &cap) -> Result<(), SyscallError> {
     if capability.clone().get_tag() != cap_tag::cap_frame_cap {
         debug!("Requested IPC Buffer is not a frame cap.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     if cap::cap_frame_cap(capability).get_capFIsDevice() != 0 {
         debug!("Specifying a device frame as an IPC buffer is not permitted.");
-        unsafe {
-            current_syscall_error._type = SEL4_ILLEGAL_OPERATION;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::IllegalOperation);
     }
 
     if !is_aligned!(vptr, SEL4_IPC_BUFFER_SIZE_BITS) {
         debug!("Requested IPC Buffer location 0x%x is not aligned.");
-        unsafe {
-            current_syscall_error._type = SEL4_ALIGNMENT_ERROR;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::AlignmentError);
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[inline]
@@ -163,50 +151,36 @@ pub fn lookup_slot_for_cnode_op(
     root: &cap,
     cap_ptr: usize,
     depth: usize,
-) -> lookupSlot_ret_t {
-    let mut ret: lookupSlot_ret_t = lookupSlot_ret_t::default();
+    ctx: &mut kernel_state_t,
+) -> Result<&'static mut cte_t, SyscallError> {
     if unlikely(root.clone().get_tag() != cap_tag::cap_cnode_cap) {
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = is_source as usize;
-            current_lookup_fault = lookup_fault_invalid_root::new().unsplay();
-        }
-        ret.status = exception_t::EXCEPTION_SYSCALL_ERROR;
-        return ret;
+        return Err(SyscallError::FailedLookup {
+            source: is_source,
+            fault: lookup_fault_invalid_root::new().unsplay(),
+        });
     }
 
     if unlikely(depth < 1 || depth > WORD_BITS) {
-        unsafe {
-            current_syscall_error._type = SEL4_RANGE_ERROR;
-            current_syscall_error.rangeErrorMin = 1;
-            current_syscall_error.rangeErrorMax = WORD_BITS;
-        }
-        ret.status = exception_t::EXCEPTION_SYSCALL_ERROR;
-        return ret;
+        return Err(SyscallError::RangeError {
+            min: 1,
+            max: WORD_BITS,
+        });
     }
     let res_ret = resolve_address_bits(root, cap_ptr, depth);
     if unlikely(res_ret.status != exception_t::EXCEPTION_NONE) {
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = is_source as usize;
-        }
-        ret.status = exception_t::EXCEPTION_SYSCALL_ERROR;
-        return ret;
+        return Err(SyscallError::FailedLookup {
+            source: is_source,
+            fault: ctx.lookup_fault.clone(),
+        });
     }
 
     if unlikely(res_ret.bitsRemaining != 0) {
-        unsafe {
-            current_syscall_error._type = SEL4_FAILED_LOOKUP;
-            current_syscall_error.failedLookupWasSource = is_source as usize;
-            current_lookup_fault =
-                lookup_fault_depth_mismatch::new(0, res_ret.bitsRemaining as u64).unsplay();
-        }
-        ret.status = exception_t::EXCEPTION_SYSCALL_ERROR;
-        return ret;
+        return Err(SyscallError::FailedLookup {
+            source: is_source,
+            fault: lookup_fault_depth_mismatch::new(0, res_ret.bitsRemaining as u64).unsplay(),
+        });
     }
-    ret.slot = res_ret.slot;
-    ret.status = exception_t::EXCEPTION_NONE;
-    ret
+    Ok(convert_to_mut_type_ref::<cte_t>(res_ret.slot as usize))
 }
 
 pub fn lookupSlotForCNodeOp(
@@ -214,23 +188,33 @@ pub fn lookupSlotForCNodeOp(
     root: &cap,
     capptr: usize,
     depth: usize,
-) -> lookupSlot_ret_t {
-    lookup_slot_for_cnode_op(isSource, root, capptr, depth)
+    ctx: &mut kernel_state_t,
+) -> Result<&'static mut cte_t, SyscallError> {
+    lookup_slot_for_cnode_op(isSource, root, capptr, depth, ctx)
+}
+
+/// Derive `capability` from `slot` for an invocation, failing like seL4's `deriveCap`.
+pub fn derive_cap(slot: &cte_t, capability: &cap) -> Result<cap, SyscallError> {
+    let dc_ret = slot.derive_cap(capability);
+    if dc_ret.status != exception_t::EXCEPTION_NONE {
+        return Err(match capability.get_tag() {
+            cap_tag::cap_untyped_cap => SyscallError::RevokeFirst,
+            _ => SyscallError::IllegalOperation,
+        });
+    }
+    Ok(dc_ret.capability)
 }
 
 #[inline]
-pub fn ensure_empty_slot(slot: &cte_t) -> exception_t {
+pub fn ensure_empty_slot(slot: &cte_t) -> Result<(), SyscallError> {
     if slot.capability.get_tag() != cap_tag::cap_null_cap {
-        unsafe {
-            current_syscall_error._type = SEL4_DELETE_FIRST;
-        }
-        return exception_t::EXCEPTION_SYSCALL_ERROR;
+        return Err(SyscallError::DeleteFirst);
     }
-    exception_t::EXCEPTION_NONE
+    Ok(())
 }
 
 #[no_mangle]
-pub fn ensureEmptySlot(slot: *mut cte_t) -> exception_t {
+pub fn ensureEmptySlot(slot: *mut cte_t) -> Result<(), SyscallError> {
     unsafe { ensure_empty_slot(&*slot) }
 }
 
@@ -1,9 +1,19 @@
+// With a 44-bit physical address space the kernel window grows from 512 GiB to 16 TiB,
+// so RAM above 1 TiB is kernel memory too. The hypervisor window stays below bit 47,
+// which the cap bitfields sign extend.
 cfg_if::cfg_if! {
-    if #[cfg(feature = "hypervisor")] {
+    if #[cfg(all(feature = "hypervisor", feature = "pa44"))] {
+        pub const PPTR_TOP: usize = 0x00007fffc0000000;
+        pub const PPTR_BASE: usize = 0x0000700000000000;
+        pub const KDEV_BASE: usize = 0x00007fffffe00000;
+    } else if #[cfg(feature = "hypervisor")] {
         pub const PPTR_TOP: usize = 0x000000ffc0000000;
         pub const PPTR_BASE: usize = 0x0000008000000000;
         pub const KDEV_BASE: usize = 0x000000ffffe00000;
-
+    } else if #[cfg(feature = "pa44")] {
+        pub const PPTR_TOP: usize = 0xffffffffc0000000;
+        pub const PPTR_BASE: usize = 0xfffff00000000000;
+        pub const KDEV_BASE: usize = 0xffffffffffe00000;
     } else {
         pub const PPTR_TOP: usize = 0xffffffffc0000000;
         pub const PPTR_BASE: usize = 0xffffff8000000000;
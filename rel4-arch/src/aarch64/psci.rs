@@ -67,6 +67,13 @@ fn psci_call(func: u32, arg0: usize, arg1: usize, arg2: usize) -> Result<(), Psc
     }
 }
 
+/// Suspend the calling core into `power_state` until a wakeup event.
+///
+/// For a standby `power_state` the core keeps its state and returns from the call.
+pub fn cpu_suspend(power_state: u32) -> Result<(), PsciError> {
+    psci_call(PSCI_0_2_FN64_CPU_SUSPEND, power_state as usize, 0, 0)
+}
+
 pub fn shutdown() -> ! {
     log::info!("Shutting down...");
     psci_call(PSCI_0_2_FN_SYSTEM_OFF, 0, 0, 0).ok();
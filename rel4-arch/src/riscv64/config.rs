@@ -1,5 +1,12 @@
-pub const PPTR_TOP: usize = 0xFFFF_FFFF_8000_0000;
-pub const PPTR_BASE: usize = 0xFFFF_FFC0_0000_0000;
+cfg_if::cfg_if! {
+    if #[cfg(feature = "sv48")] {
+        pub const PPTR_TOP: usize = 0xFFFF_FF80_0000_0000;
+        pub const PPTR_BASE: usize = 0xFFFF_8000_0000_0000;
+    } else {
+        pub const PPTR_TOP: usize = 0xFFFF_FFFF_8000_0000;
+        pub const PPTR_BASE: usize = 0xFFFF_FFC0_0000_0000;
+    }
+}
 pub const KDEV_BASE: usize = 0xFFFF_FFFF_C000_0000;
 
 pub const PADDR_BASE: usize = 0x0;
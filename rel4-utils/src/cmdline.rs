@@ -0,0 +1,131 @@
+//! Parsers of the kernel command line and of the log module filter.
+//!
+//! The kernel applies the options, see `boot::cmdline`. Only the parsing lives here, so
+//! that it builds and is tested on the host.
+
+/// The options of a command line separated by spaces, each a key and the value of a
+/// `key=value` pair.
+pub fn options(cmdline: &str) -> impl Iterator<Item = (&str, Option<&str>)> {
+    cmdline
+        .split_ascii_whitespace()
+        .map(|option| match option.split_once('=') {
+            Some((key, value)) => (key, Some(value)),
+            None => (option, None),
+        })
+}
+
+/// A decimal number, or a hexadecimal one with a `0x` prefix.
+pub fn parse_usize(value: &str) -> Option<usize> {
+    match value.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    }
+}
+
+/// The names of the log levels, from `off` to `trace` in the order of `log::LevelFilter`.
+pub const LOG_LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+/// The index of the log level `value` in [`LOG_LEVELS`].
+pub fn parse_level(value: &str) -> Option<usize> {
+    LOG_LEVELS.iter().position(|&level| level == value)
+}
+
+/// Parse the domain schedule `<domain>:<length>[,...]` into `schedule`, and return its
+/// number of entries. None if an entry is invalid, its domain not below `num_domains`,
+/// its length zero, or if there are more entries than `schedule` holds.
+pub fn parse_dom_schedule(
+    value: &str,
+    num_domains: usize,
+    schedule: &mut [(usize, usize)],
+) -> Option<usize> {
+    let mut n = 0;
+    for entry in value.split(',') {
+        let (domain, length) = entry.split_once(':')?;
+        let (domain, length) = (parse_usize(domain)?, parse_usize(length)?);
+        if domain >= num_domains || length == 0 || n == schedule.len() {
+            return None;
+        }
+        schedule[n] = (domain, length);
+        n += 1;
+    }
+    Some(n)
+}
+
+/// Whether the log records of `target` pass the comma separated module names `modules`,
+/// a module matches a whole `::` segment of the target. An empty filter passes everything.
+pub fn module_filter_matches(modules: &str, target: &str) -> bool {
+    modules.is_empty()
+        || target
+            .split("::")
+            .any(|part| modules.split(',').any(|module| module == part))
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+    use super::*;
+    use std::vec::Vec;
+
+    #[test]
+    fn options_split_flags_and_pairs() {
+        let options: Vec<_> = options("  loglevel=warn nofastpath\tlog=a,b  console=").collect();
+        assert_eq!(
+            options,
+            [
+                ("loglevel", Some("warn")),
+                ("nofastpath", None),
+                ("log", Some("a,b")),
+                ("console", Some(""))
+            ]
+        );
+        assert_eq!(super::options("").count(), 0);
+    }
+
+    #[test]
+    fn numbers_are_decimal_or_hex() {
+        assert_eq!(parse_usize("42"), Some(42));
+        assert_eq!(parse_usize("0x9000000"), Some(0x900_0000));
+        assert_eq!(parse_usize("0x"), None);
+        assert_eq!(parse_usize("-1"), None);
+        assert_eq!(parse_usize("0X10"), None);
+    }
+
+    #[test]
+    fn loglevel_takes_the_level_names() {
+        assert_eq!(parse_level("off"), Some(0));
+        assert_eq!(parse_level("warn"), Some(2));
+        assert_eq!(parse_level("trace"), Some(5));
+        assert_eq!(parse_level("WARN"), None);
+        assert_eq!(parse_level("3"), None);
+    }
+
+    #[test]
+    fn dom_schedule_takes_domain_length_pairs() {
+        let mut schedule = [(0, 0); 4];
+        assert_eq!(parse_dom_schedule("0:10,1:0x20", 2, &mut schedule), Some(2));
+        assert_eq!(schedule[..2], [(0, 10), (1, 0x20)]);
+    }
+
+    #[test]
+    fn dom_schedule_refuses_invalid_entries() {
+        let mut schedule = [(0, 0); 2];
+        for value in ["", "0", "0:10,", "0:0", "0:ten", "2:10", "0:1,0:1,0:1"] {
+            let n = parse_dom_schedule(value, 2, &mut schedule);
+            assert!(n.is_none(), "{}", value);
+        }
+        assert_eq!(parse_dom_schedule("0:1,0:1", 2, &mut schedule), Some(2));
+    }
+
+    #[test]
+    fn module_filter_matches_whole_path_segments() {
+        let modules = "sel4_task,fastpath";
+        assert!(module_filter_matches(
+            modules,
+            "rel4_kernel::kernel::fastpath"
+        ));
+        assert!(module_filter_matches(modules, "sel4_task::scheduler"));
+        assert!(!module_filter_matches(modules, "sel4_taskx::scheduler"));
+        assert!(!module_filter_matches(modules, "sel4_ipc::transfer"));
+        assert!(module_filter_matches("", "sel4_ipc::transfer"));
+    }
+}
@@ -0,0 +1,442 @@
+//! A minimal reader of the flattened device tree passed in by the bootloader.
+//!
+//! Only walking the structure block and reading properties is supported, which is
+//! enough for probing the hardware at boot. Every access is bounds checked, a
+//! malformed blob simply ends the walk early.
+use core::str::from_utf8;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+/// The size of the header, the shortest blob.
+pub const FDT_HEADER_SIZE: usize = 40;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+
+#[derive(Clone, Copy)]
+pub struct Fdt<'a> {
+    structs: &'a [u8],
+    strings: &'a [u8],
+    rsvmap: &'a [u8],
+}
+
+/// A node of the device tree, `depth` is 0 for the root node.
+#[derive(Clone, Copy)]
+pub struct FdtNode<'a> {
+    fdt: Fdt<'a>,
+    pub name: &'a str,
+    pub depth: usize,
+    /// Offset of the first token after the node name in the structure block.
+    offset: usize,
+}
+
+#[inline]
+fn be32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset.checked_add(4)?)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[inline]
+fn align4(offset: usize) -> usize {
+    (offset + 3) & !3
+}
+
+/// The NUL terminated string at `offset`.
+fn c_str(data: &[u8], offset: usize) -> Option<&str> {
+    let bytes = data.get(offset..)?;
+    let len = bytes.iter().position(|&b| b == 0)?;
+    from_utf8(&bytes[..len]).ok()
+}
+
+/// The number of `cells` big endian 32-bit cells at `offset`, at most 2.
+pub fn read_cells(data: &[u8], offset: usize, cells: usize) -> Option<u64> {
+    match cells {
+        1 => be32(data, offset).map(u64::from),
+        2 => Some((u64::from(be32(data, offset)?) << 32) | u64::from(be32(data, offset + 4)?)),
+        _ => None,
+    }
+}
+
+/// The strings of a `<stringlist>` property value.
+pub fn prop_strings(value: &[u8]) -> impl Iterator<Item = &str> {
+    value
+        .split(|&b| b == 0)
+        .filter(|s| !s.is_empty())
+        .filter_map(|s| from_utf8(s).ok())
+}
+
+impl<'a> Fdt<'a> {
+    pub fn new(data: &'a [u8]) -> Option<Self> {
+        if be32(data, 0)? != FDT_MAGIC {
+            return None;
+        }
+        let total_size = be32(data, 4)? as usize;
+        let data = data.get(..total_size)?;
+        if total_size < FDT_HEADER_SIZE {
+            return None;
+        }
+        let struct_off = be32(data, 8)? as usize;
+        let strings_off = be32(data, 12)? as usize;
+        let rsvmap_off = be32(data, 16)? as usize;
+        let strings_size = be32(data, 32)? as usize;
+        let struct_size = be32(data, 36)? as usize;
+        Some(Self {
+            structs: data.get(struct_off..struct_off.checked_add(struct_size)?)?,
+            strings: data.get(strings_off..strings_off.checked_add(strings_size)?)?,
+            rsvmap: data.get(rsvmap_off..)?,
+        })
+    }
+
+    /// Call `f` with the address and the size of every `/memreserve/` entry.
+    pub fn for_each_mem_reserve(&self, mut f: impl FnMut(u64, u64)) {
+        let mut offset = 0;
+        while let (Some(address), Some(size)) = (
+            read_cells(self.rsvmap, offset, 2),
+            read_cells(self.rsvmap, offset + 8, 2),
+        ) {
+            if address == 0 && size == 0 {
+                return;
+            }
+            f(address, size);
+            offset += 16;
+        }
+    }
+
+    /// Call `f` on every node in depth first order.
+    pub fn for_each_node(&self, mut f: impl FnMut(&FdtNode<'a>)) {
+        let mut offset = 0;
+        let mut depth = 0;
+        while let Some(token) = be32(self.structs, offset) {
+            offset += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let Some(name) = c_str(self.structs, offset) else {
+                        return;
+                    };
+                    offset = align4(offset + name.len() + 1);
+                    f(&FdtNode {
+                        fdt: *self,
+                        name,
+                        depth,
+                        offset,
+                    });
+                    depth += 1;
+                }
+                FDT_END_NODE => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1;
+                }
+                FDT_PROP => {
+                    let Some(len) = be32(self.structs, offset) else {
+                        return;
+                    };
+                    offset = align4(offset + 8 + len as usize);
+                }
+                FDT_NOP => {}
+                _ => return,
+            }
+        }
+    }
+}
+
+impl<'a> FdtNode<'a> {
+    /// Call `f` with the name and the value of every property of the node.
+    pub fn for_each_property(&self, mut f: impl FnMut(&'a str, &'a [u8])) {
+        let structs = self.fdt.structs;
+        let mut offset = self.offset;
+        loop {
+            match be32(structs, offset) {
+                Some(FDT_PROP) => {
+                    let (Some(len), Some(name_off)) =
+                        (be32(structs, offset + 4), be32(structs, offset + 8))
+                    else {
+                        return;
+                    };
+                    let start = offset + 12;
+                    let Some(value) = structs.get(start..start + len as usize) else {
+                        return;
+                    };
+                    if let Some(name) = c_str(self.fdt.strings, name_off as usize) {
+                        f(name, value);
+                    }
+                    offset = align4(start + len as usize);
+                }
+                Some(FDT_NOP) => offset += 4,
+                _ => return,
+            }
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<&'a [u8]> {
+        let mut found = None;
+        self.for_each_property(|prop, value| {
+            if found.is_none() && prop == name {
+                found = Some(value);
+            }
+        });
+        found
+    }
+
+    /// The first string of the property `name`.
+    pub fn property_str(&self, name: &str) -> Option<&'a str> {
+        prop_strings(self.property(name)?).next()
+    }
+
+    /// The property `name` as a single `<u32>` or `<u64>`.
+    pub fn property_int(&self, name: &str) -> Option<u64> {
+        let value = self.property(name)?;
+        read_cells(value, 0, value.len() / 4)
+    }
+
+    /// Whether the `status` of the node is absent, `okay` or `ok`.
+    pub fn is_enabled(&self) -> bool {
+        matches!(
+            self.property_str("status"),
+            None | Some("okay") | Some("ok")
+        )
+    }
+
+    /// Call `f` with the address and the size of every entry of the `reg` property, with
+    /// the `#address-cells` and `#size-cells` of the parent node.
+    pub fn for_each_reg(
+        &self,
+        address_cells: usize,
+        size_cells: usize,
+        mut f: impl FnMut(u64, u64),
+    ) {
+        let Some(value) = self.property("reg") else {
+            return;
+        };
+        let entry = (address_cells + size_cells) * 4;
+        if entry == 0 {
+            return;
+        }
+        let mut offset = 0;
+        while offset + entry <= value.len() {
+            let address = read_cells(value, offset, address_cells);
+            let size = match size_cells {
+                0 => Some(0),
+                _ => read_cells(value, offset + address_cells * 4, size_cells),
+            };
+            if let (Some(address), Some(size)) = (address, size) {
+                f(address, size);
+            }
+            offset += entry;
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    extern crate std;
+    use super::*;
+    use std::vec::Vec;
+
+    const FDT_END: u32 = 0x9;
+
+    /// Writes a device tree blob with the nodes and properties in the order they are
+    /// added.
+    #[derive(Default)]
+    pub(crate) struct FdtBuilder {
+        rsvmap: Vec<(u64, u64)>,
+        structs: Vec<u8>,
+        strings: Vec<u8>,
+    }
+
+    impl FdtBuilder {
+        pub(crate) fn mem_reserve(&mut self, address: u64, size: u64) -> &mut Self {
+            self.rsvmap.push((address, size));
+            self
+        }
+
+        pub(crate) fn begin_node(&mut self, name: &str) -> &mut Self {
+            self.token(FDT_BEGIN_NODE);
+            self.structs.extend_from_slice(name.as_bytes());
+            self.structs.push(0);
+            self.pad();
+            self
+        }
+
+        pub(crate) fn end_node(&mut self) -> &mut Self {
+            self.token(FDT_END_NODE);
+            self
+        }
+
+        pub(crate) fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+            let name_off = self.strings.len() as u32;
+            self.strings.extend_from_slice(name.as_bytes());
+            self.strings.push(0);
+            self.token(FDT_PROP);
+            self.token(value.len() as u32);
+            self.token(name_off);
+            self.structs.extend_from_slice(value);
+            self.pad();
+            self
+        }
+
+        pub(crate) fn prop_cells(&mut self, name: &str, cells: &[u32]) -> &mut Self {
+            let value: Vec<u8> = cells.iter().flat_map(|cell| cell.to_be_bytes()).collect();
+            self.prop(name, &value)
+        }
+
+        pub(crate) fn prop_str(&mut self, name: &str, value: &str) -> &mut Self {
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.push(0);
+            self.prop(name, &bytes)
+        }
+
+        fn token(&mut self, token: u32) {
+            self.structs.extend_from_slice(&token.to_be_bytes());
+        }
+
+        fn pad(&mut self) {
+            self.structs.resize(align4(self.structs.len()), 0);
+        }
+
+        /// The blob: the header, the `/memreserve/` entries, the structure block and
+        /// the strings.
+        pub(crate) fn build(&self) -> Vec<u8> {
+            let mut structs = self.structs.clone();
+            structs.extend_from_slice(&FDT_END.to_be_bytes());
+            let rsvmap_off = FDT_HEADER_SIZE;
+            let struct_off = rsvmap_off + (self.rsvmap.len() + 1) * 16;
+            let strings_off = struct_off + structs.len();
+            let header = [
+                FDT_MAGIC,
+                (strings_off + self.strings.len()) as u32,
+                struct_off as u32,
+                strings_off as u32,
+                rsvmap_off as u32,
+                17,
+                16,
+                0,
+                self.strings.len() as u32,
+                structs.len() as u32,
+            ];
+            let mut blob: Vec<u8> = header.iter().flat_map(|w| w.to_be_bytes()).collect();
+            for &(address, size) in self.rsvmap.iter().chain(&[(0, 0)]) {
+                blob.extend_from_slice(&address.to_be_bytes());
+                blob.extend_from_slice(&size.to_be_bytes());
+            }
+            blob.extend_from_slice(&structs);
+            blob.extend_from_slice(&self.strings);
+            blob
+        }
+    }
+
+    fn sample() -> Vec<u8> {
+        FdtBuilder::default()
+            .mem_reserve(0x8000_0000, 0x20_0000)
+            .begin_node("")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .begin_node("memory@80000000")
+            .prop_str("device_type", "memory")
+            .prop_cells("reg", &[0, 0x8000_0000, 0, 0x4000_0000, 0x1, 0, 0, 0x1000])
+            .end_node()
+            .begin_node("cpus")
+            .prop_cells("timebase-frequency", &[10_000_000])
+            .begin_node("cpu@0")
+            .prop_str("status", "okay")
+            .end_node()
+            .begin_node("cpu@1")
+            .prop_str("status", "disabled")
+            .end_node()
+            .end_node()
+            .end_node()
+            .build()
+    }
+
+    fn nodes<'a>(fdt: &Fdt<'a>) -> Vec<(&'a str, usize)> {
+        let mut nodes = Vec::new();
+        fdt.for_each_node(|node| nodes.push((node.name, node.depth)));
+        nodes
+    }
+
+    #[test]
+    fn walks_nodes_and_properties() {
+        let blob = sample();
+        let fdt = Fdt::new(&blob).unwrap();
+        assert_eq!(
+            nodes(&fdt),
+            [
+                ("", 0),
+                ("memory@80000000", 1),
+                ("cpus", 1),
+                ("cpu@0", 2),
+                ("cpu@1", 2)
+            ]
+        );
+
+        let mut reserves = Vec::new();
+        fdt.for_each_mem_reserve(|address, size| reserves.push((address, size)));
+        assert_eq!(reserves, [(0x8000_0000, 0x20_0000)]);
+
+        fdt.for_each_node(|node| match node.name {
+            "memory@80000000" => {
+                let mut regs = Vec::new();
+                node.for_each_reg(2, 2, |address, size| regs.push((address, size)));
+                assert_eq!(regs, [(0x8000_0000, 0x4000_0000), (0x1_0000_0000, 0x1000)]);
+                assert_eq!(node.property_str("device_type"), Some("memory"));
+            }
+            "cpus" => assert_eq!(node.property_int("timebase-frequency"), Some(10_000_000)),
+            "cpu@0" => assert!(node.is_enabled()),
+            "cpu@1" => assert!(!node.is_enabled()),
+            _ => {}
+        });
+    }
+
+    #[test]
+    fn rejects_a_truncated_or_foreign_blob() {
+        let blob = sample();
+        assert!(Fdt::new(&blob[..FDT_HEADER_SIZE - 1]).is_none());
+        // the header claims more bytes than there are
+        assert!(Fdt::new(&blob[..blob.len() - 1]).is_none());
+        let mut foreign = blob.clone();
+        foreign[0] = 0;
+        assert!(Fdt::new(&foreign).is_none());
+        // the structure block runs past the end of the blob
+        let mut overrun = blob.clone();
+        overrun[36..40].copy_from_slice(&(blob.len() as u32).to_be_bytes());
+        assert!(Fdt::new(&overrun).is_none());
+    }
+
+    #[test]
+    fn a_cut_structure_block_ends_the_walk() {
+        let blob = FdtBuilder::default()
+            .begin_node("")
+            .begin_node("soc")
+            .end_node()
+            .end_node()
+            .build();
+        // the root node, then the token of "soc" without its name
+        let mut cut = blob.clone();
+        cut[36..40].copy_from_slice(&12u32.to_be_bytes());
+        let fdt = Fdt::new(&cut).unwrap();
+        assert_eq!(nodes(&fdt), [("", 0)]);
+    }
+
+    #[test]
+    fn a_property_longer_than_the_block_is_not_read() {
+        let mut blob = FdtBuilder::default()
+            .begin_node("")
+            .prop_cells("reg", &[1])
+            .begin_node("soc")
+            .end_node()
+            .end_node()
+            .build();
+        let struct_off = be32(&blob, 8).unwrap() as usize;
+        // the length of "reg", after the root node and the FDT_PROP token
+        blob[struct_off + 12..struct_off + 16].copy_from_slice(&0xffff_ff00u32.to_be_bytes());
+        let fdt = Fdt::new(&blob).unwrap();
+        let mut roots = 0;
+        fdt.for_each_node(|node| {
+            assert_eq!(node.property("reg"), None);
+            roots += 1;
+        });
+        assert_eq!(roots, 1);
+    }
+}
@@ -0,0 +1,369 @@
+//! The platform a device tree describes, for discovering the hardware at boot.
+//!
+//! [`DtPlatform::probe`] takes the RAM, the reserved memory, the core count, the UART of
+//! `stdout-path`, the first interrupt controller and the timer frequency out of a
+//! [`Fdt`]. Addresses are taken as they are in `reg`, the `ranges` of the parent buses
+//! are assumed to be identity mappings as on QEMU virt.
+use crate::fdt::{Fdt, FdtNode};
+
+/// Deepest node whose `#address-cells` and `#size-cells` are tracked.
+const MAX_DEPTH: usize = 8;
+
+/// What the device tree says about the platform, with at most `N` memory regions.
+pub struct DtPlatform<const N: usize> {
+    /// RAM not reserved by the firmware, sorted and not overlapping.
+    pub mem: [(usize, usize); N],
+    pub n_mem: usize,
+    /// Whether some memory or reserved regions did not fit in `N`.
+    pub overflow: bool,
+    /// The UART `stdout-path` of `/chosen` points to.
+    pub uart: Option<usize>,
+    /// The first interrupt controller with registers.
+    pub intc: Option<usize>,
+    /// The `timebase-frequency` of `/cpus`, or the `clock-frequency` of a timer.
+    pub timer_freq: Option<usize>,
+    /// The number of enabled `cpu` nodes.
+    pub num_cpus: usize,
+}
+
+/// Remove `[start, end)` from the sorted regions in `mem`, a region it cuts in two takes
+/// one more slot. False if there is no slot left.
+fn carve(mem: &mut [(usize, usize)], n: &mut usize, start: usize, end: usize) -> bool {
+    let mut i = 0;
+    while i < *n {
+        let (m_start, m_end) = mem[i];
+        if end <= m_start || start >= m_end {
+            i += 1;
+            continue;
+        }
+        match (m_start < start, end < m_end) {
+            (true, true) => {
+                if *n == mem.len() {
+                    return false;
+                }
+                mem.copy_within(i + 1..*n, i + 2);
+                mem[i] = (m_start, start);
+                mem[i + 1] = (end, m_end);
+                *n += 1;
+                i += 2;
+            }
+            (true, false) => {
+                mem[i].1 = start;
+                i += 1;
+            }
+            (false, true) => {
+                mem[i].0 = end;
+                i += 1;
+            }
+            (false, false) => {
+                mem.copy_within(i + 1..*n, i);
+                *n -= 1;
+            }
+        }
+    }
+    true
+}
+
+/// Add `[start, end)` to the regions in `mem`, keeping them sorted.
+fn insert(mem: &mut [(usize, usize)], n: &mut usize, start: usize, end: usize) -> bool {
+    if start >= end {
+        return true;
+    }
+    if *n == mem.len() {
+        return false;
+    }
+    let i = mem[..*n].iter().position(|&(s, _)| s > start).unwrap_or(*n);
+    mem.copy_within(i..*n, i + 1);
+    mem[i] = (start, end);
+    *n += 1;
+    true
+}
+
+#[inline]
+fn region(address: u64, size: u64) -> (usize, usize) {
+    let start = address as usize;
+    (start, start.saturating_add(size as usize))
+}
+
+/// The node `stdout-path` of `/chosen` points to, resolved through `/aliases`.
+fn stdout_path<'a>(fdt: &Fdt<'a>) -> Option<&'a str> {
+    let mut chosen = None;
+    let mut aliases: Option<FdtNode<'a>> = None;
+    fdt.for_each_node(|node| {
+        if node.depth == 1 && node.name == "chosen" {
+            chosen = node.property_str("stdout-path");
+        } else if node.depth == 1 && node.name == "aliases" {
+            aliases = Some(*node);
+        }
+    });
+    let path = chosen?.split(':').next()?;
+    if path.starts_with('/') {
+        return Some(path);
+    }
+    aliases?.property_str(path)
+}
+
+/// Whether the node at `depth` with the ancestors `names` is at `path`.
+fn node_at_path(names: &[&str], depth: usize, path: &str) -> bool {
+    let mut parts = path.split('/').filter(|p| !p.is_empty());
+    names[1..=depth]
+        .iter()
+        .all(|name| parts.next() == Some(name))
+        && parts.next().is_none()
+}
+
+impl<const N: usize> DtPlatform<N> {
+    pub fn probe(fdt: &Fdt) -> Self {
+        let mut dt = Self {
+            mem: [(0, 0); N],
+            n_mem: 0,
+            overflow: false,
+            uart: None,
+            intc: None,
+            timer_freq: None,
+            num_cpus: 0,
+        };
+        let mut reserved = [(0, 0); N];
+        let mut n_reserved = 0;
+        let mut overflow = false;
+        fdt.for_each_mem_reserve(|address, size| {
+            let (start, end) = region(address, size);
+            overflow |= !insert(&mut reserved, &mut n_reserved, start, end);
+        });
+
+        let stdout = stdout_path(fdt);
+        // the cells and the name of the node at each depth, for its children
+        let mut cells = [(2, 1); MAX_DEPTH];
+        let mut names = [""; MAX_DEPTH];
+        fdt.for_each_node(|node| {
+            let depth = node.depth;
+            if depth >= MAX_DEPTH {
+                return;
+            }
+            names[depth] = node.name;
+            cells[depth] = (
+                node.property_int("#address-cells").unwrap_or(2) as usize,
+                node.property_int("#size-cells").unwrap_or(1) as usize,
+            );
+            if depth == 0 || !node.is_enabled() {
+                return;
+            }
+            let (address_cells, size_cells) = cells[depth - 1];
+            let first_reg = || {
+                let mut first = None;
+                node.for_each_reg(address_cells, size_cells, |address, _| {
+                    first.get_or_insert(address as usize);
+                });
+                first
+            };
+            if node.property_str("device_type") == Some("memory") {
+                node.for_each_reg(address_cells, size_cells, |address, size| {
+                    let (start, end) = region(address, size);
+                    overflow |= !insert(&mut dt.mem, &mut dt.n_mem, start, end);
+                });
+            } else if depth == 2 && names[1] == "reserved-memory" {
+                node.for_each_reg(address_cells, size_cells, |address, size| {
+                    let (start, end) = region(address, size);
+                    overflow |= !insert(&mut reserved, &mut n_reserved, start, end);
+                });
+            } else if node.property_str("device_type") == Some("cpu") {
+                dt.num_cpus += 1;
+            } else if dt.intc.is_none() && node.property("interrupt-controller").is_some() {
+                dt.intc = first_reg();
+            } else if stdout.is_some_and(|path| node_at_path(&names, depth, path)) {
+                dt.uart = first_reg();
+            }
+            if depth == 1 && node.name == "cpus" {
+                dt.timer_freq = node.property_int("timebase-frequency").map(|f| f as usize);
+            } else if node
+                .property_str("compatible")
+                .is_some_and(|c| c.ends_with("-timer"))
+                && dt.timer_freq.is_none()
+            {
+                dt.timer_freq = node.property_int("clock-frequency").map(|f| f as usize);
+            }
+        });
+
+        for &(start, end) in &reserved[..n_reserved] {
+            overflow |= !carve(&mut dt.mem, &mut dt.n_mem, start, end);
+        }
+        dt.overflow = overflow;
+        dt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdt::tests::FdtBuilder;
+
+    #[test]
+    fn carve_splits_trims_and_drops_regions() {
+        let mut mem = [(0x1000, 0x5000), (0x8000, 0x9000), (0, 0), (0, 0)];
+        let mut n = 2;
+        // in the middle of the first region
+        assert!(carve(&mut mem, &mut n, 0x2000, 0x3000));
+        assert_eq!(
+            mem[..n],
+            [(0x1000, 0x2000), (0x3000, 0x5000), (0x8000, 0x9000)]
+        );
+        // over the end of one region and the start of the next
+        assert!(carve(&mut mem, &mut n, 0x1800, 0x3800));
+        assert_eq!(
+            mem[..n],
+            [(0x1000, 0x1800), (0x3800, 0x5000), (0x8000, 0x9000)]
+        );
+        // a whole region and the gaps around it
+        assert!(carve(&mut mem, &mut n, 0x6000, 0xa000));
+        assert_eq!(mem[..n], [(0x1000, 0x1800), (0x3800, 0x5000)]);
+        // outside of every region
+        assert!(carve(&mut mem, &mut n, 0x5000, 0x6000));
+        assert_eq!(n, 2);
+    }
+
+    #[test]
+    fn carve_fails_to_split_without_a_free_slot() {
+        let mut mem = [(0x1000, 0x5000), (0x8000, 0x9000)];
+        let mut n = 2;
+        assert!(!carve(&mut mem, &mut n, 0x2000, 0x3000));
+        assert_eq!(mem[..n], [(0x1000, 0x5000), (0x8000, 0x9000)]);
+        // trimming needs no slot
+        assert!(carve(&mut mem, &mut n, 0x8000, 0x8800));
+        assert_eq!(mem[..n], [(0x1000, 0x5000), (0x8800, 0x9000)]);
+    }
+
+    #[test]
+    fn insert_keeps_the_regions_sorted() {
+        let mut mem = [(0, 0); 3];
+        let mut n = 0;
+        assert!(insert(&mut mem, &mut n, 0x8000, 0x9000));
+        assert!(insert(&mut mem, &mut n, 0x1000, 0x2000));
+        assert!(insert(&mut mem, &mut n, 0x4000, 0x4000));
+        assert!(insert(&mut mem, &mut n, 0x3000, 0x4000));
+        assert_eq!(
+            mem[..n],
+            [(0x1000, 0x2000), (0x3000, 0x4000), (0x8000, 0x9000)]
+        );
+        assert!(!insert(&mut mem, &mut n, 0xa000, 0xb000));
+        assert_eq!(n, 3);
+    }
+
+    #[test]
+    fn probe_takes_the_ram_without_overlapping_reservations() {
+        let blob = FdtBuilder::default()
+            .mem_reserve(0x8000_0000, 0x20_0000)
+            .begin_node("")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .begin_node("chosen")
+            .prop_str("stdout-path", "serial0:115200n8")
+            .end_node()
+            .begin_node("aliases")
+            .prop_str("serial0", "/soc/serial@10000000")
+            .end_node()
+            .begin_node("memory@80000000")
+            .prop_str("device_type", "memory")
+            .prop_cells("reg", &[0, 0x8000_0000, 0, 0x4000_0000])
+            .end_node()
+            .begin_node("memory@100000000")
+            .prop_str("device_type", "memory")
+            .prop_cells("reg", &[0x1, 0, 0, 0x4000_0000])
+            .end_node()
+            .begin_node("reserved-memory")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .begin_node("sbi@80100000")
+            .prop_cells("reg", &[0, 0x8010_0000, 0, 0x20_0000])
+            .end_node()
+            .begin_node("buffer@120000000")
+            .prop_cells("reg", &[0x1, 0x2000_0000, 0, 0x1000])
+            .end_node()
+            .end_node()
+            .begin_node("cpus")
+            .prop_cells("timebase-frequency", &[10_000_000])
+            .begin_node("cpu@0")
+            .prop_str("device_type", "cpu")
+            .end_node()
+            .begin_node("cpu@1")
+            .prop_str("device_type", "cpu")
+            .prop_str("status", "disabled")
+            .end_node()
+            .end_node()
+            .begin_node("soc")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .begin_node("serial@10000000")
+            .prop_cells("reg", &[0, 0x1000_0000, 0, 0x100])
+            .end_node()
+            .end_node()
+            .end_node()
+            .build();
+        let dt = DtPlatform::<4>::probe(&Fdt::new(&blob).unwrap());
+        assert_eq!(
+            dt.mem[..dt.n_mem],
+            [
+                (0x8030_0000, 0xc000_0000),
+                (0x1_0000_0000, 0x1_2000_0000),
+                (0x1_2000_1000, 0x1_4000_0000)
+            ]
+        );
+        assert_eq!(dt.num_cpus, 1);
+        assert_eq!(dt.timer_freq, Some(10_000_000));
+        assert_eq!(dt.uart, Some(0x1000_0000));
+        assert_eq!(dt.intc, None);
+        assert!(!dt.overflow);
+    }
+
+    #[test]
+    fn probe_takes_the_first_interrupt_controller_and_the_timer_clock() {
+        let blob = FdtBuilder::default()
+            .begin_node("")
+            .prop_cells("#address-cells", &[2])
+            .prop_cells("#size-cells", &[2])
+            .begin_node("chosen")
+            .prop_str("stdout-path", "/pl011@9000000")
+            .end_node()
+            .begin_node("intc@8000000")
+            .prop("interrupt-controller", &[])
+            .prop_cells(
+                "reg",
+                &[0, 0x800_0000, 0, 0x1_0000, 0, 0x801_0000, 0, 0x1_0000],
+            )
+            .begin_node("its@8080000")
+            .prop("interrupt-controller", &[])
+            .prop_cells("reg", &[0, 0x808_0000, 0, 0x2_0000])
+            .end_node()
+            .end_node()
+            .begin_node("pl011@9000000")
+            .prop_cells("reg", &[0, 0x900_0000, 0, 0x1000])
+            .end_node()
+            .begin_node("timer")
+            .prop_str("compatible", "arm,armv8-timer")
+            .prop_cells("clock-frequency", &[62_500_000])
+            .end_node()
+            .end_node()
+            .build();
+        let dt = DtPlatform::<4>::probe(&Fdt::new(&blob).unwrap());
+        assert_eq!(dt.intc, Some(0x800_0000));
+        assert_eq!(dt.uart, Some(0x900_0000));
+        assert_eq!(dt.timer_freq, Some(62_500_000));
+        assert_eq!((dt.n_mem, dt.num_cpus), (0, 0));
+    }
+
+    #[test]
+    fn probe_reports_regions_that_do_not_fit() {
+        let blob = FdtBuilder::default()
+            .begin_node("")
+            .prop_cells("#address-cells", &[1])
+            .prop_cells("#size-cells", &[1])
+            .begin_node("memory@0")
+            .prop_str("device_type", "memory")
+            .prop_cells("reg", &[0, 0x1000, 0x2000, 0x1000, 0x4000, 0x1000])
+            .end_node()
+            .end_node()
+            .build();
+        let dt = DtPlatform::<2>::probe(&Fdt::new(&blob).unwrap());
+        assert_eq!(dt.mem[..dt.n_mem], [(0, 0x1000), (0x2000, 0x3000)]);
+        assert!(dt.overflow);
+    }
+}
@@ -0,0 +1,132 @@
+//! Declarative decoding of invocation arguments.
+//!
+//! [`invocation_args!`](crate::invocation_args) declares the arguments of an invocation as
+//! a struct and generates its `decode`. Each field type says how many message registers
+//! and extra caps it takes and how it is validated, so the decoders only describe the
+//! message and never index it by hand.
+//!
+//! The kernel implements [`ArgSource`] for the message of the current invocation, and
+//! [`Arg`] for its argument types, which is where the error of a bad argument is chosen.
+
+/// The message an invocation decodes its arguments from.
+pub trait ArgSource {
+    /// The error of a failed decode.
+    type Error;
+    /// An extra cap of the message.
+    type Slot;
+
+    /// The number of message registers in the message.
+    fn length(&self) -> usize;
+    /// The message register `index`, it must be less than [`ArgSource::length`].
+    fn mr(&self, index: usize) -> usize;
+    /// The extra cap `index`, `None` if the message has no such cap.
+    fn extra_cap(&self, index: usize) -> Option<Self::Slot>;
+    /// The error of a message without all the arguments of the invocation.
+    fn truncated(&self) -> Self::Error;
+}
+
+/// An argument decoded from an [`ArgSource`].
+pub trait Arg<S: ArgSource>: Sized {
+    /// The message registers the argument takes.
+    const MRS: usize = 0;
+    /// The extra caps the argument takes.
+    const CAPS: usize = 0;
+
+    /// Decode the argument from the message registers starting at `mr` and the extra
+    /// caps starting at `cap`. The message is known to hold all of them.
+    fn decode(source: &S, mr: usize, cap: usize) -> Result<Self, S::Error>;
+}
+
+impl<S: ArgSource> Arg<S> for usize {
+    const MRS: usize = 1;
+
+    fn decode(source: &S, mr: usize, _cap: usize) -> Result<Self, S::Error> {
+        Ok(source.mr(mr))
+    }
+}
+
+impl<S: ArgSource> Arg<S> for bool {
+    const MRS: usize = 1;
+
+    fn decode(source: &S, mr: usize, _cap: usize) -> Result<Self, S::Error> {
+        Ok(source.mr(mr) != 0)
+    }
+}
+
+/// The message registers of arguments decoded before, for an invocation that checks
+/// its message in steps like seL4 does. `Skip<2>` takes two message registers and
+/// decodes nothing.
+pub struct Skip<const N: usize>;
+
+impl<S: ArgSource, const N: usize> Arg<S> for Skip<N> {
+    const MRS: usize = N;
+
+    fn decode(_source: &S, _mr: usize, _cap: usize) -> Result<Self, S::Error> {
+        Ok(Self)
+    }
+}
+
+/// Declare the arguments of an invocation.
+///
+/// The fields take the message registers and the extra caps in declaration order.
+/// `decode` fails with [`ArgSource::truncated`] if the message is too short for all of
+/// them, which is what seL4 checks first, and then decodes the fields in order, so the
+/// first invalid field is the one reported.
+///
+/// ```ignore
+/// invocation_args! {
+///     /// The arguments of `TCBSetPriority`.
+///     pub struct SetPriorityArgs {
+///         pub prio: Priority,
+///         pub auth: ThreadCap,
+///     }
+/// }
+///
+/// let args = SetPriorityArgs::decode(&message)?;
+/// ```
+#[macro_export]
+macro_rules! invocation_args {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[$field_meta:meta])*
+                $field_vis:vis $field:ident: $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $name {
+            /// Decode the arguments from `source`.
+            #[allow(unused_assignments, unused_mut)]
+            $vis fn decode<S>(source: &S) -> Result<Self, S::Error>
+            where
+                S: $crate::invocation::ArgSource,
+                $($ty: $crate::invocation::Arg<S>,)*
+            {
+                use $crate::invocation::Arg;
+
+                let mrs = 0 $(+ <$ty as Arg<S>>::MRS)*;
+                let caps = 0 $(+ <$ty as Arg<S>>::CAPS)*;
+                if source.length() < mrs || (0..caps).any(|i| source.extra_cap(i).is_none()) {
+                    return Err(source.truncated());
+                }
+
+                let (mut mr, mut cap) = (0, 0);
+                $(
+                    let $field = <$ty as Arg<S>>::decode(source, mr, cap)?;
+                    mr += <$ty as Arg<S>>::MRS;
+                    cap += <$ty as Arg<S>>::CAPS;
+                )*
+                Ok(Self { $($field),* })
+            }
+        }
+    };
+}
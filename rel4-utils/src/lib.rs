@@ -2,5 +2,10 @@
 #![deny(warnings)]
 
 pub mod aligned;
+pub mod cmdline;
+pub mod fdt;
+pub mod fdt_platform;
+pub mod invocation;
 pub mod macros;
 pub mod no_lock;
+pub mod spin_lock;
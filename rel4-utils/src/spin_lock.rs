@@ -0,0 +1,76 @@
+//! This module provide a raw spin lock without guard.
+//!
+//! Kernel paths such as the syscall entry hold a lock until `restore_user_context`,
+//! which never returns, so the lock has to be released explicitly.
+use core::hint::spin_loop;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// A test-and-test-and-set spin lock.
+#[repr(align(64))]
+pub struct RawSpinLock {
+    locked: AtomicBool,
+}
+
+impl RawSpinLock {
+    pub const fn new() -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+        }
+    }
+
+    /// Spin until the lock is acquired.
+    #[inline]
+    pub fn lock(&self) {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            while self.locked.load(Ordering::Relaxed) {
+                spin_loop();
+            }
+        }
+    }
+
+    /// Try to acquire the lock once, return `true` if succeed.
+    #[inline]
+    pub fn try_lock(&self) -> bool {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Release the lock, the caller must be the owner.
+    #[inline]
+    pub fn unlock(&self) {
+        self.locked.store(false, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.locked.load(Ordering::Relaxed)
+    }
+
+    /// Acquire the lock and release it when the guard is dropped.
+    #[inline]
+    pub fn guard(&self) -> RawSpinLockGuard<'_> {
+        self.lock();
+        RawSpinLockGuard { lock: self }
+    }
+}
+
+impl Default for RawSpinLock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct RawSpinLockGuard<'a> {
+    lock: &'a RawSpinLock,
+}
+
+impl Drop for RawSpinLockGuard<'_> {
+    fn drop(&mut self) {
+        self.lock.unlock();
+    }
+}
@@ -4,14 +4,25 @@ use std::path::PathBuf;
 
 use tera::{Context, Tera};
 
-pub fn linker_gen(platform: &str) -> PathBuf {
+/// Generate the linker script of `platform`. A `KERNEL_VMEM_OFFSET=<offset>` in `defs`
+/// overrides the `memory.vmem_offset` of the platform, for a configuration that moves
+/// the kernel window.
+pub fn linker_gen(platform: &str, defs: &Vec<String>) -> PathBuf {
     let yaml_cfg = crate::utils::get_root().join(format!("cfg/platform/{}.yml", platform));
     let kstart =
         crate::utils::get_int_from_yaml(&yaml_cfg.to_str().unwrap(), "memory.kernel_start")
             .expect("memory.kernel_start not set");
-    let vmem_offset =
-        crate::utils::get_int_from_yaml(&yaml_cfg.to_str().unwrap(), "memory.vmem_offset")
-            .expect("memory.vmem_offset not set");
+    let vmem_offset = defs
+        .iter()
+        .find_map(|d| d.strip_prefix("KERNEL_VMEM_OFFSET="))
+        .map(|v| {
+            usize::from_str_radix(v.trim_start_matches("0x"), 16)
+                .expect("KERNEL_VMEM_OFFSET is not a hex number")
+        })
+        .or_else(|| {
+            crate::utils::get_int_from_yaml(&yaml_cfg.to_str().unwrap(), "memory.vmem_offset")
+        })
+        .expect("memory.vmem_offset not set");
     let arch = crate::utils::get_value_from_yaml(&yaml_cfg.to_str().unwrap(), "cpu.arch")
         .expect("cpu.arch not set");
 
@@ -50,6 +61,24 @@ pub fn platform_gen(platform: &str) -> PathBuf {
         .expect("cpu.freq not set");
     let arch = crate::utils::get_value_from_yaml(&yaml_cfg.to_str().unwrap(), "cpu.arch")
         .expect("cpu.arch not set");
+    // riscv only, the PLIC window must also be listed in device.device_region
+    let plic_max_irq =
+        crate::utils::get_int_from_yaml(&yaml_cfg.to_str().unwrap(), "device.plic.max_irq")
+            .unwrap_or(0);
+    let plic_pptr_offset =
+        crate::utils::get_int_from_yaml(&yaml_cfg.to_str().unwrap(), "device.plic.pptr_offset")
+            .unwrap_or(0);
+    // riscv only, the IMSIC has identities 1..=num_ids, the top two are the IPIs
+    let aia_max_irq =
+        crate::utils::get_int_from_yaml(&yaml_cfg.to_str().unwrap(), "device.aia.imsic_num_ids")
+            .unwrap_or(0)
+            .saturating_sub(2);
+    let aia_keys = [
+        "aplic_max_irq",
+        "aplic_pptr_offset",
+        "imsic_pptr_offset",
+        "imsic_paddr",
+    ];
     let template_path = crate::utils::get_root().join("template/*.rs");
     let mut tera = Tera::new(template_path.to_str().unwrap()).expect("Failed to initialize Tera");
     tera.register_filter("hex", crate::template::format_hex);
@@ -62,6 +91,17 @@ pub fn platform_gen(platform: &str) -> PathBuf {
     context.insert("timer_settings", &timer_settings);
     context.insert("freq", &freq);
     context.insert("arch", &arch);
+    context.insert("plic_max_irq", &plic_max_irq);
+    context.insert("plic_pptr_offset", &plic_pptr_offset);
+    context.insert("aia_max_irq", &aia_max_irq);
+    for key in aia_keys {
+        let value = crate::utils::get_int_from_yaml(
+            &yaml_cfg.to_str().unwrap(),
+            &format!("device.aia.{}", key),
+        )
+        .unwrap_or(0);
+        context.insert(key, &value);
+    }
 
     let rendered = tera
         .render("platform_gen.rs", &context)
@@ -34,7 +34,8 @@ pub(crate) fn get_value_from_yaml(file_path: &str, key: &str) -> Option<String>
     current_value.as_str().map(|s| s.to_string())
 }
 
-pub(crate) fn get_int_from_yaml(file_path: &str, key: &str) -> Option<usize> {
+/// Look up the node at the dotted `key`, e.g. `device.plic.max_irq`.
+fn get_node_from_yaml(file_path: &str, key: &str) -> Option<Value> {
     let mut file = File::open(file_path).expect(file_path);
     let mut contents = String::new();
     file.read_to_string(&mut contents)
@@ -48,7 +49,17 @@ pub(crate) fn get_int_from_yaml(file_path: &str, key: &str) -> Option<usize> {
         current_value = current_value.get(k)?;
     }
 
-    current_value.as_u64().map(|n| n as usize)
+    Some(current_value.clone())
+}
+
+pub(crate) fn get_int_from_yaml(file_path: &str, key: &str) -> Option<usize> {
+    get_node_from_yaml(file_path, key)?
+        .as_u64()
+        .map(|n| n as usize)
+}
+
+pub(crate) fn get_bool_from_yaml(file_path: &str, key: &str) -> Option<bool> {
+    get_node_from_yaml(file_path, key)?.as_bool()
 }
 
 pub(crate) fn get_all_defs(file_path: &str) -> BTreeMap<String, Option<String>> {
@@ -14,17 +14,27 @@ pub const {{ param.label }}: usize = {{ param.value }};
 {% if arch == "riscv" %}
 #[cfg(feature = "enable_smp")]
 pub const PLIC_IRQ_OFFSET: usize = 0;
-pub const PLIC_MAX_IRQ: usize = 0;
+/* PLIC sources are 1..=PLIC_MAX_IRQ, no PLIC if 0 */
+pub const PLIC_MAX_IRQ: usize = {{ plic_max_irq }};
+pub const PLIC_PPTR: usize = KDEV_BASE + {{ plic_pptr_offset | hex }};
+
+/* AIA: APLIC sources 1..=APLIC_MAX_IRQ, MSIs up to AIA_MAX_IRQ, the IPIs use the two
+   IMSIC identities above, no AIA if 0 */
+pub const AIA_MAX_IRQ: usize = {{ aia_max_irq }};
+pub const APLIC_MAX_IRQ: usize = {{ aplic_max_irq }};
+pub const APLIC_PPTR: usize = KDEV_BASE + {{ aplic_pptr_offset | hex }};
+pub const IMSIC_PPTR: usize = KDEV_BASE + {{ imsic_pptr_offset | hex }};
+pub const IMSIC_PADDR: usize = {{ imsic_paddr | hex }};
 
 #[cfg(feature = "enable_smp")]
-pub const INTERRUPT_IPI_0: usize = 1;
+pub const INTERRUPT_IPI_0: usize = PLIC_MAX_IRQ + AIA_MAX_IRQ + 1;
 #[cfg(feature = "enable_smp")]
-pub const INTERRUPT_IPI_1: usize = 2;
+pub const INTERRUPT_IPI_1: usize = PLIC_MAX_IRQ + AIA_MAX_IRQ + 2;
 #[cfg(feature = "enable_smp")]
-pub const KERNEL_TIMER_IRQ: usize = 3;
+pub const KERNEL_TIMER_IRQ: usize = PLIC_MAX_IRQ + AIA_MAX_IRQ + 3;
 
 #[cfg(all(not(feature = "enable_smp")))]
-pub const KERNEL_TIMER_IRQ: usize = 1;
+pub const KERNEL_TIMER_IRQ: usize = PLIC_MAX_IRQ + AIA_MAX_IRQ + 1;
 pub const MAX_IRQ: usize = KERNEL_TIMER_IRQ;
 pub const IRQ_INVALID: usize = 0;
 {% endif %}
@@ -57,6 +67,21 @@ pub static mut kernel_device_frames: [kernel_frame_t; {{ device_regions | length
 {% endfor %}
 ]; 
 
+/* the kernel devices with the desc "uart", the consoles `console=<paddr>` may pick */
+{% set uarts = device_regions | filter(attribute="desc", value="uart") %}
+pub const KERNEL_UART_PADDRS: [usize; {{ uarts | length }}] = [
+{% for region in uarts %}
+    {{ region.paddr | hex }},
+{% endfor %}
+];
+
+/* the kernel devices of the GIC, the desc starts with "gic", the distributor first */
+pub const KERNEL_INTC_PADDRS: &[usize] = &[
+{% for region in device_regions %}{% if region.desc is starting_with("gic") %}
+    {{ region.paddr | hex }},
+{% endif %}{% endfor %}
+];
+
 /* PHYSICAL MEMORY */
 #[cfg(feature = "build_binary")]
 #[link_section = ".boot.text"]
@@ -48,8 +48,6 @@ fn main() {
         // TODO: enable fpu fault handler if build aarch64, maybe need provide by build command
         common_defs.push("have_fpu=true".to_string());
     }
-    // TODO: pt levels should config by config file
-    common_defs.push("PT_LEVELS=3".to_string());
 
     rel4_config::generator::config_gen(&platform, &common_defs);
     let out_inc_dir = env::var("OUT_DIR").unwrap();
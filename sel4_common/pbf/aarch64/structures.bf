@@ -153,6 +153,9 @@ tagged_union cap capType {
     tag sched_context_cap           22
     tag sched_control_cap           24
 #endif
+#if defined(CONFIG_ENABLE_SMP_SUPPORT) && !defined(CONFIG_KERNEL_MCS)
+    tag cpu_control_cap             26
+#endif
 
     -- 5-bit tag arch caps
     tag frame_cap                   1
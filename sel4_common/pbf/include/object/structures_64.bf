@@ -198,6 +198,16 @@ block sched_control_cap {
 }
 #endif
 
+#if defined(CONFIG_ENABLE_SMP_SUPPORT) && !defined(CONFIG_KERNEL_MCS)
+-- Cap to take cores offline and bring them back
+block cpu_control_cap {
+    padding 64
+
+    field capType 5
+    padding 59
+}
+#endif
+
 ---- Arch-independent object types
 
 -- Endpoint: size = 16 bytes
@@ -12,8 +12,11 @@
 #if (CONFIG_PT_LEVELS == 3)
 base 64(39,1)
 #define BF_CANONICAL_RANGE 39
+#elif (CONFIG_PT_LEVELS == 4)
+base 64(48,1)
+#define BF_CANONICAL_RANGE 48
 #else
-#error "Only PT_LEVELS == 3 is currently supported on RISCV64"
+#error "Only PT_LEVELS == 3 or 4 is currently supported on RISCV64"
 #endif
 
 -- Including the common structures.bf is neccessary because
@@ -24,27 +27,61 @@ base 64(39,1)
 -- frames
 block frame_cap {
     field       capFMappedASID      16
+#if BF_CANONICAL_RANGE == 48
+    field_high  capFBasePtr         48
+#else
     field_high  capFBasePtr         39
     padding                         9
+#endif
 
     field       capType             5
+#ifdef CONFIG_RISCV_EXT_SVNAPOT
+    -- the 64KiB NAPOT page needs a third bit
+    field       capFSize            3
+#else
     field       capFSize            2
+#endif
     field       capFVMRights        2
     field       capFIsDevice        1
+#ifdef CONFIG_RISCV_EXT_SVNAPOT
+#if BF_CANONICAL_RANGE == 48
+    padding                         5
+    field_high  capFMappedAddress   48
+#else
+    padding                         14
+    field_high  capFMappedAddress   39
+#endif
+#else
+#if BF_CANONICAL_RANGE == 48
+    padding                         6
+    field_high  capFMappedAddress   48
+#else
     padding                         15
     field_high  capFMappedAddress   39
+#endif
+#endif
 }
 
 -- N-level page table
 block page_table_cap {
     field       capPTMappedASID     16
+#if BF_CANONICAL_RANGE == 48
+    field_high  capPTBasePtr        48
+#else
     field_high  capPTBasePtr        39
     padding                         9
+#endif
 
     field       capType             5
+#if BF_CANONICAL_RANGE == 48
+    padding                         10
+    field       capPTIsMapped       1
+    field_high  capPTMappedAddress  48
+#else
     padding                         19
     field       capPTIsMapped       1
     field_high  capPTMappedAddress  39
+#endif
 }
 
 -- Cap to the table of 2^6 ASID pools
@@ -83,6 +120,9 @@ tagged_union cap capType {
     tag sched_context_cap   22
     tag sched_control_cap   24
 #endif
+#if defined(CONFIG_ENABLE_SMP_SUPPORT) && !defined(CONFIG_KERNEL_MCS)
+    tag cpu_control_cap     26
+#endif
 
     -- 5-bit tag arch caps
     tag frame_cap           1
@@ -108,7 +148,9 @@ block VMFault {
 
 block vm_attributes {
     padding 32
-    padding 31
+    padding 29
+    field riscvIO            1
+    field riscvNonCacheable  1
     field riscvExecuteNever  1
 }
 
@@ -124,7 +166,9 @@ block vm_attributes {
 -- because the vspace source code is the same for both architectures and doing
 -- the bit shifting manually only for 32-bit and not 64-bit is counter-intuitive.
 block pte {
-    padding                10
+    field napot            1
+    field pbmt             2
+    padding                7
     field ppn              44
     field sw               2
     field dirty            1
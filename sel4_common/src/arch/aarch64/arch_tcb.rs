@@ -9,6 +9,28 @@ pub struct FPUState {
     vregs: [usize; 64],
     fpsr: u32,
     fpcr: u32,
+    /// The SVE vector length in bytes, 0 if the thread does not use SVE. The Z, P and
+    /// FFR registers live in the frame of the `TCB_SVE_STATE` slot.
+    #[cfg(feature = "arm_sve")]
+    sve_vl: u32,
+}
+
+/// The largest SVE vector length in bytes allowed by the architecture.
+#[cfg(feature = "arm_sve")]
+pub const SVE_MAX_VL: usize = 256;
+
+/// The bytes needed to save Z0-Z31, P0-P15 and FFR with the vector length `vl`.
+#[cfg(feature = "arm_sve")]
+pub const fn sve_state_size(vl: usize) -> usize {
+    32 * vl + 17 * (vl / 8)
+}
+
+#[cfg(feature = "arm_sve")]
+impl FPUState {
+    #[inline]
+    pub fn sve_vl(&self) -> usize {
+        self.sve_vl as usize
+    }
 }
 
 /// This is `arch_tcb_t` in the sel4_c_impl.
@@ -18,6 +40,9 @@ pub struct ArchTCB {
     pub(in crate::arch) registers: [usize; CONTEXT_REG_NUM],
     #[cfg(feature = "have_fpu")]
     pub(in crate::arch) fpu: FPUState,
+    /// The VCPU bound to this thread, 0 if there is none.
+    #[cfg(feature = "hypervisor")]
+    pub vcpu: usize,
 }
 
 /// Implements the Default for the `ArchTCB`
@@ -32,7 +57,11 @@ impl Default for ArchTCB {
                 vregs: [0; 64],
                 fpsr: 0,
                 fpcr: 0,
+                #[cfg(feature = "arm_sve")]
+                sve_vl: 0,
             },
+            #[cfg(feature = "hypervisor")]
+            vcpu: 0,
         }
     }
 }
@@ -50,6 +79,23 @@ impl ArchTCB {
         }
     }
 
+    /// Sanitise the saved SPSR before the thread returns to it. Only a thread with a VCPU
+    /// may run at EL1, any other mode falls back to the default EL0 state.
+    #[cfg(feature = "hypervisor")]
+    pub fn sanitise_spsr(&mut self, has_vcpu: bool) {
+        let spsr = self.registers[SPSR_EL1];
+        let valid = match spsr & 0x1f {
+            // EL0t
+            0 => true,
+            // EL1t, EL1h
+            4 | 5 => has_vcpu,
+            _ => false,
+        };
+        if !valid {
+            self.registers[SPSR_EL1] = (spsr & 0xf000_0000) | (1 << 6) | (1 << 8);
+        }
+    }
+
     /// Save TLS(Thread local Storage) registers
     #[inline]
     pub fn save_thread_local(&mut self) {
@@ -67,4 +113,16 @@ impl ArchTCB {
     pub fn fpu_state_ptr(&mut self) -> *const FPUState {
         &self.fpu as *const FPUState
     }
+
+    #[cfg(feature = "arm_sve")]
+    #[inline]
+    pub fn sve_vl(&self) -> usize {
+        self.fpu.sve_vl()
+    }
+
+    #[cfg(feature = "arm_sve")]
+    #[inline]
+    pub fn set_sve_vl(&mut self, vl: usize) {
+        self.fpu.sve_vl = vl as u32;
+    }
 }
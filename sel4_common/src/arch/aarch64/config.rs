@@ -14,3 +14,25 @@ pub const IRQ_REMOTE_CALL_IPI: usize = 0;
 pub const IRQ_RESCHEDULE_IPI: usize = 1;
 
 pub const MAX_UNTYPED_BITS: usize = 47;
+
+// The bits of the physical address space, ARM_PA_SIZE_BITS_40 or ARM_PA_SIZE_BITS_44
+#[cfg(not(feature = "arm_pa_size_bits_44"))]
+pub const PA_SIZE_BITS: usize = 40;
+#[cfg(feature = "arm_pa_size_bits_44")]
+pub const PA_SIZE_BITS: usize = 44;
+const _: () = assert!(
+    crate::sel4_config::CONFIG_PADDR_USER_DEVICE_TOP == bit!(PA_SIZE_BITS),
+    "PADDR_USER_DEVICE_TOP must match the configured physical address size"
+);
+
+// hypervisor 模式下用户地址空间是 stage-2 翻译的 IPA 空间.
+// 40 位 IPA 从 level 1 开始查找 (对应 AARCH64_VSPACE_S2_START_L1),
+// 此时根页表由两个相邻的页表拼接而成, 共 10 位索引; 其余情况从 level 0 开始, 共 4 级.
+pub const AARCH64_VSPACE_S2_START_L1: bool = cfg!(all(
+    feature = "hypervisor",
+    not(feature = "arm_pa_size_bits_44")
+));
+/// 用户态页表的级数
+pub const UPT_LEVELS: usize = if AARCH64_VSPACE_S2_START_L1 { 3 } else { 4 };
+/// 用户态根页表的索引位数
+pub const VSPACE_INDEX_BITS: usize = if AARCH64_VSPACE_S2_START_L1 { 10 } else { 9 };
@@ -36,7 +36,13 @@ pub enum MessageLabel {
     IRQAckIRQ,
     IRQSetIRQHandler,
     IRQClearIRQHandler,
+    #[cfg(feature = "enable_smp")]
+    IRQSetTargetCore,
     DomainSetSet,
+    #[cfg(feature = "cpu_hotplug")]
+    CPUControlOffline,
+    #[cfg(feature = "cpu_hotplug")]
+    CPUControlOnline,
     #[cfg(feature = "kernel_mcs")]
     SchedControlConfigureFlags,
     #[cfg(feature = "kernel_mcs")]
@@ -65,9 +71,45 @@ pub enum MessageLabel {
     ARMPageGetAddress,
     ARMASIDControlMakePool,
     ARMASIDPoolAssign,
+    #[cfg(feature = "hypervisor")]
+    ARMVCPUSetTCB,
+    #[cfg(feature = "hypervisor")]
+    ARMVCPUInjectIRQ,
+    #[cfg(feature = "hypervisor")]
+    ARMVCPUReadReg,
+    #[cfg(feature = "hypervisor")]
+    ARMVCPUWriteReg,
+    #[cfg(feature = "hypervisor")]
+    ARMVCPUAckVPPI,
     ARMIRQIssueIRQHandlerTrigger,
     #[cfg(feature = "enable_smp")]
     ARMIRQIssueIRQHandlerTriggerCore,
+    #[cfg(feature = "arm_smmu")]
+    ARMSIDIssueSIDManager,
+    #[cfg(feature = "arm_smmu")]
+    ARMSIDGetFault,
+    #[cfg(feature = "arm_smmu")]
+    ARMSIDClearFault,
+    #[cfg(feature = "arm_smmu")]
+    ARMSIDBindCB,
+    #[cfg(feature = "arm_smmu")]
+    ARMSIDUnbindCB,
+    #[cfg(feature = "arm_smmu")]
+    ARMCBIssueCBManager,
+    #[cfg(feature = "arm_smmu")]
+    ARMCBTLBInvalidateAll,
+    #[cfg(feature = "arm_smmu")]
+    ARMCBAssignVspace,
+    #[cfg(feature = "arm_smmu")]
+    ARMCBUnassignVspace,
+    #[cfg(feature = "arm_smmu")]
+    ARMCBTLBInvalidate,
+    #[cfg(feature = "arm_smmu")]
+    ARMCBGetFault,
+    #[cfg(feature = "arm_smmu")]
+    ARMCBClearFault,
+    #[cfg(feature = "arm_sve")]
+    ARMTCBSetSVE,
     nArchInvocationLabels,
 }
 #[cfg(not(feature = "kernel_mcs"))]
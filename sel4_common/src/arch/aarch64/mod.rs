@@ -20,6 +20,52 @@ pub use smp::*;
 
 pub use rel4_arch::aarch64::psci::shutdown;
 
+/// The standby state of the core power level, the core keeps its state and wakes up on
+/// any pending interrupt.
+const PSCI_POWER_STATE_STANDBY: u32 = 0;
+/// The StateType bit of a PSCI `power_state`, set for a powerdown state.
+const PSCI_POWER_STATE_POWERDOWN: usize = 1 << 16;
+
+const _: () = assert!(
+    crate::sel4_config::CONFIG_IDLE_SUSPEND_STATE & PSCI_POWER_STATE_POWERDOWN == 0,
+    "the idle state must keep the core state"
+);
+
+/// Put this core into PSCI `CPU_SUSPEND` standby until an interrupt is pending.
+///
+/// Falls back to `wfi` when the firmware refuses the suspend.
+pub fn cpu_standby() {
+    if rel4_arch::aarch64::psci::cpu_suspend(PSCI_POWER_STATE_STANDBY).is_err() {
+        unsafe { core::arch::asm!("wfi") };
+    }
+}
+
+/// Put this core into the deeper idle state `CONFIG_IDLE_SUSPEND_STATE` of the platform
+/// until an interrupt is pending. False if the firmware refuses the state.
+pub fn cpu_idle_suspend() -> bool {
+    use crate::sel4_config::CONFIG_IDLE_SUSPEND_STATE;
+    rel4_arch::aarch64::psci::cpu_suspend(CONFIG_IDLE_SUSPEND_STATE as u32).is_ok()
+}
+
 pub fn get_time() -> usize {
     todo!("get_time")
 }
+
+/// Start the PMU cycle counter of this core, it is not counting out of reset.
+pub fn enable_cycle_counter() {
+    unsafe {
+        let pmcr: usize;
+        core::arch::asm!("mrs {}, pmcr_el0", out(reg) pmcr);
+        // PMCR_EL0.E enables the counters, PMCNTENSET_EL0.C the cycle counter
+        core::arch::asm!("msr pmcr_el0, {}", in(reg) pmcr | bit!(0));
+        core::arch::asm!("msr pmcntenset_el0, {}", in(reg) bit!(31));
+        core::arch::asm!("isb");
+    }
+}
+
+/// The cycle counter of this core, see [enable_cycle_counter].
+pub fn read_cycles() -> usize {
+    let cycles: usize;
+    unsafe { core::arch::asm!("mrs {}, pmccntr_el0", out(reg) cycles) };
+    cycles
+}
@@ -3,43 +3,41 @@ use crate::platform::CONFIGURE_KERNEL_WCET;
 #[cfg(feature = "kernel_mcs")]
 use crate::{
     platform::{
-        time_def::{
-            ticks_t, time_t, KHZ_IN_MHZ, TIMER_CLOCK_HZ, TIMER_CLOCK_KHZ, TIMER_CLOCK_MHZ, USE_KHZ,
-        },
+        time_def::{ticks_t, time_t, timer_clock_khz, timer_clock_mhz, use_khz, KHZ_IN_MHZ},
         TIMER_OVERHEAD_TICKS, TIMER_PRECISION,
     },
     sel4_config::UINT64_MAX,
 };
 #[cfg(feature = "kernel_mcs")]
 pub fn get_max_ticks_to_us() -> ticks_t {
-    if USE_KHZ {
-        UINT64_MAX / TIMER_CLOCK_KHZ
+    if use_khz() {
+        UINT64_MAX / timer_clock_khz()
     } else {
         UINT64_MAX
     }
 }
 #[cfg(feature = "kernel_mcs")]
 pub fn get_max_us_to_ticks() -> time_t {
-    if USE_KHZ {
-        UINT64_MAX / TIMER_CLOCK_KHZ
+    if use_khz() {
+        UINT64_MAX / timer_clock_khz()
     } else {
-        UINT64_MAX / TIMER_CLOCK_MHZ
+        UINT64_MAX / timer_clock_mhz()
     }
 }
 #[cfg(feature = "kernel_mcs")]
 pub fn ticks_to_us(ticks: ticks_t) -> time_t {
-    if USE_KHZ {
-        (ticks * KHZ_IN_MHZ) / TIMER_CLOCK_KHZ
+    if use_khz() {
+        (ticks * KHZ_IN_MHZ) / timer_clock_khz()
     } else {
-        ticks / TIMER_CLOCK_MHZ
+        ticks / timer_clock_mhz()
     }
 }
 #[cfg(feature = "kernel_mcs")]
 pub fn us_to_ticks(us: time_t) -> ticks_t {
-    if USE_KHZ {
-        (us * TIMER_CLOCK_KHZ) / KHZ_IN_MHZ
+    if use_khz() {
+        (us * timer_clock_khz()) / KHZ_IN_MHZ
     } else {
-        us * TIMER_CLOCK_MHZ
+        us * timer_clock_mhz()
     }
 }
 #[cfg(feature = "kernel_mcs")]
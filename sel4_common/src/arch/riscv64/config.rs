@@ -1,21 +1,39 @@
-use crate::platform::time_def::{MS_IN_S, TIMER_CLOCK_HZ};
+use crate::platform::time_def::{timer_clock_hz, MS_IN_S};
 
 // boot 相关的常数
-pub const PPTR_TOP: usize = 0xFFFFFFFF80000000;
+// With Sv39 the kernel window ends right below the kernel ELF. With Sv48 it takes 255
+// root entries of 512GiB and leaves the last one to the kernel ELF and the devices.
+cfg_if::cfg_if! {
+    if #[cfg(feature = "riscv_sv48")] {
+        pub const PPTR_TOP: usize = 0xFFFFFF8000000000;
+        pub const PPTR_BASE: usize = 0xFFFF800000000000;
+        pub const MAX_UNTYPED_BITS: usize = 47;
+    } else {
+        pub const PPTR_TOP: usize = 0xFFFFFFFF80000000;
+        pub const PPTR_BASE: usize = 0xFFFFFFC000000000;
+        pub const MAX_UNTYPED_BITS: usize = 38;
+    }
+}
+const _: () = assert!(
+    crate::sel4_config::CONFIG_PT_LEVELS == if cfg!(feature = "riscv_sv48") { 4 } else { 3 },
+    "PT_LEVELS must be 4 with the riscv_sv48 feature and 3 without it"
+);
 pub const PHYS_BASE: usize = 0x80000000;
 pub const KERNEL_ELF_PADDR_BASE: usize = PHYS_BASE + 0x4000000;
-pub const KERNEL_ELF_BASE: usize = PPTR_TOP + (KERNEL_ELF_PADDR_BASE & mask_bits!(30));
+pub const KERNEL_ELF_BASE: usize = KDEV_BASE - bit!(30) + (KERNEL_ELF_PADDR_BASE & mask_bits!(30));
 pub const KERNEL_ELF_BASE_OFFSET: usize = KERNEL_ELF_BASE - KERNEL_ELF_PADDR_BASE;
-pub const PPTR_BASE: usize = 0xFFFFFFC000000000;
 pub const PADDR_BASE: usize = 0x0;
 pub const PPTR_BASE_OFFSET: usize = PPTR_BASE - PADDR_BASE;
 pub const PADDR_TOP: usize = PPTR_TOP - PPTR_BASE_OFFSET;
-pub const RESET_CYCLES: usize = (TIMER_CLOCK_HZ / MS_IN_S) * 2;
 pub const KDEV_BASE: usize = 0xFFFFFFFFC0000000;
 
-pub const MAX_UNTYPED_BITS: usize = 38;
-
 #[cfg(feature = "enable_smp")]
 pub const IRQ_REMOTE_CALL_IPI: usize = crate::platform::INTERRUPT_IPI_0;
 #[cfg(feature = "enable_smp")]
 pub const IRQ_RESCHEDULE_IPI: usize = crate::platform::INTERRUPT_IPI_1;
+
+/// The ticks between two periodic timer interrupts, 2ms.
+#[inline]
+pub fn reset_cycles() -> usize {
+    (timer_clock_hz() / MS_IN_S) * 2
+}
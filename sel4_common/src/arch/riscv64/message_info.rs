@@ -36,7 +36,13 @@ pub enum MessageLabel {
     IRQAckIRQ,
     IRQSetIRQHandler,
     IRQClearIRQHandler,
+    #[cfg(feature = "enable_smp")]
+    IRQSetTargetCore,
     DomainSetSet,
+    #[cfg(feature = "cpu_hotplug")]
+    CPUControlOffline,
+    #[cfg(feature = "cpu_hotplug")]
+    CPUControlOnline,
     #[cfg(feature = "kernel_mcs")]
     SchedControlConfigureFlags,
     #[cfg(feature = "kernel_mcs")]
@@ -57,6 +63,10 @@ pub enum MessageLabel {
     RISCVASIDControlMakePool,
     RISCVASIDPoolAssign,
     RISCVIRQIssueIRQHandlerTrigger,
+    #[cfg(feature = "enable_smp")]
+    RISCVIRQIssueIRQHandlerTriggerCore,
+    #[cfg(feature = "riscv_aia")]
+    RISCVIRQIssueIRQHandlerMSI,
     nArchInvocationLabels,
 }
 #[cfg(not(feature = "kernel_mcs"))]
@@ -9,7 +9,10 @@ mod registers;
 mod timer;
 mod vm_rights;
 use crate::platform::time_def::ticks_t;
+use crate::sel4_config::CONFIG_MAX_NUM_NODES;
+use crate::utils::cpu_id;
 pub use arch_tcb::ArchTCB;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 pub use message_info::*;
 pub use object::*;
 pub use registers::*;
@@ -32,6 +35,30 @@ const SBI_REMOTE_SFENCE_VMA: usize = 6;
 const SBI_SHUTDOWN: usize = 8;
 const SYSCALL_WRITE: usize = 64;
 
+/// The SBI Hart State Management extension.
+const SBI_EXT_HSM: usize = 0x48534d;
+const SBI_HSM_HART_SUSPEND: usize = 3;
+/// The default retentive suspend, the hart keeps its state and returns from the call.
+const SBI_HSM_SUSPEND_RETENTIVE: usize = 0;
+/// Set in the `suspend_type` of the non-retentive suspends.
+const SBI_HSM_SUSPEND_NON_RETENTIVE: usize = 0x8000_0000;
+
+const _: () = assert!(
+    crate::sel4_config::CONFIG_IDLE_SUSPEND_STATE & SBI_HSM_SUSPEND_NON_RETENTIVE == 0,
+    "the idle state must keep the hart state"
+);
+
+/// The CSR number of `stimecmp`, defined by the Sstc extension.
+const CSR_STIMECMP: usize = 0x14d;
+
+/// Set at boot when the DTB lists Sstc for every hart, the `riscv_ext_sstc` feature
+/// assumes Sstc without probing.
+static SSTC_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// The deadline last programmed on each hart, the SBI timer can not be read back.
+static TIMER_DEADLINE: [AtomicUsize; CONFIG_MAX_NUM_NODES] =
+    [const { AtomicUsize::new(usize::MAX) }; CONFIG_MAX_NUM_NODES];
+
 #[no_mangle]
 pub fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     let mut ret;
@@ -47,8 +74,71 @@ pub fn sbi_call(which: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
     ret
 }
 
+/// Program the next supervisor timer interrupt at `timer`.
+///
+/// With Sstc the deadline is written to `stimecmp` directly, otherwise it takes an
+/// SBI call, which traps into M-mode every time. Both the periodic tick and the MCS
+/// deadlines are programmed here.
 pub fn set_timer(timer: ticks_t) {
-    sbi_call(SBI_SET_TIMER, timer, 0, 0);
+    if sstc_enabled() {
+        unsafe { core::arch::asm!("csrw {csr}, {0}", in(reg) timer, csr = const CSR_STIMECMP) };
+    } else {
+        sbi_call(SBI_SET_TIMER, timer, 0, 0);
+    }
+    TIMER_DEADLINE[cpu_id()].store(timer, Ordering::Relaxed);
+}
+
+/// The next timer deadline of this hart, as programmed by [`set_timer`].
+pub fn get_timer() -> ticks_t {
+    if sstc_enabled() {
+        let timer: ticks_t;
+        unsafe { core::arch::asm!("csrr {0}, {csr}", out(reg) timer, csr = const CSR_STIMECMP) };
+        timer
+    } else {
+        TIMER_DEADLINE[cpu_id()].load(Ordering::Relaxed)
+    }
+}
+
+/// Use `stimecmp` for the timer from now on, the SBI firmware must have set
+/// `menvcfg.STCE` for the harts, which it does when they implement Sstc.
+pub fn enable_sstc() {
+    SSTC_ENABLED.store(true, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn sstc_enabled() -> bool {
+    cfg!(feature = "riscv_ext_sstc") || SSTC_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Put this hart into HSM retentive suspend until an interrupt is pending.
+///
+/// Falls back to `wfi` when the SBI does not implement HSM suspend.
+pub fn cpu_standby() {
+    if hart_suspend(SBI_HSM_SUSPEND_RETENTIVE) != 0 {
+        unsafe { core::arch::asm!("wfi") };
+    }
+}
+
+/// Put this hart into the deeper idle state `CONFIG_IDLE_SUSPEND_STATE` of the platform
+/// until an interrupt is pending. False if the SBI refuses the state.
+pub fn cpu_idle_suspend() -> bool {
+    hart_suspend(crate::sel4_config::CONFIG_IDLE_SUSPEND_STATE) == 0
+}
+
+/// The SBI `hart_suspend` call for a retentive `suspend_type`, it returns the SBI error.
+fn hart_suspend(suspend_type: usize) -> isize {
+    let error: isize;
+    unsafe {
+        core::arch::asm!(
+            "ecall",
+            inlateout("a0") suspend_type => error,
+            inlateout("a1") 0usize => _,
+            in("a2") 0usize,
+            in("a6") SBI_HSM_HART_SUSPEND,
+            in("a7") SBI_EXT_HSM,
+        );
+    }
+    error
 }
 
 pub fn clear_ipi() {
@@ -72,6 +162,11 @@ pub fn get_time() -> usize {
     time::read()
 }
 
+/// The cycle counter of this hart, the SBI firmware grants S-mode access to it.
+pub fn read_cycles() -> usize {
+    riscv::register::cycle::read()
+}
+
 #[cfg(feature = "enable_smp")]
 #[no_mangle]
 pub fn sbi_send_ipi(hart_mask: usize) {
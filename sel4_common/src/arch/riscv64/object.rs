@@ -2,6 +2,8 @@ use crate::sel4_config::{
     RISCV_4K_PAGE, RISCV_GIGA_PAGE, RISCV_MEGA_PAGE, SEL4_HUGE_PAGE_BITS, SEL4_LARGE_PAGE_BITS,
     SEL4_PAGE_BITS,
 };
+#[cfg(feature = "riscv_ext_svnapot")]
+use crate::sel4_config::{RISCV_NAPOT_64K_PAGE, RISCV_NAPOT_64K_PAGE_BITS};
 
 /// Represents the type of an object.
 #[cfg(not(feature = "kernel_mcs"))]
@@ -17,6 +19,8 @@ pub enum ObjectType {
     NormalPageObject = 6,
     MegaPageObject = 7,
     PageTableObject = 8,
+    #[cfg(feature = "riscv_ext_svnapot")]
+    NapotPageObject = 9,
 }
 #[cfg(feature = "kernel_mcs")]
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
@@ -33,6 +37,8 @@ pub enum ObjectType {
     NormalPageObject = 8,
     MegaPageObject = 9,
     PageTableObject = 10,
+    #[cfg(feature = "riscv_ext_svnapot")]
+    NapotPageObject = 11,
 }
 
 impl ObjectType {
@@ -42,6 +48,8 @@ impl ObjectType {
             ObjectType::NormalPageObject => SEL4_PAGE_BITS,
             ObjectType::MegaPageObject => SEL4_LARGE_PAGE_BITS,
             ObjectType::PageTableObject => SEL4_PAGE_BITS,
+            #[cfg(feature = "riscv_ext_svnapot")]
+            ObjectType::NapotPageObject => RISCV_NAPOT_64K_PAGE_BITS,
             _ => panic!("unsupported cap type:{}", (*self) as usize),
         }
     }
@@ -56,6 +64,8 @@ impl ObjectType {
             ObjectType::NormalPageObject => RISCV_4K_PAGE,
             ObjectType::MegaPageObject => RISCV_MEGA_PAGE,
             ObjectType::GigaPageObject => RISCV_GIGA_PAGE,
+            #[cfg(feature = "riscv_ext_svnapot")]
+            ObjectType::NapotPageObject => RISCV_NAPOT_64K_PAGE,
             _ => {
                 panic!("Invalid frame type: {:?}", self);
             }
@@ -68,9 +78,11 @@ impl ObjectType {
     ///
     /// true if the object type is an architecture-specific type, false otherwise.
     pub fn is_arch_type(self) -> bool {
-        matches!(
-            self,
-            Self::GigaPageObject | Self::NormalPageObject | Self::MegaPageObject
-        )
+        match self {
+            Self::GigaPageObject | Self::NormalPageObject | Self::MegaPageObject => true,
+            #[cfg(feature = "riscv_ext_svnapot")]
+            Self::NapotPageObject => true,
+            _ => false,
+        }
     }
 }
@@ -1,15 +1,44 @@
 //! SBI console driver, for text output
 
-use crate::platform::default_serial;
+use crate::platform::{default_serial, serial_at};
 use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicUsize, Ordering};
 use serial_frame::SerialDriver;
 
+/// Print with the default serial driver of the platform.
+pub const CONSOLE_DEFAULT: usize = 0;
+/// Drop everything printed.
+pub const CONSOLE_OFF: usize = usize::MAX;
+
+/// The console chosen at boot: [CONSOLE_DEFAULT], [CONSOLE_OFF] or the kernel virtual
+/// address of the UART to print on.
+static CONSOLE: AtomicUsize = AtomicUsize::new(CONSOLE_DEFAULT);
+
+/// Switch the kernel console, a UART at `pptr` must be mapped and of the type the
+/// platform's serial driver handles.
+pub fn set_console(console: usize) {
+    if console != CONSOLE_DEFAULT && console != CONSOLE_OFF {
+        serial_at(console).init();
+    }
+    CONSOLE.store(console, Ordering::Relaxed);
+}
+
 struct Stdout;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        for c in s.chars() {
-            default_serial().putchar(c as _);
+        match CONSOLE.load(Ordering::Relaxed) {
+            CONSOLE_OFF => {}
+            CONSOLE_DEFAULT => {
+                for c in s.chars() {
+                    default_serial().putchar(c as _);
+                }
+            }
+            pptr => {
+                for c in s.chars() {
+                    serial_at(pptr).putchar(c as _);
+                }
+            }
         }
         Ok(())
     }
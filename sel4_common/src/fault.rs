@@ -5,6 +5,12 @@ pub const VM_FAULT_PREFETCH_FAULT: usize = 2;
 pub const VM_FAULT_FSR: usize = 3;
 pub const VM_FAULT_LENGTH: usize = 4;
 pub const TIMEOUT_DATA: usize = 0;
+//seL4_VCPUFault_Msg
+#[cfg(feature = "hypervisor")]
+pub const VCPU_FAULT_HSR: usize = 0;
+//seL4_VPPIEvent_Msg
+#[cfg(feature = "hypervisor")]
+pub const VPPI_EVENT_IRQ: usize = 0;
 
 pub const CAP_FAULT_IP: usize = 0;
 pub const CAP_FAULT_ADDR: usize = 1;
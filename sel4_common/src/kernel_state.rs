@@ -0,0 +1,90 @@
+//! Per-core state of the current kernel entry.
+//!
+//! The fault, lookup fault and extra caps of a kernel entry are scratch state that lives
+//! from the kernel entry until the reply to the user. Each core has its own
+//! `kernel_state_t`, so two cores in the kernel never share them.
+//!
+//! The trap handler of a kernel entry takes the state with [`kernel_entry_t::enter`] and
+//! lends it down by `&mut`, e.g. to `decode_invocation` or `send_fault_ipc`. The borrow
+//! can not outlive the token, so a second reference to the state is a compile error
+//! rather than a convention. Soundness then relies on two rules:
+//!
+//! * a core only touches its own entry, and only while it is in the kernel,
+//! * kernel entries are not reentrant, interrupts are masked while in the kernel.
+use crate::sel4_bitfield_types::Bitfield;
+use core::marker::PhantomData;
+#[cfg(feature = "enable_smp")]
+use crate::sel4_config::CONFIG_MAX_NUM_NODES;
+use crate::structures::extra_caps_t;
+use crate::structures_gen::{lookup_fault, seL4_Fault};
+
+/// The state of the kernel entry in progress on one core.
+pub struct kernel_state_t {
+    /// The fault to deliver to the fault handler of the current thread.
+    pub fault: seL4_Fault,
+    /// The lookup fault of the last failed cap lookup.
+    pub lookup_fault: lookup_fault,
+    /// The extra caps of the current invocation.
+    pub extra_caps: extra_caps_t,
+}
+
+impl kernel_state_t {
+    pub const fn new() -> Self {
+        Self {
+            fault: seL4_Fault(Bitfield { arr: [0; 2] }),
+            lookup_fault: lookup_fault(Bitfield { arr: [0; 2] }),
+            extra_caps: extra_caps_t::new(),
+        }
+    }
+}
+
+impl Default for kernel_state_t {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "enable_smp")]
+static mut ksKernelState: [kernel_state_t; CONFIG_MAX_NUM_NODES] = {
+    const STATE: kernel_state_t = kernel_state_t::new();
+    [STATE; CONFIG_MAX_NUM_NODES]
+};
+
+#[cfg(not(feature = "enable_smp"))]
+static mut ksKernelState: kernel_state_t = kernel_state_t::new();
+
+/// The kernel state of the current core, owned by one kernel entry.
+///
+/// It is neither `Clone` nor `Send`, and [`kernel_entry_t::state`] borrows it, so the
+/// state is only reachable through the `&mut` the trap handler passes down.
+pub struct kernel_entry_t {
+    _core: PhantomData<*mut kernel_state_t>,
+}
+
+impl kernel_entry_t {
+    /// Take the kernel state of the current core for this kernel entry.
+    ///
+    /// # Safety
+    ///
+    /// Only the trap handlers (`c_handle_*`) may call it, once per kernel entry, and no
+    /// token of an earlier entry on this core may be alive.
+    #[inline]
+    pub unsafe fn enter() -> Self {
+        Self {
+            _core: PhantomData,
+        }
+    }
+
+    /// The kernel state of this entry, borrowed for as long as the token.
+    #[inline]
+    pub fn state(&mut self) -> &mut kernel_state_t {
+        #[cfg(feature = "enable_smp")]
+        unsafe {
+            &mut *core::ptr::addr_of_mut!(ksKernelState[crate::utils::cpu_id()])
+        }
+        #[cfg(not(feature = "enable_smp"))]
+        unsafe {
+            &mut *core::ptr::addr_of_mut!(ksKernelState)
+        }
+    }
+}
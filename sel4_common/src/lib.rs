@@ -15,6 +15,7 @@ pub mod arch;
 pub mod console;
 pub mod fault;
 pub mod ffi;
+pub mod kernel_state;
 pub mod logging;
 pub mod message_info;
 pub mod object;
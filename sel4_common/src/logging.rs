@@ -7,6 +7,7 @@
 //! This is a simple logger that logs to the console.
 
 use log::{self, Level, LevelFilter, Log, Metadata, Record};
+use rel4_utils::cmdline::module_filter_matches;
 
 use super::utils::cpu_id;
 use crate::println;
@@ -14,11 +15,23 @@ use crate::println;
 use spin::Mutex;
 
 static LOG_MUTEX: Mutex<()> = Mutex::new(());
+
+/// Longest module filter, in bytes.
+const LOG_FILTER_LEN: usize = 128;
+
+/// Comma separated module names set at boot, only records from a path containing one
+/// of them are printed. Empty to print everything.
+static LOG_FILTER: Mutex<([u8; LOG_FILTER_LEN], usize)> = Mutex::new(([0; LOG_FILTER_LEN], 0));
+
 struct SimpleLogger;
 
 impl Log for SimpleLogger {
-    fn enabled(&self, _metadata: &Metadata) -> bool {
-        true
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let filter = LOG_FILTER.lock();
+        let Ok(modules) = core::str::from_utf8(&filter.0[..filter.1]) else {
+            return true;
+        };
+        module_filter_matches(modules, metadata.target())
     }
     fn log(&self, record: &Record) {
         let _lock = LOG_MUTEX.lock();
@@ -50,6 +63,23 @@ impl Log for SimpleLogger {
     }
 }
 
+/// Replace the maximum level set by the `LOG` environment variable at build time.
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+/// Only print the records of the modules in the comma separated `modules`, for example
+/// `sel4_task,fastpath`. False if the list is too long.
+pub fn set_module_filter(modules: &str) -> bool {
+    let mut filter = LOG_FILTER.lock();
+    if modules.len() > LOG_FILTER_LEN {
+        return false;
+    }
+    filter.0[..modules.len()].copy_from_slice(modules.as_bytes());
+    filter.1 = modules.len();
+    true
+}
+
 pub fn init() {
     static LOGGER: SimpleLogger = SimpleLogger;
     log::set_logger(&LOGGER).unwrap();
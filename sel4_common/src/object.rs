@@ -7,11 +7,15 @@ use crate::arch::ObjectType;
 
 use super::sel4_config::*;
 
-#[cfg(target_arch = "riscv64")]
+#[cfg(all(target_arch = "riscv64", not(feature = "riscv_ext_svnapot")))]
 pub const OBJECT_TYPE_COUNT: usize = ObjectType::PageTableObject as usize + 1;
+#[cfg(all(target_arch = "riscv64", feature = "riscv_ext_svnapot"))]
+pub const OBJECT_TYPE_COUNT: usize = ObjectType::NapotPageObject as usize + 1;
 // FIXED: Need to add 1 to cover all possible object types
-#[cfg(any(target_arch = "aarch64", test))]
+#[cfg(all(any(target_arch = "aarch64", test), not(feature = "hypervisor")))]
 pub const OBJECT_TYPE_COUNT: usize = ObjectType::seL4_ARM_PageTableObject as usize + 1;
+#[cfg(all(any(target_arch = "aarch64", test), feature = "hypervisor"))]
+pub const OBJECT_TYPE_COUNT: usize = ObjectType::seL4_ARM_VCPUObject as usize + 1;
 #[cfg(not(feature = "kernel_mcs"))]
 pub const NON_ARCH_OBJECT_TYPE_COUNT: usize = ObjectType::CapTableObject as usize + 1;
 #[cfg(feature = "kernel_mcs")]
@@ -20,6 +20,7 @@ pub trait Timer_func {
     fn init_timer(self);
     fn get_current_time(self) -> ticks_t;
     fn set_deadline(self, deadline: ticks_t);
+    fn get_deadline(self) -> ticks_t;
     fn reset_timer(self);
     fn ack_deadline_irq(self);
 }
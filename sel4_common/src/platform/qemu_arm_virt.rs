@@ -1,17 +1,91 @@
 use crate::arch::config::KDEV_BASE;
 use crate::sel4_config::UINT64_MAX;
-use aarch64_cpu::registers::{
-    Readable, Writeable, CNTFRQ_EL0, CNTVCT_EL0, CNTV_CTL_EL0, CNTV_CVAL_EL0, CNTV_TVAL_EL0,
-};
+use aarch64_cpu::registers::{Readable, CNTFRQ_EL0};
 use core::ptr::NonNull;
 use serial_frame::SerialDriver;
 use serial_impl_pl011::Pl011Uart;
 
 use super::{
-    time_def::{ticks_t, TIMER_CLOCK_HZ},
+    time_def::{ticks_t, timer_clock_hz},
     Timer_func,
 };
 
+/// The kernel timer is the EL2 physical timer in hyp mode, so that the virtual timer is
+/// left to the VCPUs.
+#[cfg(feature = "hypervisor")]
+pub const KERNEL_TIMER_IRQ: usize = super::INTERRUPT_HYP_TIMER_EVENT;
+/// The kernel timer is the virtual timer without hyp mode.
+#[cfg(not(feature = "hypervisor"))]
+pub const KERNEL_TIMER_IRQ: usize = super::INTERRUPT_VTIMER_EVENT;
+
+/// The registers of the generic timer the kernel uses, see [`KERNEL_TIMER_IRQ`].
+#[cfg(feature = "hypervisor")]
+mod generic_timer {
+    use core::arch::asm;
+
+    #[inline]
+    pub fn read_count() -> u64 {
+        let value: u64;
+        unsafe { asm!("mrs {}, cntpct_el0", out(reg) value) };
+        value
+    }
+
+    #[inline]
+    pub fn write_ctl(value: u64) {
+        unsafe { asm!("msr cnthp_ctl_el2, {}", in(reg) value) };
+    }
+
+    #[inline]
+    pub fn write_tval(value: u64) {
+        unsafe { asm!("msr cnthp_tval_el2, {}", in(reg) value) };
+    }
+
+    #[inline]
+    pub fn read_cval() -> u64 {
+        let value: u64;
+        unsafe { asm!("mrs {}, cnthp_cval_el2", out(reg) value) };
+        value
+    }
+
+    #[inline]
+    pub fn write_cval(value: u64) {
+        unsafe { asm!("msr cnthp_cval_el2, {}", in(reg) value) };
+    }
+}
+
+/// The registers of the generic timer the kernel uses, see [`KERNEL_TIMER_IRQ`].
+#[cfg(not(feature = "hypervisor"))]
+mod generic_timer {
+    use aarch64_cpu::registers::{
+        Readable, Writeable, CNTVCT_EL0, CNTV_CTL_EL0, CNTV_CVAL_EL0, CNTV_TVAL_EL0,
+    };
+
+    #[inline]
+    pub fn read_count() -> u64 {
+        CNTVCT_EL0.get()
+    }
+
+    #[inline]
+    pub fn write_ctl(value: u64) {
+        CNTV_CTL_EL0.set(value);
+    }
+
+    #[inline]
+    pub fn write_tval(value: u64) {
+        CNTV_TVAL_EL0.set(value);
+    }
+
+    #[inline]
+    pub fn read_cval() -> u64 {
+        CNTV_CVAL_EL0.get()
+    }
+
+    #[inline]
+    pub fn write_cval(value: u64) {
+        CNTV_CVAL_EL0.set(value);
+    }
+}
+
 pub(crate) const CONFIGURE_TIMER_FREQUENCY: usize = 62500000;
 #[cfg(feature = "kernel_mcs")]
 #[allow(dead_code)]
@@ -36,13 +110,13 @@ impl Timer_func for timer {
         // Here use the generic timer init
         // check frequency is correct
         let gpt_cntfrq = CNTFRQ_EL0.get() as usize;
-        if gpt_cntfrq != 0 && gpt_cntfrq != TIMER_CLOCK_HZ {
+        if gpt_cntfrq != 0 && gpt_cntfrq != timer_clock_hz() {
             panic!("The gpt_cntfrq is unequal to the system configure");
         }
         #[cfg(feature = "kernel_mcs")]
         {
             self.ack_deadline_irq();
-            CNTV_CTL_EL0.set(bit!(0) as u64);
+            generic_timer::write_ctl(bit!(0) as u64);
         }
         #[cfg(not(feature = "kernel_mcs"))]
         {
@@ -50,10 +124,13 @@ impl Timer_func for timer {
         }
     }
     fn get_current_time(self) -> ticks_t {
-        CNTVCT_EL0.get() as _
+        generic_timer::read_count() as _
     }
     fn set_deadline(self, deadline: ticks_t) {
-        CNTV_CVAL_EL0.set(deadline as u64);
+        generic_timer::write_cval(deadline as u64);
+    }
+    fn get_deadline(self) -> ticks_t {
+        generic_timer::read_cval() as _
     }
     /// Reset the current Timer
     #[no_mangle]
@@ -63,8 +140,8 @@ impl Timer_func for timer {
             SYSTEM_WRITE_WORD(CNT_CTL, BIT(0));
         */
         // TODO: Set a proper timer clock
-        CNTV_TVAL_EL0.set(TIMER_CLOCK_HZ as u64 / 1000 * 2);
-        CNTV_CTL_EL0.set(1);
+        generic_timer::write_tval(timer_clock_hz() as u64 / 1000 * 2);
+        generic_timer::write_ctl(1);
     }
     fn ack_deadline_irq(self) {
         let deadline: ticks_t = UINT64_MAX;
@@ -73,5 +150,10 @@ impl Timer_func for timer {
 }
 
 pub fn default_serial() -> impl SerialDriver {
-    Pl011Uart::new(NonNull::new(KDEV_BASE as _).unwrap())
+    serial_at(KDEV_BASE)
+}
+
+/// The serial driver of the platform for the UART mapped at `pptr`.
+pub fn serial_at(pptr: usize) -> impl SerialDriver {
+    Pl011Uart::new(NonNull::new(pptr as _).unwrap())
 }
@@ -1,7 +1,7 @@
 pub const CONFIGURE_TIMER_FREQUENCY: usize = 10000000;
 use super::Timer_func;
-use crate::arch::config::RESET_CYCLES;
-use crate::arch::{get_time, set_timer};
+use crate::arch::config::reset_cycles;
+use crate::arch::{get_time, get_timer, set_timer};
 use crate::platform::time_def::ticks_t;
 use core::arch::asm;
 use core::ptr::NonNull;
@@ -18,12 +18,15 @@ impl Timer_func for timer {
     fn set_deadline(self, deadline: ticks_t) {
         set_timer(deadline)
     }
+    fn get_deadline(self) -> ticks_t {
+        get_timer()
+    }
     #[no_mangle]
     fn reset_timer(self) {
-        let mut target = read_time() + RESET_CYCLES;
+        let mut target = read_time() + reset_cycles();
         set_timer(target);
         while read_time() > target {
-            target = read_time() + RESET_CYCLES;
+            target = read_time() + reset_cycles();
             set_timer(target);
         }
     }
@@ -41,5 +44,11 @@ pub fn read_time() -> usize {
 /// Initialize Default Serial Driver
 pub fn default_serial() -> impl SerialDriver {
     // 0xf is a random number, the argument of this function will never be used
-    SerialSBI::new(unsafe { NonNull::new_unchecked(0xf as _) })
+    serial_at(0xf)
+}
+
+/// The serial driver of the platform for the UART mapped at `pptr`, the SBI console has
+/// no registers so `pptr` is ignored.
+pub fn serial_at(pptr: usize) -> impl SerialDriver {
+    SerialSBI::new(unsafe { NonNull::new_unchecked(pptr as _) })
 }
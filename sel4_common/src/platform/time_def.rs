@@ -1,4 +1,5 @@
 use super::CONFIGURE_TIMER_FREQUENCY;
+use core::sync::atomic::{AtomicUsize, Ordering};
 
 /// 时钟ticks
 pub type ticks_t = usize;
@@ -10,8 +11,34 @@ pub const HZ_IN_KHZ: usize = 1000;
 pub const KHZ_IN_MHZ: usize = 1000;
 pub const HZ_IN_MHZ: usize = 1000000;
 
+/// The timer frequency of the platform YAML, see [`timer_clock_hz`].
 pub const TIMER_CLOCK_HZ: usize = CONFIGURE_TIMER_FREQUENCY;
-#[allow(clippy::absurd_extreme_comparisons)]
-pub const USE_KHZ: bool = TIMER_CLOCK_HZ % HZ_IN_MHZ > 0;
-pub const TIMER_CLOCK_KHZ: usize = TIMER_CLOCK_HZ / HZ_IN_KHZ;
-pub const TIMER_CLOCK_MHZ: usize = TIMER_CLOCK_HZ / HZ_IN_MHZ;
+
+static TIMER_CLOCK: AtomicUsize = AtomicUsize::new(TIMER_CLOCK_HZ);
+
+/// The frequency of the kernel timer, the one of the DTB with `dtb_platform` or
+/// [`TIMER_CLOCK_HZ`].
+#[inline]
+pub fn timer_clock_hz() -> usize {
+    TIMER_CLOCK.load(Ordering::Relaxed)
+}
+
+/// Replace the frequency of the kernel timer, on the boot core before the timer is set up.
+pub fn set_timer_clock_hz(hz: usize) {
+    TIMER_CLOCK.store(hz, Ordering::Relaxed);
+}
+
+#[inline]
+pub fn use_khz() -> bool {
+    timer_clock_hz() % HZ_IN_MHZ > 0
+}
+
+#[inline]
+pub fn timer_clock_khz() -> usize {
+    timer_clock_hz() / HZ_IN_KHZ
+}
+
+#[inline]
+pub fn timer_clock_mhz() -> usize {
+    timer_clock_hz() / HZ_IN_MHZ
+}
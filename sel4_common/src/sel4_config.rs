@@ -23,9 +23,12 @@ pub const RISCV_4K_PAGE: usize = 0;
 pub const RISCV_MEGA_PAGE: usize = 1;
 pub const RISCV_GIGA_PAGE: usize = 2;
 pub const RISCV_TERA_PAGE: usize = 3;
+/// The 64KiB Svnapot page, mapped by 16 contiguous 4KiB entries
+pub const RISCV_NAPOT_64K_PAGE: usize = 4;
 pub const RISCV_PAGE_BITS: usize = SEL4_PAGE_BITS;
 pub const RISCV_MEGA_PAGE_BITS: usize = SEL4_LARGE_PAGE_BITS;
 pub const RISCV_GIGA_PAGE_BITS: usize = SEL4_HUGE_PAGE_BITS;
+pub const RISCV_NAPOT_64K_PAGE_BITS: usize = 16;
 pub const RISCV_INSTRUCTION_MISALIGNED: usize = 0;
 pub const RISCV_INSTRUCTION_ACCESS_FAULT: usize = 1;
 pub const RISCV_INSTRUCTION_ILLEGAL: usize = 2;
@@ -63,6 +66,14 @@ pub const ASID_BITS: usize = ASID_HIGH_BITS + ASID_LOW_BITS;
 /// kernel/include/arch/{arch}/arch/api/types.h
 pub const ASID_INVALID: usize = 0;
 
+/// kernel/include/arch/arm/arch/object/smmu.h
+/// An `ARMCB` cap that is not bound to any stream ID.
+#[cfg(feature = "arm_smmu")]
+pub const SID_INVALID: usize = mask_bits!(12);
+/// A vspace cap that is not assigned to any context bank.
+#[cfg(feature = "arm_smmu")]
+pub const CB_INVALID: usize = mask_bits!(8);
+
 /// kernel/include/arch/{arch}/arch/kernel/vspace.h
 pub const IT_ASID: usize = 1;
 
@@ -88,7 +99,13 @@ pub const TCB_REPLY: usize = 2;
 pub const TCB_CALLER: usize = 3;
 #[cfg(not(feature = "kernel_mcs"))]
 pub const TCB_BUFFER: usize = 4;
+/// The frame the SVE registers of the thread are saved to.
+#[cfg(feature = "arm_sve")]
+pub const TCB_SVE_STATE: usize = 5;
+#[cfg(not(feature = "arm_sve"))]
 pub const TCB_CNODE_ENTRIES: usize = 5;
+#[cfg(feature = "arm_sve")]
+pub const TCB_CNODE_ENTRIES: usize = 6;
 pub const ASID_LOW_BITS: usize = 9;
 pub const ASID_HIGH_BITS: usize = 7;
 
@@ -140,7 +157,8 @@ pub const SEL4_CAP_SMMU_SID_CONTROL: usize = 12;
 pub const SEL4_CAP_SMMU_CB_CONTROL: usize = 13;
 pub const SEL4_CAP_INIT_THREAD_SC: usize = 14;
 pub const SEL4_CAP_SMC: usize = 15;
-pub const SEL4_NUM_INITIAL_CAPS: usize = 16;
+pub const SEL4_CAP_CPU_CONTROL: usize = 16;
+pub const SEL4_NUM_INITIAL_CAPS: usize = 17;
 
 /// kernel/include/machine/registerset.h
 pub const MESSAGE_ID_SYSCALL: usize = 0;
@@ -153,8 +171,15 @@ pub const SEL4_IPC_BUFFER_SIZE_BITS: usize = 10;
 pub const SEL4_NUM_ASID_POOLS_BITS: usize = 7;
 pub const SEL4_ASID_POOL_INDEX_BITS: usize = 9;
 pub const SEL4_ASID_POOL_BITS: usize = 12;
-#[cfg(all(target_arch = "riscv64", feature = "have_fpu"))]
+#[cfg(all(
+    target_arch = "riscv64",
+    feature = "have_fpu",
+    not(feature = "riscv_ext_v")
+))]
 pub const SEL4_TCB_BITS: usize = 11;
+/// The vector registers are saved in the TCB object after the `tcb_t`.
+#[cfg(all(target_arch = "riscv64", feature = "riscv_ext_v"))]
+pub const SEL4_TCB_BITS: usize = 12;
 #[cfg(all(target_arch = "riscv64", not(feature = "have_fpu")))]
 pub const SEL4_TCB_BITS: usize = 10;
 #[cfg(any(target_arch = "aarch64", test))]
@@ -176,9 +201,25 @@ pub const SEL4_PGD_BITS: usize = 12;
 pub const SEL4_HUGE_PAGE_BITS: usize = 30;
 pub const SEL4_LARGE_PAGE_BITS: usize = 21;
 pub const SEL4_PML4_BITS: usize = 12;
+#[cfg(not(target_arch = "aarch64"))]
 pub const SEL4_VSPACE_BITS: usize = SEL4_PML4_BITS;
+#[cfg(target_arch = "aarch64")]
+pub const SEL4_VSPACE_BITS: usize = crate::arch::config::VSPACE_INDEX_BITS + 3;
+#[cfg(feature = "hypervisor")]
+pub const SEL4_VCPU_BITS: usize = 12;
 pub const SEL4_WORD_BITS: usize = 64;
+/// libsel4/sel4_arch_include/{arch}/sel4/sel4_arch/constants.h
+#[cfg(all(not(target_arch = "riscv64"), not(feature = "hypervisor")))]
 pub const SEL4_USER_TOP: usize = 0x00007fffffffffff;
+/// The user address space of a guest is its IPA space.
+#[cfg(feature = "hypervisor")]
+pub const SEL4_USER_TOP: usize = mask_bits!(crate::arch::config::PA_SIZE_BITS);
+#[cfg(target_arch = "riscv64")]
+pub const SEL4_USER_TOP: usize = match CONFIG_PT_LEVELS {
+    3 => 0x0000003ffffff000,
+    4 => 0x00007ffffffff000,
+    _ => panic!("unsupported PT_LEVELS"),
+};
 pub const USER_TOP: usize = SEL4_USER_TOP;
 
 /// rel4_kernel/sel4_common/src/sel4_config.rs
@@ -44,6 +44,21 @@ impl seL4_IPCBuffer {
     }
 }
 
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// The slots of the extra caps looked up for the current invocation.
+pub struct extra_caps_t {
+    pub excaprefs: [PPtr; SEL4_MSG_MAX_EXTRA_CAPS],
+}
+
+impl extra_caps_t {
+    pub const fn new() -> Self {
+        Self {
+            excaprefs: [PPtr::null(); SEL4_MSG_MAX_EXTRA_CAPS],
+        }
+    }
+}
+
 use crate::arch::config::PPTR_BASE;
 use core::{
     ffi::CStr,
@@ -113,6 +113,8 @@ pub fn pageBitsForSize(page_size: usize) -> usize {
         RISCV_4K_PAGE => RISCV_PAGE_BITS,
         RISCV_MEGA_PAGE => RISCV_MEGA_PAGE_BITS,
         RISCV_GIGA_PAGE => RISCV_GIGA_PAGE_BITS,
+        #[cfg(feature = "riscv_ext_svnapot")]
+        RISCV_NAPOT_64K_PAGE => RISCV_NAPOT_64K_PAGE_BITS,
         _ => panic!("Invalid page size!"),
     }
 }
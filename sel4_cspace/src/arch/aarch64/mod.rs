@@ -64,6 +64,8 @@ impl cap_arch_func for cap {
             // cap_tag::CapPageGlobalDirectoryCap => self.get_pgd_base_ptr(),
             cap_tag::cap_asid_control_cap => 0,
             cap_tag::cap_asid_pool_cap => cap::cap_asid_pool_cap(self).get_capASIDPool() as usize,
+            #[cfg(feature = "hypervisor")]
+            cap_tag::cap_vcpu_cap => cap::cap_vcpu_cap(self).get_capVCPUPtr() as usize,
             #[cfg(feature = "kernel_mcs")]
             cap_tag::cap_reply_cap => cap::cap_reply_cap(self).get_capReplyPtr() as usize,
             #[cfg(feature = "kernel_mcs")]
@@ -150,10 +152,21 @@ impl cte_t {
             cap_tag::cap_asid_control_cap | cap_tag::cap_asid_pool_cap => {
                 ret.capability = capability.clone();
             }
+            #[cfg(feature = "hypervisor")]
+            cap_tag::cap_vcpu_cap => {
+                ret.capability = capability.clone();
+            }
             #[cfg(feature = "enable_smc")]
             cap_tag::cap_smc_cap => {
                 ret.capability = capability.clone();
             }
+            #[cfg(feature = "arm_smmu")]
+            cap_tag::cap_sid_control_cap
+            | cap_tag::cap_sid_cap
+            | cap_tag::cap_cb_control_cap
+            | cap_tag::cap_cb_cap => {
+                ret.capability = capability.clone();
+            }
             _ => {
                 panic!(" Invalid arch cap type : {}", capability.get_tag() as usize);
             }
@@ -214,12 +227,41 @@ pub fn arch_same_region_as(cap1: &cap, cap2: &cap) -> bool {
                     == cap::cap_asid_pool_cap(cap2).get_capASIDPool();
             }
         }
+        #[cfg(feature = "hypervisor")]
+        cap_tag::cap_vcpu_cap => {
+            if cap2.get_tag() == cap_tag::cap_vcpu_cap {
+                return cap::cap_vcpu_cap(cap1).get_capVCPUPtr()
+                    == cap::cap_vcpu_cap(cap2).get_capVCPUPtr();
+            }
+        }
         #[cfg(feature = "enable_smc")]
         cap_tag::cap_smc_cap => {
             if cap2.get_tag() == cap_tag::cap_smc_cap {
                 return true;
             }
         }
+        #[cfg(feature = "arm_smmu")]
+        cap_tag::cap_sid_control_cap => {
+            return cap2.get_tag() == cap_tag::cap_sid_control_cap
+                || cap2.get_tag() == cap_tag::cap_sid_cap;
+        }
+        #[cfg(feature = "arm_smmu")]
+        cap_tag::cap_sid_cap => {
+            if cap2.get_tag() == cap_tag::cap_sid_cap {
+                return cap::cap_sid_cap(cap1).get_capSID() == cap::cap_sid_cap(cap2).get_capSID();
+            }
+        }
+        #[cfg(feature = "arm_smmu")]
+        cap_tag::cap_cb_control_cap => {
+            return cap2.get_tag() == cap_tag::cap_cb_control_cap
+                || cap2.get_tag() == cap_tag::cap_cb_cap;
+        }
+        #[cfg(feature = "arm_smmu")]
+        cap_tag::cap_cb_cap => {
+            if cap2.get_tag() == cap_tag::cap_cb_cap {
+                return cap::cap_cb_cap(cap1).get_capCB() == cap::cap_cb_cap(cap2).get_capCB();
+            }
+        }
         _ => panic!("unknown cap"),
     }
     false
@@ -233,5 +275,12 @@ pub fn arch_same_object_as(cap1: &cap, cap2: &cap) -> bool {
             && cap::cap_frame_cap(cap1).get_capFIsDevice()
                 == cap::cap_frame_cap(cap2).get_capFIsDevice();
     }
+    // A control cap is the parent of the SID or CB caps it issues, not the same object.
+    #[cfg(feature = "arm_smmu")]
+    if (cap1.get_tag() == cap_tag::cap_sid_control_cap && cap2.get_tag() == cap_tag::cap_sid_cap)
+        || (cap1.get_tag() == cap_tag::cap_cb_control_cap && cap2.get_tag() == cap_tag::cap_cb_cap)
+    {
+        return false;
+    }
     arch_same_region_as(cap1, cap2)
 }
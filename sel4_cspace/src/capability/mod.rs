@@ -107,6 +107,10 @@ impl cap_func for cap {
                 cap::cap_cnode_cap(self).get_capCNodeRadix() as usize + SEL4_SLOT_BITS
             }
             cap_tag::cap_page_table_cap => PT_SIZE_BITS,
+            #[cfg(target_arch = "aarch64")]
+            cap_tag::cap_vspace_cap => SEL4_VSPACE_BITS,
+            #[cfg(feature = "hypervisor")]
+            cap_tag::cap_vcpu_cap => SEL4_VCPU_BITS,
             #[cfg(feature = "kernel_mcs")]
             cap_tag::cap_reply_cap => SEL4_REPLY_BITS,
             #[cfg(not(feature = "kernel_mcs"))]
@@ -124,6 +128,10 @@ impl cap_func for cap {
         if self.get_tag() == cap_tag::cap_vspace_cap {
             return true;
         }
+        #[cfg(feature = "hypervisor")]
+        if self.get_tag() == cap_tag::cap_vcpu_cap {
+            return true;
+        }
         #[cfg(not(feature = "kernel_mcs"))]
         {
             matches!(
@@ -226,6 +234,13 @@ pub fn same_region_as(cap1: &cap, cap2: &cap) -> bool {
             }
             false
         }
+        #[cfg(feature = "cpu_hotplug")]
+        cap_tag::cap_cpu_control_cap => {
+            if cap2.get_tag() == cap_tag::cap_cpu_control_cap {
+                return true;
+            }
+            false
+        }
         #[cfg(feature = "kernel_mcs")]
         cap_tag::cap_sched_control_cap => {
             if cap2.get_tag() == cap_tag::cap_sched_control_cap {
@@ -322,15 +322,10 @@ impl cte_t {
         unsafe {
             let raw_value = ptr::read_volatile((self.get_ptr() + 24) as *const usize);
             let mut value = ((raw_value >> 2) & mask_bits!(46)) << 2;
-            if (value & (1usize << 46)) != 0 {
-                #[cfg(not(feature = "hypervisor"))]
-                {
-                    value |= 0xffffff8000000000;
-                }
-                #[cfg(feature = "hypervisor")]
-                {
-                    value |= 0x8000000000;
-                }
+            // sign extend the 48-bit pointer, the kernel window of a 44-bit physical
+            // address space reaches below 0xffffff8000000000
+            if (value & (1usize << 47)) != 0 {
+                value |= 0xffff000000000000;
             }
             value
         }
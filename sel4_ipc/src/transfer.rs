@@ -247,6 +247,16 @@ impl Transfer for tcb_t {
                     seL4_Fault::seL4_Fault_VMFault(&self.tcbFault).get_FSR() as usize,
                 )
             }
+            #[cfg(feature = "hypervisor")]
+            seL4_Fault_tag::seL4_Fault_VCPUFault => receiver.set_mr(
+                VCPU_FAULT_HSR,
+                seL4_Fault::seL4_Fault_VCPUFault(&self.tcbFault).get_hsr() as usize,
+            ),
+            #[cfg(feature = "hypervisor")]
+            seL4_Fault_tag::seL4_Fault_VPPIEvent => receiver.set_mr(
+                VPPI_EVENT_IRQ,
+                seL4_Fault::seL4_Fault_VPPIEvent(&self.tcbFault).get_irq_w() as usize,
+            ),
             #[cfg(feature = "kernel_mcs")]
             seL4_Fault_tag::seL4_Fault_Timeout => {
                 let len = receiver.set_mr(
@@ -284,8 +294,8 @@ impl Transfer for tcb_t {
             seL4_MessageInfo::from_word_security(self.tcbArch.get_register(ArchReg::MsgInfo));
         let mut current_extra_caps = [PPtr::null(); SEL4_MSG_MAX_EXTRA_CAPS];
         if can_grant {
-            let status = self.lookup_extra_caps(&mut current_extra_caps);
-            if unlikely(status != exception_t::EXCEPTION_NONE) {
+            // a sender that can't look up its caps sends none, the fault is not reported
+            if unlikely(self.lookup_extra_caps(&mut current_extra_caps).is_err()) {
                 current_extra_caps[0] = PPtr::null();
             }
         } else {
@@ -378,7 +388,6 @@ impl Transfer for tcb_t {
     }
     #[cfg(feature = "kernel_mcs")]
     fn do_reply(&mut self, reply: &mut reply_t, grant: bool) {
-        use sel4_common::{ffi::current_fault, structures_gen::seL4_Fault_Timeout};
         use sel4_task::handleTimeout;
 
         if reply.replyTCB == 0
@@ -425,10 +434,7 @@ impl Transfer for tcb_t {
                 if receiver.valid_timeout_handler()
                     && fault_type != seL4_Fault_tag::seL4_Fault_Timeout as u64
                 {
-                    unsafe {
-                        current_fault = seL4_Fault_Timeout::new(sc.scBadge as u64).unsplay();
-                        handleTimeout(receiver)
-                    };
+                    unsafe { handleTimeout(receiver, sc.scBadge) };
                 } else {
                     sc.postpone();
                 }
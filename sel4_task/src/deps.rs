@@ -1,6 +1,9 @@
 #[cfg(feature = "kernel_mcs")]
 use sel4_common::sel4_config::SEL4_MIN_SCHED_CONTEXT_BITS;
 use sel4_common::sel4_config::{CONFIG_MAX_NUM_NODES, SEL4_TCB_BITS};
+
+#[cfg(feature = "cpu_hotplug")]
+use crate::tcb_t;
 #[repr(align(2048))]
 pub struct ksIdleThreadTCB_data {
     pub data: [[u8; bit!(SEL4_TCB_BITS)]; CONFIG_MAX_NUM_NODES],
@@ -28,4 +31,10 @@ pub static mut ksIdleThreadSC: ksIdleThreadSC_data = ksIdleThreadSC_data {
 extern "C" {
     #[cfg(feature = "enable_smp")]
     pub fn do_mask_reschedule(mask: usize);
+    #[cfg(feature = "cpu_hotplug")]
+    pub fn migrate_tcb(tcb: &mut tcb_t, new_core: usize);
+    #[cfg(feature = "cpu_hotplug")]
+    pub fn park_core();
+    #[cfg(feature = "irq_stats")]
+    pub fn irq_stats_woken(index: usize);
 }
@@ -7,7 +7,9 @@ extern "C" {
     pub fn reorder_ep(ep: &mut endpoint, thread: &mut tcb_t);
     pub fn reorder_ntfn(ntfn: &mut notification, thread: &mut tcb_t);
     pub fn endTimeslice(can_timeout_fault: bool);
-    pub fn handleTimeout(tptr: &mut tcb_t);
+    pub fn handleTimeout(tptr: &mut tcb_t, badge: usize);
     pub fn migrate_tcb(tcb: &mut tcb_t, new_core: usize);
     pub fn remote_tcb_stall(tcb: &tcb_t);
+    #[cfg(feature = "hypervisor")]
+    pub fn vcpu_switch(new: usize);
 }
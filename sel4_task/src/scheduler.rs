@@ -9,6 +9,8 @@
 #[cfg(feature = "enable_smp")]
 use crate::deps::do_mask_reschedule;
 use crate::deps::ksIdleThreadTCB;
+#[cfg(feature = "cpu_hotplug")]
+use crate::deps::park_core;
 #[cfg(feature = "kernel_mcs")]
 use crate::sched_context::{sched_context_t, MIN_REFILLS};
 use crate::tcb::{set_thread_state, tcb_t};
@@ -18,6 +20,10 @@ use crate::thread_state::ThreadState;
 use crate::{deps::ksIdleThreadSC, sched_context::refill_budget_check, tcb_release_dequeue};
 use core::arch::asm;
 use core::intrinsics::{likely, unlikely};
+#[cfg(feature = "cpu_hotplug")]
+use core::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "fine_grained_lock")]
+use rel4_utils::spin_lock::RawSpinLock;
 use sel4_common::arch::ArchReg;
 #[cfg(feature = "enable_smp")]
 use sel4_common::sel4_config::CONFIG_MAX_NUM_NODES;
@@ -25,6 +31,8 @@ use sel4_common::sel4_config::{
     CONFIG_NUM_DOMAINS, CONFIG_NUM_PRIORITIES, CONFIG_TIME_SLICE, L2_BITMAP_SIZE, NUM_READY_QUEUES,
     TCB_OFFSET, WORD_BITS, WORD_RADIX,
 };
+#[cfg(feature = "cpu_hotplug")]
+use sel4_common::utils::convert_to_option_mut_type_ref;
 #[cfg(feature = "enable_smp")]
 use sel4_common::utils::cpu_id;
 use sel4_common::utils::{convert_to_mut_type_ref, ptr_to_usize_add};
@@ -157,6 +165,77 @@ cfg_if::cfg_if! {
     }
 }
 
+/// Per-core locks of the ready queues.
+///
+/// With `fine_grained_lock`, IPC syscalls run without the big kernel lock, and a
+/// core may wake a thread whose affinity is another core, so every access to
+/// `ksSMP[cpu].ksReadyQueues` and its bitmaps must hold `ksReadyQueuesLock[cpu]`.
+#[cfg(feature = "fine_grained_lock")]
+pub static ksReadyQueuesLock: [RawSpinLock; CONFIG_MAX_NUM_NODES] = {
+    const LOCK: RawSpinLock = RawSpinLock::new();
+    [LOCK; CONFIG_MAX_NUM_NODES]
+};
+
+/// The cores that run threads, a core leaves the set while it is offline.
+///
+/// A thread whose affinity is an inactive core is moved to an active core when it is
+/// queued, see [`active_core_for`].
+#[cfg(feature = "cpu_hotplug")]
+static ACTIVE_CORES: AtomicUsize = AtomicUsize::new(bit!(CONFIG_MAX_NUM_NODES) - 1);
+
+/// The bitmap of the active cores.
+#[cfg(feature = "cpu_hotplug")]
+#[inline]
+pub fn active_cores() -> usize {
+    ACTIVE_CORES.load(Ordering::Acquire)
+}
+
+#[cfg(feature = "cpu_hotplug")]
+#[inline]
+pub fn core_is_active(cpu: usize) -> bool {
+    active_cores() & bit!(cpu) != 0
+}
+
+/// Add `cpu` to or remove it from the active cores, with the big kernel lock held.
+#[cfg(feature = "cpu_hotplug")]
+pub fn set_core_active(cpu: usize, active: bool) {
+    if active {
+        ACTIVE_CORES.fetch_or(bit!(cpu), Ordering::AcqRel);
+    } else {
+        ACTIVE_CORES.fetch_and(!bit!(cpu), Ordering::AcqRel);
+    }
+}
+
+/// `cpu` if it is active, otherwise the lowest active core.
+#[cfg(feature = "cpu_hotplug")]
+#[inline]
+pub fn active_core_for(cpu: usize) -> usize {
+    let active = active_cores();
+    if active & bit!(cpu) != 0 {
+        cpu
+    } else {
+        active.trailing_zeros() as usize
+    }
+}
+
+/// Hand the threads of this core, which has been taken offline, to the active cores.
+///
+/// The current thread and the scheduler action are queued through
+/// [`reschedule_required`], and the ready queues are drained in order, so `schedule`
+/// only finds the idle thread afterwards.
+#[cfg(feature = "cpu_hotplug")]
+fn release_inactive_core() {
+    reschedule_required();
+    for idx in 0..NUM_READY_QUEUES {
+        while let Some(tcb) = convert_to_option_mut_type_ref::<tcb_t>(unsafe {
+            ksSMP[cpu_id()].ksReadyQueues[idx].head
+        }) {
+            tcb.sched_dequeue();
+            tcb.sched_append();
+        }
+    }
+}
+
 #[repr(C)]
 #[derive(Debug, PartialEq, Clone, Copy)]
 /// Struct representing a domain schedule.
@@ -169,7 +248,8 @@ pub struct dschedule_t {
 
 pub const SCHEDULER_ACTION_RESUME_CURRENT_THREAD: usize = 0;
 pub const SCHEDULER_ACTION_CHOOSE_NEW_THREAD: usize = 1;
-pub const KS_DOM_SCHEDULE_LENGTH: usize = 1;
+/// Most entries a domain schedule given at boot can have.
+pub const KS_DOM_SCHEDULE_MAX: usize = 16;
 
 pub const SCHED_CONTEXT_NO_FLAGS: usize = 0;
 pub const SCHED_CONTEXT_SPORADIC: usize = 1;
@@ -187,10 +267,28 @@ pub static mut ksDomScheduleIdx: usize = 0;
 pub static mut ksWorkUnitsCompleted: usize = 0;
 
 // #[link_section = ".boot.bss"]
-pub static mut ksDomSchedule: [dschedule_t; KS_DOM_SCHEDULE_LENGTH] = [dschedule_t {
+pub static mut ksDomSchedule: [dschedule_t; KS_DOM_SCHEDULE_MAX] = [dschedule_t {
     domain: 0,
     length: 60,
-}; KS_DOM_SCHEDULE_LENGTH];
+}; KS_DOM_SCHEDULE_MAX];
+
+/// Number of entries of `ksDomSchedule` in use.
+#[no_mangle]
+pub static mut ksDomScheduleLength: usize = 1;
+
+/// Replace the domain schedule, before the root server is created. The entries must
+/// have a valid domain and a non-zero length. False if there are none or too many.
+pub fn set_dom_schedule(schedule: &[dschedule_t]) -> bool {
+    if schedule.is_empty() || schedule.len() > KS_DOM_SCHEDULE_MAX {
+        return false;
+    }
+    unsafe {
+        (*(&raw mut ksDomSchedule))[..schedule.len()].copy_from_slice(schedule);
+        ksDomScheduleLength = schedule.len();
+        ksDomScheduleIdx = 0;
+    }
+    true
+}
 
 #[allow(non_camel_case_types)]
 pub type prio_t = usize;
@@ -431,7 +529,7 @@ pub fn remove_from_bigmap(_cpu: usize, dom: usize, prio: usize) {
 fn next_domain() {
     unsafe {
         ksDomScheduleIdx += 1;
-        if ksDomScheduleIdx >= KS_DOM_SCHEDULE_LENGTH {
+        if ksDomScheduleIdx >= ksDomScheduleLength {
             ksDomScheduleIdx = 0;
         }
         #[cfg(feature = "kernel_mcs")]
@@ -469,6 +567,8 @@ fn schedule_choose_new_thread() {
 fn choose_thread() {
     unsafe {
         let dom = 0;
+        #[cfg(feature = "fine_grained_lock")]
+        let queues_guard = ksReadyQueuesLock[cpu_id()].guard();
         let ks_l1_bit = {
             #[cfg(feature = "enable_smp")]
             {
@@ -504,10 +604,18 @@ fn choose_thread() {
                 )
                 .refill_ready());
             }
+            #[cfg(feature = "fine_grained_lock")]
+            drop(queues_guard);
             convert_to_mut_type_ref::<tcb_t>(thread).switch_to_this();
         } else {
+            #[cfg(feature = "fine_grained_lock")]
+            drop(queues_guard);
             #[cfg(target_arch = "aarch64")]
             {
+                #[cfg(feature = "hypervisor")]
+                unsafe {
+                    crate::ffi::vcpu_switch(0)
+                };
                 set_current_user_vspace_root(ttbr_new(
                     0,
                     kpptr_to_paddr(get_arm_global_user_vspace_base()),
@@ -769,6 +877,10 @@ pub fn schedule() {
         awaken();
         check_domain_time();
     }
+    #[cfg(feature = "cpu_hotplug")]
+    if unlikely(!core_is_active(cpu_id())) {
+        release_inactive_core();
+    }
     if NODE_STATE!(ksSchedulerAction) != SCHEDULER_ACTION_RESUME_CURRENT_THREAD {
         let was_runnable: bool;
         let current_tcb = get_currenct_thread();
@@ -807,6 +919,10 @@ pub fn schedule() {
         do_mask_reschedule(ksSMP[cpu_id()].ipiReschedulePending);
         ksSMP[cpu_id()].ipiReschedulePending = 0;
     }
+    #[cfg(feature = "cpu_hotplug")]
+    if unlikely(!core_is_active(cpu_id())) {
+        unsafe { park_core() };
+    }
     #[cfg(feature = "kernel_mcs")]
     {
         switch_sched_context();
@@ -918,6 +1034,11 @@ pub fn timer_tick() {
 /// Activate the current thread.
 pub fn activateThread() {
     let thread = get_currenct_thread();
+    #[cfg(feature = "irq_stats")]
+    if unlikely(thread.tcbIRQWake != 0) {
+        unsafe { crate::deps::irq_stats_woken(thread.tcbIRQWake - 1) };
+        thread.tcbIRQWake = 0;
+    }
     // debug!("current: {:#x}", thread.get_ptr());
     #[cfg(feature = "kernel_mcs")]
     {
@@ -1018,8 +1139,67 @@ pub fn create_idle_thread() {
     }
 }
 
+/// The idle thread of every core, it waits for interrupts in a low-power state.
+///
+/// By default the core only waits with `wfi`. With `idle_suspend` it enters the deeper
+/// `CONFIG_IDLE_SUSPEND_STATE` of the platform instead, a PSCI `CPU_SUSPEND` power
+/// state or an SBI HSM suspend type, when the next timer deadline is at least
+/// `CONFIG_IDLE_SUSPEND_MIN_US` away with MCS, and on every idle wait without it. Once the firmware refuses that state the core
+/// takes the PSCI standby or HSM default retentive suspend. All these states keep the
+/// core state and wake up on any pending interrupt, so the timer, the MCS release
+/// queue deadline programmed before `awaken` and the reschedule and remote call IPIs
+/// still wake the core. An interrupt taken between the check and the suspend is
+/// handled first, the core then suspends once and checks the deadline again on wake up.
 pub fn idle_thread() {
     loop {
+        #[cfg(feature = "idle_suspend")]
+        if IDLE_SUSPEND.load(core::sync::atomic::Ordering::Relaxed) && idle_deadline_is_far() {
+            if !IDLE_SUSPEND_DEEP.load(core::sync::atomic::Ordering::Relaxed)
+                || !sel4_common::arch::cpu_idle_suspend()
+            {
+                IDLE_SUSPEND_DEEP.store(false, core::sync::atomic::Ordering::Relaxed);
+                sel4_common::arch::cpu_standby();
+            }
+            continue;
+        }
         unsafe { asm!("wfi") };
     }
 }
+
+/// Cleared by the `idle=wfi` boot option.
+#[cfg(feature = "idle_suspend")]
+static IDLE_SUSPEND: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(true);
+
+/// Cleared once the firmware refuses `CONFIG_IDLE_SUSPEND_STATE`.
+#[cfg(feature = "idle_suspend")]
+static IDLE_SUSPEND_DEEP: core::sync::atomic::AtomicBool =
+    core::sync::atomic::AtomicBool::new(true);
+
+/// Let the idle thread suspend the core, or only wait with `wfi`. False if the kernel is
+/// built without `idle_suspend`.
+pub fn set_idle_suspend(enabled: bool) -> bool {
+    #[cfg(feature = "idle_suspend")]
+    IDLE_SUSPEND.store(enabled, core::sync::atomic::Ordering::Relaxed);
+    cfg!(feature = "idle_suspend") || !enabled
+}
+
+/// Whether the next timer deadline of this core is far enough away for a suspend.
+///
+/// Without MCS the deadline is always the next periodic tick, `CONFIG_IDLE_SUSPEND_MIN_US`
+/// only applies to the one-shot deadlines of MCS.
+#[cfg(all(feature = "idle_suspend", not(feature = "kernel_mcs")))]
+fn idle_deadline_is_far() -> bool {
+    true
+}
+
+/// Whether the next timer deadline of this core is far enough away for a suspend.
+#[cfg(all(feature = "idle_suspend", feature = "kernel_mcs"))]
+fn idle_deadline_is_far() -> bool {
+    use sel4_common::platform::time_def::{timer_clock_hz, MS_IN_S, US_IN_MS};
+    use sel4_common::platform::{timer, Timer_func};
+    use sel4_common::sel4_config::CONFIG_IDLE_SUSPEND_MIN_US;
+
+    let min_ticks = CONFIG_IDLE_SUSPEND_MIN_US * (timer_clock_hz() / (US_IN_MS * MS_IN_S));
+    let now = timer.get_current_time();
+    timer.get_deadline().saturating_sub(now) >= min_ticks
+}
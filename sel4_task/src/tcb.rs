@@ -11,7 +11,6 @@ use sel4_common::arch::{
     vm_rights_t, ArchReg, ArchTCB, MSG_REGISTER_NUM, N_EXCEPTON_MESSAGE, N_SYSCALL_MESSAGE,
 };
 use sel4_common::fault::*;
-use sel4_common::ffi::current_fault;
 use sel4_common::message_info::seL4_MessageInfo_func;
 use sel4_common::sel4_config::*;
 use sel4_common::shared_types_bf_gen::seL4_MessageInfo;
@@ -80,6 +79,9 @@ pub struct tcb_t {
     pub tcbEPNext: usize,
     /// The previous TCB in the EP queue
     pub tcbEPPrev: usize,
+    #[cfg(feature = "irq_stats")]
+    /// The IRQ index plus one whose signal woke the TCB, 0 if none
+    pub tcbIRQWake: usize,
 }
 
 impl tcb_t {
@@ -271,6 +273,10 @@ impl tcb_t {
             );
         }
 
+        #[cfg(feature = "cpu_hotplug")]
+        self.leave_inactive_core();
+        #[cfg(feature = "fine_grained_lock")]
+        let _guard = super::scheduler::ksReadyQueuesLock[self.tcbAffinity].guard();
         if self.tcbState.get_tcbQueued() == 0 {
             let dom = self.domain;
             let prio = self.tcbPriority;
@@ -311,6 +317,16 @@ impl tcb_t {
         self.update_queue();
     }
 
+    /// Move the TCB to an active core if its core has been taken offline.
+    #[cfg(feature = "cpu_hotplug")]
+    #[inline]
+    fn leave_inactive_core(&mut self) {
+        let core = super::scheduler::active_core_for(self.tcbAffinity);
+        if unlikely(core != self.tcbAffinity && self.tcbState.get_tcbQueued() == 0) {
+            unsafe { crate::deps::migrate_tcb(self, core) };
+        }
+    }
+
     #[inline]
     /// Get the scheduling queue by index from ksReadyQueues
     pub fn get_sched_queue(&mut self, index: usize) -> &'static mut tcb_queue_t {
@@ -345,6 +361,8 @@ impl tcb_t {
     pub fn sched_dequeue(&mut self) {
         // let thread = self as *mut tcb_t as usize;
         // sel4_common::println!("{}: sched_dequeue: {:#x}, tcb queued: {}", self.get_cpu(), thread, self.tcbState.get_tcbQueued());
+        #[cfg(feature = "fine_grained_lock")]
+        let _guard = super::scheduler::ksReadyQueuesLock[self.tcbAffinity].guard();
         if self.tcbState.get_tcbQueued() != 0 {
             let dom = self.domain;
             let prio = self.tcbPriority;
@@ -392,6 +410,10 @@ impl tcb_t {
             );
         }
         let self_ptr = self as *mut tcb_t;
+        #[cfg(feature = "cpu_hotplug")]
+        self.leave_inactive_core();
+        #[cfg(feature = "fine_grained_lock")]
+        let _guard = super::scheduler::ksReadyQueuesLock[self.tcbAffinity].guard();
         if self.tcbState.get_tcbQueued() == 0 {
             let dom = self.domain;
             let prio = self.tcbPriority;
@@ -493,6 +515,10 @@ impl tcb_t {
         // if hart_id() == 0 {
         //     debug!("switch_to_this: {:#x}", self.get_ptr());
         // }
+        #[cfg(feature = "hypervisor")]
+        unsafe {
+            crate::ffi::vcpu_switch(self.tcbArch.vcpu)
+        };
         let _ = self.set_vm_root();
         self.sched_dequeue();
         set_current_thread(self);
@@ -685,7 +711,10 @@ impl tcb_t {
     /// * `res` - The result array to store the extra caps
     /// # Returns
     /// The result of the lookup represented by seL4_Fault_t
-    pub fn lookup_extra_caps(&mut self, res: &mut [PPtr; SEL4_MSG_MAX_EXTRA_CAPS]) -> exception_t {
+    pub fn lookup_extra_caps(
+        &mut self,
+        res: &mut [PPtr; SEL4_MSG_MAX_EXTRA_CAPS],
+    ) -> Result<(), seL4_Fault> {
         let info =
             seL4_MessageInfo::from_word_security(self.tcbArch.get_register(ArchReg::MsgInfo));
         if let Some(buffer) = self.lookup_ipc_buffer(false) {
@@ -695,11 +724,7 @@ impl tcb_t {
                 let cptr = buffer.get_extra_cptr(i as usize);
                 let lu_ret = self.lookup_slot(cptr);
                 if unlikely(lu_ret.status != exception_t::EXCEPTION_NONE) {
-                    unsafe {
-                        current_fault =
-                            seL4_Fault_CapFault::new(cptr as u64, false as u64).unsplay();
-                    }
-                    return lu_ret.status;
+                    return Err(seL4_Fault_CapFault::new(cptr as u64, false as u64).unsplay());
                 }
                 res[i as usize] = pptr!(lu_ret.slot);
                 i += 1;
@@ -710,7 +735,7 @@ impl tcb_t {
         } else {
             res[0] = PPtr::new(0);
         }
-        exception_t::EXCEPTION_NONE
+        Ok(())
     }
 
     /// Look up the extra caps of the TCB with IPC buffer
@@ -987,6 +1012,16 @@ impl tcb_t {
                     seL4_Fault::seL4_Fault_VMFault(&self.tcbFault).get_FSR() as usize,
                 )
             }
+            #[cfg(feature = "hypervisor")]
+            seL4_Fault_tag::seL4_Fault_VCPUFault => receiver.set_mr(
+                VCPU_FAULT_HSR,
+                seL4_Fault::seL4_Fault_VCPUFault(&self.tcbFault).get_hsr() as usize,
+            ),
+            #[cfg(feature = "hypervisor")]
+            seL4_Fault_tag::seL4_Fault_VPPIEvent => receiver.set_mr(
+                VPPI_EVENT_IRQ,
+                seL4_Fault::seL4_Fault_VPPIEvent(&self.tcbFault).get_irq_w() as usize,
+            ),
             _ => {
                 panic!("invalid fault")
             }
@@ -9,14 +9,30 @@ use sel4_common::{
     utils::{convert_to_mut_type_ref, convert_to_option_mut_type_ref},
 };
 
-use crate::{asid_pool_t, asid_t, findVSpaceForASID_ret, set_vm_root};
+use crate::{asid_pool_t, asid_t, findVSpaceForASID_ret, invalidate_tlb_by_asid, set_vm_root};
+#[cfg(feature = "hypervisor")]
+use sel4_common::sel4_config::ASID_INVALID;
 use sel4_common::structures_gen::asid_map;
 
 use super::asid_pool_from_addr;
+#[cfg(feature = "hypervisor")]
 use super::machine::invalidate_local_tlb_asid;
 
 pub(crate) static mut armKSASIDTable: [usize; bit!(ASID_HIGH_BITS)] = [0; bit!(ASID_HIGH_BITS)];
 
+/// 硬件 VMID 的位数
+#[cfg(feature = "hypervisor")]
+const HW_VMID_BITS: usize = 8;
+
+/// hypervisor 模式下 VTTBR_EL2 中只能放 VMID, 这里记录每个 VMID 当前分配给了哪个`asid`.
+/// VMID 0 保留给全局的用户页表.
+#[cfg(feature = "hypervisor")]
+static mut armKSHWASIDTable: [asid_t; bit!(HW_VMID_BITS)] = [ASID_INVALID; bit!(HW_VMID_BITS)];
+
+/// 下一个尝试分配的 VMID
+#[cfg(feature = "hypervisor")]
+static mut armKSNextASID: usize = 1;
+
 #[inline]
 fn get_asid_table() -> &'static mut [usize] {
     unsafe { core::slice::from_raw_parts_mut(&raw mut armKSASIDTable as _, bit!(ASID_HIGH_BITS)) }
@@ -65,6 +81,117 @@ pub fn find_vspace_for_asid(asid: usize) -> findVSpaceForASID_ret {
     ret
 }
 
+/// 获取`asid`对应的硬件 ASID, hypervisor 模式下为其分配的 VMID
+#[cfg(not(feature = "hypervisor"))]
+#[inline]
+pub fn get_hw_asid(asid: asid_t) -> asid_t {
+    asid
+}
+
+/// 获取`asid`对应的硬件 ASID, hypervisor 模式下为其分配的 VMID
+#[cfg(feature = "hypervisor")]
+pub fn get_hw_asid(asid: asid_t) -> asid_t {
+    let map = find_map_for_asid(asid).expect("asid is not mapped");
+    let vspace_map = asid_map::asid_map_vspace(map);
+    if vspace_map.get_stored_vmid_valid() != 0 {
+        return vspace_map.get_stored_hw_vmid() as asid_t;
+    }
+    let hw_asid = find_free_hw_asid();
+    vspace_map.set_stored_hw_vmid(hw_asid as u64);
+    vspace_map.set_stored_vmid_valid(1);
+    unsafe {
+        armKSHWASIDTable[hw_asid] = asid;
+    }
+    hw_asid
+}
+
+/// 找到一个空闲的 VMID, 如果都已被占用则回收下一个 VMID
+#[cfg(feature = "hypervisor")]
+fn find_free_hw_asid() -> asid_t {
+    unsafe {
+        for _ in 0..bit!(HW_VMID_BITS) {
+            let hw_asid = armKSNextASID;
+            advance_next_hw_asid();
+            if armKSHWASIDTable[hw_asid] == ASID_INVALID {
+                return hw_asid;
+            }
+        }
+        let hw_asid = armKSNextASID;
+        advance_next_hw_asid();
+        if let Some(map) = find_map_for_asid(armKSHWASIDTable[hw_asid]) {
+            asid_map::asid_map_vspace(map).set_stored_vmid_valid(0);
+        }
+        invalidate_local_tlb_asid(hw_asid);
+        #[cfg(feature = "enable_smp")]
+        {
+            extern "C" {
+                fn remote_invalidate_tlb_asid(asid: asid_t);
+            }
+            remote_invalidate_tlb_asid(hw_asid);
+        }
+        armKSHWASIDTable[hw_asid] = ASID_INVALID;
+        hw_asid
+    }
+}
+
+#[cfg(feature = "hypervisor")]
+#[inline]
+fn advance_next_hw_asid() {
+    unsafe {
+        armKSNextASID = (armKSNextASID + 1) & mask_bits!(HW_VMID_BITS);
+        if armKSNextASID == 0 {
+            armKSNextASID = 1;
+        }
+    }
+}
+
+/// 释放`asid`占用的 VMID
+#[cfg(feature = "hypervisor")]
+fn invalidate_asid_entry(asid: asid_t) {
+    if let Some(map) = find_map_for_asid(asid) {
+        let vspace_map = asid_map::asid_map_vspace(map);
+        if vspace_map.get_stored_vmid_valid() != 0 {
+            unsafe {
+                armKSHWASIDTable[vspace_map.get_stored_hw_vmid() as usize] = ASID_INVALID;
+            }
+        }
+        vspace_map.set_stored_vmid_valid(0);
+    }
+}
+
+/// `asid`的页表被几个 SMMU context bank 使用, 非零时 TLB 维护也需要作用于 SMMU
+#[cfg(feature = "arm_smmu")]
+pub fn asid_bind_cb(asid: asid_t) -> usize {
+    match find_map_for_asid(asid) {
+        Some(map) if map.get_tag() == asid_map_tag::asid_map_asid_map_vspace => {
+            asid_map::asid_map_vspace(map).get_bind_cb() as usize
+        }
+        _ => 0,
+    }
+}
+
+/// context bank 使用`asid`的页表时调用
+#[cfg(feature = "arm_smmu")]
+pub fn increase_asid_bind_cb(asid: asid_t) {
+    if let Some(map) = find_map_for_asid(asid)
+        .filter(|map| map.get_tag() == asid_map_tag::asid_map_asid_map_vspace)
+    {
+        let vspace_map = asid_map::asid_map_vspace(map);
+        vspace_map.set_bind_cb(vspace_map.get_bind_cb() + 1);
+    }
+}
+
+/// context bank 不再使用`asid`的页表时调用
+#[cfg(feature = "arm_smmu")]
+pub fn decrease_asid_bind_cb(asid: asid_t) {
+    if let Some(map) = find_map_for_asid(asid)
+        .filter(|map| map.get_tag() == asid_map_tag::asid_map_asid_map_vspace)
+    {
+        let vspace_map = asid_map::asid_map_vspace(map);
+        vspace_map.set_bind_cb(vspace_map.get_bind_cb() - 1);
+    }
+}
+
 #[no_mangle]
 pub fn delete_asid(asid: usize, vspace: *mut PTE, capability: &cap) -> Result<(), lookup_fault> {
     let ptr =
@@ -74,7 +201,9 @@ pub fn delete_asid(asid: usize, vspace: *mut PTE, capability: &cap) -> Result<()
         match asidmap.clone().splay() {
             asid_map_Splayed::asid_map_vspace(data) => {
                 if data.get_vspace_root() == vspace as u64 {
-                    invalidate_local_tlb_asid(asid);
+                    invalidate_tlb_by_asid(asid);
+                    #[cfg(feature = "hypervisor")]
+                    invalidate_asid_entry(asid);
                     pool[asid & mask_bits!(ASID_LOW_BITS)] =
                         asid_map_asid_map_none::new().unsplay();
                     return set_vm_root(capability);
@@ -99,7 +228,9 @@ pub fn delete_asid_pool(
         for offset in 0..bit!(ASID_LOW_BITS) {
             let asidmap = &pool[offset];
             if asidmap.get_tag() == asid_map_tag::asid_map_asid_map_vspace {
-                invalidate_local_tlb_asid(asid_base + offset);
+                invalidate_tlb_by_asid(asid_base + offset);
+                #[cfg(feature = "hypervisor")]
+                invalidate_asid_entry(asid_base + offset);
             }
         }
         set_asid_pool_by_index(asid_base >> ASID_LOW_BITS, 0);
@@ -111,7 +242,14 @@ pub fn delete_asid_pool(
 #[no_mangle]
 pub fn write_it_asid_pool(it_ap_cap: &cap_asid_pool_cap, it_vspace_cap: &cap_vspace_cap) {
     let ap = asid_pool_from_addr(it_ap_cap.get_capASIDPool() as usize);
+    #[cfg(not(any(feature = "hypervisor", feature = "arm_smmu")))]
     let asidmap = asid_map_asid_map_vspace::new(it_vspace_cap.get_capVSBasePtr() as u64).unsplay();
+    #[cfg(feature = "arm_smmu")]
+    let asidmap =
+        asid_map_asid_map_vspace::new(0, it_vspace_cap.get_capVSBasePtr() as u64).unsplay();
+    #[cfg(feature = "hypervisor")]
+    let asidmap =
+        asid_map_asid_map_vspace::new(it_vspace_cap.get_capVSBasePtr() as u64, 0, 0).unsplay();
     ap[IT_ASID] = asidmap;
     set_asid_pool_by_index(IT_ASID >> ASID_LOW_BITS, ap as *const _ as usize);
 }
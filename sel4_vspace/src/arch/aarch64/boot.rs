@@ -1,22 +1,28 @@
 use rel4_arch::basic::{PPtr, VPtr};
 use sel4_common::{
     arch::{
-        config::{PADDR_BASE, PADDR_TOP, PPTR_BASE, PPTR_TOP},
+        config::{PADDR_BASE, PADDR_TOP, PA_SIZE_BITS, PPTR_BASE, PPTR_TOP},
         vm_rights_t,
     },
-    sel4_config::{ARM_LARGE_PAGE, ARM_SMALL_PAGE, PUD_INDEX_BITS, SEL4_LARGE_PAGE_BITS},
+    sel4_config::{
+        ARM_LARGE_PAGE, ARM_SMALL_PAGE, PGD_INDEX_OFFSET, PUD_INDEX_BITS, SEL4_HUGE_PAGE_BITS,
+        SEL4_LARGE_PAGE_BITS,
+    },
     structures_gen::{cap, cap_frame_cap, cap_page_table_cap, cap_vspace_cap},
-    utils::convert_to_mut_type_ref,
+    utils::{convert_to_mut_slice, convert_to_mut_type_ref},
 };
 
 use crate::{
     arch::VAddr, asid_t, get_kernel_page_directory_base_by_index, get_kernel_page_table_base,
-    get_kernel_page_upper_directory_base, kpptr_to_paddr, mair_types,
+    get_kernel_page_upper_directory_base_by_index, kpptr_to_paddr, mair_types,
     set_kernel_page_directory_by_index, set_kernel_page_global_directory_by_index,
     set_kernel_page_table_by_index, set_kernel_page_upper_directory_by_index, vm_attributes_t, PTE,
 };
 
-use super::{map_kernel_devices, page_slice};
+use super::{
+    interface::KERNEL_PUD_NUM, map_kernel_devices, page_slice, AARCH64_VSPACE_S2_START_L1,
+    VSPACE_INDEX_BITS,
+};
 
 #[derive(PartialEq, Eq, Debug)]
 enum find_type {
@@ -25,17 +31,83 @@ enum find_type {
     PTE,
 }
 
+// The kernel window maps [PADDR_BASE, PADDR_TOP), the physical memory from there up to
+// PADDR_USER_DEVICE_TOP is only handed to user space as device untypeds.
+const _: () = assert!(
+    PADDR_TOP <= bit!(PA_SIZE_BITS),
+    "kernel window exceeds the physical address space"
+);
+
 #[no_mangle]
 #[link_section = ".boot.text"]
 pub fn rust_map_kernel_window() {
-    set_kernel_page_global_directory_by_index(
-        (VAddr(PPTR_BASE)).get_kpt_index(0),
-        PTE::pte_new_table(kpptr_to_paddr(get_kernel_page_upper_directory_base())),
+    for pud in 0..KERNEL_PUD_NUM {
+        set_kernel_page_global_directory_by_index(
+            VAddr(PPTR_BASE).get_kpt_index(0) + pud,
+            PTE::pte_new_table(kpptr_to_paddr(
+                get_kernel_page_upper_directory_base_by_index(pud),
+            )),
+        );
+    }
+
+    if KERNEL_PUD_NUM > 1 {
+        map_kernel_window_huge();
+    } else {
+        map_kernel_window_large();
+    }
+
+    set_kernel_page_upper_directory_by_index(
+        KERNEL_PUD_NUM - 1,
+        VAddr(PPTR_TOP).get_kpt_index(1),
+        PTE::pte_new_table(kpptr_to_paddr(get_kernel_page_directory_base_by_index(
+            bit!(PUD_INDEX_BITS) - 1,
+        ))),
+    );
+    set_kernel_page_directory_by_index(
+        bit!(PUD_INDEX_BITS) - 1,
+        bit!(PUD_INDEX_BITS) - 1,
+        PTE::pte_new_table(kpptr_to_paddr(get_kernel_page_table_base())),
     );
+    map_kernel_devices();
+    // ffi_call!(map_kernel_devices());
+}
 
+/// Map the kernel window of a 44-bit physical address space with 1 GiB blocks, it
+/// spans several upper directories and there are not enough page directories for it.
+#[link_section = ".boot.text"]
+fn map_kernel_window_huge() {
+    let mut vaddr = PPTR_BASE;
+    let mut paddr = PADDR_BASE;
+    let shareable = if cfg!(feature = "enable_smp") { 3 } else { 0 };
+    let uxn = if cfg!(feature = "hypervisor") { 0 } else { 1 };
+
+    while paddr < PADDR_TOP {
+        set_kernel_page_upper_directory_by_index(
+            (vaddr - PPTR_BASE) >> PGD_INDEX_OFFSET,
+            VAddr(vaddr).get_kpt_index(1),
+            PTE::pte_new_page(
+                uxn,
+                paddr!(paddr),
+                0,
+                1,
+                shareable,
+                0,
+                mair_types::NORMAL as usize,
+            ),
+        );
+
+        vaddr += bit!(SEL4_HUGE_PAGE_BITS);
+        paddr += bit!(SEL4_HUGE_PAGE_BITS);
+    }
+}
+
+/// Map the kernel window of a 40-bit physical address space with 2 MiB pages.
+#[link_section = ".boot.text"]
+fn map_kernel_window_large() {
     let mut idx = VAddr(PPTR_BASE).get_kpt_index(1);
     while idx < VAddr(PPTR_TOP).get_kpt_index(1) {
         set_kernel_page_upper_directory_by_index(
+            0,
             idx,
             PTE::pte_new_table(kpptr_to_paddr(get_kernel_page_directory_base_by_index(idx))),
         );
@@ -79,20 +151,6 @@ pub fn rust_map_kernel_window() {
         vaddr += bit!(SEL4_LARGE_PAGE_BITS);
         paddr += bit!(SEL4_LARGE_PAGE_BITS)
     }
-
-    set_kernel_page_upper_directory_by_index(
-        VAddr(PPTR_TOP).get_kpt_index(1),
-        PTE::pte_new_table(kpptr_to_paddr(get_kernel_page_directory_base_by_index(
-            bit!(PUD_INDEX_BITS) - 1,
-        ))),
-    );
-    set_kernel_page_directory_by_index(
-        bit!(PUD_INDEX_BITS) - 1,
-        bit!(PUD_INDEX_BITS) - 1,
-        PTE::pte_new_table(kpptr_to_paddr(get_kernel_page_table_base())),
-    );
-    map_kernel_devices();
-    // ffi_call!(map_kernel_devices());
 }
 
 #[no_mangle]
@@ -143,14 +201,12 @@ pub fn map_it_pt_cap(vspace_cap: &cap_vspace_cap, pt_cap: &cap_page_table_cap) {
 #[no_mangle]
 #[link_section = ".boot.text"]
 pub fn map_it_pd_cap(vspace_cap: &cap_vspace_cap, pd_cap: &cap_page_table_cap) {
-    let pgd = page_slice::<PTE>(pptr!(vspace_cap.get_capVSBasePtr()));
     let pd_addr = pd_cap.get_capPTBasePtr() as usize;
     let vptr: VAddr = (pd_cap.get_capPTMappedAddress() as usize).into();
     assert_eq!(pd_cap.get_capPTIsMapped(), 1);
+    let pude = find_pude(vspace_cap.get_capVSBasePtr() as usize, vptr);
     // TODO: move 0x3 into a proper position.
-    assert_eq!(pgd[vptr.pgd_index()].attr(), 0x3);
-    let pud = pgd[vptr.pgd_index()].next_level_slice::<PTE>();
-    pud[vptr.pud_index()] = PTE::new_page(pptr!(pd_addr).to_paddr(), 0x3);
+    *pude = PTE::new_page(pptr!(pd_addr).to_paddr(), 0x3);
 }
 
 /// TODO: Write the comments.
@@ -177,22 +233,26 @@ pub fn map_it_frame_cap(vspace_cap: &cap_vspace_cap, frame_cap: &cap_frame_cap,
     // TIPS: exec true will be cast to 1 and false to 0.
     let shareable = if cfg!(feature = "enable_smp") { 3 } else { 0 };
     #[cfg(not(feature = "hypervisor"))]
-    let (ng, attr) = (1, 0);
+    let (ng, ap, attr) = (1, 1, 0);
+    // stage-2 页表项没有 nG 位, 权限和内存属性分别由 S2AP 和 MemAttr 给出
     #[cfg(feature = "hypervisor")]
-    let (ng, attr) = (1, 0);
-    pte.set_attr(PTE::pte_new_4k_page((!exec) as usize, paddr!(0), ng, 1, shareable, 1, attr).0);
+    let (ng, ap, attr) = (
+        0,
+        PTE::ap_from_vm_rights_t(vm_rights_t::VMReadWrite).bits() >> 6,
+        PTE::attr_index_from_cacheable(true),
+    );
+    pte.set_attr(PTE::pte_new_4k_page((!exec) as usize, paddr!(0), ng, 1, shareable, ap, attr).0);
     pte.set_next_level_paddr(pptr!(frame_cap.get_capFBasePtr()).to_paddr());
 }
 
 /// TODO: Write the comments.
 #[link_section = ".boot.text"]
 fn find_pt(vspace_root: usize, vptr: VAddr, ftype: find_type) -> usize {
-    let pgd = page_slice::<PTE>(pptr!(vspace_root));
-    let pud = pgd[vptr.pgd_index()].next_level_slice::<PTE>();
+    let pude = find_pude(vspace_root, vptr);
     if ftype == find_type::PUDE {
-        return pud[vptr.pud_index()].self_addr();
+        return pude.self_addr();
     }
-    let pd = pud[vptr.pud_index()].next_level_slice::<PTE>();
+    let pd = pude.next_level_slice::<PTE>();
     if ftype == find_type::PDE {
         return pd[vptr.pd_index()].self_addr();
     }
@@ -201,6 +261,19 @@ fn find_pt(vspace_root: usize, vptr: VAddr, ftype: find_type) -> usize {
     pt[vptr.pt_index()].self_addr()
 }
 
+/// 找到`vptr`在 PUD 中对应的表项, stage-2 从 level 1 开始查找时根页表本身就是 PUD
+#[link_section = ".boot.text"]
+fn find_pude(vspace_root: usize, vptr: VAddr) -> &'static mut PTE {
+    let root = convert_to_mut_slice::<PTE>(vspace_root, bit!(VSPACE_INDEX_BITS));
+    if AARCH64_VSPACE_S2_START_L1 {
+        return &mut root[vptr.get_upt_index(0)];
+    }
+    // TODO: move 0x3 into a proper position.
+    assert_eq!(root[vptr.pgd_index()].attr(), 0x3);
+    let pud = root[vptr.pgd_index()].next_level_slice::<PTE>();
+    &mut pud[vptr.pud_index()]
+}
+
 /// Create a new pud cap in the vspace.
 ///
 /// vptr is the virtual address of the pud cap will be created
@@ -30,3 +30,31 @@ pub fn map_kernel_devices() {
         }
     }
 }
+
+/// Move the kernel devices at the physical addresses `paddrs` by `offset` bytes, keeping
+/// their kernel addresses, for a device the DTB places elsewhere than the platform YAML.
+///
+/// Only on the boot core, after the kernel vspace is active and before the devices are
+/// used. The frames they leave stay reserved.
+pub fn move_kernel_devices(paddrs: &[usize], offset: isize) {
+    unsafe {
+        for kernel_frame in (*(&raw mut kernel_device_frames)).iter_mut() {
+            let paddr = kernel_frame.paddr.raw();
+            if !paddrs.contains(&paddr) {
+                continue;
+            }
+            let moved = paddr.wrapping_add_signed(offset);
+            kernel_frame.paddr = paddr!(moved);
+            map_kernel_frame(
+                moved,
+                kernel_frame.pptr.raw(),
+                VMKernelOnly,
+                vm_attributes_t(kernel_frame.armExecuteNever as usize),
+            );
+            if kernel_frame.userAvailable == 0 {
+                reserve_region(PRegion::new(paddr!(moved), paddr!(moved + bit!(PAGE_BITS))));
+            }
+        }
+    }
+    super::invalidate_local_tlb();
+}
@@ -1,24 +1,38 @@
 use super::pte::pte_tag_t;
 use super::{kpptr_to_paddr, machine::*, UPT_LEVELS};
 use crate::arch::VAddr;
+#[cfg(feature = "arm_smmu")]
+use crate::asid_bind_cb;
+#[cfg(feature = "hypervisor")]
+use crate::find_map_for_asid;
 use crate::utils::PageAligned;
-use crate::{asid_t, find_vspace_for_asid, PTE};
+use crate::{asid_t, find_vspace_for_asid, get_hw_asid, PTE};
 use core::intrinsics::unlikely;
 use rel4_arch::basic::{PAddr, PPtr, VPtr};
+use sel4_common::arch::config::{PPTR_BASE, PPTR_TOP};
 use sel4_common::arch::MessageLabel;
 use sel4_common::structures::exception_t;
+#[cfg(feature = "hypervisor")]
+use sel4_common::structures_gen::asid_map;
 use sel4_common::structures_gen::{cap, cap_tag, cap_vspace_cap};
 use sel4_common::utils::{pageBitsForSize, ptr_to_mut};
-use sel4_common::{sel4_config::SEL4_PAGE_BITS, structures_gen::lookup_fault};
+use sel4_common::{
+    sel4_config::{PGD_INDEX_OFFSET, SEL4_PAGE_BITS},
+    structures_gen::lookup_fault,
+};
 use sel4_cspace::capability::cap_arch_func;
 
 #[no_mangle]
 #[link_section = ".page_table"]
 pub(crate) static mut armKSGlobalKernelPGD: PageAligned<PTE> = PageAligned::new(PTE(0));
 
+/// The number of upper directories of the kernel window, one for each 512 GiB of it.
+pub(crate) const KERNEL_PUD_NUM: usize = (PPTR_TOP - PPTR_BASE).div_ceil(bit!(PGD_INDEX_OFFSET));
+
 #[no_mangle]
 #[link_section = ".page_table"]
-pub(crate) static mut armKSGlobalKernelPUD: PageAligned<PTE> = PageAligned::new(PTE(0));
+pub(crate) static mut armKSGlobalKernelPUD: [PageAligned<PTE>; KERNEL_PUD_NUM] =
+    [PageAligned::new(PTE(0)); KERNEL_PUD_NUM];
 
 #[no_mangle]
 #[link_section = ".page_table"]
@@ -44,13 +58,13 @@ pub fn set_kernel_page_global_directory_by_index(idx: usize, pgde: PTE) {
 }
 
 #[inline]
-pub fn get_kernel_page_upper_directory_base() -> usize {
-    &raw const armKSGlobalKernelPUD as usize
+pub fn get_kernel_page_upper_directory_base_by_index(idx: usize) -> usize {
+    unsafe { armKSGlobalKernelPUD[idx].as_ptr() as usize }
 }
 
 #[inline]
-pub fn set_kernel_page_upper_directory_by_index(idx: usize, pude: PTE) {
-    unsafe { armKSGlobalKernelPUD[idx] = pude }
+pub fn set_kernel_page_upper_directory_by_index(idx1: usize, idx2: usize, pude: PTE) {
+    unsafe { armKSGlobalKernelPUD[idx1][idx2] = pude }
 }
 
 #[inline]
@@ -103,11 +117,17 @@ pub fn set_vm_root(thread_root: &cap) -> Result<(), lookup_fault> {
             return Ok(());
         }
     }
+    #[cfg(not(feature = "hypervisor"))]
     set_current_user_vspace_root(
         pptr!(thread_root_vspace.get_capVSBasePtr())
             .to_paddr()
             .raw(),
     );
+    #[cfg(feature = "hypervisor")]
+    set_current_user_vspace_root(ttbr_new(
+        get_hw_asid(asid),
+        pptr!(thread_root_vspace.get_capVSBasePtr()).to_paddr(),
+    ));
     Ok(())
 }
 
@@ -141,12 +161,27 @@ pub fn set_vm_root_for_flush_with_thread_root(
     }
 
     // armv_context_switch(vspace, asid);
-    set_current_user_vspace_root(ttbr_new(asid, paddr!(vspace)));
+    set_current_user_vspace_root(ttbr_new(get_hw_asid(asid), paddr!(vspace)));
     true
 }
 
+/// 获取`asid`当前持有的 VMID, 没有分配 VMID 时 TLB 中也不会有它的表项
+#[cfg(feature = "hypervisor")]
+#[inline]
+fn stored_hw_asid(asid: asid_t) -> Option<asid_t> {
+    let map = asid_map::asid_map_vspace(find_map_for_asid(asid)?);
+    if map.get_stored_vmid_valid() == 0 {
+        return None;
+    }
+    Some(map.get_stored_hw_vmid() as asid_t)
+}
+
 #[inline]
 pub fn invalidate_tlb_by_asid(asid: asid_t) {
+    #[cfg(feature = "hypervisor")]
+    let Some(asid) = stored_hw_asid(asid) else {
+        return;
+    };
     invalidate_local_tlb_asid(asid);
     #[cfg(feature = "enable_smp")]
     {
@@ -157,10 +192,23 @@ pub fn invalidate_tlb_by_asid(asid: asid_t) {
             remote_invalidate_tlb_asid(asid);
         }
     }
+    #[cfg(feature = "arm_smmu")]
+    if asid_bind_cb(asid) != 0 {
+        extern "C" {
+            fn smmu_tlb_invalidate_asid(asid: asid_t);
+        }
+        unsafe {
+            smmu_tlb_invalidate_asid(asid);
+        }
+    }
 }
 
 #[inline]
 pub fn invalidate_tlb_by_asid_va(asid: asid_t, vaddr: VPtr) {
+    #[cfg(feature = "hypervisor")]
+    let Some(asid) = stored_hw_asid(asid) else {
+        return;
+    };
     invalidate_local_tlb_va_asid((asid << 48) | vaddr.raw() >> SEL4_PAGE_BITS);
     #[cfg(feature = "enable_smp")]
     {
@@ -171,6 +219,15 @@ pub fn invalidate_tlb_by_asid_va(asid: asid_t, vaddr: VPtr) {
             remote_invalidate_translation_single((asid << 48) | vaddr.raw() >> SEL4_PAGE_BITS);
         }
     }
+    #[cfg(feature = "arm_smmu")]
+    if asid_bind_cb(asid) != 0 {
+        extern "C" {
+            fn smmu_tlb_invalidate_asid_va(asid: asid_t, vaddr: usize);
+        }
+        unsafe {
+            smmu_tlb_invalidate_asid_va(asid, vaddr.raw());
+        }
+    }
 }
 
 pub fn unmap_page_table(asid: asid_t, vaddr: VPtr, pt: &PTE) {
@@ -222,9 +279,7 @@ pub fn unmap_page(
     }
 
     let pte = ptr_to_mut(lu_ret.ptSlot);
-    if !(pte.get_type() == (pte_tag_t::pte_4k_page) as usize
-        || pte.get_type() == (pte_tag_t::pte_page) as usize)
-    {
+    if !pte.pte_is_page_type() {
         return Ok(());
     }
     if pte.get_page_base_address() != addr {
@@ -235,83 +290,14 @@ pub fn unmap_page(
         pte.update(*(lu_ret.ptSlot));
     }
     assert!(asid < bit!(16));
-    invalidate_tlb_by_asid(asid);
+    invalidate_tlb_by_asid_va(asid, vptr);
     Ok(())
-
-    // match page_size {
-    //     ARM_SMALL_PAGE => {
-    //         let lu_ret =
-    //             PGDE::new_from_pte(find_ret.vspace_root.unwrap() as usize).lookup_pt_slot(vptr);
-    //         if unlikely(lu_ret.status != exception_t::EXCEPTION_NONE) {
-    //             return Ok(());
-    //         }
-    //         let pte = ptr_to_mut(lu_ret.ptSlot);
-    //         if pte.is_present() && pte.pte_ptr_get_page_base_address() == addr {
-    //             *pte = PTE(0);
-    //             unsafe { core::arch::asm!("tlbi vmalle1; dsb sy; isb") };
-    //             clean_by_va_pou(
-    //                 convert_ref_type_to_usize(pte),
-    //                 paddr_to_pptr(convert_ref_type_to_usize(pte)),
-    //             );
-    //         }
-    //         Ok(())
-    //     }
-    //     ARM_LARGE_PAGE => {
-    //         log::info!("unmap large page: {:#x?}", vptr);
-    //         let lu_ret =
-    //             PGDE::new_from_pte(find_ret.vspace_root.unwrap() as usize).lookup_pd_slot(vptr);
-    //         if unlikely(lu_ret.status != exception_t::EXCEPTION_NONE) {
-    //             return Ok(());
-    //         }
-    //         let pde = ptr_to_mut(lu_ret.pdSlot);
-    //         if pde.get_present() && pde.get_base_address() == addr {
-    //             *pde = PDE(0);
-    //             unsafe { core::arch::asm!("tlbi vmalle1; dsb sy; isb") };
-    //             clean_by_va_pou(
-    //                 convert_ref_type_to_usize(pde),
-    //                 paddr_to_pptr(convert_ref_type_to_usize(pde)),
-    //             );
-    //         }
-    //         Ok(())
-    //     }
-    //     _ => unimplemented!("unMapPage: {page_size}"),
-    // }
-    /*
-        switch (page_size) {
-        case ARMLargePage: {
-            lookupPDSlot_ret_t lu_ret;
-            lu_ret = lookupPDSlot(find_ret.vspace_root, vptr);
-            if (unlikely(lu_ret.status != EXCEPTION_NONE)) {
-                return;
-            }
-            if (pde_pde_large_ptr_get_present(lu_ret.pdSlot) &&
-                pde_pde_large_ptr_get_page_base_address(lu_ret.pdSlot) == addr) {
-                *(lu_ret.pdSlot) = pde_invalid_new();
-                cleanByVA_PoU((vptr_t)lu_ret.pdSlot, pptr_to_paddr(lu_ret.pdSlot));
-            }
-            break;
-        }
-        case ARMHugePage: {
-            lookupPUDSlot_ret_t lu_ret;
-            lu_ret = lookupPUDSlot(find_ret.vspace_root, vptr);
-            if (unlikely(lu_ret.status != EXCEPTION_NONE)) {
-                return;
-            }
-            if (pude_pude_1g_ptr_get_present(lu_ret.pudSlot) &&
-                pude_pude_1g_ptr_get_page_base_address(lu_ret.pudSlot) == addr) {
-                *(lu_ret.pudSlot) = pude_invalid_new();
-                cleanByVA_PoU((vptr_t)lu_ret.pudSlot, pptr_to_paddr(lu_ret.pudSlot));
-            }
-            break;
-        }
-        default:
-            fail("Invalid ARM page type");
-        }
-        assert(asid < BIT(16));
-        invalidateTLBByASIDVA(asid, vptr);
-    */
 }
 
+/// Do the cache maintenance of `invLabel` on `[start, end]`, `end` included, where `pstart`
+/// is the physical address of `start`.
+///
+/// The decode stage rejects every other label, so they are ignored here.
 pub fn do_flush(invLabel: MessageLabel, start: usize, end: usize, pstart: PAddr) {
     match invLabel {
         MessageLabel::ARMPageClean_Data | MessageLabel::ARMVSpaceClean_Data => {
@@ -329,6 +315,6 @@ pub fn do_flush(invLabel: MessageLabel, start: usize, end: usize, pstart: PAddr)
             invalidate_cache_range_i(start, end, pstart);
             isb();
         }
-        _ => unimplemented!("unimplemented do_flush :{:?}", invLabel),
+        _ => {}
     };
 }
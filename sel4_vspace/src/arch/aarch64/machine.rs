@@ -1,5 +1,7 @@
 use core::arch::asm;
 
+#[cfg(feature = "hypervisor")]
+use aarch64_cpu::registers::Readable;
 use aarch64_cpu::registers::Writeable;
 use aarch64_cpu::{asm::barrier, registers};
 use rel4_arch::basic::PAddr;
@@ -26,20 +28,11 @@ pub fn set_current_user_vspace_root(val: usize) {
         registers::TTBR0_EL1.set(val as _);
         unsafe { core::arch::asm!("tlbi vmalle1") };
     }
+    // stage-2 的 TLB 表项由 VMID 区分, 切换时不需要刷新 TLB
     #[cfg(feature = "hypervisor")]
-    {
-        registers::VTTBR_EL2.set(val as _);
-        invalidate_local_tlb();
-        unsafe {
-            asm!("tlbi alle2");
-            dsb();
-            asm!("tlbi alle1");
-        }
-    }
+    registers::VTTBR_EL2.set(val as _);
     dsb();
     isb();
-    // log::warn!("virtual ttbr el2: {:#x}", val);
-    // FIXME: use aisd instead of flush tlb
 }
 
 #[inline]
@@ -68,6 +61,7 @@ pub fn isb() {
     barrier::isb(barrier::SY);
 }
 
+#[cfg(not(feature = "hypervisor"))]
 #[inline]
 pub fn invalidate_local_tlb_asid(asid: usize) {
     assert!(asid < (1 << 16)); // BIT(16) 相当于 1 << 16
@@ -80,6 +74,7 @@ pub fn invalidate_local_tlb_asid(asid: usize) {
     isb();
 }
 
+#[cfg(not(feature = "hypervisor"))]
 #[inline]
 pub fn invalidate_local_tlb_va_asid(mva_plus_asid: usize) {
     dsb();
@@ -90,6 +85,49 @@ pub fn invalidate_local_tlb_va_asid(mva_plus_asid: usize) {
     isb();
 }
 
+/// 按 VMID 失效 stage-1 和 stage-2 的 TLB 表项.
+///
+/// `tlbi` 使用的是 VTTBR_EL2 中当前的 VMID, 所以需要临时切换 VTTBR_EL2.
+#[cfg(feature = "hypervisor")]
+#[inline]
+pub fn invalidate_local_tlb_asid(vmid: usize) {
+    assert!(vmid < (1 << 8));
+
+    let vttbr = registers::VTTBR_EL2.get();
+    registers::VTTBR_EL2.set(ttbr_new(vmid, paddr!(0)) as _);
+    isb();
+    unsafe {
+        asm!("tlbi vmalls12e1is");
+    }
+    dsb();
+    isb();
+    registers::VTTBR_EL2.set(vttbr);
+    isb();
+}
+
+/// 失效某个 VMID 下单个 IPA 的 TLB 表项, `ipa_plus_vmid` 的格式与 stage-1 的
+/// `mva_plus_asid` 相同: VMID 位于 48 位以上, 低位是 IPA >> 12.
+#[cfg(feature = "hypervisor")]
+#[inline]
+pub fn invalidate_local_tlb_va_asid(ipa_plus_vmid: usize) {
+    let vttbr = registers::VTTBR_EL2.get();
+    registers::VTTBR_EL2.set(ttbr_new(ipa_plus_vmid >> 48, paddr!(0)) as _);
+    isb();
+    dsb();
+    unsafe {
+        asm!("tlbi ipas2e1is, {}", in(reg) ipa_plus_vmid & mask_bits!(48));
+    }
+    dsb();
+    // stage-1 的表项可能缓存了经过该 IPA 的完整翻译
+    unsafe {
+        asm!("tlbi vmalle1is");
+    }
+    dsb();
+    isb();
+    registers::VTTBR_EL2.set(vttbr);
+    isb();
+}
+
 #[inline(always)]
 pub fn clean_by_va_pou(vaddr: usize, _paddr: PAddr) {
     unsafe {
@@ -1,6 +1,8 @@
 use crate::{arch::aarch64::machine::clean_by_va_pou, vm_attributes_t, PTE};
 
-use super::{mair_types, UPT_LEVELS, VSPACE_INDEX_BITS};
+#[cfg(not(feature = "hypervisor"))]
+use super::mair_types;
+use super::{UPT_LEVELS, VSPACE_INDEX_BITS};
 use crate::lookupPTSlot_ret_t;
 use rel4_arch::basic::{PAddr, VPtr};
 use sel4_common::utils::ptr_to_mut;
@@ -87,9 +89,32 @@ bitflags::bitflags! {
         /// levels of lookup.
         const NS_TABLE =            bit!(63);
 
+        // Attribute fields in stage 2 VMSAv8-64 Block and Page descriptors:
+
+        /// Stage 2 memory attributes field.
+        #[cfg(feature = "hypervisor")]
+        const S2_MEM_ATTR =         0b1111 << 2;
+        /// Stage 2 memory attributes: Device-nGnRnE.
+        #[cfg(feature = "hypervisor")]
+        const S2_DEVICE_nGnRnE =    0b0000 << 2;
+        /// Stage 2 memory attributes: Normal, Inner/Outer Write-back cacheable.
+        #[cfg(feature = "hypervisor")]
+        const S2_NORMAL =           0b1111 << 2;
+        /// Stage 2 access permission: read access permitted.
+        #[cfg(feature = "hypervisor")]
+        const S2AP_READ =           bit!(6);
+        /// Stage 2 access permission: write access permitted.
+        #[cfg(feature = "hypervisor")]
+        const S2AP_WRITE =          bit!(7);
     }
 }
 
+/// 页表项中 AttrIndx 的掩码, stage-2 的 MemAttr 占 4 位, stage-1 的 AttrIndx 占 3 位
+#[cfg(not(feature = "hypervisor"))]
+const ATTR_INDX_MASK: usize = 0x7;
+#[cfg(feature = "hypervisor")]
+const ATTR_INDX_MASK: usize = 0xf;
+
 impl PTE {
     pub fn new(addr: PAddr, flags: PTEFlags) -> Self {
         Self((addr.raw() & 0xfffffffff000) | flags.bits())
@@ -140,6 +165,7 @@ impl PTE {
         );
     }
 
+    #[cfg(not(feature = "hypervisor"))]
     pub fn ap_from_vm_rights_t(rights: vm_rights_t) -> PTEFlags {
         match rights {
             vm_rights_t::VMKernelOnly => PTEFlags::empty(),
@@ -148,6 +174,37 @@ impl PTE {
         }
     }
 
+    /// 用户页表是 stage-2 页表, 访问权限由 S2AP 表示
+    #[cfg(feature = "hypervisor")]
+    pub fn ap_from_vm_rights_t(rights: vm_rights_t) -> PTEFlags {
+        match rights {
+            vm_rights_t::VMKernelOnly => PTEFlags::empty(),
+            vm_rights_t::VMReadWrite => PTEFlags::S2AP_READ | PTEFlags::S2AP_WRITE,
+            vm_rights_t::VMReadOnly => PTEFlags::S2AP_READ,
+        }
+    }
+
+    /// Get the memory attributes of a user page.
+    #[cfg(not(feature = "hypervisor"))]
+    pub fn attr_index_from_cacheable(cacheable: bool) -> usize {
+        if cacheable {
+            mair_types::NORMAL as usize
+        } else {
+            mair_types::DEVICE_nGnRnE as usize
+        }
+    }
+
+    /// Get the memory attributes of a user page, stage-2 descriptors hold
+    /// the MemAttr field directly instead of an index into MAIR.
+    #[cfg(feature = "hypervisor")]
+    pub fn attr_index_from_cacheable(cacheable: bool) -> usize {
+        if cacheable {
+            PTEFlags::S2_NORMAL.bits() >> 2
+        } else {
+            PTEFlags::S2_DEVICE_nGnRnE.bits() >> 2
+        }
+    }
+
     pub fn make_user_pte(
         paddr: PAddr,
         rights: vm_rights_t,
@@ -155,12 +212,9 @@ impl PTE {
         page_size: usize,
     ) -> Self {
         let nonexecutable = attr.get_arm_execute_never();
-        let cacheable = attr.get_arm_page_cachable();
-        let mut attrindx = mair_types::DEVICE_nGnRnE as usize;
-        if cacheable {
-            attrindx = mair_types::NORMAL as usize;
-        }
-        let nG: usize = 1;
+        let attrindx = Self::attr_index_from_cacheable(attr.get_arm_page_cachable());
+        // stage-2 页表项没有 nG 位
+        let nG: usize = if cfg!(feature = "hypervisor") { 0 } else { 1 };
         let vm_right: usize = Self::ap_from_vm_rights_t(rights).bits() >> 6;
         let shareable = if cfg!(feature = "enable_smp") { 3 } else { 0 };
         if VMPageSize::ARMSmallPage as usize == page_size {
@@ -207,7 +261,7 @@ impl PTE {
             | (AF & 0x1) << 10
             | (SH & 0x3) << 8
             | (AP & 0x3) << 6
-            | (AttrIndx & 0x7) << 2
+            | (AttrIndx & ATTR_INDX_MASK) << 2
             | (0x1 << 0);
 
         PTE(val)
@@ -229,7 +283,7 @@ impl PTE {
             | (AF & 0x1) << 10
             | (SH & 0x3) << 8
             | (AP & 0x3) << 6
-            | (AttrIndx & 0x7) << 2
+            | (AttrIndx & ATTR_INDX_MASK) << 2
             | 0x400000000000003;
         PTE(val)
     }
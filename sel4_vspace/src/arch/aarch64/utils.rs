@@ -1,5 +1,6 @@
 use crate::arch::VAddr;
 use rel4_arch::basic::{PAddr, PPtr};
+pub use sel4_common::arch::config::{AARCH64_VSPACE_S2_START_L1, UPT_LEVELS, VSPACE_INDEX_BITS};
 use sel4_common::{
     arch::{config::KERNEL_ELF_BASE_OFFSET, vm_rights_t},
     sel4_config::*,
@@ -7,8 +8,6 @@ use sel4_common::{
 };
 
 pub const KPT_LEVELS: usize = 4;
-pub const UPT_LEVELS: usize = 4;
-pub const VSPACE_INDEX_BITS: usize = 9;
 pub(self) const PAGE_ADDR_MASK: usize = mask_bits!(48) & !0xfff;
 #[inline]
 pub fn ulvl_frm_arm_pt_lvl(n: usize) -> usize {
@@ -66,7 +65,13 @@ impl VAddr {
         ((self.0) >> (kpt_level_shift(n))) & mask_bits!(PT_INDEX_BITS)
     }
     pub(super) fn get_upt_index(&self, n: usize) -> usize {
-        ((self.0) >> (upt_level_shift(n))) & mask_bits!(PT_INDEX_BITS)
+        // 根页表的索引位数可能与其他级不同
+        let index_bits = if n == 0 {
+            VSPACE_INDEX_BITS
+        } else {
+            PT_INDEX_BITS
+        };
+        ((self.0) >> (upt_level_shift(n))) & mask_bits!(index_bits)
     }
 
     /// Get the index of the pt(last level, bit 12..20)
@@ -1,4 +1,6 @@
 use super::utils::riscv_get_lvl_pgsize_bits;
+#[cfg(feature = "riscv_sv48")]
+use crate::arch::riscv64::pagetable::KERNEL_IMAGE_LEVEL1_PT;
 use crate::arch::riscv64::pagetable::KERNEL_ROOT_PAGE_TABLE;
 use crate::{riscv_get_pt_index, sfence, PTEFlags, PTE};
 use rel4_arch::basic::{PAddr, PPtr, VPtr};
@@ -6,7 +8,7 @@ use sel4_common::structures_gen::{cap_frame_cap, cap_page_table_cap};
 use sel4_common::{
     arch::config::KDEV_BASE,
     arch::vm_rights_t,
-    sel4_config::{RISCV_MEGA_PAGE_BITS, RISCV_PAGE_BITS, SEL4_PAGE_BITS},
+    sel4_config::{RISCV_MEGA_PAGE_BITS, RISCV_PAGE_BITS, SEL4_HUGE_PAGE_BITS, SEL4_PAGE_BITS},
     utils::convert_to_mut_type_ref,
 };
 
@@ -14,9 +16,19 @@ use sel4_common::{
 #[link_section = ".boot.text"]
 pub fn map_kernel_frame(paddr: PAddr, vaddr: usize, _vm_rights: vm_rights_t) {
     if vaddr >= KDEV_BASE {
-        let paddr = paddr.align_down(riscv_get_lvl_pgsize_bits(1));
-        KERNEL_ROOT_PAGE_TABLE.no_lock()[riscv_get_pt_index(vaddr, 0)] =
-            PTE::pte_next_table(paddr, true);
+        // 设备映射在`1GB`大页中，`pptr`相对`KDEV_BASE`的偏移即`paddr`在大页中的偏移
+        let paddr = paddr.align_down(SEL4_HUGE_PAGE_BITS);
+        #[cfg(not(feature = "riscv_sv48"))]
+        {
+            KERNEL_ROOT_PAGE_TABLE.no_lock()[riscv_get_pt_index(vaddr, 0)] =
+                PTE::pte_kernel_device(paddr);
+        }
+        // `SV48`下设备窗口是`kernel_image_level1_pt`中的一个`1GB`大页
+        #[cfg(feature = "riscv_sv48")]
+        {
+            KERNEL_IMAGE_LEVEL1_PT.no_lock()[riscv_get_pt_index(vaddr, 1)] =
+                PTE::pte_kernel_device(paddr);
+        }
     } else {
         let paddr = paddr.align_down(riscv_get_lvl_pgsize_bits(0));
         KERNEL_ROOT_PAGE_TABLE.no_lock()[riscv_get_pt_index(vaddr, 0)] =
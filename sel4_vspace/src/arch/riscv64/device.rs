@@ -1,4 +1,6 @@
 use super::boot::map_kernel_frame;
+#[cfg(feature = "riscv_ext_svpbmt")]
+use super::{pte::set_svpbmt_enabled, sfence};
 use rel4_arch::basic::PRegion;
 use sel4_common::arch::vm_rights_t::VMKernelOnly;
 use sel4_common::platform::kernel_device_frames;
@@ -22,3 +24,16 @@ pub fn map_kernel_devices() {
         }
     }
 }
+
+/// 启动时检测到硬件支持 Svpbmt 后调用，此后的映射会带上 Svpbmt 内存类型，
+/// 已经建立的内核设备映射会被重新映射为`IO`类型
+#[cfg(feature = "riscv_ext_svpbmt")]
+pub fn enable_svpbmt() {
+    set_svpbmt_enabled();
+    unsafe {
+        for kernel_frame in kernel_device_frames {
+            map_kernel_frame(kernel_frame.paddr, kernel_frame.pptr.raw(), VMKernelOnly);
+        }
+    }
+    sfence();
+}
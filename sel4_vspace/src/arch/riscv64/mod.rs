@@ -14,7 +14,9 @@ pub use interface::{set_vm_root, unmap_page_table};
 pub use pagetable::{
     activate_kernel_vspace, copyGlobalMappings, rust_map_kernel_window, unmap_page,
 };
-pub use pte::PTEFlags;
+#[cfg(feature = "riscv_ext_svnapot")]
+pub use pte::enable_svnapot;
+pub use pte::{pt_bits_for_frame_size, PTEFlags};
 pub use satp::{set_vspace_root, sfence};
 pub use structures::*;
 pub use utils::*;
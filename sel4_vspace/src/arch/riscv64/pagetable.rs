@@ -6,18 +6,18 @@ use sel4_common::{
         KERNEL_ELF_BASE, KERNEL_ELF_PADDR_BASE, PADDR_BASE, PADDR_TOP, PPTR_BASE, PPTR_BASE_OFFSET,
         PPTR_TOP,
     },
-    sel4_config::{PT_INDEX_BITS, SEL4_PAGE_BITS},
+    sel4_config::{PT_INDEX_BITS, RISCV_GIGA_PAGE_BITS, RISCV_MEGA_PAGE_BITS, SEL4_PAGE_BITS},
     structures::exception_t,
     structures_gen::lookup_fault,
     utils::pageBitsForSize,
 };
 
 use super::{
-    kpptr_to_paddr, map_kernel_devices, riscv_get_lvl_pgsize, set_vspace_root,
-    utils::{riscv_get_lvl_pgsize_bits, riscv_get_pt_index},
+    kpptr_to_paddr, map_kernel_devices, pt_bits_for_frame_size, riscv_get_lvl_pgsize,
+    set_vspace_root, utils::riscv_get_pt_index,
 };
 
-///页表采用`SV39`，该变量是内核使用的页表的根页表（一级页表）
+///页表采用`SV39`或`SV48`，该变量是内核使用的页表的根页表（一级页表）
 #[link_section = ".page_table"]
 #[export_name = "kernel_root_pageTable"]
 pub(crate) static KERNEL_ROOT_PAGE_TABLE: NoLock<[PTE; bit!(PT_INDEX_BITS)]> =
@@ -29,6 +29,16 @@ pub(crate) static KERNEL_ROOT_PAGE_TABLE: NoLock<[PTE; bit!(PT_INDEX_BITS)]> =
 pub(crate) static KERNEL_IMAGE_LEVEL2_PT: NoLock<[PTE; bit!(PT_INDEX_BITS)]> =
     NoLock::new([PTE::pte_invalid(); bit!(PT_INDEX_BITS)]);
 
+///`SV48`下根页表最后一项指向的页表，以`1GB`为单位映射`KERNEL ELF`和`Kernel Devices`
+///
+/// With Sv48 a root entry covers 512GiB, so the kernel ELF and the kernel devices
+/// share the last one through this table.
+#[cfg(feature = "riscv_sv48")]
+#[link_section = ".page_table"]
+#[export_name = "kernel_image_level1_pt"]
+pub(crate) static KERNEL_IMAGE_LEVEL1_PT: NoLock<[PTE; bit!(PT_INDEX_BITS)]> =
+    NoLock::new([PTE::pte_invalid(); bit!(PT_INDEX_BITS)]);
+
 /// 构建`reL4`的内核页表,主要完成了`PSpace`和`KERNEL ELF`两段虚拟地址空间的映射
 ///
 /// 其中`PSpace`是对整个物理地址空间的线性映射，`KERNEL ELF`是对内核代码的再一次映射
@@ -75,9 +85,10 @@ pub(crate) static KERNEL_IMAGE_LEVEL2_PT: NoLock<[PTE; bit!(PT_INDEX_BITS)]> =
 ///                      virtual address space                          physical address space
 /// ```
 ///
+/// 上图为`SV39`的布局。`SV48`下`PPTR_TOP`下移到`2^64 - 512GiB`，`Kernel ELF`和`Kernel Devices`共用根页表的最后一项。
 #[no_mangle]
 pub fn rust_map_kernel_window() {
-    // 物理地址到内核地址空间的直接映射，用`1GB`大页的方式映射
+    // 物理地址到内核地址空间的直接映射，用根页表的大页映射（`SV39`为`1GB`，`SV48`为`512GB`）
     for (pptr, paddr) in (PPTR_BASE..PPTR_TOP)
         .step_by(riscv_get_lvl_pgsize(0))
         .zip((PADDR_BASE..PADDR_TOP).step_by(riscv_get_lvl_pgsize(0)))
@@ -86,27 +97,33 @@ pub fn rust_map_kernel_window() {
             PTE::pte_next_table(paddr!(paddr), true);
     }
 
-    let mut pptr = pptr!(KERNEL_ELF_BASE).align_down(riscv_get_lvl_pgsize_bits(0));
-    let mut paddr = paddr!(KERNEL_ELF_PADDR_BASE).align_down(riscv_get_lvl_pgsize_bits(0));
+    let pptr = pptr!(KERNEL_ELF_BASE).align_down(RISCV_GIGA_PAGE_BITS);
+    let mut paddr = paddr!(KERNEL_ELF_PADDR_BASE).align_down(RISCV_GIGA_PAGE_BITS);
+    let image_pt = kpptr_to_paddr(KERNEL_IMAGE_LEVEL2_PT.as_ptr() as usize);
     // 将根页表`KERNEL_ELF_PADDR_BASE`和`KERNEL_ELF_BASE`处的页表项改为使用`kernel_image_level2_pt`映射
-    KERNEL_ROOT_PAGE_TABLE.no_lock()
-        [riscv_get_pt_index(KERNEL_ELF_PADDR_BASE + PPTR_BASE_OFFSET, 0)] = PTE::pte_next_table(
-        kpptr_to_paddr(KERNEL_IMAGE_LEVEL2_PT.as_ptr() as usize),
-        false,
-    );
-
-    KERNEL_ROOT_PAGE_TABLE.no_lock()[riscv_get_pt_index(pptr.raw(), 0)] = PTE::pte_next_table(
-        kpptr_to_paddr(KERNEL_IMAGE_LEVEL2_PT.as_ptr() as usize),
-        false,
-    );
-
-    let mut index = 0;
-    // 做了 `0xFFFF_FFFF_8400_0000(KERNEL_ELF_BASE)~0xFFFF_FFFF_C4000_0000(KDEV_BASE)`到`0x8400_0000~0xC400_0000`的地址映射。
-    while pptr.raw() < PPTR_TOP + riscv_get_lvl_pgsize(0) {
-        KERNEL_IMAGE_LEVEL2_PT.no_lock()[index] = PTE::pte_next_table(paddr, true);
-        pptr += riscv_get_lvl_pgsize(1);
-        paddr += riscv_get_lvl_pgsize(1);
-        index += 1;
+    #[cfg(not(feature = "riscv_sv48"))]
+    {
+        KERNEL_ROOT_PAGE_TABLE.no_lock()
+            [riscv_get_pt_index(KERNEL_ELF_PADDR_BASE + PPTR_BASE_OFFSET, 0)] =
+            PTE::pte_next_table(image_pt, false);
+        KERNEL_ROOT_PAGE_TABLE.no_lock()[riscv_get_pt_index(pptr.raw(), 0)] =
+            PTE::pte_next_table(image_pt, false);
+    }
+    // `SV48`下`KERNEL_ELF_PADDR_BASE`已由`512GB`大页映射，`KERNEL_ELF_BASE`经`kernel_image_level1_pt`指向`kernel_image_level2_pt`
+    #[cfg(feature = "riscv_sv48")]
+    {
+        KERNEL_ROOT_PAGE_TABLE.no_lock()[riscv_get_pt_index(pptr.raw(), 0)] = PTE::pte_next_table(
+            kpptr_to_paddr(KERNEL_IMAGE_LEVEL1_PT.as_ptr() as usize),
+            false,
+        );
+        KERNEL_IMAGE_LEVEL1_PT.no_lock()[riscv_get_pt_index(pptr.raw(), 1)] =
+            PTE::pte_next_table(image_pt, false);
+    }
+
+    // 以`2MB`大页做了 `0xFFFF_FFFF_8000_0000~0xFFFF_FFFF_C000_0000(KDEV_BASE)`到`0x8000_0000~0xC000_0000`的地址映射。
+    for entry in KERNEL_IMAGE_LEVEL2_PT.no_lock().iter_mut() {
+        *entry = PTE::pte_next_table(paddr, true);
+        paddr += bit!(RISCV_MEGA_PAGE_BITS);
     }
     map_kernel_devices();
 }
@@ -125,7 +142,7 @@ pub fn activate_kernel_vspace() {
 /// when create a new process, a new page table will be alloced to the new process.
 #[no_mangle]
 pub fn copyGlobalMappings(Lvl1pt: PPtr) {
-    let mut i: usize = riscv_get_pt_index(0x80000000, 0);
+    let mut i: usize = riscv_get_pt_index(PPTR_BASE, 0);
     while i < bit!(PT_INDEX_BITS) {
         let newLvl1pt = (Lvl1pt + i * 8).get_mut_ref();
         *newLvl1pt = KERNEL_ROOT_PAGE_TABLE.no_lock()[i];
@@ -183,20 +200,19 @@ pub fn unmap_page(
     }
     // TODO: Unify lookup_pt_slot
     let lu_ret = unsafe { (*find_ret.vspace_root.unwrap()).lookup_pt_slot(vptr) };
-    if lu_ret.ptBitsLeft != pageBitsForSize(page_size) {
+    if lu_ret.ptBitsLeft != pt_bits_for_frame_size(page_size) {
         return Ok(());
     }
 
-    let slot = unsafe { &(*lu_ret.ptSlot) };
+    let slot = unsafe { &mut (*lu_ret.ptSlot) };
 
     if slot.get_valid() == 0
         || slot.is_pte_table()
-        || slot.get_ppn() << SEL4_PAGE_BITS != pptr.to_paddr().raw()
+        || slot.get_frame_ppn() << SEL4_PAGE_BITS != pptr.to_paddr().raw()
     {
         return Ok(());
     }
 
-    *pptr!(lu_ret.ptSlot).get_mut_ref::<usize>() = 0;
-    sfence();
+    slot.update_frame(PTE::pte_invalid(), page_size);
     Ok(())
 }
@@ -1,15 +1,20 @@
 use bitflags::bitflags;
 use core::intrinsics::unlikely;
+#[cfg(any(feature = "riscv_ext_svpbmt", feature = "riscv_ext_svnapot"))]
+use core::sync::atomic::{AtomicBool, Ordering};
 use rel4_arch::basic::{PAddr, VPtr};
+#[cfg(feature = "riscv_ext_svnapot")]
+use sel4_common::sel4_config::{RISCV_NAPOT_64K_PAGE, RISCV_PAGE_BITS};
 use sel4_common::{
     arch::{riscv_get_read_from_vm_rights, riscv_get_write_from_vm_rights, vm_rights_t},
     sel4_config::{CONFIG_PT_LEVELS, PT_INDEX_BITS, SEL4_PAGE_BITS, SEL4_PAGE_TABLE_BITS},
     structures::exception_t,
+    utils::pageBitsForSize,
 };
 
 use crate::{
     arch::riscv64::{sfence, utils::riscv_get_pt_index},
-    asid_t, find_vspace_for_asid, lookupPTSlot_ret_t, PTE,
+    asid_t, find_vspace_for_asid, lookupPTSlot_ret_t, vm_attributes_t, PTE,
 };
 
 bitflags! {
@@ -23,6 +28,11 @@ bitflags! {
         const G = bit!(5);
         const A = bit!(6);
         const D = bit!(7);
+        /// Svpbmt 的页面内存类型，`NC`为不可缓存的主存，`IO`为设备内存
+        const PBMT_NC = bit!(61);
+        const PBMT_IO = bit!(62);
+        /// Svnapot 的连续页面，`ppn`的低 4 位为`0b1000`时表示 64KiB 页面
+        const N = bit!(63);
 
         const VRWX  = Self::V.bits() | Self::R.bits() | Self::W.bits() | Self::X.bits();
         const ADUVRX = Self::A.bits() | Self::D.bits() | Self::U.bits() | Self::V.bits() | Self::R.bits() | Self::X.bits();
@@ -32,6 +42,73 @@ bitflags! {
     }
 }
 
+/// 硬件是否实现了 Svpbmt，在启动时根据 DTB 中的 ISA 字符串设置
+#[cfg(feature = "riscv_ext_svpbmt")]
+static SVPBMT_ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "riscv_ext_svpbmt")]
+#[inline]
+pub(crate) fn set_svpbmt_enabled() {
+    SVPBMT_ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// 硬件是否实现了 Svnapot，在启动时根据 DTB 中的 ISA 字符串设置
+#[cfg(feature = "riscv_ext_svnapot")]
+static SVNAPOT_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// 启动时检测到硬件支持 Svnapot 后调用，此后 64KiB 页面按 NAPOT 格式映射
+#[cfg(feature = "riscv_ext_svnapot")]
+#[inline]
+pub fn enable_svnapot() {
+    SVNAPOT_ENABLED.store(true, Ordering::Relaxed);
+}
+
+#[inline]
+fn svnapot_enabled() -> bool {
+    #[cfg(feature = "riscv_ext_svnapot")]
+    return SVNAPOT_ENABLED.load(Ordering::Relaxed);
+    #[cfg(not(feature = "riscv_ext_svnapot"))]
+    false
+}
+
+/// 映射`frame_size`大小的页面的页表项所在的层级，即`lookup_pt_slot`应返回的`ptBitsLeft`
+#[inline]
+pub fn pt_bits_for_frame_size(frame_size: usize) -> usize {
+    #[cfg(feature = "riscv_ext_svnapot")]
+    if frame_size == RISCV_NAPOT_64K_PAGE {
+        return RISCV_PAGE_BITS;
+    }
+    pageBitsForSize(frame_size)
+}
+
+/// 映射`frame_size`大小的页面需要的连续页表项的数量，64KiB 页面为 16 项，其余为 1 项
+#[inline]
+pub fn pte_count_for_frame_size(frame_size: usize) -> usize {
+    bit!(pageBitsForSize(frame_size) - pt_bits_for_frame_size(frame_size))
+}
+
+/// 用户页面的 Svpbmt 内存类型：设备内存默认为`IO`，`attr`可以显式要求`NC`或`IO`，
+/// 硬件不支持 Svpbmt 时总是使用 PMA 给出的类型
+#[cfg(feature = "riscv_ext_svpbmt")]
+fn pbmt_from_attr(attr: vm_attributes_t, is_device: bool) -> PTEFlags {
+    if !SVPBMT_ENABLED.load(Ordering::Relaxed) {
+        return PTEFlags::empty();
+    }
+    if attr.get_riscv_io() || (is_device && !attr.get_riscv_non_cacheable()) {
+        PTEFlags::PBMT_IO
+    } else if attr.get_riscv_non_cacheable() {
+        PTEFlags::PBMT_NC
+    } else {
+        PTEFlags::empty()
+    }
+}
+
+#[cfg(not(feature = "riscv_ext_svpbmt"))]
+#[inline]
+fn pbmt_from_attr(_attr: vm_attributes_t, _is_device: bool) -> PTEFlags {
+    PTEFlags::empty()
+}
+
 impl From<usize> for PTE {
     fn from(value: usize) -> Self {
         Self(value)
@@ -44,15 +121,22 @@ impl PTE {
         Self(flags.bits() | (ppn << 10))
     }
 
-    /// 创建一个用户使用的页表项（`Global=0`、`User=1`）
+    /// 创建一个用户使用的页表项（`Global=0`、`User=1`），`is_device`表示页面来自设备内存
     #[inline]
-    pub fn make_user_pte(paddr: PAddr, executable: bool, vm_rights: vm_rights_t) -> Self {
+    pub fn make_user_pte(
+        paddr: PAddr,
+        vm_rights: vm_rights_t,
+        attr: vm_attributes_t,
+        is_device: bool,
+    ) -> Self {
+        let executable = attr.get_execute_never() == 0;
         let write = riscv_get_write_from_vm_rights(&vm_rights);
         let read = riscv_get_read_from_vm_rights(&vm_rights);
         if !executable && !read && !write {
             return Self::pte_invalid();
         }
-        let mut flag = PTEFlags::V | PTEFlags::D | PTEFlags::A | PTEFlags::U;
+        let mut flag =
+            PTEFlags::V | PTEFlags::D | PTEFlags::A | PTEFlags::U | pbmt_from_attr(attr, is_device);
         if executable {
             flag |= PTEFlags::X;
         }
@@ -77,12 +161,62 @@ impl PTE {
         Self::new(ppn, flag)
     }
 
+    /// 内核设备窗口的页表项，硬件支持 Svpbmt 时使用`IO`类型
+    #[inline]
+    pub fn pte_kernel_device(phys_addr: PAddr) -> Self {
+        let pte = Self::pte_next_table(phys_addr, true);
+        #[cfg(feature = "riscv_ext_svpbmt")]
+        if SVPBMT_ENABLED.load(Ordering::Relaxed) {
+            return Self(pte.0 | PTEFlags::PBMT_IO.bits());
+        }
+        pte
+    }
+
     #[inline]
     pub fn update(&mut self, pte: Self) {
         *self = pte;
         sfence();
     }
 
+    /// 以`self`为第一项，写入映射`frame_size`大小页面的全部页表项。
+    ///
+    /// 64KiB 页面占用连续的 16 项：硬件支持 Svnapot 时每一项都写成同一个 NAPOT 表项，
+    /// 否则退化为 16 个连续的 4KiB 映射
+    pub fn update_frame(&mut self, pte: Self, frame_size: usize) {
+        let count = pte_count_for_frame_size(frame_size);
+        let slots = unsafe { core::slice::from_raw_parts_mut(self as *mut Self, count) };
+        if count == 1 || pte.get_valid() == 0 {
+            slots.fill(pte);
+        } else if svnapot_enabled() {
+            let napot_ppn = (pte.get_ppn() & !mask_bits!(4)) | bit!(3);
+            slots.fill(Self(
+                (pte.0 & !(mask_bits!(44) << 10)) | (napot_ppn << 10) | PTEFlags::N.bits(),
+            ));
+        } else {
+            for (i, slot) in slots.iter_mut().enumerate() {
+                *slot = Self(pte.0 + (i << 10));
+            }
+        }
+        sfence();
+    }
+
+    /// 以`self`为第一项，映射`frame_size`大小页面的页表项中是否有有效项
+    pub fn frame_slots_valid(&self, frame_size: usize) -> bool {
+        let count = pte_count_for_frame_size(frame_size);
+        let slots = unsafe { core::slice::from_raw_parts(self as *const Self, count) };
+        slots.iter().any(|slot| slot.get_valid() != 0)
+    }
+
+    /// 叶子页表项映射的页面的起始物理页号，NAPOT 表项需要去掉`ppn`低位中的编码
+    #[inline]
+    pub fn get_frame_ppn(&self) -> usize {
+        if self.0 & PTEFlags::N.bits() != 0 {
+            self.get_ppn() & !mask_bits!(4)
+        } else {
+            self.get_ppn()
+        }
+    }
+
     pub fn unmap_page_table(&mut self, asid: asid_t, vptr: VPtr) {
         let target_pt = self as *mut PTE;
         let find_ret = find_vspace_for_asid(asid);
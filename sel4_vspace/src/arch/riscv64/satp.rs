@@ -2,6 +2,14 @@ use rel4_arch::basic::PAddr;
 use riscv::register::satp;
 #[cfg(feature = "enable_smp")]
 use sel4_common::arch::riscv64::remote_sfence_vma;
+use sel4_common::sel4_config::CONFIG_PT_LEVELS;
+
+/// `satp.MODE` of the configured paging scheme.
+const SATP_MODE: usize = match CONFIG_PT_LEVELS {
+    3 => 8, // Sv39
+    4 => 9, // Sv48
+    _ => panic!("unsupported PT_LEVELS"),
+};
 
 ///`satp`寄存器对应的内存备份
 #[repr(C)]
@@ -60,7 +68,7 @@ pub fn sfence() {
 #[inline]
 #[no_mangle]
 pub fn set_vspace_root(addr: PAddr, asid: usize) {
-    let satp = satp_t::new(8usize, asid, addr.raw() >> 12);
+    let satp = satp_t::new(SATP_MODE, asid, addr.raw() >> 12);
     satp::write(satp.words);
     #[cfg(not(feature = "enable_smp"))]
     sfence();
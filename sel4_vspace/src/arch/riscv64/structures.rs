@@ -1,7 +1,29 @@
 use rel4_arch::basic::PPtr;
+use sel4_common::sel4_bitfield_types::Bitfield;
+use sel4_common::structures_gen::vm_attributes;
 use sel4_common::{sel4_config::ASID_LOW_BITS, utils::convert_to_option_mut_type_ref};
 
-use crate::PTE;
+use crate::{vm_attributes_t, PTE};
+
+impl vm_attributes_t {
+    /// 按生成的`vm_attributes`位域读取属性字
+    #[inline]
+    fn bitfield(&self) -> vm_attributes {
+        vm_attributes(Bitfield {
+            arr: [self.0 as u64],
+        })
+    }
+
+    /// `seL4_RISCV_NonCacheable`，需要 Svpbmt，以`NC`类型映射页面
+    pub fn get_riscv_non_cacheable(&self) -> bool {
+        self.bitfield().get_riscvNonCacheable() != 0
+    }
+
+    /// `seL4_RISCV_IO`，需要 Svpbmt，以`IO`类型映射页面
+    pub fn get_riscv_io(&self) -> bool {
+        self.bitfield().get_riscvIO() != 0
+    }
+}
 
 ///lookup_pt_slot函数的返回值，
 /// `ptSlot`：找到的虚地址对应的`pte`的存放槽
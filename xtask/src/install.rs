@@ -15,8 +15,8 @@ pub fn install(opts: &BuildOptions) -> Result<(), anyhow::Error> {
     let defines = parse_cmake_defines(opts)?;
 
     let preload_cmake = match opts.platform.as_str() {
-        "spike" => "kernel-settings-riscv64.cmake",
-        "qemu-arm-virt" => "kernel-settings-aarch64.cmake",
+        "spike" | "qemu-riscv-virt" => "kernel-settings-riscv64.cmake",
+        "qemu-arm-virt" | "qemu-arm-virt-gicv3" => "kernel-settings-aarch64.cmake",
         _ => unreachable!(),
     };
 
@@ -20,13 +20,22 @@ use std::{
 /// * `rust_only` - Builds the kernel using only Rust code, excluding any external dependencies.
 /// * `bin` - Generates a binary output for the kernel. Can be specified with `-B` or `--bin`.
 /// * `benchmark` -Enable Benchmark.
+/// * `fine_lock` - Use fine-grained locks for IPC instead of the big kernel lock, only with smp and without mcs.
+/// * `lock_stats` - Count big kernel lock contention, only with smp.
+/// * `hotplug` - Add the CPU control cap to take cores offline and back, only with smp and without mcs.
+/// * `sv48` - Use 4-level Sv48 page tables instead of Sv39, only on riscv64.
+/// * `arm_pa44` - Use a 44-bit physical address space instead of 40 bits, only on aarch64.
+/// * `arm_smmu` - Enable the SMMUv3 and the SID/CB caps, only on aarch64 without hypervisor.
+/// * `arm_sve` - Enable lazily switched per-thread SVE state, only on aarch64 without hypervisor.
+/// * `idle_suspend` - Suspend idle cores with PSCI/SBI HSM when the next timer deadline is far away, only on aarch64 and riscv64.
+/// * `irq_stats` - Count deliveries and latencies of every IRQ, read with SysDebugIRQStats.
 #[derive(Debug, Parser, Clone)]
 pub struct BuildOptions {
     #[clap(
         default_value = "spike",
         short,
         long,
-        help = "support spike and qemu-arm-virt"
+        help = "support spike, qemu-riscv-virt, qemu-arm-virt and qemu-arm-virt-gicv3"
     )]
     pub platform: String,
     #[clap(
@@ -57,11 +66,7 @@ pub struct BuildOptions {
         help = "Enable ptmr regs read/write in userspace"
     )]
     pub arm_ptmr: bool,
-    #[clap(
-        long,
-        default_value_t = false,
-        help = "Enable hypervisor feature(TODO)"
-    )]
+    #[clap(long, default_value_t = false, help = "Enable hypervisor feature")]
     pub arm_hypervisor: bool,
     #[clap(long, help = "Only build the reL4 rust kernel")]
     pub rust_only: bool,
@@ -83,6 +88,60 @@ pub struct BuildOptions {
     pub log: String,
     #[clap(long)]
     pub benchmark: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Use fine-grained locks for IPC syscalls, only valid with smp"
+    )]
+    pub fine_lock: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Count big kernel lock contention, only valid with smp"
+    )]
+    pub lock_stats: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Enable the CPU control cap for core hotplug, only valid with smp"
+    )]
+    pub hotplug: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Use Sv48 (4-level) page tables, only valid on riscv64"
+    )]
+    pub sv48: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Use a 44-bit physical address space, only valid on aarch64"
+    )]
+    pub arm_pa44: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Enable the SMMUv3 driver, only valid on aarch64 without hypervisor"
+    )]
+    pub arm_smmu: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Enable SVE for threads, only valid on aarch64 without hypervisor"
+    )]
+    pub arm_sve: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Suspend idle cores with PSCI CPU_SUSPEND or SBI HSM instead of wfi when the next deadline is far away, only valid on aarch64 and riscv64"
+    )]
+    pub idle_suspend: bool,
+    #[clap(
+        long,
+        default_value_t = false,
+        help = "Count IRQ deliveries, spurious IRQs and ack/wake latencies, read with SysDebugIRQStats"
+    )]
+    pub irq_stats: bool,
 }
 
 /// Parse CMAKE DEFINES from build options
@@ -107,23 +166,49 @@ pub fn parse_cmake_defines(opts: &BuildOptions) -> Result<Vec<String>, anyhow::E
     if opts.arm_hypervisor {
         define.push("-DKernelArmHypervisorSupport=ON".to_string());
     }
+    if opts.arm_pa44 {
+        define.push("-DKernelArmPASizeBits=44".to_string());
+    }
+    if opts.arm_smmu {
+        define.push("-DKernelArmSMMU=ON".to_string());
+    }
     if opts.num_nodes > 1 {
         define.push(String::from("-DSMP=TRUE"));
         define.push(format!("-DNUM_NODES={}", opts.num_nodes));
     }
+    let riscv = matches!(opts.platform.as_str(), "spike" | "qemu-riscv-virt");
+    if opts.sv48 && riscv {
+        define.push("-DKernelPTLevels=4".to_string());
+    }
+    // The vector state makes a TCB object 2^12 bytes, libsel4 takes seL4_TCBBits from it
+    if riscv
+        && rel4_config::get_bool_from_cfg(&opts.platform, "definitions.RISCV_EXT_V")
+            .unwrap_or(false)
+    {
+        define.push("-DKernelRiscvExtV=ON".to_string());
+    }
     match opts.platform.as_str() {
-        "spike" => define.push("-DKernelRiscvExtD=ON".to_string()),
+        "spike" | "qemu-riscv-virt" => define.push("-DKernelRiscvExtD=ON".to_string()),
         "qemu-arm-virt" => {}
+        "qemu-arm-virt-gicv3" => define.push("-DKernelArmGicV3=ON".to_string()),
         _ => return Err(anyhow::anyhow!("Unsupported platform")),
     };
     Ok(define)
 }
 
+/// The seL4 cmake platform of a build platform, the GICv3 variant is still qemu-arm-virt.
+pub fn cmake_platform(platform: &str) -> &str {
+    match platform {
+        "qemu-arm-virt-gicv3" => "qemu-arm-virt",
+        _ => platform,
+    }
+}
+
 pub fn cargo(command: &str, dir: &str, opts: &BuildOptions) -> Result<(), anyhow::Error> {
     let dir: PathBuf = PathBuf::from(dir);
     let target = match opts.platform.as_str() {
-        "spike" => "--target=riscv64gc-unknown-none-elf",
-        "qemu-arm-virt" => "--target=aarch64-unknown-none-softfloat",
+        "spike" | "qemu-riscv-virt" => "--target=riscv64gc-unknown-none-elf",
+        "qemu-arm-virt" | "qemu-arm-virt-gicv3" => "--target=aarch64-unknown-none-softfloat",
         _ => return Err(anyhow::anyhow!("Unsupported platform")),
     };
     let current_dir = std::env::var("CARGO_MANIFEST_DIR")?;
@@ -176,7 +261,95 @@ pub fn cargo(command: &str, dir: &str, opts: &BuildOptions) -> Result<(), anyhow
     if opts.arm_hypervisor && target.contains("aarch64") {
         append_features(&mut args, "hypervisor".to_string());
         marcos.push("ARCH_ARM_HYP=true".to_string());
-        marcos.push("AARCH64_VSPACE_S2_START_L1=true".to_string());
+        marcos.push("ARM_HYPERVISOR_SUPPORT=true".to_string());
+        if !opts.arm_pa44 {
+            marcos.push("AARCH64_VSPACE_S2_START_L1=true".to_string());
+        }
+    }
+
+    if opts.arm_pa44 && target.contains("aarch64") {
+        append_features(&mut args, "arm_pa_size_bits_44".to_string());
+        marcos.push("ARM_PA_SIZE_BITS_44=true".to_string());
+        marcos.push("PADDR_USER_DEVICE_TOP=17592186044416".to_string());
+        // the kernel window of a 44-bit physical address space starts lower
+        if opts.arm_hypervisor {
+            marcos.push("KERNEL_VMEM_OFFSET=0x700000000000".to_string());
+        } else {
+            marcos.push("KERNEL_VMEM_OFFSET=0xfffff00000000000".to_string());
+        }
+    } else if target.contains("aarch64") {
+        marcos.push("ARM_PA_SIZE_BITS_40=true".to_string());
+    }
+
+    if opts.arm_smmu && target.contains("aarch64") {
+        if opts.arm_hypervisor {
+            return Err(anyhow::anyhow!("SMMU does not support hypervisor mode"));
+        }
+        append_features(&mut args, "arm_smmu".to_string());
+        marcos.push("ARM_SMMU=true".to_string());
+    }
+
+    if opts.arm_sve && target.contains("aarch64") {
+        if opts.arm_hypervisor {
+            return Err(anyhow::anyhow!("SVE does not support hypervisor mode"));
+        }
+        append_features(&mut args, "arm_sve".to_string());
+        marcos.push("ARM_SVE=true".to_string());
+    }
+
+    if opts.idle_suspend && (target.contains("aarch64") || target.contains("riscv64")) {
+        append_features(&mut args, "idle_suspend".to_string());
+        marcos.push("IDLE_SUSPEND=true".to_string());
+    }
+
+    if opts.irq_stats {
+        append_features(&mut args, "irq_stats".to_string());
+    }
+
+    if opts.sv48 && target.contains("riscv64") {
+        append_features(&mut args, "riscv_sv48".to_string());
+        marcos.push("PT_LEVELS=4".to_string());
+    }
+
+    if target.contains("riscv64")
+        && rel4_config::get_bool_from_cfg(&opts.platform, "definitions.RISCV_EXT_SSTC")
+            .unwrap_or(false)
+    {
+        append_features(&mut args, "riscv_ext_sstc".to_string());
+    }
+
+    if target.contains("riscv64")
+        && rel4_config::get_bool_from_cfg(&opts.platform, "definitions.RISCV_AIA")
+            .unwrap_or(false)
+    {
+        append_features(&mut args, "riscv_aia".to_string());
+    }
+
+    if rel4_config::get_bool_from_cfg(&opts.platform, "definitions.DTB_PLATFORM")
+        .unwrap_or(false)
+    {
+        append_features(&mut args, "dtb_platform".to_string());
+    }
+
+    if target.contains("riscv64")
+        && rel4_config::get_bool_from_cfg(&opts.platform, "definitions.RISCV_EXT_V")
+            .unwrap_or(false)
+    {
+        append_features(&mut args, "riscv_ext_v".to_string());
+    }
+
+    if target.contains("riscv64")
+        && rel4_config::get_bool_from_cfg(&opts.platform, "definitions.RISCV_EXT_SVPBMT")
+            .unwrap_or(false)
+    {
+        append_features(&mut args, "riscv_ext_svpbmt".to_string());
+    }
+
+    if target.contains("riscv64")
+        && rel4_config::get_bool_from_cfg(&opts.platform, "definitions.RISCV_EXT_SVNAPOT")
+            .unwrap_or(false)
+    {
+        append_features(&mut args, "riscv_ext_svnapot".to_string());
     }
 
     if Path::new(&easy_setting_cmake_file).exists() {
@@ -223,6 +396,21 @@ pub fn cargo(command: &str, dir: &str, opts: &BuildOptions) -> Result<(), anyhow
         append_features(&mut args, "enable_smp".to_string());
         marcos.push(format!("MAX_NUM_NODES={}", opts.num_nodes));
         marcos.push("ENABLE_SMP_SUPPORT=true".to_string());
+        if opts.fine_lock {
+            if opts.mcs {
+                return Err(anyhow::anyhow!("fine-grained locking does not support MCS"));
+            }
+            append_features(&mut args, "fine_grained_lock".to_string());
+        }
+        if opts.lock_stats {
+            append_features(&mut args, "clh_lock_stats".to_string());
+        }
+        if opts.hotplug {
+            if opts.mcs {
+                return Err(anyhow::anyhow!("core hotplug does not support MCS"));
+            }
+            append_features(&mut args, "cpu_hotplug".to_string());
+        }
     }
 
     //TODO: add fpu config according the opts
@@ -230,11 +418,15 @@ pub fn cargo(command: &str, dir: &str, opts: &BuildOptions) -> Result<(), anyhow
     append_features(&mut args, "have_fpu".to_string());
     marcos.push("HAVE_FPU=true".to_string());
     match opts.platform.as_str() {
-        "spike" => {
+        "spike" | "qemu-riscv-virt" => {
             append_features(&mut args, "riscv_ext_d".to_string());
             marcos.push("RISCV_EXT_D=true".to_string())
         }
         "qemu-arm-virt" => {}
+        "qemu-arm-virt-gicv3" => {
+            append_features(&mut args, "arm_gic_v3".to_string());
+            marcos.push("ARM_GIC_V3_SUPPORT=true".to_string())
+        }
         _ => return Err(anyhow::anyhow!("Unsupported platform")),
     };
 
@@ -261,7 +453,7 @@ pub fn build(opts: &BuildOptions) -> Result<(), anyhow::Error> {
 
     if !opts.rust_only {
         let defines = parse_cmake_defines(opts)?;
-        crate::cmake::sel4test_build(&opts.platform, &defines, super::cmake::get_build_dir(opts.benchmark))?;
+        crate::cmake::sel4test_build(cmake_platform(&opts.platform), &defines, super::cmake::get_build_dir(opts.benchmark))?;
     }
     println!("Building complete, enjoy rel4!");
     Ok(())
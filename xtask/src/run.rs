@@ -1,6 +1,6 @@
 use std::{path::PathBuf, process::Command};
 
-use crate::kernel::{cargo, parse_cmake_defines, BuildOptions};
+use crate::kernel::{cargo, cmake_platform, parse_cmake_defines, BuildOptions};
 
 pub fn run(opts: &BuildOptions) -> Result<(), anyhow::Error> {
     let current_dir = std::env::var("CARGO_MANIFEST_DIR")?;
@@ -10,7 +10,7 @@ pub fn run(opts: &BuildOptions) -> Result<(), anyhow::Error> {
 
     let defines = parse_cmake_defines(opts)?;
     crate::cmake::sel4test_build(
-        &opts.platform,
+        cmake_platform(&opts.platform),
         &defines,
         super::cmake::get_build_dir(opts.benchmark),
     )?;
@@ -18,6 +18,12 @@ pub fn run(opts: &BuildOptions) -> Result<(), anyhow::Error> {
     if opts.num_nodes > 1 {
         cmd.args(["--cpu-num", "4"]);
     }
+    if opts.arm_smmu {
+        cmd.args(["--extra-qemu-args", "-machine iommu=smmuv3"]);
+    }
+    if rel4_config::get_bool_from_cfg(&opts.platform, "definitions.RISCV_AIA").unwrap_or(false) {
+        cmd.args(["--extra-qemu-args", "-machine aia=aplic-imsic"]);
+    }
     cmd.current_dir(
         proj_dir
             .join("target")